@@ -0,0 +1,104 @@
+//! Times loading a large world: building the content registry from its content header
+//! and decoding a 500x500 map where every other tile holds a building.
+//!
+//! Run with `cargo bench --bench world_load`.
+
+use mindustry_net::block_io::{BaseBlockData, Block, SpecificBlockData};
+use mindustry_net::content::ContentRegistry;
+use mindustry_net::save_io::{Map, read_content_header, read_map, write_content_header, write_map};
+use mindustry_net::type_io::Reader;
+use std::collections::HashMap;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const SIZE: u32 = 500;
+
+/// Every block of the game, so the registry covers all the bundled data
+fn content() -> ContentRegistry {
+    let blocks: HashMap<String, String> =
+        serde_json::from_str(include_str!("../src/data/block_types.json")).unwrap();
+    let mut blocks: Vec<String> = blocks.into_keys().collect();
+    blocks.sort();
+    blocks.retain(|name| name != "air" && name != "unloader");
+    blocks.splice(0..0, ["air".to_string(), "unloader".to_string()]);
+    let items = ["copper", "lead", "metaglass", "graphite", "sand", "coal"];
+    ContentRegistry::new(HashMap::from([
+        ("block".to_string(), blocks),
+        (
+            "item".to_string(),
+            items.iter().map(|item| item.to_string()).collect(),
+        ),
+    ]))
+}
+
+fn unloader() -> Block {
+    Block {
+        name: "unloader".to_string(),
+        block_type: "Unloader".to_string(),
+        revision: 1,
+        base: BaseBlockData {
+            health: 70.0,
+            rotation: 0,
+            version: 3,
+            legacy: false,
+            on: Some(1),
+            team: 1,
+            module_bitmask: 1,
+            items: Some(HashMap::from([(0, 10)])),
+            liquids: None,
+            power: None,
+            efficiency: Some(255),
+            optional_efficiency: Some(0),
+            visible_flags: None,
+        },
+        specific: Some(SpecificBlockData::Unloader { item_id: 0 }),
+    }
+}
+
+fn world() -> Vec<u8> {
+    let mut map = Map::new(SIZE, SIZE);
+    for x in 0..SIZE {
+        for y in 0..SIZE {
+            map.set_floor(x, y, 1);
+            if (x + y) % 2 == 0 {
+                map.set_block_id(x, y, 1);
+                map.set_block(x, y, unloader());
+            }
+        }
+    }
+    let mut buf = vec![];
+    write_content_header(&mut buf, &content()).unwrap();
+    write_map(&mut buf, &map).unwrap();
+    buf
+}
+
+/// Runs `f` until a second has passed and prints the median time of a run
+fn bench(name: &str, mut f: impl FnMut()) {
+    let mut times = vec![];
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_secs(1) || times.len() < 5 {
+        let run = Instant::now();
+        f();
+        times.push(run.elapsed());
+    }
+    times.sort();
+    println!(
+        "{name}: {:?} median over {} runs",
+        times[times.len() / 2],
+        times.len()
+    );
+}
+
+fn main() {
+    let names = content().names().clone();
+    bench("content registry", || {
+        black_box(ContentRegistry::new(black_box(names.clone())));
+    });
+
+    let world = world();
+    bench("world load (500x500, 125000 buildings)", || {
+        let mut reader = Reader::new(world.clone());
+        let content = read_content_header(&mut reader).unwrap();
+        black_box(read_map(&mut reader, &content).unwrap());
+    });
+}
@@ -0,0 +1,73 @@
+//! Builds the `phf` tables of the bundled content data, so nothing parses the JSON files
+//! at runtime. The generated file is included by `src/content.rs`.
+
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+const BLOCK_TYPES: &str = "src/data/block_types.json";
+const BLOCK_PARAMS: &str = "src/data/block_params.json";
+const CONTENT_TYPES: &str = "src/data/content_types.json";
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &str) -> T {
+    println!("cargo::rerun-if-changed={path}");
+    let data = fs::read_to_string(path).unwrap();
+    serde_json::from_str(&data).unwrap()
+}
+
+/// Sizes are stored as numbers or strings
+fn size(params: &BTreeMap<String, Value>) -> Option<u8> {
+    match params.get("size")? {
+        Value::Number(size) => u8::try_from(size.as_u64()?).ok(),
+        Value::String(size) => size.parse().ok(),
+        _ => None,
+    }
+}
+
+fn main() {
+    let block_types: BTreeMap<String, String> = read_json(BLOCK_TYPES);
+    let block_params: BTreeMap<String, BTreeMap<String, Value>> = read_json(BLOCK_PARAMS);
+    let content_types: BTreeMap<String, Vec<String>> = read_json(CONTENT_TYPES);
+
+    let mut out = String::new();
+    writeln!(out, "static CONTENT_TYPES: &[&str] = &[").unwrap();
+    for content_type in &content_types["contentTypes"] {
+        writeln!(out, "    {content_type:?},").unwrap();
+    }
+    writeln!(out, "];").unwrap();
+
+    writeln!(
+        out,
+        "static BLOCK_TYPES: phf::Map<&'static str, &'static str> = phf::phf_map! {{"
+    )
+    .unwrap();
+    for (name, class) in &block_types {
+        writeln!(out, "    {name:?} => {class:?},").unwrap();
+    }
+    writeln!(out, "}};").unwrap();
+
+    writeln!(
+        out,
+        "static BLOCK_PARAMS: phf::Map<&'static str, BlockParams> = phf::phf_map! {{"
+    )
+    .unwrap();
+    for (name, params) in &block_params {
+        let flag = |key: &str| params.get(key).and_then(Value::as_bool);
+        writeln!(
+            out,
+            "    {name:?} => BlockParams {{ size: {:?}, has_items: {:?}, has_power: {:?}, has_liquids: {:?} }},",
+            size(params),
+            flag("hasItems"),
+            flag("hasPower"),
+            flag("hasLiquids"),
+        )
+        .unwrap();
+    }
+    writeln!(out, "}};").unwrap();
+
+    let path = Path::new(&env::var("OUT_DIR").unwrap()).join("content_data.rs");
+    fs::write(path, out).unwrap();
+}
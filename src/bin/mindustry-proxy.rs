@@ -0,0 +1,374 @@
+use mindustry_net::content::ContentRegistry;
+use mindustry_net::packet::{
+    AnyPacket, FrameworkPacket, Packet, parse_packet_checked, read_frame_tcp,
+};
+use mindustry_net::stream_builder::StreamBuilder;
+use mindustry_net::type_io::write_unsigned_short;
+use mindustry_net::version::ProtocolVersion;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::io::AsyncWriteExt;
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{TcpListener, TcpStream, UdpSocket};
+use tokio::task::JoinHandle;
+
+const USAGE: &str =
+    "Usage: mindustry-proxy <listen address> <upstream address> [--dump <id,id,...>]";
+
+#[derive(Clone, Copy)]
+enum Side {
+    Client,
+    Server,
+}
+
+/// Decoding state shared by both directions of one connection
+struct Session {
+    id: usize,
+    upstream_udp: UdpSocket,
+    content_map: Mutex<Option<ContentRegistry>>,
+    /// Taken from the client's `Connect` packet
+    version: Mutex<ProtocolVersion>,
+    streams: Mutex<HashMap<u32, StreamBuilder>>,
+    /// Forwards upstream datagrams to the client once it registered its UDP address
+    downstream: Mutex<Option<JoinHandle<()>>>,
+}
+
+#[derive(Default)]
+struct Registry {
+    by_register_id: HashMap<u32, Arc<Session>>,
+    by_udp_address: HashMap<SocketAddr, Arc<Session>>,
+}
+
+struct Proxy {
+    upstream: String,
+    dump: HashSet<u8>,
+    udp: UdpSocket,
+    registry: Mutex<Registry>,
+}
+
+/// The variant name from a packet's `Debug` output
+fn packet_name(packet: &impl fmt::Debug) -> String {
+    format!("{packet:?}")
+        .split(|c: char| !c.is_alphanumeric())
+        .next()
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Marks packets whose data was not read completely, which points at a decoding bug
+fn trailing_flag(trailing: usize) -> String {
+    match trailing {
+        0 => String::new(),
+        trailing => format!(" !! {trailing} trailing bytes"),
+    }
+}
+
+impl Proxy {
+    fn inspect(&self, session: &Session, side: Side, transport: &str, frame: &[u8]) {
+        let arrow = match side {
+            Side::Client => "C->S",
+            Side::Server => "S->C",
+        };
+        let label = format!("#{} {arrow} {transport}", session.id);
+        let id = frame.first().copied().unwrap_or_default();
+
+        let content_map = session.content_map.lock().unwrap().clone();
+        let version = *session.version.lock().unwrap();
+        let (packet, trailing) = match parse_packet_checked(frame.to_vec(), &content_map, version) {
+            Ok(result) => result,
+            Err(e) => {
+                println!("{label} {id:>3} !! {e}");
+                return;
+            }
+        };
+
+        let flag = trailing_flag(trailing);
+        let name = match &packet {
+            AnyPacket::Framework(packet) => packet_name(packet),
+            AnyPacket::Regular(packet) => packet_name(packet),
+        };
+        println!("{label} {id:>3} {name} ({} bytes){flag}", frame.len());
+        if self.dump.contains(&id) {
+            println!("{packet:#?}");
+        }
+
+        if let AnyPacket::Regular(packet) = packet {
+            if let Packet::Connect { version, .. } = &packet {
+                match ProtocolVersion::from_build(*version) {
+                    Some(version) => *session.version.lock().unwrap() = version,
+                    None => println!("{label} !! unsupported build {version}"),
+                }
+            }
+            self.track_stream(session, &label, packet);
+        }
+    }
+
+    /// Reassembles streamed packets such as the world data and decodes them once complete
+    fn track_stream(&self, session: &Session, label: &str, packet: Packet) {
+        let mut streams = session.streams.lock().unwrap();
+        match packet {
+            Packet::StreamBegin {
+                id,
+                total,
+                stream_type,
+            } => {
+                streams.insert(id, StreamBuilder::new(id, stream_type, total));
+            }
+            Packet::StreamChunk { id, data } => {
+                let Some(stream) = streams.get_mut(&id) else {
+                    println!("{label} !! chunk for unknown stream {id}");
+                    return;
+                };
+                stream.add(data);
+                if !stream.is_done() {
+                    return;
+                }
+                let stream = streams.remove(&id).unwrap();
+                let content_map = session.content_map.lock().unwrap().clone();
+                let version = *session.version.lock().unwrap();
+                match stream.build_checked(&content_map, version) {
+                    Ok((packet, trailing)) => {
+                        let flag = trailing_flag(trailing);
+                        println!("{label} stream {id}: {}{flag}", packet_name(&packet));
+                        if let Packet::WorldStream(world) = &packet {
+                            *session.content_map.lock().unwrap() = Some(world.content_map.clone());
+                        }
+                        if self.dump.contains(&2) {
+                            println!("{packet:#?}");
+                        }
+                    }
+                    Err(e) => println!("{label} stream {id} !! {e}"),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    async fn forward_tcp(
+        &self,
+        session: &Arc<Session>,
+        side: Side,
+        mut from: OwnedReadHalf,
+        mut to: OwnedWriteHalf,
+    ) {
+        loop {
+            let frame = match read_frame_tcp(&mut from).await {
+                Ok(frame) => frame,
+                Err(_) => break,
+            };
+            self.inspect(session, side, "tcp", &frame);
+
+            if let (Side::Server, [0xFE, 0x04, id @ ..]) = (side, frame.as_slice()) {
+                let id = u32::from_be_bytes(id.try_into().unwrap_or_default());
+                let mut registry = self.registry.lock().unwrap();
+                registry.by_register_id.insert(id, Arc::clone(session));
+            }
+
+            let mut buf = vec![];
+            write_unsigned_short(&mut buf, frame.len() as u16);
+            buf.extend_from_slice(&frame);
+            if to.write_all(&buf).await.is_err() {
+                break;
+            }
+        }
+        let _ = to.shutdown().await;
+    }
+
+    async fn handle_client(self: Arc<Self>, client: TcpStream, id: usize) {
+        let upstream = match TcpStream::connect(&self.upstream).await {
+            Ok(upstream) => upstream,
+            Err(e) => {
+                println!("#{id} !! failed to connect upstream: {e}");
+                return;
+            }
+        };
+        let upstream_udp = match UdpSocket::bind("0.0.0.0:0").await {
+            Ok(socket) => socket,
+            Err(e) => {
+                println!("#{id} !! failed to bind UDP: {e}");
+                return;
+            }
+        };
+        if let Err(e) = upstream_udp.connect(&self.upstream).await {
+            println!("#{id} !! failed to connect upstream UDP: {e}");
+            return;
+        }
+
+        let session = Arc::new(Session {
+            id,
+            upstream_udp,
+            content_map: Mutex::new(None),
+            version: Mutex::new(ProtocolVersion::default()),
+            streams: Mutex::new(HashMap::new()),
+            downstream: Mutex::new(None),
+        });
+        let (client_read, client_write) = client.into_split();
+        let (upstream_read, upstream_write) = upstream.into_split();
+
+        tokio::join!(
+            self.forward_tcp(&session, Side::Client, client_read, upstream_write),
+            self.forward_tcp(&session, Side::Server, upstream_read, client_write),
+        );
+        let mut registry = self.registry.lock().unwrap();
+        registry
+            .by_register_id
+            .retain(|_, other| !Arc::ptr_eq(other, &session));
+        registry
+            .by_udp_address
+            .retain(|_, other| !Arc::ptr_eq(other, &session));
+        drop(registry);
+        // Registrations spawn the task while holding the registry, so none can follow
+        if let Some(downstream) = session.downstream.lock().unwrap().take() {
+            downstream.abort();
+        }
+        println!("#{id} closed");
+    }
+
+    /// Forwards datagrams from the upstream server back to the client's UDP address
+    async fn forward_udp_downstream(self: Arc<Self>, session: Arc<Session>, client: SocketAddr) {
+        let mut buf = [0u8; 32768];
+        while let Ok(length) = session.upstream_udp.recv(&mut buf).await {
+            self.inspect(&session, Side::Server, "udp", &buf[..length]);
+            if self.udp.send_to(&buf[..length], client).await.is_err() {
+                break;
+            }
+        }
+    }
+
+    async fn forward_udp_upstream(self: Arc<Self>) {
+        let mut buf = [0u8; 32768];
+        loop {
+            let (length, address) = match self.udp.recv_from(&mut buf).await {
+                Ok(result) => result,
+                Err(e) => {
+                    println!("!! UDP receive failed: {e}");
+                    continue;
+                }
+            };
+            let datagram = &buf[..length];
+
+            let known = self
+                .registry
+                .lock()
+                .unwrap()
+                .by_udp_address
+                .get(&address)
+                .cloned();
+            let session = match known {
+                Some(session) => session,
+                None => {
+                    // Clients announce their UDP address with the id they got over TCP
+                    let content_map = None;
+                    let registered = match parse_packet_checked(
+                        datagram.to_vec(),
+                        &content_map,
+                        ProtocolVersion::default(),
+                    ) {
+                        Ok((AnyPacket::Framework(FrameworkPacket::RegisterUDP(id)), _)) => {
+                            let mut registry = self.registry.lock().unwrap();
+                            let session = registry.by_register_id.get(&id).cloned();
+                            if let Some(session) = &session {
+                                registry.by_udp_address.insert(address, Arc::clone(session));
+                                let downstream = tokio::spawn(
+                                    Arc::clone(&self)
+                                        .forward_udp_downstream(Arc::clone(session), address),
+                                );
+                                let previous =
+                                    session.downstream.lock().unwrap().replace(downstream);
+                                if let Some(previous) = previous {
+                                    previous.abort();
+                                }
+                            }
+                            session
+                        }
+                        _ => None,
+                    };
+                    match registered {
+                        Some(session) => session,
+                        None => {
+                            println!("!! dropping UDP datagram from unknown client {address}");
+                            continue;
+                        }
+                    }
+                }
+            };
+
+            self.inspect(&session, Side::Client, "udp", datagram);
+            if let Err(e) = session.upstream_udp.send(datagram).await {
+                println!("#{} !! UDP send failed: {e}", session.id);
+            }
+        }
+    }
+}
+
+fn parse_dump(ids: &str) -> Option<HashSet<u8>> {
+    ids.split(',').map(|id| id.trim().parse().ok()).collect()
+}
+
+#[tokio::main]
+async fn main() {
+    tracing_subscriber::fmt::init();
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (listen, upstream, dump) = match args.as_slice() {
+        [listen, upstream] => (listen, upstream, HashSet::new()),
+        [listen, upstream, flag, ids] if flag == "--dump" => match parse_dump(ids) {
+            Some(dump) => (listen, upstream, dump),
+            None => {
+                eprintln!("Invalid packet ids: {ids}");
+                std::process::exit(1);
+            }
+        },
+        _ => {
+            eprintln!("{USAGE}");
+            std::process::exit(1);
+        }
+    };
+
+    let listener = match TcpListener::bind(listen).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("Failed to listen on {listen}: {e}");
+            std::process::exit(1);
+        }
+    };
+    let address = match listener.local_addr() {
+        Ok(address) => address,
+        Err(e) => {
+            eprintln!("Failed to get the listen address: {e}");
+            std::process::exit(1);
+        }
+    };
+    let udp = match UdpSocket::bind(address).await {
+        Ok(udp) => udp,
+        Err(e) => {
+            eprintln!("Failed to bind UDP on {address}: {e}");
+            std::process::exit(1);
+        }
+    };
+    println!("Proxying {address} -> {upstream}");
+
+    let proxy = Arc::new(Proxy {
+        upstream: upstream.clone(),
+        dump,
+        udp,
+        registry: Mutex::new(Registry::default()),
+    });
+    tokio::spawn(Arc::clone(&proxy).forward_udp_upstream());
+
+    let mut next_id = 1;
+    loop {
+        let (client, address) = match listener.accept().await {
+            Ok(result) => result,
+            Err(e) => {
+                println!("!! accept failed: {e}");
+                continue;
+            }
+        };
+        println!("#{next_id} connected from {address}");
+        tokio::spawn(Arc::clone(&proxy).handle_client(client, next_id));
+        next_id += 1;
+    }
+}
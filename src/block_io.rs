@@ -1,62 +1,40 @@
-use crate::arc_types::Point2;
+use crate::content::{BlockInfo, ContentRegistry};
+use crate::packet::PacketError;
 use crate::type_io::{
-    Reader, Tile, read_command, read_object_boxed, read_prefixed_string, read_string,
-    read_vec2_nullable,
+    Content, Object, Reader, Tile, Vec2, read_content, read_object_boxed, read_prefixed_string,
+    read_string, read_vec2_nullable, write_bool, write_byte, write_content, write_double,
+    write_float, write_int, write_long, write_object, write_prefixed_string, write_short,
+    write_unsigned_short, write_utf, write_vec2,
 };
-use crate::unit_io::{Plan, read_payload, read_plans};
+use crate::unit_io::{Payload, Plan, read_payload, read_plans, write_payload, write_plans};
 use num_enum::{IntoPrimitive, TryFromPrimitive};
-use serde::Deserialize;
 use std::collections::HashMap;
 
-// TODO: Everything basically
-
-#[derive(Deserialize)]
-#[serde(default)]
-struct BlockParam {
-    has_items: bool,
-    has_power: bool,
-    has_liquids: bool,
-}
-impl Default for BlockParam {
-    fn default() -> Self {
-        Self {
-            has_items: false,
-            has_power: false,
-            has_liquids: false,
-        }
-    }
-}
-
-fn load_block_params() -> HashMap<String, BlockParam> {
-    let data = include_str!("data/block_params.json");
-    serde_json::from_str(data).unwrap()
-}
-
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct ConveyorItem {
-    pub item_id: u32,
-    pub x: u32,
-    pub y: u32,
+    pub item_id: i16,
+    pub x: f32,
+    pub y: f32,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct DirectionalItemBuffer {
     indexes: Vec<u8>,
     values: Vec<Vec<u64>>,
     capacity: u8,
 }
 impl DirectionalItemBuffer {
-    pub fn read(reader: &mut Reader, capacity: u8) -> DirectionalItemBuffer {
+    pub fn read(reader: &mut Reader, capacity: u8) -> Result<DirectionalItemBuffer, PacketError> {
         let mut indexes = vec![];
         let mut values = vec![];
 
         for _ in 0..4 {
             let mut sub_values = vec![];
-            indexes.push(reader.byte());
-            let length = reader.byte();
+            indexes.push(reader.byte()?);
+            let length = reader.byte()?;
 
             for j in 0..length {
-                let value = reader.long();
+                let value = reader.long()?;
                 if j < capacity {
                     sub_values.push(value);
                 }
@@ -64,10 +42,20 @@ impl DirectionalItemBuffer {
             values.push(sub_values);
         }
 
-        DirectionalItemBuffer {
+        Ok(DirectionalItemBuffer {
             indexes,
             values,
             capacity,
+        })
+    }
+
+    pub fn write(&self, buf: &mut Vec<u8>) {
+        for (index, values) in self.indexes.iter().zip(&self.values) {
+            write_byte(buf, *index);
+            write_byte(buf, values.len() as u8);
+            for value in values {
+                write_long(buf, *value);
+            }
         }
     }
 }
@@ -80,13 +68,45 @@ pub enum MassDriverState {
     Shooting = 2,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ItemBuffer {
+    pub index: u8,
+    pub values: Vec<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PayloadStack {
+    pub content_type: u8,
+    pub id: i16,
+    pub count: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BuildAccumulator {
+    pub accumulator: f32,
+    pub total_accumulator: f32,
+    pub items_left: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LogicProgram {
+    /// Deflate compressed code and links
+    Compressed(Vec<u8>),
+    Legacy {
+        code: Option<String>,
+        links: Vec<u32>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum SpecificBlockData {
-    // TODO GenericCrafter
-    // TODO Separator
-    // TODO HeatProducer
-    // TODO HeatCrafter
-    // TODO AttributeCrafter
+    GenericCrafter {
+        progress: f32,
+        warmup: f32,
+        cultivator_warmup: Option<f32>,
+        heat: Option<f32>,
+        seed: Option<u32>,
+    },
     Door {
         open: bool,
     },
@@ -129,7 +149,13 @@ pub enum SpecificBlockData {
     Junction {
         buffer: DirectionalItemBuffer,
     },
-    // TODO Bridges (split up?)
+    ItemBridge {
+        link: u32,
+        warmup: f32,
+        incoming: Vec<u32>,
+        moved: Option<bool>,
+        buffer: Option<ItemBuffer>,
+    },
     Sorter {
         sort_item: i16,
         buffer: Option<DirectionalItemBuffer>,
@@ -159,15 +185,29 @@ pub enum SpecificBlockData {
         item_id: i16,
         stale: bool,
     },
-    // TODO reactors (split up?)
+    Reactor {
+        productivity: f32,
+        generate_time: Option<f32>,
+        heat: Option<f32>,
+        instability: Option<f32>,
+        warmup: Option<f32>,
+    },
     HeaterGenerator {
         heat: f32,
     },
-    // TODO drills (split up?)
+    Drill {
+        progress: Option<f32>,
+        time: Option<f32>,
+        warmup: Option<f32>,
+    },
     Unloader {
         item_id: i16,
     },
-    // TODO ItemTurret
+    ItemTurret {
+        reload_counter: f32,
+        rotation: f32,
+        ammo: Vec<(i16, i16)>,
+    },
     TractorBeamTurret {
         rotation: f32,
     },
@@ -182,26 +222,92 @@ pub enum SpecificBlockData {
     RepairTurret {
         rotation: f32,
     },
-    // TODO UnitFactory
-    // TODO Reconstructor
-    // TODO UnitAssembler
-    // TODO PayloadConveyor
-    // TODO PayloadRouter
-    // TODO PayloadMassDriver
-    // TODO PayloadDeconstructor
-    // TODO Constructor
-    // TODO PayloadLoader
+    /// Unit factories and reconstructors
+    UnitFactory {
+        payload_x: f32,
+        payload_y: f32,
+        payload_rotation: f32,
+        payload: Option<Payload>,
+        progress: Option<f32>,
+        current_plan: Option<i16>,
+        command_position: Option<Vec2>,
+        /// Raw command id, 255 if there is none
+        command: Option<u8>,
+    },
+    UnitAssembler {
+        payload_x: f32,
+        payload_y: f32,
+        payload_rotation: f32,
+        payload: Option<Payload>,
+        progress: f32,
+        units: Vec<u32>,
+        blocks: Vec<PayloadStack>,
+        command_position: Option<Vec2>,
+    },
+    PayloadConveyor {
+        progress: f32,
+        item_rotation: f32,
+        item: Option<Payload>,
+        sort: Option<Content>,
+        received_direction: Option<u8>,
+    },
+    PayloadMassDriver {
+        payload_x: f32,
+        payload_y: f32,
+        payload_rotation: f32,
+        payload: Option<Payload>,
+        link: u32,
+        rotation: f32,
+        state: u8,
+        reload_counter: f32,
+        charge: f32,
+        loaded: bool,
+        charging: bool,
+    },
+    PayloadDeconstructor {
+        payload_x: f32,
+        payload_y: f32,
+        payload_rotation: f32,
+        payload: Option<Payload>,
+        progress: f32,
+        accumulator: Vec<f32>,
+        deconstructing: Option<Payload>,
+    },
+    Constructor {
+        payload_x: f32,
+        payload_y: f32,
+        payload_rotation: f32,
+        payload: Option<Payload>,
+        progress: f32,
+        recipe: i16,
+    },
+    PayloadLoader {
+        payload_x: f32,
+        payload_y: f32,
+        payload_rotation: f32,
+        payload: Option<Payload>,
+        exporting: bool,
+    },
     ItemSource {
         item_id: i16,
     },
     LiquidSource {
         liquid_id: i16,
     },
-    // TODO PayloadSource
+    PayloadSource {
+        payload_x: f32,
+        payload_y: f32,
+        payload_rotation: f32,
+        payload: Option<Payload>,
+        unit: i16,
+        block: i16,
+    },
     LightBlock {
         color: u32,
     },
-    // TODO LaunchPad
+    LaunchPad {
+        launch_counter: f32,
+    },
     Accelerator {
         progress: f32,
     },
@@ -211,10 +317,14 @@ pub enum SpecificBlockData {
     Switch {
         enabled: bool,
     },
-    // TODO ConsumeGenerator
-    // TODO ThermalGenerator
-    // TODO SolarGenerator
-    // TODO StackRouter
+    /// Consume, thermal and solar generators
+    ConsumeGenerator {
+        productivity: f32,
+        generate_time: f32,
+    },
+    StackRouter {
+        sort_item: i16,
+    },
     LiquidTurret {
         reload_counter: f32,
         rotation: f32,
@@ -227,16 +337,39 @@ pub enum SpecificBlockData {
         reload_counter: f32,
         rotation: f32,
     },
-    // TODO UnitAssemblerModule
+    UnitAssemblerModule {
+        payload_x: f32,
+        payload_y: f32,
+        payload_rotation: f32,
+        payload: Option<Payload>,
+    },
     Memory {
         memory: Vec<f64>,
     },
-    // TODO LogicDisplay
-    // TODO LogicBlock
+    LogicDisplay {
+        transform: Option<Vec<f32>>,
+    },
+    LogicBlock {
+        program: LogicProgram,
+        variables: Vec<(Option<String>, Object)>,
+        memory: Vec<f64>,
+        instructions_per_tick: Option<i16>,
+        tag: Option<String>,
+        /// Only present with revision 3 and above, the tag is written along with it
+        icon_tag: Option<u16>,
+    },
     Canvas {
         data: Vec<u8>,
     },
-    // TODO Build
+    Build {
+        progress: f32,
+        previous_block: i16,
+        current_block: i16,
+        accumulators: Option<Vec<BuildAccumulator>>,
+    },
+    CoreBlock {
+        command_position: Vec2,
+    },
 }
 
 fn read_specific_block_data(
@@ -244,80 +377,84 @@ fn read_specific_block_data(
     block_name: String,
     block_type: String,
     version: u8,
-    content_map: &HashMap<String, Vec<String>>,
-) -> Option<SpecificBlockData> {
+    content_map: &ContentRegistry,
+) -> Result<Option<SpecificBlockData>, PacketError> {
     if block_type == "GenericCrafter"
         || block_type == "Separator"
         || block_type == "HeatProducer"
         || block_type == "HeatCrafter"
         || block_type == "AttributeCrafter"
     {
-        let progress = reader.float();
-        let warmup = reader.float();
+        let progress = reader.float()?;
+        let warmup = reader.float()?;
 
-        if block_name == "cultivator" {
-            reader.float();
-        }
+        let cultivator_warmup = if block_name == "cultivator" {
+            Some(reader.float()?)
+        } else {
+            None
+        };
 
-        let heat;
-        if block_type == "HeatProducer" {
-            heat = reader.float();
-        }
+        let heat = if block_type == "HeatProducer" {
+            Some(reader.float()?)
+        } else {
+            None
+        };
 
-        let seed;
-        if block_type == "Separator" || version == 1 {
-            seed = reader.int();
-        }
+        let seed = if block_type == "Separator" || version == 1 {
+            Some(reader.int()?)
+        } else {
+            None
+        };
 
-        //let result = {
-        //  progress,
-        //  warmup,
-        //  seed,
-        //  heat
-        //}
-        //return result
+        return Ok(Some(SpecificBlockData::GenericCrafter {
+            progress,
+            warmup,
+            cultivator_warmup,
+            heat,
+            seed,
+        }));
     } else if block_type == "Door" || block_type == "AutoDoor" {
-        return Some(SpecificBlockData::Door {
-            open: reader.bool(),
-        });
+        return Ok(Some(SpecificBlockData::Door {
+            open: reader.bool()?,
+        }));
     } else if block_type == "ShieldWall" {
-        return Some(SpecificBlockData::ShieldWall {
-            shield: reader.float(),
-        });
+        return Ok(Some(SpecificBlockData::ShieldWall {
+            shield: reader.float()?,
+        }));
     } else if block_type == "MendProjector" {
-        return Some(SpecificBlockData::MendProjector {
-            heat: reader.float(),
-            phase_heat: reader.float(),
-        });
+        return Ok(Some(SpecificBlockData::MendProjector {
+            heat: reader.float()?,
+            phase_heat: reader.float()?,
+        }));
     } else if block_type == "OverdriveProjector" {
-        return Some(SpecificBlockData::OverdriveProjector {
-            heat: reader.float(),
-            phase_heat: reader.float(),
-        });
+        return Ok(Some(SpecificBlockData::OverdriveProjector {
+            heat: reader.float()?,
+            phase_heat: reader.float()?,
+        }));
     } else if block_type == "ForceProjector" {
-        return Some(SpecificBlockData::ForceProjector {
-            broken: reader.bool(),
-            buildup: reader.float(),
-            radius_scale: reader.float(),
-            warmup: reader.float(),
-            phase_heat: reader.float(),
-        });
+        return Ok(Some(SpecificBlockData::ForceProjector {
+            broken: reader.bool()?,
+            buildup: reader.float()?,
+            radius_scale: reader.float()?,
+            warmup: reader.float()?,
+            phase_heat: reader.float()?,
+        }));
     } else if block_type == "Radar" {
-        return Some(SpecificBlockData::Radar {
-            progress: reader.float(),
-        });
+        return Ok(Some(SpecificBlockData::Radar {
+            progress: reader.float()?,
+        }));
     } else if block_type == "BuildTurret" {
-        return Some(SpecificBlockData::BuildTurret {
-            rotation: reader.float(),
-            plans: read_plans(reader),
-        });
+        return Ok(Some(SpecificBlockData::BuildTurret {
+            rotation: reader.float()?,
+            plans: read_plans(reader)?,
+        }));
     } else if block_type == "BaseShield" {
-        return Some(SpecificBlockData::BaseShield {
-            smooth_radius: reader.float(),
-            broken: reader.bool(),
-        });
+        return Ok(Some(SpecificBlockData::BaseShield {
+            smooth_radius: reader.float()?,
+            broken: reader.bool()?,
+        }));
     } else if block_type == "Conveyor" || block_type == "ArmoredConveyor" {
-        let amount = reader.int();
+        let amount = reader.int()?;
         let mut items = vec![];
 
         for _ in 0..amount {
@@ -325,625 +462,1059 @@ fn read_specific_block_data(
             let x;
             let y;
             if version == 0 {
-                let val = reader.int();
-                item_id = (val >> 24) & 0xff;
-                x = ((val >> 16) & 0xff) / 127;
-                y = (((val >> 8) & 0xff) + 128) / 255;
+                let val = reader.int()?;
+                item_id = ((val >> 24) & 0xff) as i16;
+                x = ((val >> 16) & 0xff) as u8 as i8 as f32 / 127.0;
+                y = (((val >> 8) & 0xff) as u8 as i8 as f32 + 128.0) / 255.0;
             } else {
-                item_id = reader.short() as u32;
-                x = (reader.byte() / 127) as u32;
-                y = ((reader.byte() + 128) / 255) as u32
+                item_id = reader.short()?;
+                x = reader.byte()? as i8 as f32 / 127.0;
+                y = (reader.byte()? as i8 as f32 + 128.0) / 255.0;
             }
             items.push(ConveyorItem { item_id, x, y })
         }
 
-        return Some(SpecificBlockData::Conveyor { items });
+        return Ok(Some(SpecificBlockData::Conveyor { items }));
     } else if block_type == "StackConveyor" {
-        return Some(SpecificBlockData::StackConveyor {
-            link: reader.int(),
-            cooldown: reader.float(),
-        });
+        return Ok(Some(SpecificBlockData::StackConveyor {
+            link: reader.int()?,
+            cooldown: reader.float()?,
+        }));
     } else if block_type == "Junction" {
-        return Some(SpecificBlockData::Junction {
-            buffer: DirectionalItemBuffer::read(reader, 6),
-        });
+        return Ok(Some(SpecificBlockData::Junction {
+            buffer: DirectionalItemBuffer::read(reader, 6)?,
+        }));
     } else if block_type == "BufferedItemBridge"
         || block_type == "ItemBridge"
         || block_type == "LiquidBridge"
     {
-        let link = reader.int();
-        let warmup = reader.float();
-        let links = reader.byte();
-        //let incoming = [];
-        let moved;
-        for i in 0..links {
-            /*incoming.push(*/
-            reader.int()/*)*/;
+        let link = reader.int()?;
+        let warmup = reader.float()?;
+
+        let links = reader.byte()?;
+        let mut incoming = vec![];
+        for _ in 0..links {
+            incoming.push(reader.int()?);
         }
-        if version >= 1 {
-            moved = reader.byte();
-        }
-        let index;
-        let length;
-        //let buffer;
-        if block_type == "BufferedItemBridge" {
-            index = reader.byte();
-            length = reader.byte();
-            //buffer = [];
-            for i in 0..length {
-                let l = reader.long();
-                //buffer[i] = l;
+
+        let moved = if version >= 1 {
+            Some(reader.bool()?)
+        } else {
+            None
+        };
+
+        let buffer = if block_type == "BufferedItemBridge" {
+            let index = reader.byte()?;
+            let length = reader.byte()?;
+            let mut values = vec![];
+            for _ in 0..length {
+                values.push(reader.long()?);
             }
-        }
-        //let result = {
-        //  link,
-        //  warmup,
-        //  incoming,
-        //  index,
-        //  buffer
-        //}
-        //return result
+            Some(ItemBuffer { index, values })
+        } else {
+            None
+        };
+
+        return Ok(Some(SpecificBlockData::ItemBridge {
+            link,
+            warmup,
+            incoming,
+            moved,
+            buffer,
+        }));
     } else if block_type == "Sorter" {
-        let sort_item = reader.short();
+        let sort_item = reader.short()?;
         let buffer = if version == 1 {
-            Some(DirectionalItemBuffer::read(reader, 20))
+            Some(DirectionalItemBuffer::read(reader, 20)?)
         } else {
             None
         };
-        return Some(SpecificBlockData::Sorter { sort_item, buffer });
+        return Ok(Some(SpecificBlockData::Sorter { sort_item, buffer }));
     } else if block_type == "OverflowGate" {
         let buffer = if version == 1 {
-            Some(DirectionalItemBuffer::read(reader, 25))
+            Some(DirectionalItemBuffer::read(reader, 25)?)
         } else {
             None
         };
         if version == 3 {
-            reader.int();
+            reader.int()?;
         }
-        return Some(SpecificBlockData::OverflowGate { buffer });
+        return Ok(Some(SpecificBlockData::OverflowGate { buffer }));
     } else if block_type == "MassDriver" {
-        return Some(SpecificBlockData::MassDriver {
-            link: reader.int(),
-            rotation: reader.float(),
-            state: MassDriverState::try_from(reader.byte()).unwrap(),
-        });
+        return Ok(Some(SpecificBlockData::MassDriver {
+            link: reader.int()?,
+            rotation: reader.float()?,
+            state: {
+                let state = reader.byte()?;
+                MassDriverState::try_from(state)
+                    .map_err(|_| reader.error(format!("unknown mass driver state {state}")))?
+            },
+        }));
     } else if block_type == "Duct" {
         let received_direction = if version >= 1 {
-            Some(reader.byte())
+            Some(reader.byte()?)
         } else {
             None
         };
-        return Some(SpecificBlockData::Duct { received_direction });
+        return Ok(Some(SpecificBlockData::Duct { received_direction }));
     } else if block_type == "DuctRouter" {
         let sort_item = if version >= 1 {
-            Some(reader.short())
+            Some(reader.short()?)
         } else {
             None
         };
-        return Some(SpecificBlockData::DuctRouter { sort_item });
+        return Ok(Some(SpecificBlockData::DuctRouter { sort_item }));
     } else if block_type == "DirectionalUnloader" {
-        return Some(SpecificBlockData::DirectionalUnloader {
-            item_id: reader.short(),
-            offset: reader.short(),
-        });
+        return Ok(Some(SpecificBlockData::DirectionalUnloader {
+            item_id: reader.short()?,
+            offset: reader.short()?,
+        }));
     } else if block_type == "UnitCargoLoader" {
-        return Some(SpecificBlockData::UnitCargoLoader {
-            unit_id: reader.int(),
-        });
+        return Ok(Some(SpecificBlockData::UnitCargoLoader {
+            unit_id: reader.int()?,
+        }));
     } else if block_type == "UnitCargoUnloadPoint" {
-        return Some(SpecificBlockData::UnitCargoUnloadPoint {
-            item_id: reader.short(),
-            stale: reader.bool(),
-        });
+        return Ok(Some(SpecificBlockData::UnitCargoUnloadPoint {
+            item_id: reader.short()?,
+            stale: reader.bool()?,
+        }));
     } else if block_type == "NuclearReactor"
         || block_type == "ImpactReactor"
         || block_type == "VariableReactor"
     {
-        let peff = reader.float();
-        let gentime;
-        if version >= 1 {
-            gentime = reader.float();
-        }
-        let heat;
-        let warmup;
-        let instability;
-        if block_type == "NuclearReactor" || block_type == "VariableReactor" {
-            heat = reader.float();
-        }
-        if block_type == "VariableReactor" {
-            instability = reader.float();
-        }
-        if block_type == "ImpactReactor" || block_type == "VariableReactor" {
-            warmup = reader.float();
-        }
-        //let result = {
-        //  peff,
-        //  gentime,
-        //  heat,
-        //  warmup,
-        //  instability
-        //}
-        //return result
+        let productivity = reader.float()?;
+        let generate_time = if version >= 1 {
+            Some(reader.float()?)
+        } else {
+            None
+        };
+        let heat = if block_type == "NuclearReactor" || block_type == "VariableReactor" {
+            Some(reader.float()?)
+        } else {
+            None
+        };
+        let instability = if block_type == "VariableReactor" {
+            Some(reader.float()?)
+        } else {
+            None
+        };
+        let warmup = if block_type == "ImpactReactor" || block_type == "VariableReactor" {
+            Some(reader.float()?)
+        } else {
+            None
+        };
+        return Ok(Some(SpecificBlockData::Reactor {
+            productivity,
+            generate_time,
+            heat,
+            instability,
+            warmup,
+        }));
     } else if block_type == "HeaterGenerator" {
-        return Some(SpecificBlockData::HeaterGenerator {
-            heat: reader.float(),
-        });
+        return Ok(Some(SpecificBlockData::HeaterGenerator {
+            heat: reader.float()?,
+        }));
     } else if block_type == "Drill" || block_type == "BeamDrill" || block_type == "BurstDrill" {
-        let progress;
-        let warmup;
-        let time;
+        let mut progress = None;
+        let mut time = None;
+        let mut warmup = None;
         if version >= 1 {
             if block_type == "Drill" || block_type == "BurstDrill" {
-                progress = reader.float();
+                progress = Some(reader.float()?);
             } else {
-                time = reader.float();
+                time = Some(reader.float()?);
             }
-            warmup = reader.float();
-        }
-        //let result = {
-        //  progress,
-        //  warmup,
-        //  time
-        //}
-        //return result
+            warmup = Some(reader.float()?);
+        }
+        return Ok(Some(SpecificBlockData::Drill {
+            progress,
+            time,
+            warmup,
+        }));
     } else if block_type == "Unloader" {
         let item_id = if version == 1 {
-            reader.short()
+            reader.short()?
         } else {
-            reader.byte() as i16
+            reader.byte()? as i16
         };
-        return Some(SpecificBlockData::Unloader { item_id });
+        return Ok(Some(SpecificBlockData::Unloader { item_id }));
     } else if block_type == "ItemTurret" {
-        let reloadc = reader.float();
-        let rotation = reader.float();
-        //let ammo = []
-        let amount = reader.byte();
-        for i in 0..amount {
-            let item = reader.short();
-            let a = reader.short();
-            //ammo[i] = [item, a]
-        }
-        //let result = {
-        //  reloadc,
-        //  rotation,
-        //  ammo
-        //}
-        //return result
+        let reload_counter = reader.float()?;
+        let rotation = reader.float()?;
+        let amount = reader.byte()?;
+        let mut ammo = vec![];
+        for _ in 0..amount {
+            let item = reader.short()?;
+            let count = reader.short()?;
+            ammo.push((item, count));
+        }
+        return Ok(Some(SpecificBlockData::ItemTurret {
+            reload_counter,
+            rotation,
+            ammo,
+        }));
     } else if block_type == "TractorBeamTurret" {
-        return Some(SpecificBlockData::TractorBeamTurret {
-            rotation: reader.float(),
-        });
+        return Ok(Some(SpecificBlockData::TractorBeamTurret {
+            rotation: reader.float()?,
+        }));
     } else if block_type == "PointDefenseTurret" {
-        return Some(SpecificBlockData::PointDefenseTurret {
-            rotation: reader.float(),
-        });
+        return Ok(Some(SpecificBlockData::PointDefenseTurret {
+            rotation: reader.float()?,
+        }));
     } else if block_type == "ContinuousTurret" || block_type == "ContinuousLiquidTurret" {
         let reload_counter = if version >= 1 {
-            Some(reader.float())
+            Some(reader.float()?)
         } else {
             None
         };
         let rotation = if version >= 1 {
-            Some(reader.float())
+            Some(reader.float()?)
         } else {
             None
         };
         let last_length = if version >= 3 {
-            Some(reader.float())
+            Some(reader.float()?)
         } else {
             None
         };
-        return Some(SpecificBlockData::ContinuousTurret {
+        return Ok(Some(SpecificBlockData::ContinuousTurret {
             reload_counter,
             rotation,
             last_length,
-        });
+        }));
     } else if block_type == "RepairTurret" {
-        return Some(SpecificBlockData::RepairTurret {
-            rotation: reader.float(),
-        });
+        return Ok(Some(SpecificBlockData::RepairTurret {
+            rotation: reader.float()?,
+        }));
     } else if block_type == "UnitFactory" || block_type == "Reconstructor" {
-        let px = reader.float();
-        let py = reader.float();
-        let prot = reader.float();
-        let payload = read_payload(reader, content_map);
-        let progress;
-        if block_type == "UnitFactory" {
-            progress = reader.float();
-        } else if version >= 1 {
-            progress = reader.float();
-        }
-        let currentplan;
-        if block_type == "UnitFactory" {
-            currentplan = reader.short();
-        }
-        let commandpos;
-        let command;
-        if version >= 2 {
-            commandpos = read_vec2_nullable(reader);
-        }
-        if version >= 3 {
-            command = read_command(reader);
-        }
-        //let result = {
-        //  px,
-        //  py,
-        //  prot,
-        //  payload,
-        //  progress,
-        //  currentplan,
-        //  commandpos,
-        //  command
-        //}
-        //return result
+        let payload_x = reader.float()?;
+        let payload_y = reader.float()?;
+        let payload_rotation = reader.float()?;
+        let payload = read_payload(reader, content_map)?;
+        let progress = if block_type == "UnitFactory" || version >= 1 {
+            Some(reader.float()?)
+        } else {
+            None
+        };
+        let current_plan = if block_type == "UnitFactory" {
+            Some(reader.short()?)
+        } else {
+            None
+        };
+        let command_position = if version >= 2 {
+            Some(read_vec2_nullable(reader)?)
+        } else {
+            None
+        };
+        let command = if version >= 3 {
+            Some(reader.byte()?)
+        } else {
+            None
+        };
+        return Ok(Some(SpecificBlockData::UnitFactory {
+            payload_x,
+            payload_y,
+            payload_rotation,
+            payload,
+            progress,
+            current_plan,
+            command_position,
+            command,
+        }));
     } else if block_type == "UnitAssembler" {
-        let px = reader.float();
-        let py = reader.float();
-        let prot = reader.float();
-        let payload = read_payload(reader, content_map);
-        let progress = reader.float();
-        let count = reader.byte();
-        //let units = [];
-        for i in 0..count {
-            let unit = reader.int();
-            //units.push(unit)
-        }
-        let pay = read_payload_seq(reader);
-        let commandpos;
-        if version >= 2 {
-            commandpos = read_vec2_nullable(reader);
-        }
-        //let result = {
-        //  px,
-        //  py,
-        //  prot,
-        //  payload,
-        //  progress,
-        //  units,
-        //  pay,
-        //  commandpos
-        //}
-        //return result
+        let payload_x = reader.float()?;
+        let payload_y = reader.float()?;
+        let payload_rotation = reader.float()?;
+        let payload = read_payload(reader, content_map)?;
+        let progress = reader.float()?;
+        let count = reader.byte()?;
+        let mut units = vec![];
+        for _ in 0..count {
+            units.push(reader.int()?);
+        }
+        let blocks = read_payload_seq(reader)?;
+        let command_position = if version >= 2 {
+            Some(read_vec2_nullable(reader)?)
+        } else {
+            None
+        };
+        return Ok(Some(SpecificBlockData::UnitAssembler {
+            payload_x,
+            payload_y,
+            payload_rotation,
+            payload,
+            progress,
+            units,
+            blocks,
+            command_position,
+        }));
     } else if block_type == "PayloadConveyor" || block_type == "PayloadRouter" {
-        let progress = reader.float();
-        let itemrotation = reader.float();
-        let item = read_payload(reader, content_map);
-        let sort;
-        let recdir;
+        let progress = reader.float()?;
+        let item_rotation = reader.float()?;
+        let item = read_payload(reader, content_map)?;
+        let mut sort = None;
+        let mut received_direction = None;
         if block_type == "PayloadRouter" {
-            let ctype = reader.byte();
-            sort = reader.short();
-            recdir = reader.byte();
-        }
-        //let result = {
-        //  progress,
-        //  itemrotation,
-        //  item,
-        //  sort,
-        //  recdir
-        //}
-        //return result
+            sort = Some(read_content(reader)?);
+            received_direction = Some(reader.byte()?);
+        }
+        return Ok(Some(SpecificBlockData::PayloadConveyor {
+            progress,
+            item_rotation,
+            item,
+            sort,
+            received_direction,
+        }));
     } else if block_type == "PayloadMassDriver" {
-        let px = reader.float();
-        let py = reader.float();
-        let prot = reader.float();
-        let payload = read_payload(reader, content_map);
-        let link = reader.int();
-        let rotation = reader.float();
-        let state = reader.byte();
-        let reloadc = reader.float();
-        let charge = reader.float();
-        let loaded = reader.byte();
-        let charging = reader.byte();
-        //let result = {
-        //  px,
-        //  py,
-        //  prot,
-        //  payload,
-        //  link,
-        //  rotation,
-        //  state,
-        //  reloadc,
-        //  charge,
-        //  loaded,
-        //  charging
-        //}
-        //return result
+        return Ok(Some(SpecificBlockData::PayloadMassDriver {
+            payload_x: reader.float()?,
+            payload_y: reader.float()?,
+            payload_rotation: reader.float()?,
+            payload: read_payload(reader, content_map)?,
+            link: reader.int()?,
+            rotation: reader.float()?,
+            state: reader.byte()?,
+            reload_counter: reader.float()?,
+            charge: reader.float()?,
+            loaded: reader.bool()?,
+            charging: reader.bool()?,
+        }));
     } else if block_type == "PayloadDeconstructor" {
-        let px = reader.float();
-        let py = reader.float();
-        let prot = reader.float();
-        let payload = read_payload(reader, content_map);
-        let progress = reader.float();
-        let accums = reader.short();
-        //let accum = [];
-        for i in 0..accums {
-            /*accum[i] =*/
-            reader.float();
-        }
-        //let decp;
-        //[decp, offset] = rpl(buf, offset);
-        //let result = {
-        //  px,
-        //  py,
-        //  prot,
-        //  payload,
-        //  progress,
-        //  accum,
-        //  decp
-        //}
-        //return result
+        let payload_x = reader.float()?;
+        let payload_y = reader.float()?;
+        let payload_rotation = reader.float()?;
+        let payload = read_payload(reader, content_map)?;
+        let progress = reader.float()?;
+        let accumulators = reader.short()?;
+        let mut accumulator = vec![];
+        for _ in 0..accumulators {
+            accumulator.push(reader.float()?);
+        }
+        let deconstructing = read_payload(reader, content_map)?;
+        return Ok(Some(SpecificBlockData::PayloadDeconstructor {
+            payload_x,
+            payload_y,
+            payload_rotation,
+            payload,
+            progress,
+            accumulator,
+            deconstructing,
+        }));
     } else if block_type == "Constructor" {
-        let px = reader.float();
-        let py = reader.float();
-        let prot = reader.float();
-        let payload = read_payload(reader, content_map);
-        let progress = reader.float();
-        let rec = reader.short();
-        //let result = {
-        //  px,
-        //  py,
-        //  prot,
-        //  payload,
-        //  progress,
-        //  rec
-        //}
-        //return result
+        return Ok(Some(SpecificBlockData::Constructor {
+            payload_x: reader.float()?,
+            payload_y: reader.float()?,
+            payload_rotation: reader.float()?,
+            payload: read_payload(reader, content_map)?,
+            progress: reader.float()?,
+            recipe: reader.short()?,
+        }));
     } else if block_type == "PayloadLoader" {
-        let px = reader.float();
-        let py = reader.float();
-        let prot = reader.float();
-        let payload = read_payload(reader, content_map);
-        let exporting = reader.byte();
-        //let result = {
-        //  px,
-        //  py,
-        //  prot,
-        //  payload,
-        //  exporting
-        //}
-        //return result
+        return Ok(Some(SpecificBlockData::PayloadLoader {
+            payload_x: reader.float()?,
+            payload_y: reader.float()?,
+            payload_rotation: reader.float()?,
+            payload: read_payload(reader, content_map)?,
+            exporting: reader.bool()?,
+        }));
     } else if block_type == "ItemSource" {
-        return Some(SpecificBlockData::ItemSource {
-            item_id: reader.short(),
-        });
+        return Ok(Some(SpecificBlockData::ItemSource {
+            item_id: reader.short()?,
+        }));
     } else if block_type == "LiquidSource" {
-        return Some(SpecificBlockData::LiquidSource {
-            liquid_id: reader.short(),
-        });
+        return Ok(Some(SpecificBlockData::LiquidSource {
+            liquid_id: reader.short()?,
+        }));
     } else if block_type == "PayloadSource" {
-        let px = reader.float();
-        let py = reader.float();
-        let prot = reader.float();
-        let payload = read_payload(reader, content_map);
-        let unit = reader.short();
-        let block = reader.short();
-        //let result = {
-        //  px,
-        //  py,
-        //  prot,
-        //  payload,
-        //  unit,
-        //  block
-        //}
-        //return result
+        return Ok(Some(SpecificBlockData::PayloadSource {
+            payload_x: reader.float()?,
+            payload_y: reader.float()?,
+            payload_rotation: reader.float()?,
+            payload: read_payload(reader, content_map)?,
+            unit: reader.short()?,
+            block: reader.short()?,
+        }));
     } else if block_type == "LightBlock" {
-        return Some(SpecificBlockData::LightBlock {
-            color: reader.int(),
-        });
+        return Ok(Some(SpecificBlockData::LightBlock {
+            color: reader.int()?,
+        }));
     } else if block_type == "LaunchPad" {
-        let lc = reader.float();
-        //let result = {
-        //  lc
-        //}
-        //return result
+        return Ok(Some(SpecificBlockData::LaunchPad {
+            launch_counter: reader.float()?,
+        }));
     } else if block_type == "Accelerator" {
-        return Some(SpecificBlockData::Accelerator {
-            progress: reader.float(),
-        });
+        return Ok(Some(SpecificBlockData::Accelerator {
+            progress: reader.float()?,
+        }));
     } else if block_type == "MessageBlock" {
-        return Some(SpecificBlockData::Message {
-            message: read_string(reader),
-        });
+        return Ok(Some(SpecificBlockData::Message {
+            message: read_string(reader)?,
+        }));
     } else if block_type == "SwitchBlock" {
-        return Some(SpecificBlockData::Switch {
-            enabled: reader.bool(),
-        });
+        return Ok(Some(SpecificBlockData::Switch {
+            enabled: reader.bool()?,
+        }));
     } else if block_type == "ConsumeGenerator"
         || block_type == "ThermalGenerator"
         || block_type == "SolarGenerator"
     {
-        let pe = reader.float();
-        let gentime = reader.float();
-        //let result = {
-        //  pe,
-        //  gentime
-        //}
-        //return result
+        return Ok(Some(SpecificBlockData::ConsumeGenerator {
+            productivity: reader.float()?,
+            generate_time: reader.float()?,
+        }));
     } else if block_type == "StackRouter" {
-        let sortitem = reader.short();
-        //let result = {
-        //  sortitem
-        //}
-        //return result
+        return Ok(Some(SpecificBlockData::StackRouter {
+            sort_item: reader.short()?,
+        }));
     } else if block_type == "LiquidTurret" {
         if version >= 1 {
-            return Some(SpecificBlockData::LiquidTurret {
-                reload_counter: reader.float(),
-                rotation: reader.float(),
-            });
+            return Ok(Some(SpecificBlockData::LiquidTurret {
+                reload_counter: reader.float()?,
+                rotation: reader.float()?,
+            }));
         }
     } else if block_type == "PowerTurret" {
         if version >= 1 {
-            return Some(SpecificBlockData::PowerTurret {
-                reload_counter: reader.float(),
-                rotation: reader.float(),
-            });
+            return Ok(Some(SpecificBlockData::PowerTurret {
+                reload_counter: reader.float()?,
+                rotation: reader.float()?,
+            }));
         }
     } else if block_type == "LaserTurret" {
         if version >= 1 {
-            return Some(SpecificBlockData::LaserTurret {
-                reload_counter: reader.float(),
-                rotation: reader.float(),
-            });
+            return Ok(Some(SpecificBlockData::LaserTurret {
+                reload_counter: reader.float()?,
+                rotation: reader.float()?,
+            }));
         }
     } else if block_type == "UnitAssemblerModule" {
-        let px = reader.float();
-        let py = reader.float();
-        let prot = reader.float();
-        let payload = read_payload(reader, content_map);
-        //let result = {
-        //  px,
-        //  py,
-        //  prot,
-        //  payload
-        //}
-        //return result
+        return Ok(Some(SpecificBlockData::UnitAssemblerModule {
+            payload_x: reader.float()?,
+            payload_y: reader.float()?,
+            payload_rotation: reader.float()?,
+            payload: read_payload(reader, content_map)?,
+        }));
     } else if block_type == "MemoryBlock" {
-        let amount = reader.int();
+        let amount = reader.int()?;
         let mut memory = vec![];
 
         for _ in 0..amount {
-            let value = reader.double();
+            let value = reader.double()?;
             memory.push(value)
         }
 
-        return Some(SpecificBlockData::Memory { memory });
+        return Ok(Some(SpecificBlockData::Memory { memory }));
     } else if block_type == "LogicDisplay" {
         if version >= 1 {
-            let has_transform = reader.bool();
-            //let map = [];
-            if has_transform {
-                for i in 0..9 {
-                    let val = reader.float();
-                    //map[i] = val
+            let has_transform = reader.bool()?;
+            let transform = if has_transform {
+                let mut values = vec![];
+                for _ in 0..9 {
+                    values.push(reader.float()?);
                 }
-            }
-            //let result = {
-            //  map
-            //}
-            //return result
+                Some(values)
+            } else {
+                None
+            };
+            return Ok(Some(SpecificBlockData::LogicDisplay { transform }));
         }
     } else if block_type == "LogicBlock" {
-        if version >= 1 {
-            let compl = reader.int();
-            //byte[] bytes = new byte[compl];
-            reader.bytes(compl as usize);
-            //readCompressed(bytes, false);
+        let program = if version >= 1 {
+            let compressed_length = reader.int()?;
+            LogicProgram::Compressed(reader.bytes(compressed_length as usize)?)
         } else {
-            let code = read_string(reader);
-            //links.clear();
-            let total = reader.short();
+            let code = read_string(reader)?;
+            let total = reader.short()?;
+            let mut links = vec![];
             for _ in 0..total {
-                reader.int();
+                links.push(reader.int()?);
             }
-        }
+            LogicProgram::Legacy { code, links }
+        };
 
-        let varcount = reader.int();
+        let variable_count = reader.int()?;
+        let mut variables = vec![];
+        for _ in 0..variable_count {
+            let name = read_string(reader)?;
+            let value = read_object_boxed(reader, true)?;
+            variables.push((name, value));
+        }
 
-        //variables need to be temporarily stored in an array until they can be used
-        //String[] names = new String[varcount];
-        //Object[] values = new Object[varcount];
+        // Memory isn't used anymore, but is still sent
+        let memory_size = reader.int()?;
+        let mut memory = vec![];
+        for _ in 0..memory_size {
+            memory.push(reader.double()?);
+        }
 
-        for i in 0..varcount {
-            let name = read_string(reader);
-            let value = read_object_boxed(reader, true);
+        let instructions_per_tick = if block_name == "world-processor" && version >= 2 {
+            Some(reader.short()?)
+        } else {
+            None
+        };
 
-            //names[i] = name;
-            //values[i] = value;
+        let mut tag = None;
+        let mut icon_tag = None;
+        if version >= 3 {
+            tag = read_prefixed_string(reader)?;
+            icon_tag = Some(reader.unsigned_short()?);
         }
 
-        let memory = reader.int();
-        //skip memory, it isn't used anymore
-        reader.bytes((memory * 8) as usize);
+        return Ok(Some(SpecificBlockData::LogicBlock {
+            program,
+            variables,
+            memory,
+            instructions_per_tick,
+            tag,
+            icon_tag,
+        }));
+    } else if block_type == "CanvasBlock" {
+        let length = reader.int()?;
+        let bytes = reader.bytes(length as usize)?;
+        return Ok(Some(SpecificBlockData::Canvas { data: bytes }));
+    } else if block_type.starts_with("Build") {
+        let progress = reader.float()?;
+        let previous_block = reader.short()?;
+        let current_block = reader.short()?;
+        let accumulator_size = reader.byte()?;
+        let accumulators = if accumulator_size != 255 {
+            let mut accumulators = vec![];
+            for _ in 0..accumulator_size {
+                accumulators.push(BuildAccumulator {
+                    accumulator: reader.float()?,
+                    total_accumulator: reader.float()?,
+                    items_left: if version >= 1 {
+                        Some(reader.int()?)
+                    } else {
+                        None
+                    },
+                });
+            }
+            Some(accumulators)
+        } else {
+            None
+        };
+        return Ok(Some(SpecificBlockData::Build {
+            progress,
+            previous_block,
+            current_block,
+            accumulators,
+        }));
+    } else if block_type == "CoreBlock" && version >= 1 {
+        return Ok(Some(SpecificBlockData::CoreBlock {
+            command_position: read_vec2_nullable(reader)?,
+        }));
+    }
+
+    Ok(None)
+}
+
+fn write_payload_header(
+    buf: &mut Vec<u8>,
+    payload_x: f32,
+    payload_y: f32,
+    payload_rotation: f32,
+    payload: Option<Payload>,
+) -> Result<(), PacketError> {
+    write_float(buf, payload_x);
+    write_float(buf, payload_y);
+    write_float(buf, payload_rotation);
+    write_payload(buf, payload)?;
+    Ok(())
+}
 
-        //loadBlock = () -> updateCode(code, false, asm -> {
-        //  //load up the variables that were stored
-        //  for(int i = 0; i < varcount; i++){
-        //    LVar var = asm.getVar(names[i]);
-        //    if(var != null && (!var.constant || var.name.equals("@unit"))){
-        //      var value = values[i];
-        //      if(value instanceof Boxed<?> boxed) value = boxed.unbox();
-        //
-        //      if(value instanceof Number num){
-        //        var.numval = num.doubleValue();
-        //        var.isobj = false;
-        //      }else{
-        //        var.objval = value;
-        //        var.isobj = true;
-        //      }
-        //    }
-        //  }
-        //});
+fn write_optional_float(buf: &mut Vec<u8>, value: Option<f32>) {
+    if let Some(value) = value {
+        write_float(buf, value);
+    }
+}
 
-        if block_name == "world-processor" && version >= 2 {
-            reader.short();
-          //ipt = Mathf.clamp(read.s(), 1, maxInstructionsPerTick);
+fn write_specific_block_data(
+    buf: &mut Vec<u8>,
+    data: SpecificBlockData,
+    version: u8,
+) -> Result<(), PacketError> {
+    match data {
+        SpecificBlockData::GenericCrafter {
+            progress,
+            warmup,
+            cultivator_warmup,
+            heat,
+            seed,
+        } => {
+            write_float(buf, progress);
+            write_float(buf, warmup);
+            write_optional_float(buf, cultivator_warmup);
+            write_optional_float(buf, heat);
+            if let Some(seed) = seed {
+                write_int(buf, seed);
+            }
+        }
+        SpecificBlockData::Door { open } => write_bool(buf, open),
+        SpecificBlockData::ShieldWall { shield } => write_float(buf, shield),
+        SpecificBlockData::MendProjector { heat, phase_heat }
+        | SpecificBlockData::OverdriveProjector { heat, phase_heat } => {
+            write_float(buf, heat);
+            write_float(buf, phase_heat);
+        }
+        SpecificBlockData::ForceProjector {
+            broken,
+            buildup,
+            radius_scale,
+            warmup,
+            phase_heat,
+        } => {
+            write_bool(buf, broken);
+            write_float(buf, buildup);
+            write_float(buf, radius_scale);
+            write_float(buf, warmup);
+            write_float(buf, phase_heat);
+        }
+        SpecificBlockData::Radar { progress } => write_float(buf, progress),
+        SpecificBlockData::BuildTurret { rotation, plans } => {
+            write_float(buf, rotation);
+            write_plans(buf, plans)?;
+        }
+        SpecificBlockData::BaseShield {
+            smooth_radius,
+            broken,
+        } => {
+            write_float(buf, smooth_radius);
+            write_bool(buf, broken);
+        }
+        SpecificBlockData::Conveyor { items } => {
+            write_int(buf, items.len() as u32);
+            for item in items {
+                let x = (item.x * 127.0).round() as i8 as u8;
+                let y = (item.y * 255.0 - 128.0).round() as i8 as u8;
+                if version == 0 {
+                    write_int(
+                        buf,
+                        ((item.item_id as u32 & 0xff) << 24)
+                            | ((x as u32) << 16)
+                            | ((y as u32) << 8),
+                    );
+                } else {
+                    write_short(buf, item.item_id);
+                    write_byte(buf, x);
+                    write_byte(buf, y);
+                }
+            }
+        }
+        SpecificBlockData::StackConveyor { link, cooldown } => {
+            write_int(buf, link);
+            write_float(buf, cooldown);
+        }
+        SpecificBlockData::Junction { buffer } => buffer.write(buf),
+        SpecificBlockData::ItemBridge {
+            link,
+            warmup,
+            incoming,
+            moved,
+            buffer,
+        } => {
+            write_int(buf, link);
+            write_float(buf, warmup);
+            write_byte(buf, incoming.len() as u8);
+            for position in incoming {
+                write_int(buf, position);
+            }
+            if let Some(moved) = moved {
+                write_bool(buf, moved);
+            }
+            if let Some(buffer) = buffer {
+                write_byte(buf, buffer.index);
+                write_byte(buf, buffer.values.len() as u8);
+                for value in buffer.values {
+                    write_long(buf, value);
+                }
+            }
+        }
+        SpecificBlockData::Sorter { sort_item, buffer } => {
+            write_short(buf, sort_item);
+            if let Some(buffer) = buffer {
+                buffer.write(buf);
+            }
+        }
+        SpecificBlockData::OverflowGate { buffer } => {
+            if let Some(buffer) = buffer {
+                buffer.write(buf);
+            }
+        }
+        SpecificBlockData::MassDriver {
+            link,
+            rotation,
+            state,
+        } => {
+            write_int(buf, link);
+            write_float(buf, rotation);
+            write_byte(buf, state.into());
+        }
+        SpecificBlockData::Duct { received_direction } => {
+            if let Some(received_direction) = received_direction {
+                write_byte(buf, received_direction);
+            }
+        }
+        SpecificBlockData::DuctRouter { sort_item } => {
+            if let Some(sort_item) = sort_item {
+                write_short(buf, sort_item);
+            }
+        }
+        SpecificBlockData::DirectionalUnloader { item_id, offset } => {
+            write_short(buf, item_id);
+            write_short(buf, offset);
+        }
+        SpecificBlockData::UnitCargoLoader { unit_id } => write_int(buf, unit_id),
+        SpecificBlockData::UnitCargoUnloadPoint { item_id, stale } => {
+            write_short(buf, item_id);
+            write_bool(buf, stale);
+        }
+        SpecificBlockData::Reactor {
+            productivity,
+            generate_time,
+            heat,
+            instability,
+            warmup,
+        } => {
+            write_float(buf, productivity);
+            write_optional_float(buf, generate_time);
+            write_optional_float(buf, heat);
+            write_optional_float(buf, instability);
+            write_optional_float(buf, warmup);
+        }
+        SpecificBlockData::HeaterGenerator { heat } => write_float(buf, heat),
+        SpecificBlockData::Drill {
+            progress,
+            time,
+            warmup,
+        } => {
+            write_optional_float(buf, progress);
+            write_optional_float(buf, time);
+            write_optional_float(buf, warmup);
+        }
+        SpecificBlockData::Unloader { item_id } => {
+            if version == 1 {
+                write_short(buf, item_id);
+            } else {
+                write_byte(buf, item_id as u8);
+            }
+        }
+        SpecificBlockData::ItemTurret {
+            reload_counter,
+            rotation,
+            ammo,
+        } => {
+            write_float(buf, reload_counter);
+            write_float(buf, rotation);
+            write_byte(buf, ammo.len() as u8);
+            for (item, count) in ammo {
+                write_short(buf, item);
+                write_short(buf, count);
+            }
+        }
+        SpecificBlockData::TractorBeamTurret { rotation }
+        | SpecificBlockData::PointDefenseTurret { rotation }
+        | SpecificBlockData::RepairTurret { rotation } => write_float(buf, rotation),
+        SpecificBlockData::ContinuousTurret {
+            reload_counter,
+            rotation,
+            last_length,
+        } => {
+            write_optional_float(buf, reload_counter);
+            write_optional_float(buf, rotation);
+            write_optional_float(buf, last_length);
+        }
+        SpecificBlockData::UnitFactory {
+            payload_x,
+            payload_y,
+            payload_rotation,
+            payload,
+            progress,
+            current_plan,
+            command_position,
+            command,
+        } => {
+            write_payload_header(buf, payload_x, payload_y, payload_rotation, payload)?;
+            write_optional_float(buf, progress);
+            if let Some(current_plan) = current_plan {
+                write_short(buf, current_plan);
+            }
+            if let Some(command_position) = command_position {
+                write_vec2(buf, command_position);
+            }
+            if let Some(command) = command {
+                write_byte(buf, command);
+            }
+        }
+        SpecificBlockData::UnitAssembler {
+            payload_x,
+            payload_y,
+            payload_rotation,
+            payload,
+            progress,
+            units,
+            blocks,
+            command_position,
+        } => {
+            write_payload_header(buf, payload_x, payload_y, payload_rotation, payload)?;
+            write_float(buf, progress);
+            write_byte(buf, units.len() as u8);
+            for unit in units {
+                write_int(buf, unit);
+            }
+            write_payload_seq(buf, blocks);
+            if let Some(command_position) = command_position {
+                write_vec2(buf, command_position);
+            }
+        }
+        SpecificBlockData::PayloadConveyor {
+            progress,
+            item_rotation,
+            item,
+            sort,
+            received_direction,
+        } => {
+            write_float(buf, progress);
+            write_float(buf, item_rotation);
+            write_payload(buf, item)?;
+            if let Some(sort) = sort {
+                write_content(buf, sort);
+            }
+            if let Some(received_direction) = received_direction {
+                write_byte(buf, received_direction);
+            }
+        }
+        SpecificBlockData::PayloadMassDriver {
+            payload_x,
+            payload_y,
+            payload_rotation,
+            payload,
+            link,
+            rotation,
+            state,
+            reload_counter,
+            charge,
+            loaded,
+            charging,
+        } => {
+            write_payload_header(buf, payload_x, payload_y, payload_rotation, payload)?;
+            write_int(buf, link);
+            write_float(buf, rotation);
+            write_byte(buf, state);
+            write_float(buf, reload_counter);
+            write_float(buf, charge);
+            write_bool(buf, loaded);
+            write_bool(buf, charging);
+        }
+        SpecificBlockData::PayloadDeconstructor {
+            payload_x,
+            payload_y,
+            payload_rotation,
+            payload,
+            progress,
+            accumulator,
+            deconstructing,
+        } => {
+            write_payload_header(buf, payload_x, payload_y, payload_rotation, payload)?;
+            write_float(buf, progress);
+            write_short(buf, accumulator.len() as i16);
+            for value in accumulator {
+                write_float(buf, value);
+            }
+            write_payload(buf, deconstructing)?;
+        }
+        SpecificBlockData::Constructor {
+            payload_x,
+            payload_y,
+            payload_rotation,
+            payload,
+            progress,
+            recipe,
+        } => {
+            write_payload_header(buf, payload_x, payload_y, payload_rotation, payload)?;
+            write_float(buf, progress);
+            write_short(buf, recipe);
+        }
+        SpecificBlockData::PayloadLoader {
+            payload_x,
+            payload_y,
+            payload_rotation,
+            payload,
+            exporting,
+        } => {
+            write_payload_header(buf, payload_x, payload_y, payload_rotation, payload)?;
+            write_bool(buf, exporting);
+        }
+        SpecificBlockData::ItemSource { item_id } => write_short(buf, item_id),
+        SpecificBlockData::LiquidSource { liquid_id } => write_short(buf, liquid_id),
+        SpecificBlockData::PayloadSource {
+            payload_x,
+            payload_y,
+            payload_rotation,
+            payload,
+            unit,
+            block,
+        } => {
+            write_payload_header(buf, payload_x, payload_y, payload_rotation, payload)?;
+            write_short(buf, unit);
+            write_short(buf, block);
+        }
+        SpecificBlockData::LightBlock { color } => write_int(buf, color),
+        SpecificBlockData::LaunchPad { launch_counter } => write_float(buf, launch_counter),
+        SpecificBlockData::Accelerator { progress } => write_float(buf, progress),
+        SpecificBlockData::Message { message } => write_utf(buf, &message.unwrap_or_default())?,
+        SpecificBlockData::Switch { enabled } => write_bool(buf, enabled),
+        SpecificBlockData::ConsumeGenerator {
+            productivity,
+            generate_time,
+        } => {
+            write_float(buf, productivity);
+            write_float(buf, generate_time);
+        }
+        SpecificBlockData::StackRouter { sort_item } => write_short(buf, sort_item),
+        SpecificBlockData::LiquidTurret {
+            reload_counter,
+            rotation,
+        }
+        | SpecificBlockData::PowerTurret {
+            reload_counter,
+            rotation,
+        }
+        | SpecificBlockData::LaserTurret {
+            reload_counter,
+            rotation,
+        } => {
+            write_float(buf, reload_counter);
+            write_float(buf, rotation);
+        }
+        SpecificBlockData::UnitAssemblerModule {
+            payload_x,
+            payload_y,
+            payload_rotation,
+            payload,
+        } => write_payload_header(buf, payload_x, payload_y, payload_rotation, payload)?,
+        SpecificBlockData::Memory { memory } => {
+            write_int(buf, memory.len() as u32);
+            for value in memory {
+                write_double(buf, value);
+            }
+        }
+        SpecificBlockData::LogicDisplay { transform } => {
+            write_bool(buf, transform.is_some());
+            for value in transform.unwrap_or_default() {
+                write_float(buf, value);
+            }
         }
+        SpecificBlockData::LogicBlock {
+            program,
+            variables,
+            memory,
+            instructions_per_tick,
+            tag,
+            icon_tag,
+        } => {
+            match program {
+                LogicProgram::Compressed(bytes) => {
+                    write_int(buf, bytes.len() as u32);
+                    buf.extend_from_slice(&bytes);
+                }
+                LogicProgram::Legacy { code, links } => {
+                    write_utf(buf, &code.unwrap_or_default())?;
+                    write_short(buf, links.len() as i16);
+                    for link in links {
+                        write_int(buf, link);
+                    }
+                }
+            }
 
-        if version >= 3 {
-            let tag = read_prefixed_string(reader);
-            let iconTag = reader.unsigned_short();
+            write_int(buf, variables.len() as u32);
+            for (name, value) in variables {
+                write_utf(buf, &name.unwrap_or_default())?;
+                write_object(buf, value)?;
+            }
+
+            write_int(buf, memory.len() as u32);
+            for value in memory {
+                write_double(buf, value);
+            }
+
+            if let Some(instructions_per_tick) = instructions_per_tick {
+                write_short(buf, instructions_per_tick);
+            }
+
+            if let Some(icon_tag) = icon_tag {
+                write_prefixed_string(buf, tag.as_deref())?;
+                write_unsigned_short(buf, icon_tag);
+            }
         }
-    } else if block_type == "CanvasBlock" {
-        let length = reader.int();
-        let bytes = reader.bytes(length as usize);
-        return Some(SpecificBlockData::Canvas { data: bytes });
-    } else if block_type.starts_with("Build") {
-        let progress = reader.float();
-        let pid = reader.short();
-        let rid = reader.short();
-        let acsize = reader.byte();
-        //let acc = []
-        //let totalacc = []
-        //let itemsLeft = []
-        if acsize != 255 {
-            for i in 0..acsize {
-                /*acc[i] =*/
-                reader.float();
-                /*totalacc[i] =*/
-                reader.float();
-                if version >= 1 {
-                    /*itemsLeft[i] =*/
-                    reader.int();
+        SpecificBlockData::Canvas { data } => {
+            write_int(buf, data.len() as u32);
+            buf.extend_from_slice(&data);
+        }
+        SpecificBlockData::Build {
+            progress,
+            previous_block,
+            current_block,
+            accumulators,
+        } => {
+            write_float(buf, progress);
+            write_short(buf, previous_block);
+            write_short(buf, current_block);
+            match accumulators {
+                Some(accumulators) => {
+                    write_byte(buf, accumulators.len() as u8);
+                    for accumulator in accumulators {
+                        write_float(buf, accumulator.accumulator);
+                        write_float(buf, accumulator.total_accumulator);
+                        if let Some(items_left) = accumulator.items_left {
+                            write_int(buf, items_left);
+                        }
+                    }
                 }
+                None => write_byte(buf, 255),
             }
         }
-        //let result = {
-        //  progress,
-        //  pid,
-        //  rid,
-        //  acsize,
-        //  acc,
-        //  totalacc,
-        //  itemsLeft
-        //}
-        //return result
-    } else if block_type == "CoreBlock" {
-        // TODO
-        if version >= 1 {
-            read_vec2_nullable(reader);
+        SpecificBlockData::CoreBlock { command_position } => write_vec2(buf, command_position),
+    }
+    Ok(())
+}
+
+fn read_payload_seq(reader: &mut Reader) -> Result<Vec<PayloadStack>, PacketError> {
+    let amount = reader.short()?;
+    let mut stacks = vec![];
+
+    if amount >= 0 {
+        // Legacy format, only contains units
+        for _ in 0..amount {
+            stacks.push(PayloadStack {
+                content_type: 6,
+                id: reader.short()?,
+                count: reader.int()?,
+            });
         }
     } else {
-        return None;
+        for _ in 0..-amount {
+            stacks.push(PayloadStack {
+                content_type: reader.byte()?,
+                id: reader.short()?,
+                count: reader.int()?,
+            });
+        }
     }
 
-    // println!("Unknown block type: {block_type}");
-    None
+    Ok(stacks)
 }
 
-fn read_payload_seq(reader: &mut Reader) {
-    let amount = reader.short();
-    //let ent = []
-    for i in 0..(-1 * amount as i16) {
-        let payload_type = reader.byte();
-        let entr = reader.short();
-        let count = reader.int();
+fn write_payload_seq(buf: &mut Vec<u8>, stacks: Vec<PayloadStack>) {
+    write_short(buf, -(stacks.len() as i16));
+    for stack in stacks {
+        write_byte(buf, stack.content_type);
+        write_short(buf, stack.id);
+        write_int(buf, stack.count);
     }
-    //return ent
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct BaseBlockData {
     pub health: f32,
     pub rotation: u8,
@@ -953,69 +1524,90 @@ pub struct BaseBlockData {
     pub team: u8,
     pub module_bitmask: u8,
     pub items: Option<HashMap<i16, u32>>,
-    pub liquids: Option<HashMap<i16, u32>>,
+    pub liquids: Option<HashMap<i16, f32>>,
     pub power: Option<BlockPowerData>,
+    pub efficiency: Option<u8>,
+    pub optional_efficiency: Option<u8>,
+    pub visible_flags: Option<u64>,
 }
-fn read_base_block_data(reader: &mut Reader, id: String) -> BaseBlockData {
-    let block_params = load_block_params();
-
-    let health = reader.float();
+fn read_base_block_data(
+    reader: &mut Reader,
+    id: &str,
+    content_map: &ContentRegistry,
+) -> Result<BaseBlockData, PacketError> {
+    let health = reader.float()?;
 
-    let rotation_byte = reader.byte();
+    let rotation_byte = reader.byte()?;
     let rotation = rotation_byte & 0b01111111;
 
-    let team = reader.byte();
+    let team = reader.byte()?;
     let mut version = 0;
 
     let mut legacy = true;
     let mut on = None;
 
     let mut module_bitmask = 0;
-    if block_params.contains_key(&id) {
-        module_bitmask = get_module_bitmask(id, block_params)
+    if let Some(info) = content_map
+        .block_id(id)
+        .and_then(|block| content_map.block_info(block))
+    {
+        module_bitmask = get_module_bitmask(info)
     }
 
     if (rotation_byte & 0b10000000) != 0 {
-        version = reader.byte();
+        version = reader.byte()?;
+        let protocol = reader.version();
+        if version > protocol.max_building_revision() {
+            return Err(reader.error(format!(
+                "building revision {version} is not used by build {}",
+                protocol.build()
+            )));
+        }
         if version >= 1 {
-            on = Some(reader.byte());
+            on = Some(reader.byte()?);
         }
         if version >= 2 {
-            module_bitmask = reader.byte();
+            module_bitmask = reader.byte()?;
         }
         legacy = false;
     }
 
     let items = if (module_bitmask & 1) != 0 {
-        Some(read_block_items(reader, legacy))
+        Some(read_block_items(reader, legacy)?)
     } else {
         None
     };
 
     let power = if (module_bitmask & 2) != 0 {
-        Some(read_block_power(reader))
+        Some(read_block_power(reader)?)
     } else {
         None
     };
 
     let liquids = if (module_bitmask & 4) != 0 {
-        Some(read_block_liquids(reader, legacy))
+        Some(read_block_liquids(reader, legacy)?)
     } else {
         None
     };
 
     if version <= 2 {
-        reader.byte();
+        reader.byte()?;
     }
 
-    let eff;
-    let opteff;
+    let mut efficiency = None;
+    let mut optional_efficiency = None;
     if version >= 3 {
-        eff = reader.byte();
-        opteff = reader.byte();
+        efficiency = Some(reader.byte()?);
+        optional_efficiency = Some(reader.byte()?);
     }
 
-    BaseBlockData {
+    let visible_flags = if version == 4 {
+        Some(reader.long()?)
+    } else {
+        None
+    };
+
+    Ok(BaseBlockData {
         health,
         rotation,
         team,
@@ -1026,90 +1618,178 @@ fn read_base_block_data(reader: &mut Reader, id: String) -> BaseBlockData {
         items,
         power,
         liquids,
-        //eff,  TODO
-        //opteff
-    }
+        efficiency,
+        optional_efficiency,
+        visible_flags,
+    })
 }
 
-fn get_module_bitmask(id: String, block_parms: HashMap<String, BlockParam>) -> u8 {
-    let has_items = block_parms.get(&id).unwrap().has_items;
-    let has_power = block_parms.get(&id).unwrap().has_power;
-    let has_liquids = block_parms.get(&id).unwrap().has_liquids;
+fn write_base_block_data(buf: &mut Vec<u8>, data: BaseBlockData) {
+    write_float(buf, data.health);
+
+    let rotation_byte = if data.legacy {
+        data.rotation
+    } else {
+        data.rotation | 0b10000000
+    };
+    write_byte(buf, rotation_byte);
+    write_byte(buf, data.team);
+
+    if !data.legacy {
+        write_byte(buf, data.version);
+        if data.version >= 1 {
+            write_byte(buf, data.on.unwrap_or(1));
+        }
+        if data.version >= 2 {
+            write_byte(buf, data.module_bitmask);
+        }
+    }
+
+    if (data.module_bitmask & 1) != 0 {
+        write_block_items(buf, data.items.unwrap_or_default(), data.legacy);
+    }
+    if (data.module_bitmask & 2) != 0 {
+        write_block_power(
+            buf,
+            data.power.unwrap_or(BlockPowerData {
+                links: vec![],
+                status: 0.0,
+            }),
+        );
+    }
+    if (data.module_bitmask & 4) != 0 {
+        write_block_liquids(buf, data.liquids.unwrap_or_default(), data.legacy);
+    }
+
+    if data.version <= 2 {
+        write_byte(buf, 0);
+    }
+
+    if data.version >= 3 {
+        write_byte(buf, data.efficiency.unwrap_or(0));
+        write_byte(buf, data.optional_efficiency.unwrap_or(0));
+    }
 
-    let a = if has_items { 1 } else { 0 };
-    let b = if has_power { 2 } else { 0 };
-    let c = if has_liquids { 4 } else { 0 };
+    if data.version == 4 {
+        write_long(buf, data.visible_flags.unwrap_or(0));
+    }
+}
+
+fn get_module_bitmask(info: &BlockInfo) -> u8 {
+    let a = if info.has_items { 1 } else { 0 };
+    let b = if info.has_power { 2 } else { 0 };
+    let c = if info.has_liquids { 4 } else { 0 };
     a | b | c | 8
 }
 
-fn read_block_items(reader: &mut Reader, legacy: bool) -> HashMap<i16, u32> {
+pub(crate) fn read_block_items(
+    reader: &mut Reader,
+    legacy: bool,
+) -> Result<HashMap<i16, u32>, PacketError> {
     let count = if legacy {
-        reader.byte() as i16
+        reader.byte()? as i16
     } else {
-        reader.short()
+        reader.short()?
     };
 
     let mut items = HashMap::new();
     for _ in 0..count {
         let item_id = if legacy {
-            reader.byte() as i16
+            reader.byte()? as i16
         } else {
-            reader.short()
+            reader.short()?
         };
-        let item_amount = reader.int();
+        let item_amount = reader.int()?;
         items.insert(item_id, item_amount);
     }
-    items
+    Ok(items)
 }
 
-fn read_block_liquids(reader: &mut Reader, legacy: bool) -> HashMap<i16, u32> {
+pub(crate) fn write_block_items(buf: &mut Vec<u8>, items: HashMap<i16, u32>, legacy: bool) {
+    if legacy {
+        write_byte(buf, items.len() as u8);
+    } else {
+        write_short(buf, items.len() as i16);
+    }
+
+    for (item_id, item_amount) in items {
+        if legacy {
+            write_byte(buf, item_id as u8);
+        } else {
+            write_short(buf, item_id);
+        }
+        write_int(buf, item_amount);
+    }
+}
+
+fn read_block_liquids(reader: &mut Reader, legacy: bool) -> Result<HashMap<i16, f32>, PacketError> {
     let count = if legacy {
-        reader.byte() as i16
+        reader.byte()? as i16
     } else {
-        reader.short()
+        reader.short()?
     };
 
     let mut liquids = HashMap::new();
     for _ in 0..count {
         let liquid_id = if legacy {
-            reader.byte() as i16
+            reader.byte()? as i16
         } else {
-            reader.short()
+            reader.short()?
         };
-        let liquid_amount = reader.int();
+        let liquid_amount = reader.float()?;
         liquids.insert(liquid_id, liquid_amount);
     }
-    liquids
+    Ok(liquids)
+}
+
+fn write_block_liquids(buf: &mut Vec<u8>, liquids: HashMap<i16, f32>, legacy: bool) {
+    if legacy {
+        write_byte(buf, liquids.len() as u8);
+    } else {
+        write_short(buf, liquids.len() as i16);
+    }
+
+    for (liquid_id, liquid_amount) in liquids {
+        if legacy {
+            write_byte(buf, liquid_id as u8);
+        } else {
+            write_short(buf, liquid_id);
+        }
+        write_float(buf, liquid_amount);
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct BlockPowerData {
     pub links: Vec<Tile>,
     pub status: f32,
 }
-fn read_block_power(reader: &mut Reader) -> BlockPowerData {
-    let amount = reader.short();
+fn read_block_power(reader: &mut Reader) -> Result<BlockPowerData, PacketError> {
+    let amount = reader.short()?;
     let mut links = vec![];
 
     for _ in 0..amount {
-        let link = reader.int();
-        let data = Point2::unpack(link);
-        let tile = Tile {
-            x: data.x,
-            y: data.y,
-        };
-        links.push(tile)
+        links.push(Tile::unpack(reader.int()?))
     }
 
-    let status = reader.float();
+    let status = reader.float()?;
 
-    BlockPowerData { links, status }
+    Ok(BlockPowerData { links, status })
 }
 
-#[derive(Debug, Clone)]
+fn write_block_power(buf: &mut Vec<u8>, power: BlockPowerData) {
+    write_short(buf, power.links.len() as i16);
+    for link in power.links {
+        write_int(buf, link.pack());
+    }
+    write_float(buf, power.status);
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Block {
     pub name: String,
     pub block_type: String,
+    pub revision: u8,
     pub base: BaseBlockData,
     pub specific: Option<SpecificBlockData>,
 }
@@ -1119,9 +1799,25 @@ pub fn read_block(
     id: String,
     block_type: String,
     version: u8,
-    content_map: &HashMap<String, Vec<String>>,
-) -> Block {
-    let base = read_base_block_data(reader, id.clone());
-    let specific = read_specific_block_data(reader, id.clone(), block_type.clone(), version, content_map);
-    Block { name: id, block_type, base, specific }
+    content_map: &ContentRegistry,
+) -> Result<Block, PacketError> {
+    let base = read_base_block_data(reader, &id, content_map)?;
+    let specific =
+        read_specific_block_data(reader, id.clone(), block_type.clone(), version, content_map)?;
+    Ok(Block {
+        name: id,
+        block_type,
+        revision: version,
+        base,
+        specific,
+    })
+}
+
+/// Writes the building data of a block, without the revision byte in front of it
+pub fn write_block(buf: &mut Vec<u8>, block: Block) -> Result<(), PacketError> {
+    write_base_block_data(buf, block.base);
+    if let Some(specific) = block.specific {
+        write_specific_block_data(buf, specific, block.revision)?;
+    }
+    Ok(())
 }
@@ -0,0 +1,215 @@
+use crate::client::{ClientEvent, ClientInfo};
+use crate::packet::{AnyPacket, Packet, PacketError, parse_packet};
+use crate::protocol::ClientProtocol;
+use crate::type_io::{FieldContext, Reader, write_byte, write_int, write_long};
+use crate::version::ProtocolVersion;
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const MAGIC: &[u8; 4] = b"MCAP";
+const VERSION: u8 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, IntoPrimitive, TryFromPrimitive)]
+#[repr(u8)]
+pub enum Transport {
+    Tcp = 0,
+    Udp = 1,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, IntoPrimitive, TryFromPrimitive)]
+#[repr(u8)]
+pub enum Direction {
+    Inbound = 0,
+    Outbound = 1,
+}
+
+/// A raw frame as it was sent or received, still compressed and without the TCP
+/// length prefix
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaptureFrame {
+    /// Time since the recording started
+    pub timestamp: Duration,
+    pub transport: Transport,
+    pub direction: Direction,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Capture {
+    /// Milliseconds since the unix epoch at which the recording started
+    pub started: u64,
+    pub frames: Vec<CaptureFrame>,
+}
+
+#[derive(Debug)]
+pub enum CaptureError {
+    Io(std::io::Error),
+    Decode(PacketError),
+}
+impl fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CaptureError::Io(e) => write!(f, "{e}"),
+            CaptureError::Decode(e) => write!(f, "Invalid capture file: {e}"),
+        }
+    }
+}
+impl std::error::Error for CaptureError {}
+impl From<std::io::Error> for CaptureError {
+    fn from(e: std::io::Error) -> Self {
+        CaptureError::Io(e)
+    }
+}
+impl From<PacketError> for CaptureError {
+    fn from(e: PacketError) -> Self {
+        CaptureError::Decode(e)
+    }
+}
+
+/// Appends frames to a capture file as they pass through the client.
+///
+/// The file starts with `MCAP`, a version byte and the start time in unix
+/// milliseconds, followed by one record per frame: timestamp in microseconds (long),
+/// transport (byte), direction (byte), length (int) and the frame bytes.
+pub struct Recorder {
+    file: Mutex<File>,
+    start: Instant,
+}
+
+impl Recorder {
+    pub fn create(path: impl AsRef<Path>) -> Result<Recorder, CaptureError> {
+        let started = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+
+        let mut header = MAGIC.to_vec();
+        write_byte(&mut header, VERSION);
+        write_long(&mut header, started);
+
+        let mut file = File::create(path)?;
+        file.write_all(&header)?;
+
+        Ok(Recorder {
+            file: Mutex::new(file),
+            start: Instant::now(),
+        })
+    }
+
+    pub fn record(&self, transport: Transport, direction: Direction, data: &[u8]) {
+        let frame = CaptureFrame {
+            timestamp: self.start.elapsed(),
+            transport,
+            direction,
+            data: data.to_vec(),
+        };
+        let mut buf = vec![];
+        write_capture_frame(&mut buf, &frame);
+
+        let mut file = self.file.lock().unwrap();
+        if let Err(e) = file.write_all(&buf) {
+            tracing::error!("Failed to record frame: {e}");
+        }
+    }
+}
+
+pub fn write_capture_frame(buf: &mut Vec<u8>, frame: &CaptureFrame) {
+    write_long(buf, frame.timestamp.as_micros() as u64);
+    write_byte(buf, frame.transport.into());
+    write_byte(buf, frame.direction.into());
+    write_int(buf, frame.data.len() as u32);
+    buf.extend_from_slice(&frame.data);
+}
+
+pub fn read_capture_frame(reader: &mut Reader) -> Result<CaptureFrame, PacketError> {
+    let timestamp = Duration::from_micros(reader.long().field("timestamp")?);
+    let transport = reader.byte().field("transport")?;
+    let transport = Transport::try_from(transport)
+        .map_err(|_| reader.error(format!("unknown transport {transport}")))
+        .field("transport")?;
+    let direction = reader.byte().field("direction")?;
+    let direction = Direction::try_from(direction)
+        .map_err(|_| reader.error(format!("unknown direction {direction}")))
+        .field("direction")?;
+    let length = reader.int().field("length")?;
+    let data = reader.bytes(length as usize).field("data")?;
+
+    Ok(CaptureFrame {
+        timestamp,
+        transport,
+        direction,
+        data,
+    })
+}
+
+pub fn read_capture(path: impl AsRef<Path>) -> Result<Capture, CaptureError> {
+    let mut reader = Reader::new(fs::read(path)?);
+
+    let magic = reader.bytes(MAGIC.len()).field("magic")?;
+    if magic != MAGIC {
+        return Err(reader.error("not a capture file").into());
+    }
+    let version = reader.byte().field("version")?;
+    if version != VERSION {
+        return Err(reader
+            .error(format!("unsupported capture version {version}"))
+            .into());
+    }
+    let started = reader.long().field("started")?;
+
+    let mut frames = vec![];
+    while reader.remaining() > 0 {
+        let frame = read_capture_frame(&mut reader).field(&format!("frames[{}]", frames.len()))?;
+        frames.push(frame);
+    }
+
+    Ok(Capture { started, frames })
+}
+
+/// Feeds the inbound frames of a capture through a fresh protocol state machine,
+/// rebuilding its state exactly as it was during the recording. Returns the protocol
+/// together with every event it emitted.
+///
+/// Frames are decoded for the build of the recorded `Connect` packet, or the oldest
+/// supported build if the capture has none.
+pub fn replay(capture: &Capture) -> (ClientProtocol, Vec<ClientEvent>) {
+    let mut info = ClientInfo::new("replay".to_string());
+    if let Some(build) = recorded_build(capture) {
+        info.version = build;
+    }
+    let mut protocol = ClientProtocol::with_info(info, None);
+    let start = Instant::now();
+    let mut events = vec![];
+
+    for frame in &capture.frames {
+        if frame.direction != Direction::Inbound {
+            continue;
+        }
+        protocol.receive_frame(start + frame.timestamp, frame.data.clone());
+        while let Some(event) = protocol.poll_event() {
+            events.push(event);
+        }
+    }
+
+    (protocol, events)
+}
+
+/// The build the recording client connected with
+fn recorded_build(capture: &Capture) -> Option<u32> {
+    capture
+        .frames
+        .iter()
+        .filter(|frame| frame.direction == Direction::Outbound)
+        .find_map(|frame| {
+            // The layout of `Connect` is the same in every build
+            match parse_packet(frame.data.clone(), &None, ProtocolVersion::OLDEST) {
+                Ok(AnyPacket::Regular(Packet::Connect { version, .. })) => Some(version),
+                _ => None,
+            }
+        })
+}
@@ -1,24 +1,36 @@
-use crate::block_io::{BaseBlockData, Block, read_block};
-use crate::packet::{
-    AnyPacket, FrameworkPacket, Packet, read_packet_tcp, read_packet_udp, write_framework_packet,
-    write_packet,
-};
-use crate::save_io::{Map, load_block_types};
-use crate::stream_builder::StreamBuilder;
-use crate::type_io::{Reader, Tile, Unit, read_tile};
+use crate::block_io::Block;
+use crate::capture::{CaptureError, Direction, Recorder, Transport};
+use crate::codec::FrameCodec;
+use crate::content::{ContentRegistry, ContentStore};
+use crate::identity::{Identity, generate_id, is_valid_id};
+use crate::packet::Packet;
+use crate::protocol::{ClientProtocol, DEFAULT_TIMEOUT};
+use crate::rules::Rules;
+use crate::save_io::{Map, MapInfo, Marker, TeamPlan};
+use crate::type_io::{KickReason, Tile, Unit};
 use crate::unit_io::{FullUnit, Plan};
-use std::collections::HashMap;
-use std::fs;
-use std::path::PathBuf;
+use futures_util::StreamExt;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::{Mutex, RwLock};
+use tokio::sync::{Mutex, Notify};
 use tokio::{
     io::AsyncWriteExt,
-    net::{TcpStream, UdpSocket},
+    net::{TcpStream, ToSocketAddrs, UdpSocket},
+    sync::broadcast::{self, error::RecvError},
     sync::mpsc,
+    task::JoinHandle,
     time,
 };
+use tokio_util::codec::FramedRead;
+
+/// How many events a subscription can fall behind before it skips some
+const EVENT_CAPACITY: usize = 1024;
+/// How long opening the TCP connection may take, like the official client
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
 
 pub struct QueuedPacket {
     pub reliable: bool,
@@ -27,6 +39,8 @@ pub struct QueuedPacket {
 
 pub struct State {
     pub player_id: u32,
+    pub team: u8,
+    pub admin: bool,
     pub unit: Unit,
     pub x: f32,
     pub y: f32,
@@ -35,26 +49,493 @@ pub struct State {
 
     pub units: HashMap<u32, FullUnit>,
     pub map: Map,
+    pub map_info: MapInfo,
+    pub rules: Rules,
+    /// Rebuild plans of each team, by team id
+    pub team_plans: HashMap<u32, Vec<TeamPlan>>,
+    pub markers: HashMap<i32, Marker>,
+    pub custom_chunks: HashMap<String, Vec<u8>>,
+
+    pub wave: u32,
+    /// Ticks until the next wave
+    pub wave_countdown: f32,
+    pub enemies: u32,
+    pub paused: bool,
+    pub game_over: bool,
+    pub tps: u8,
+    /// Items in the core of every team, by team id and item name
+    pub cores: HashMap<u8, HashMap<String, u32>>,
+    /// Names of the other players seen in entity snapshots, by player id
+    pub players: HashMap<u32, Option<String>>,
+}
+impl State {
+    pub fn new() -> Self {
+        State {
+            player_id: 0,
+            team: 0,
+            admin: false,
+            unit: Unit {
+                unit_type: 0,
+                id: 0,
+            },
+            x: -1.0,
+            y: -1.0,
+            chatting: false,
+            plans: vec![],
+
+            units: HashMap::new(),
+            map: Map::new(0, 0),
+            map_info: MapInfo::default(),
+            rules: Rules::default(),
+            team_plans: HashMap::new(),
+            markers: HashMap::new(),
+            custom_chunks: HashMap::new(),
+
+            wave: 0,
+            wave_countdown: 0.0,
+            enemies: 0,
+            paused: false,
+            game_over: false,
+            tps: 0,
+            cores: HashMap::new(),
+            players: HashMap::new(),
+        }
+    }
+}
+impl Default for State {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// What the client reports about itself in its `Connect` packet
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClientInfo {
+    pub name: String,
+    pub version: u32,
+    /// The build type, `official` for release builds
+    pub client: String,
+    pub lang: String,
+    pub usid: String,
+    pub uuid: String,
+    pub mobile: bool,
+    /// RGBA
+    pub color: u32,
+    pub mods: Vec<String>,
 }
 
+impl ClientInfo {
+    /// Matches the official desktop client, with a freshly generated uuid and usid
+    pub fn new(name: String) -> Self {
+        ClientInfo {
+            name,
+            version: 146,
+            client: "official".to_string(),
+            lang: "en".to_string(),
+            usid: generate_id(),
+            uuid: generate_id(),
+            mobile: false,
+            color: 0xffa108ff,
+            mods: vec![],
+        }
+    }
+
+    pub fn connect_packet(&self) -> Packet {
+        Packet::Connect {
+            version: self.version,
+            client: self.client.clone(),
+            name: self.name.clone(),
+            lang: self.lang.clone(),
+            usid: self.usid.clone(),
+            uuid: self.uuid.clone(),
+            mobile: self.mobile,
+            color: self.color.to_be_bytes().to_vec(),
+            mods: self.mods.clone(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ClientError {
+    Io(std::io::Error),
+    Capture(CaptureError),
+    /// The server did not accept the TCP connection in time
+    Timeout,
+    /// The uuid is not the base64 of 8 bytes
+    InvalidUuid(String),
+}
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Io(e) => write!(f, "{e}"),
+            ClientError::Capture(e) => write!(f, "{e}"),
+            ClientError::Timeout => write!(f, "timed out connecting to the server"),
+            ClientError::InvalidUuid(uuid) => {
+                write!(f, "invalid uuid {uuid:?}, expected the base64 of 8 bytes")
+            }
+        }
+    }
+}
+impl std::error::Error for ClientError {}
+impl From<std::io::Error> for ClientError {
+    fn from(e: std::io::Error) -> Self {
+        ClientError::Io(e)
+    }
+}
+impl From<CaptureError> for ClientError {
+    fn from(e: CaptureError) -> Self {
+        ClientError::Capture(e)
+    }
+}
+
+/// Configures the identity a client connects with.
+///
+/// Without an identity file every connection uses a new random uuid and usid. With one,
+/// the uuid is kept in the file together with a usid for every server ip, so the server
+/// recognizes the client across restarts.
+pub struct ClientBuilder {
+    address: String,
+    info: ClientInfo,
+    uuid: Option<String>,
+    usid: Option<String>,
+    identity: Option<PathBuf>,
+    recording: Option<PathBuf>,
+    connect_timeout: Duration,
+    timeout: Duration,
+    reconnect: Option<Reconnect>,
+    content_store: Option<Arc<dyn ContentStore>>,
+}
+
+impl ClientBuilder {
+    pub fn version(mut self, version: u32) -> Self {
+        self.info.version = version;
+        self
+    }
+
+    pub fn client(mut self, client: String) -> Self {
+        self.info.client = client;
+        self
+    }
+
+    pub fn lang(mut self, lang: String) -> Self {
+        self.info.lang = lang;
+        self
+    }
+
+    /// RGBA player color
+    pub fn color(mut self, color: u32) -> Self {
+        self.info.color = color;
+        self
+    }
+
+    pub fn mods(mut self, mods: Vec<String>) -> Self {
+        self.info.mods = mods;
+        self
+    }
+
+    pub fn mobile(mut self, mobile: bool) -> Self {
+        self.info.mobile = mobile;
+        self
+    }
+
+    /// Uses a fixed uuid, overriding the identity file. Uuids are 8 bytes in base64,
+    /// `connect` fails for anything else.
+    pub fn uuid(mut self, uuid: String) -> Self {
+        self.uuid = Some(uuid);
+        self
+    }
+
+    /// Uses a fixed usid, overriding the identity file
+    pub fn usid(mut self, usid: String) -> Self {
+        self.usid = Some(usid);
+        self
+    }
+
+    /// Loads the uuid and per-server usid from a file, creating it if needed
+    pub fn identity(mut self, path: impl AsRef<Path>) -> Self {
+        self.identity = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Records every frame into a capture file
+    pub fn record(mut self, path: impl AsRef<Path>) -> Self {
+        self.recording = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// How long opening the TCP connection may take, 5 seconds by default
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// How long the server may stay silent before the connection counts as dead,
+    /// 12 seconds by default
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Reconnects when the connection is lost, unless the client was banned or
+    /// disconnected on purpose
+    pub fn reconnect(mut self, reconnect: Reconnect) -> Self {
+        self.reconnect = Some(reconnect);
+        self
+    }
+
+    /// Caches the content of every world the client loads. Connecting to a server with
+    /// cached content decodes packets referencing it before the world arrives.
+    pub fn content_store(mut self, store: Arc<dyn ContentStore>) -> Self {
+        self.content_store = Some(store);
+        self
+    }
+
+    pub async fn connect(self) -> Result<Client, ClientError> {
+        let mut info = self.info;
+        if let Some(uuid) = self.uuid.as_ref().filter(|uuid| !is_valid_id(uuid)) {
+            return Err(ClientError::InvalidUuid(uuid.clone()));
+        }
+        let recorder = match self.recording {
+            Some(path) => Some(Arc::new(Recorder::create(path)?)),
+            None => None,
+        };
+
+        tracing::debug!("Creating TCP connection...");
+        let tcp = time::timeout(self.connect_timeout, TcpStream::connect(&self.address))
+            .await
+            .map_err(|_| ClientError::Timeout)??;
+        let server = tcp.peer_addr()?;
+
+        if let Some(path) = &self.identity {
+            let mut identity = Identity::load_or_create(path)?;
+            if self.uuid.is_none() && !is_valid_id(&identity.uuid) {
+                return Err(ClientError::InvalidUuid(identity.uuid));
+            }
+            info.uuid = identity.uuid.clone();
+            info.usid = identity.usid(server.ip());
+            identity.save(path)?;
+        }
+        if let Some(uuid) = self.uuid {
+            info.uuid = uuid;
+        }
+        // Redirects look up the usid of the new server, unless it is fixed
+        let identity = match self.usid {
+            Some(usid) => {
+                info.usid = usid;
+                None
+            }
+            None => self.identity,
+        };
+
+        tracing::info!("Client '{}' connecting to {server}", info.name);
+        let connection = Connection::spawn(tcp, server, recorder.clone()).await?;
+
+        let content_map = self
+            .content_store
+            .as_ref()
+            .and_then(|store| load_content(store.as_ref(), server));
+        let mut protocol = ClientProtocol::with_info(info, content_map);
+        protocol.set_timeout(self.timeout);
+        protocol.handle_open(time::Instant::now().into_std());
+
+        tracing::debug!("Client ready!");
+        Ok(Client {
+            protocol: Arc::new(Mutex::new(protocol)),
+            server,
+            identity,
+            recorder,
+            connect_timeout: self.connect_timeout,
+            reconnect: self.reconnect,
+            content_store: self.content_store,
+            attempts: 0,
+            connection: Some(connection),
+            disconnect: Arc::new(Notify::new()),
+            events: broadcast::channel(EVENT_CAPACITY).0,
+        })
+    }
+}
+
+/// How `handle_packets` reconnects after losing the connection. The delay doubles with
+/// every attempt, up to `max_delay`, and starts over once a connection succeeds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reconnect {
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    /// Gives up after this many attempts in a row, `None` retries forever
+    pub max_attempts: Option<u32>,
+}
+impl Default for Reconnect {
+    fn default() -> Self {
+        Reconnect {
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+            max_attempts: Some(10),
+        }
+    }
+}
+impl Reconnect {
+    /// The delay before `attempt`, counting from 1
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_delay
+            .saturating_mul(factor)
+            .min(self.max_delay)
+    }
+}
+
+/// Drives a `ClientProtocol` over tokio sockets
 pub struct Client {
-    pub state: Arc<Mutex<State>>,
-    username: String,
-    rx_in: mpsc::Receiver<AnyPacket>,
-    tx_in: mpsc::Sender<AnyPacket>,
+    pub protocol: Arc<Mutex<ClientProtocol>>,
+    server: SocketAddr,
+    identity: Option<PathBuf>,
+    recorder: Option<Arc<Recorder>>,
+    connect_timeout: Duration,
+    reconnect: Option<Reconnect>,
+    content_store: Option<Arc<dyn ContentStore>>,
+    /// Reconnect attempts since the last successful connection
+    attempts: u32,
+    connection: Option<Connection>,
+    disconnect: Arc<Notify>,
+    events: broadcast::Sender<ClientEvent>,
+}
+
+/// Closes the connection of a `Client` while `handle_packets` runs in another task
+#[derive(Clone)]
+pub struct DisconnectHandle(Arc<Notify>);
+impl DisconnectHandle {
+    /// Makes `handle_packets` publish `Disconnected`, close the sockets and return
+    pub fn disconnect(&self) {
+        self.0.notify_one();
+    }
+}
+
+/// The sockets of a single connection. Dropping it stops the tasks owning them, which
+/// closes the sockets.
+struct Connection {
+    rx_in: mpsc::Receiver<Incoming>,
     tx_out: mpsc::Sender<QueuedPacket>,
-    streams: HashMap<u32, StreamBuilder>,
-    content_map: Arc<RwLock<Option<HashMap<String, Vec<String>>>>>,
+    tasks: Vec<JoinHandle<()>>,
+}
+impl Drop for Connection {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
 }
 
-// TODO improve included data
-#[derive(Debug)]
+/// What the socket tasks hand to `handle_packets`
+enum Incoming {
+    Frame(Vec<u8>),
+    /// The TCP connection closed, with the error that closed it
+    Closed(Option<String>),
+}
+
+/// How the server explained a kick, either with a preset reason or a custom message
+#[derive(Debug, Clone, PartialEq)]
+pub enum Kick {
+    Reason(KickReason),
+    Message(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DisconnectCause {
+    /// The server kicked the client before closing the connection
+    Kicked(Kick),
+    /// The server closed the connection without a reason
+    Closed,
+    /// Reading from the connection failed
+    Error(String),
+    /// The server sent nothing for longer than the timeout
+    TimedOut,
+    /// The client disconnected on purpose
+    Requested,
+    /// The server sent the client to another server, which `Client` connects to
+    Redirected { ip: String, port: u32 },
+}
+impl DisconnectCause {
+    /// Whether connecting again could succeed, which is not the case after a
+    /// disconnect on purpose, a kick by a player or admin, a ban or a kick for a
+    /// mismatched client or an empty name. Rejoining right after a kick only gets
+    /// `RecentKick`.
+    pub fn allows_reconnect(&self) -> bool {
+        match self {
+            DisconnectCause::Requested => false,
+            DisconnectCause::Kicked(Kick::Reason(reason)) => !matches!(
+                reason,
+                KickReason::Kick
+                    | KickReason::RecentKick
+                    | KickReason::Vote
+                    | KickReason::Banned
+                    | KickReason::ClientOutdated
+                    | KickReason::ServerOutdated
+                    | KickReason::CustomClient
+                    | KickReason::TypeMismatch
+                    | KickReason::Whitelist
+                    | KickReason::NameEmpty
+            ),
+            _ => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub enum ClientEvent {
+    /// The handshake finished and the client asked to join
+    Connected,
+    Kicked {
+        reason: Kick,
+    },
+    /// The connection is gone. Only `Reconnecting` or, after a redirect, the events of the
+    /// new connection can follow this one
+    Disconnected {
+        cause: DisconnectCause,
+    },
+    /// Waiting `delay` before reconnecting, the events of the new connection follow
+    Reconnecting {
+        attempt: u32,
+        delay: Duration,
+    },
     WorldLoaded,
+    /// A world replaced the one loaded before, sent right after its `WorldLoaded`
+    MapChanged {
+        map_info: MapInfo,
+    },
+    /// The local player spawned at the core on `tile`
+    Spawned {
+        tile: Tile,
+    },
+    /// The unit of the local player died
+    Died,
+    /// The position of the local player was set by the server
+    PositionChanged {
+        x: f32,
+        y: f32,
+    },
+    PlayerJoined {
+        id: u32,
+        name: Option<String>,
+    },
+    PlayerLeft {
+        id: u32,
+        name: Option<String>,
+    },
+    /// The building on `tile` changed, `None` means there is no building
     BlockChanged {
         tile: Tile,
+        old: Option<Box<Block>>,
+        new: Option<Box<Block>>,
     },
     UnitSnapshot,
+    /// A `StateSnapshot` was applied, after any `WaveChanged` or `GameOver` it caused
+    StateUpdated,
+    WaveChanged {
+        wave: u32,
+    },
+    /// The winner is only known when the server announces the game over itself
+    GameOver {
+        winner: Option<u8>,
+    },
     ChatMessage {
         message: String,
         unformatted: Option<String>,
@@ -62,490 +543,480 @@ pub enum ClientEvent {
     },
 }
 
-impl Client {
-    pub async fn new(ip: String, username: String) -> Client {
-        tracing::info!("Client '{username}' connecting to {ip}");
-        let (tx_in, rx_in) = mpsc::channel::<AnyPacket>(100);
-        let (tx_out, mut rx_out) = mpsc::channel::<QueuedPacket>(100);
+impl ClientEvent {
+    pub fn kind(&self) -> EventKind {
+        match self {
+            ClientEvent::Connected => EventKind::Connected,
+            ClientEvent::Kicked { .. } => EventKind::Kicked,
+            ClientEvent::Disconnected { .. } => EventKind::Disconnected,
+            ClientEvent::Reconnecting { .. } => EventKind::Reconnecting,
+            ClientEvent::WorldLoaded => EventKind::WorldLoaded,
+            ClientEvent::MapChanged { .. } => EventKind::MapChanged,
+            ClientEvent::Spawned { .. } => EventKind::Spawned,
+            ClientEvent::Died => EventKind::Died,
+            ClientEvent::PositionChanged { .. } => EventKind::PositionChanged,
+            ClientEvent::PlayerJoined { .. } => EventKind::PlayerJoined,
+            ClientEvent::PlayerLeft { .. } => EventKind::PlayerLeft,
+            ClientEvent::BlockChanged { .. } => EventKind::BlockChanged,
+            ClientEvent::UnitSnapshot => EventKind::UnitSnapshot,
+            ClientEvent::StateUpdated => EventKind::StateUpdated,
+            ClientEvent::WaveChanged { .. } => EventKind::WaveChanged,
+            ClientEvent::GameOver { .. } => EventKind::GameOver,
+            ClientEvent::ChatMessage { .. } => EventKind::ChatMessage,
+        }
+    }
 
-        tracing::debug!("Creating TCP connection...");
-        let tcp = TcpStream::connect(&ip).await.unwrap();
-        let (mut tcp_read, mut tcp_write) = tcp.into_split();
+    /// The tile the event happened on, if it is about one
+    pub fn tile(&self) -> Option<&Tile> {
+        match self {
+            ClientEvent::Spawned { tile } | ClientEvent::BlockChanged { tile, .. } => Some(tile),
+            _ => None,
+        }
+    }
+}
 
-        tracing::debug!("Creating UDP connection...");
-        let udp = Arc::from(UdpSocket::bind("0.0.0.0:0").await.unwrap());
-        let mut udp_read = udp.clone();
-        udp.connect(ip).await.unwrap();
-
-        tracing::debug!("Loading content map...");
-        let default_content_map_path = PathBuf::from("content-map.json");
-        let default_content_map = if default_content_map_path.exists() {
-            let default_content_map_data = fs::read_to_string(default_content_map_path).unwrap();
-            Some(serde_json::from_str(&default_content_map_data).unwrap())
-        } else {
-            None
-        };
-        let content_map = Arc::new(RwLock::new(default_content_map));
+/// The variants of `ClientEvent` without their data, to filter subscriptions by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EventKind {
+    Connected,
+    Kicked,
+    Disconnected,
+    Reconnecting,
+    WorldLoaded,
+    MapChanged,
+    Spawned,
+    Died,
+    PositionChanged,
+    PlayerJoined,
+    PlayerLeft,
+    BlockChanged,
+    UnitSnapshot,
+    StateUpdated,
+    WaveChanged,
+    GameOver,
+    ChatMessage,
+}
 
-        tracing::debug!("Setting up threads...");
-        // TCP Read
-        let tx_in_tcp = tx_in.clone();
-        let content = Arc::clone(&content_map);
-        tokio::spawn(async move {
-            loop {
-                let content_map = content.read().await;
-                let packet = read_packet_tcp(&mut tcp_read, &content_map).await.unwrap();
-                tx_in_tcp.send(packet).await.unwrap();
-            }
-        });
+/// A rectangle of tiles, `x` and `y` being its bottom left corner
+#[derive(Debug, Clone, PartialEq)]
+pub struct Region {
+    pub x: i16,
+    pub y: i16,
+    pub width: i16,
+    pub height: i16,
+}
+impl Region {
+    pub fn contains(&self, tile: &Tile) -> bool {
+        let (x, y) = (i32::from(tile.x), i32::from(tile.y));
+        let (left, bottom) = (i32::from(self.x), i32::from(self.y));
+        (left..left + i32::from(self.width)).contains(&x)
+            && (bottom..bottom + i32::from(self.height)).contains(&y)
+    }
+}
 
-        // UDP Read
-        let tx_in_udp = tx_in.clone();
-        let content = Arc::clone(&content_map);
-        tokio::spawn(async move {
-            loop {
-                let map = content.read().await;
-                let packet = match read_packet_udp(&mut udp_read, &map).await {
-                    Ok(packet) => packet,
-                    Err(err) => {
-                        tracing::error!("Error receiving UDP packet: {err:?}");
-                        continue;
-                    }
-                };
-                tx_in_udp.send(packet).await.unwrap();
-            }
-        });
+/// Events of a `Client`, created with `Client::subscribe`. Every subscription receives its
+/// own copy of each event, a subscription that falls too far behind skips the oldest ones.
+pub struct Subscription {
+    receiver: broadcast::Receiver<ClientEvent>,
+    kinds: Option<HashSet<EventKind>>,
+    region: Option<Region>,
+}
+impl Subscription {
+    /// Only receives events of these kinds
+    pub fn kinds(mut self, kinds: impl IntoIterator<Item = EventKind>) -> Self {
+        self.kinds = Some(kinds.into_iter().collect());
+        self
+    }
 
-        // Send
-        tokio::spawn(async move {
-            while let Some(packet) = rx_out.recv().await {
-                match packet.reliable {
-                    true => {
-                        tcp_write.write(&packet.packet).await.unwrap();
-                    }
-                    false => {
-                        udp.send(&packet.packet).await.unwrap();
-                    }
+    /// Only receives events about tiles inside `region`
+    pub fn region(mut self, region: Region) -> Self {
+        self.region = Some(region);
+        self
+    }
+
+    pub fn matches(&self, event: &ClientEvent) -> bool {
+        if let Some(kinds) = &self.kinds
+            && !kinds.contains(&event.kind())
+        {
+            return false;
+        }
+        match &self.region {
+            Some(region) => event.tile().is_some_and(|tile| region.contains(tile)),
+            None => true,
+        }
+    }
+
+    /// Waits for the next matching event, `None` once the client is gone
+    pub async fn recv(&mut self) -> Option<ClientEvent> {
+        loop {
+            match self.receiver.recv().await {
+                Ok(event) if self.matches(&event) => return Some(event),
+                Ok(_) => {}
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!("Subscription fell behind, skipped {skipped} events")
                 }
+                Err(RecvError::Closed) => return None,
             }
-        });
+        }
+    }
+}
 
-        tracing::debug!("Client ready!");
-        Client {
-            state: Arc::new(Mutex::new(State {
-                player_id: 0,
-                unit: Unit {
-                    unit_type: 0,
-                    id: 0,
-                },
-                x: -1.0,
-                y: -1.0,
-                chatting: false,
-                plans: vec![],
-
-                units: HashMap::new(),
-                map: Map::new(0, 0),
-            })),
-            username,
-            rx_in,
-            tx_in,
-            tx_out,
-            streams: HashMap::new(),
-            content_map: content_map.clone(),
+/// The content cached for `server`, if there is any
+fn load_content(store: &dyn ContentStore, server: SocketAddr) -> Option<ContentRegistry> {
+    match store.load(&server.to_string()) {
+        Ok(content) => content,
+        Err(e) => {
+            tracing::warn!("Failed to load the content of {server}: {e}");
+            None
+        }
+    }
+}
+
+impl Client {
+    pub fn builder(address: String, name: String) -> ClientBuilder {
+        ClientBuilder {
+            address,
+            info: ClientInfo::new(name),
+            uuid: None,
+            usid: None,
+            identity: None,
+            recording: None,
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            timeout: DEFAULT_TIMEOUT,
+            reconnect: None,
+            content_store: None,
+        }
+    }
+
+    /// Connects with the default client info and a random identity
+    pub async fn new(ip: String, username: String) -> Result<Client, ClientError> {
+        Self::builder(ip, username).connect().await
+    }
+
+    /// Connects like `new`, additionally recording every frame into a capture file
+    pub async fn new_recording(
+        ip: String,
+        username: String,
+        path: impl AsRef<Path>,
+    ) -> Result<Client, CaptureError> {
+        match Self::builder(ip, username).record(path).connect().await {
+            Ok(client) => Ok(client),
+            Err(ClientError::Capture(e)) => Err(e),
+            Err(ClientError::Io(e)) => Err(CaptureError::Io(e)),
+            Err(ClientError::Timeout) => Err(CaptureError::Io(std::io::ErrorKind::TimedOut.into())),
+            Err(e @ ClientError::InvalidUuid(_)) => Err(CaptureError::Io(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                e.to_string(),
+            ))),
         }
     }
 
+    /// Queues a packet for the send task, dropping it if there is no connection
     pub async fn queue_out_packet(&self, packet: QueuedPacket) {
-        self.tx_out.send(packet).await.unwrap()
+        let Some(connection) = &self.connection else {
+            tracing::warn!("Dropping packet, not connected");
+            return;
+        };
+        if connection.tx_out.send(packet).await.is_err() {
+            tracing::warn!("Dropping packet, the send task stopped");
+        }
     }
 
-    async fn queue_in_packet(&self, packet: AnyPacket) {
-        self.tx_in.send(packet).await.unwrap();
+    /// Receives the events of this client from now on, independent of other subscriptions
+    pub fn subscribe(&self) -> Subscription {
+        Subscription {
+            receiver: self.events.subscribe(),
+            kinds: None,
+            region: None,
+        }
     }
 
-    pub async fn handle_packets(&mut self, channel: mpsc::Sender<ClientEvent>) {
-        while let Some(packet) = self.rx_in.recv().await {
-            match packet {
-                AnyPacket::Framework(packet) => {
-                    self.handle_framework_packet(packet).await;
-                }
-                AnyPacket::Regular(packet) => {
-                    self.handle_regular_packet(packet, &channel).await;
-                }
+    /// Lets another task disconnect the client while `handle_packets` runs
+    pub fn disconnect_handle(&self) -> DisconnectHandle {
+        DisconnectHandle(self.disconnect.clone())
+    }
+
+    /// Closes the connection and stops the snapshots, publishing `Disconnected`
+    pub async fn disconnect(&mut self) {
+        let mut events = vec![];
+        {
+            let mut protocol = self.protocol.lock().await;
+            protocol.disconnect();
+            while let Some(event) = protocol.poll_event() {
+                events.push(event);
             }
         }
+        for event in events {
+            let _ = self.events.send(event);
+        }
+        self.connection = None;
     }
 
-    async fn handle_framework_packet(&mut self, packet: FrameworkPacket) {
-        match packet {
-            FrameworkPacket::KeepAlive => tracing::debug!("KeepAlive received!"),
-            FrameworkPacket::RegisterTCP(id) => {
-                tracing::debug!("TCP Registered, registering UDP...");
-                let answer = write_framework_packet(FrameworkPacket::RegisterUDP(id));
-                self.queue_out_packet(QueuedPacket {
-                    reliable: false,
-                    packet: answer,
-                })
-                .await;
+    /// Feeds received frames and timeouts into the protocol until the connection closes,
+    /// publishing events to every subscription. Redirects to another server are followed.
+    /// With `Reconnect` configured, lost connections are opened again and this only
+    /// returns once reconnecting gives up.
+    pub async fn handle_packets(&mut self) {
+        while let Some(cause) = self.run_connection().await {
+            if let DisconnectCause::Redirected { ip, port } = &cause {
+                match self.open(format!("{ip}:{port}")).await {
+                    Ok(()) => continue,
+                    Err(e) => tracing::warn!("Following the redirect to {ip}:{port} failed: {e}"),
+                }
             }
-            FrameworkPacket::RegisterUDP(..) => {
-                tracing::debug!("UDP registered, sending connect packet...");
-                let connect_packet = write_packet(Packet::Connect {
-                    version: 146,
-                    client: "official".to_string(),
-                    name: self.username.clone(),
-                    lang: "en".to_string(),
-                    usid: "USIGAAAAAAA=".to_string(),
-                    uuid: "UUIGAAAAAAA=".to_string(),
-                    mobile: false,
-                    color: vec![0xff, 0xa1, 0x08, 0xff],
-                    mods: vec![],
-                });
-                self.queue_out_packet(QueuedPacket {
-                    reliable: true,
-                    packet: connect_packet,
-                })
-                .await;
-
-                let confirm_connect_call_packet = write_packet(Packet::ConnectCallConfirm);
-                self.queue_out_packet(QueuedPacket {
-                    reliable: true,
-                    packet: confirm_connect_call_packet,
-                })
-                .await;
-
-                // KeepAlive & State
-                tracing::debug!("Starting main task...");
-                let tx_out_keepalive = self.tx_out.clone();
-                let state_keepalive = Arc::clone(&self.state);
-                tokio::spawn(async move {
-                    let mut i = 0;
-                    let mut interval = time::interval(Duration::from_millis(200));
-                    loop {
-                        interval.tick().await;
-                        i += 1;
-
-                        let current_state = state_keepalive.lock().await;
-
-                        let snapshot = Packet::ClientSnapshot {
-                            snapshot_id: i,
-                            unit_id: current_state.unit.id,
-                            dead: false,
-                            x: current_state.x,
-                            y: current_state.y,
-                            pointer_x: current_state.x,
-                            pointer_y: current_state.y,
-                            rotation: 0.0,
-                            base_rotation: 0.0,
-                            x_velocity: 0.0,
-                            y_velocity: 0.0,
-                            mining_x: 0,
-                            mining_y: 0,
-                            boosting: false,
-                            shooting: false,
-                            chatting: current_state.chatting,
-                            building: true,
-                            plans: current_state.plans.clone(),
-                            view_x: 0.0,
-                            view_y: 0.0,
-                            view_width: 1920.0,
-                            view_height: 1080.0,
-                        };
-
-                        tracing::debug!("Sending ClientSnapshot");
-                        tx_out_keepalive
-                            .send(QueuedPacket {
-                                reliable: true,
-                                packet: write_packet(snapshot),
-                            })
-                            .await
-                            .unwrap();
-
-                        if i % (5 * 5) == 0 {
-                            tracing::debug!("Sending TCP KeepAlive");
-                            tx_out_keepalive
-                                .send(QueuedPacket {
-                                    reliable: true,
-                                    packet: write_framework_packet(FrameworkPacket::KeepAlive),
-                                })
-                                .await
-                                .unwrap();
-                        }
-                        if i % (15 * 5) == 0 {
-                            tracing::debug!("Sending UDP KeepAlive");
-                            tx_out_keepalive
-                                .send(QueuedPacket {
-                                    reliable: false,
-                                    packet: write_framework_packet(FrameworkPacket::KeepAlive),
-                                })
-                                .await
-                                .unwrap();
-                        }
-                    }
-                });
+            let Some(reconnect) = self.reconnect.clone() else {
+                break;
+            };
+            if !cause.allows_reconnect() {
+                tracing::info!("Not reconnecting after {cause:?}");
+                break;
+            }
+            if !self.reconnect(&reconnect).await {
+                break;
             }
-            _ => println!("fw> {packet:?}"),
         }
     }
 
-    async fn handle_regular_packet(&mut self, packet: Packet, sender: &mpsc::Sender<ClientEvent>) {
-        match packet {
-            Packet::StreamBegin {
-                id,
-                stream_type,
-                total,
-            } => {
-                tracing::debug!("Receiving stream {id} ({total} bytes)");
-                self.streams
-                    .insert(id, StreamBuilder::new(id, stream_type, total));
-            }
-            Packet::StreamChunk { id, data } => {
-                let stream = match self.streams.get_mut(&id) {
-                    Some(stream) => stream,
-                    None => {
-                        tracing::error!("Stream {id} not found!");
-                        return;
+    /// Runs the current connection until it closes, returning why
+    async fn run_connection(&mut self) -> Option<DisconnectCause> {
+        let connection = self.connection.as_mut()?;
+        loop {
+            let timeout = self.protocol.lock().await.poll_timeout();
+            let sleep = async {
+                match timeout {
+                    Some(timeout) => time::sleep_until(timeout.into()).await,
+                    None => std::future::pending().await,
+                }
+            };
+            let mut requested = false;
+            let incoming = tokio::select! {
+                incoming = connection.rx_in.recv() => Some(incoming.unwrap_or(Incoming::Closed(None))),
+                _ = self.disconnect.notified() => {
+                    requested = true;
+                    None
+                }
+                _ = sleep => None,
+            };
+
+            let mut transmits = vec![];
+            let mut events = vec![];
+            let mut loaded = None;
+            {
+                let mut protocol = self.protocol.lock().await;
+                let now = time::Instant::now().into_std();
+                match incoming {
+                    Some(Incoming::Frame(frame)) => protocol.receive_frame(now, frame),
+                    Some(Incoming::Closed(error)) => protocol.handle_close(error),
+                    None => {}
+                }
+                if requested {
+                    protocol.disconnect();
+                }
+                protocol.handle_timeout(now);
+                while let Some(transmit) = protocol.poll_transmit() {
+                    transmits.push(transmit);
+                }
+                while let Some(event) = protocol.poll_event() {
+                    if let ClientEvent::WorldLoaded = event {
+                        loaded = protocol.content_map().clone();
                     }
-                };
-                stream.add(data);
-                if stream.is_done() {
-                    tracing::debug!("Stream {id} completed");
-                    let stream = self.streams.remove(&id).unwrap();
-                    let content = self.content_map.read().await;
-                    let packet = stream.build(&content).unwrap();
-                    self.queue_in_packet(AnyPacket::Regular(packet)).await;
+                    events.push(event);
                 }
             }
-            Packet::WorldStream {
-                id,
-                content_map: content,
-                map,
-                ..
-            } => {
-                let mut current_state = self.state.lock().await;
-                current_state.player_id = id;
-                current_state.map = map;
 
-                {
-                    let mut content_map = self.content_map.write().await;
-                    *content_map = Some(content.clone());
+            if let (Some(store), Some(content)) = (&self.content_store, loaded)
+                && let Err(e) = store.save(&self.server.to_string(), &content)
+            {
+                tracing::warn!("Failed to save the content of {}: {e}", self.server);
+            }
+            for transmit in transmits {
+                if connection.tx_out.send(transmit).await.is_err() {
+                    tracing::warn!("Dropping packet, the send task stopped");
+                }
+            }
+            let mut cause = None;
+            for event in events {
+                match &event {
+                    ClientEvent::Connected => self.attempts = 0,
+                    ClientEvent::Disconnected { cause: c } => cause = Some(c.clone()),
+                    _ => {}
                 }
+                // Fails only when nobody is subscribed
+                let _ = self.events.send(event);
+            }
+            if cause.is_some() {
+                self.connection = None;
+                return cause;
+            }
+        }
+    }
 
-                tracing::info!("World loaded!");
-                sender.send(ClientEvent::WorldLoaded).await.unwrap();
+    /// Waits for the backoff and opens a new connection with a fresh protocol state,
+    /// returning false once the attempts are used up or the client was disconnected
+    async fn reconnect(&mut self, reconnect: &Reconnect) -> bool {
+        loop {
+            self.attempts += 1;
+            if let Some(max_attempts) = reconnect.max_attempts
+                && self.attempts > max_attempts
+            {
+                tracing::warn!("Giving up after {max_attempts} reconnect attempts");
+                return false;
             }
-            Packet::BeginPlace {
-                x,
-                y,
-                rotation,
-                result,
-                team,
-                ..
-            } => {
-                let mut state = self.state.lock().await;
-                let map_tile = match state.map.get_mut(x, y) {
-                    Some(map_tile) => map_tile,
-                    None => {
-                        tracing::warn!("Received begin place before map was loaded, ignoring");
-                        return;
-                    }
-                };
-                map_tile.block_id = Some(result as i16);
-                // TODO improve
-                map_tile.block = Some(Block {
-                    block_type: "Construct".to_string(),
-                    name: "Construct".to_string(),
-                    base: BaseBlockData {
-                        team,
-                        rotation: rotation as u8,
-                        version: 0,
-                        legacy: false,
-                        items: None,
-                        liquids: None,
-                        power: None,
-                        on: None,
-                        module_bitmask: 0,
-                        health: 1f32,
-                    },
-                    specific: None,
-                })
+            let delay = reconnect.delay(self.attempts);
+            tracing::info!("Reconnecting in {delay:?} (attempt {})", self.attempts);
+            let _ = self.events.send(ClientEvent::Reconnecting {
+                attempt: self.attempts,
+                delay,
+            });
+            tokio::select! {
+                _ = time::sleep(delay) => {}
+                _ = self.disconnect.notified() => return false,
+            }
+
+            match self.open(self.server).await {
+                Ok(()) => return true,
+                Err(e) => tracing::warn!("Reconnecting failed: {e}"),
             }
-            Packet::ConstructFinish { tile, block, .. } => {
-                let mut state = self.state.lock().await;
-                let map_tile = state.map.get_mut(tile.x as u32, tile.y as u32).unwrap();
-                map_tile.block_id = Some(block);
-
-                let block_types = load_block_types();
-                let content_map = match self.content_map.read().await.clone() {
-                    Some(map) => map,
-                    None => {
-                        // TODO
-                        return;
+        }
+    }
+
+    /// Connects to `address` with a fresh protocol state and the same client info. When
+    /// the server ip changes, the usid for it is taken from the identity file.
+    async fn open(&mut self, address: impl ToSocketAddrs) -> Result<(), ClientError> {
+        let tcp = time::timeout(self.connect_timeout, TcpStream::connect(address))
+            .await
+            .map_err(|_| ClientError::Timeout)??;
+        let server = tcp.peer_addr()?;
+        let connection = Connection::spawn(tcp, server, self.recorder.clone()).await?;
+
+        let mut protocol = self.protocol.lock().await;
+        let mut info = protocol.info().clone();
+        if let Some(path) = &self.identity
+            && server.ip() != self.server.ip()
+        {
+            let mut identity = Identity::load_or_create(path)?;
+            info.usid = identity.usid(server.ip());
+            identity.save(path)?;
+        }
+        // Content of the previous server is a better guess than none at all
+        let content_map = self
+            .content_store
+            .as_ref()
+            .and_then(|store| load_content(store.as_ref(), server))
+            .or_else(|| protocol.content_map().clone());
+        let mut fresh = ClientProtocol::with_info(info, content_map);
+        fresh.set_timeout(protocol.timeout());
+        fresh.handle_open(time::Instant::now().into_std());
+        *protocol = fresh;
+
+        tracing::info!("Client connecting to {server}");
+        self.server = server;
+        self.connection = Some(connection);
+        Ok(())
+    }
+}
+
+impl Connection {
+    /// Spawns the tasks reading and writing the sockets of a new connection
+    async fn spawn(
+        tcp: TcpStream,
+        server: SocketAddr,
+        recorder: Option<Arc<Recorder>>,
+    ) -> Result<Connection, ClientError> {
+        let (tx_in, rx_in) = mpsc::channel::<Incoming>(100);
+        let (tx_out, mut rx_out) = mpsc::channel::<QueuedPacket>(100);
+
+        let (tcp_read, mut tcp_write) = tcp.into_split();
+        let mut tcp_read = FramedRead::new(tcp_read, FrameCodec);
+
+        tracing::debug!("Creating UDP connection...");
+        let udp = Arc::from(UdpSocket::bind("0.0.0.0:0").await?);
+        let udp_read = udp.clone();
+        udp.connect(server).await?;
+
+        tracing::debug!("Setting up threads...");
+        // TCP Read
+        let tx_in_tcp = tx_in.clone();
+        let recorder_tcp = recorder.clone();
+        let tcp_task = tokio::spawn(async move {
+            let error = loop {
+                let frame = match tcp_read.next().await {
+                    Some(Ok(frame)) => frame,
+                    None => break None,
+                    Some(Err(err)) => {
+                        tracing::error!("Error receiving TCP packet: {err}");
+                        break Some(err.to_string());
                     }
                 };
-
-                let block_name = content_map
-                    .get("block")
-                    .unwrap()
-                    .get(block as usize)
-                    .unwrap();
-                let block_type = block_types.get(block_name).unwrap();
-
-                if let Some(block) = &mut map_tile.block {
-                    block.block_type = block_type.clone();
-                    block.name = block_name.clone();
-                    // TODO update config
-                } else {
-                    tracing::warn!("Construct block at {tile:?} missing!");
+                if let Some(recorder) = &recorder_tcp {
+                    recorder.record(Transport::Tcp, Direction::Inbound, &frame);
+                }
+                if tx_in_tcp.send(Incoming::Frame(frame)).await.is_err() {
+                    return;
                 }
+            };
+            let _ = tx_in_tcp.send(Incoming::Closed(error)).await;
+        });
 
-                sender
-                    .send(ClientEvent::BlockChanged { tile })
-                    .await
-                    .unwrap();
-            }
-            Packet::DeconstructFinish { tile, .. } => {
-                let mut state = self.state.lock().await;
-                let map_tile = state.map.get_mut(tile.x as u32, tile.y as u32).unwrap();
-                map_tile.block_id = None;
-                map_tile.block = None;
-                sender
-                    .send(ClientEvent::BlockChanged { tile })
+        // UDP Read
+        let recorder_udp = recorder.clone();
+        let udp_task = tokio::spawn(async move {
+            let mut buf = [0u8; 32768];
+            loop {
+                let length = match udp_read.recv(&mut buf).await {
+                    Ok(length) => length,
+                    Err(err) => {
+                        tracing::error!("Error receiving UDP packet: {err}");
+                        break;
+                    }
+                };
+                if let Some(recorder) = &recorder_udp {
+                    recorder.record(Transport::Udp, Direction::Inbound, &buf[..length]);
+                }
+                if tx_in
+                    .send(Incoming::Frame(buf[..length].to_vec()))
                     .await
-                    .unwrap();
+                    .is_err()
+                {
+                    break;
+                }
             }
-            // TODO Broken
-            Packet::BlockSnapshot { amount, data } => {
-                return;
-
-                let mut reader = Reader::new(data);
-                let mut state = self.state.lock().await;
-
-                for _ in 0..amount {
-                    let tile = read_tile(&mut reader);
-                    let block_id = reader.short();
-
-                    let map_tile = match state.map.get_mut(tile.x as u32, tile.y as u32) {
-                        Some(map_tile) => map_tile,
-                        None => {
-                            tracing::warn!(
-                                "Invalid state: Block snapshot contains locally missing block at {tile:?}"
+        });
+
+        // Send
+        let send_task = tokio::spawn(async move {
+            while let Some(packet) = rx_out.recv().await {
+                let result = match packet.reliable {
+                    true => {
+                        if let Some(recorder) = &recorder {
+                            // Strip the length prefix, like inbound frames
+                            recorder.record(
+                                Transport::Tcp,
+                                Direction::Outbound,
+                                &packet.packet[2..],
                             );
-                            return;
                         }
-                    };
-                    if map_tile.block_id.unwrap() != block_id {
-                        tracing::warn!(
-                            "Invalid block id at {tile:?}: Expected {block_id} but found {:?}",
-                            map_tile.block_id
-                        );
+                        tcp_write.write_all(&packet.packet).await
                     }
-
-                    let block_types = load_block_types();
-                    let content = self.content_map.read().await.clone().unwrap();
-
-                    let block_name = content
-                        .get("block")
-                        .unwrap()
-                        .get(block_id as usize)
-                        .unwrap();
-                    let block_type = block_types.get(block_name).unwrap();
-
-                    let content_map = self.content_map.read().await;
-                    let block = read_block(
-                        &mut reader,
-                        block_name.clone(),
-                        block_type.clone(),
-                        map_tile.block.clone().unwrap().base.version,
-                        &content_map.clone().unwrap(),
-                    );
-
-                    map_tile.block = Some(block);
-
-                    sender
-                        .send(ClientEvent::BlockChanged { tile })
-                        .await
-                        .unwrap();
-                }
-            }
-            Packet::EntitySnapshot { units } => {
-                let mut current_state = self.state.lock().await;
-                let possible_unit = current_state.units.get(&current_state.player_id).cloned();
-
-                match possible_unit {
-                    Some(unit) => match unit {
-                        FullUnit::Player { unit, x, y, .. } => {
-                            // TODO pos changed event?
-                            if current_state.x == -1.0 && x != -1.0 {
-                                current_state.x = x;
-                                println!("Update x: {}", x);
-                            }
-                            if current_state.y == -1.0 && y != -1.0 {
-                                current_state.y = y;
-                                println!("Update y: {}", y);
-                            }
-
-                            current_state.unit = unit.clone();
+                    false => {
+                        if let Some(recorder) = &recorder {
+                            recorder.record(Transport::Udp, Direction::Outbound, &packet.packet);
                         }
-                        _ => unreachable!(),
-                    },
-                    None => {}
+                        udp.send(&packet.packet).await.map(|_| ())
+                    }
                 };
-
-                current_state.units = units;
-
-                sender.send(ClientEvent::UnitSnapshot).await.unwrap();
-            }
-            Packet::KickCall { reason } => {
-                tracing::warn!("Client was kicked: {reason}");
-            }
-            Packet::KickCall2 { reason } => {
-                tracing::warn!("Client was kicked: {reason:?}");
-            }
-            Packet::SpawnCall {
-                tile_x,
-                tile_y,
-                entity,
-            } => {
-                // TODO player spawned event?
-                println!("Spawn call: {tile_x}/{tile_y} {entity}");
-                let mut current_state = self.state.lock().await;
-                println!("{}", current_state.player_id);
-                if current_state.player_id == entity {
-                    // TODO pos change event?
-                    println!("Set coords");
-                    current_state.x = (tile_x * 8) as f32;
-                    current_state.y = (tile_y * 8) as f32;
+                // A broken TCP connection is reported by the read task
+                if let Err(err) = result {
+                    tracing::error!("Error sending packet: {err}");
+                    break;
                 }
             }
-            Packet::RotateBlockCall { tile, rotation, .. } => {
-                let mut state = self.state.lock().await;
-                let map_tile = state.map.get_mut(tile.x as u32, tile.y as u32).unwrap();
-                map_tile.block.as_mut().unwrap().base.rotation = rotation;
+        });
 
-                sender
-                    .send(ClientEvent::BlockChanged { tile })
-                    .await
-                    .unwrap();
-            }
-            Packet::SendMessageCall2 {
-                message,
-                unformatted,
-                sender: author,
-            } => {
-                sender
-                    .send(ClientEvent::ChatMessage {
-                        message,
-                        unformatted,
-                        sender: author,
-                    })
-                    .await
-                    .unwrap();
-            }
-            Packet::Other(id) => {
-                tracing::debug!("Unhandled packet: {id}");
-            }
-            _ => {}
-        }
+        Ok(Connection {
+            rx_in,
+            tx_out,
+            tasks: vec![tcp_task, udp_task, send_task],
+        })
     }
 }
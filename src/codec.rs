@@ -0,0 +1,123 @@
+use crate::content::ContentRegistry;
+use crate::packet::{
+    AnyPacket, FrameworkPacket, Packet, PacketError, parse_packet, write_framework_frame,
+    write_packet_frames,
+};
+use crate::version::ProtocolVersion;
+use bytes::{Buf, BytesMut};
+use tokio_util::codec::{Decoder, Encoder};
+
+/// The 2-byte big-endian length prefix used for every TCP frame. Frames are returned
+/// without the prefix, still compressed.
+#[derive(Debug, Default)]
+pub struct FrameCodec;
+
+impl Decoder for FrameCodec {
+    type Item = Vec<u8>;
+    type Error = PacketError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Vec<u8>>, PacketError> {
+        if src.len() < 2 {
+            return Ok(None);
+        }
+        let length = u16::from_be_bytes([src[0], src[1]]) as usize;
+        if src.len() < length + 2 {
+            src.reserve(length + 2 - src.len());
+            return Ok(None);
+        }
+        src.advance(2);
+        Ok(Some(src.split_to(length).to_vec()))
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Vec<u8>>, PacketError> {
+        match self.decode(src)? {
+            None if !src.is_empty() => {
+                Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into())
+            }
+            frame => Ok(frame),
+        }
+    }
+}
+
+impl Encoder<Vec<u8>> for FrameCodec {
+    type Error = PacketError;
+
+    fn encode(&mut self, frame: Vec<u8>, dst: &mut BytesMut) -> Result<(), PacketError> {
+        dst.extend_from_slice(&(frame.len() as u16).to_be_bytes());
+        dst.extend_from_slice(&frame);
+        Ok(())
+    }
+}
+
+/// Decodes TCP frames into packets, for use with `tokio_util::codec::Framed` or its
+/// `FramedRead` and `FramedWrite` halves. A malformed frame is consumed before its error is
+/// returned, so decoding can continue with the next one.
+///
+/// Packets too large for a single frame are encoded as a stream. Streams are not
+/// reassembled when decoding, their `StreamBegin` and `StreamChunk` packets are returned
+/// as is.
+#[derive(Debug, Default)]
+pub struct PacketCodec {
+    frames: FrameCodec,
+    /// Used to decode packets that reference content, updated once the world is loaded
+    pub content_map: Option<ContentRegistry>,
+    pub version: ProtocolVersion,
+    next_stream_id: u32,
+}
+
+impl PacketCodec {
+    pub fn new(content_map: Option<ContentRegistry>, version: ProtocolVersion) -> Self {
+        PacketCodec {
+            frames: FrameCodec,
+            content_map,
+            version,
+            next_stream_id: 0,
+        }
+    }
+
+    /// Reserves an id for a stream sent through this codec
+    pub fn next_stream_id(&mut self) -> u32 {
+        let id = self.next_stream_id;
+        self.next_stream_id = self.next_stream_id.wrapping_add(1);
+        id
+    }
+}
+
+impl Decoder for PacketCodec {
+    type Item = AnyPacket;
+    type Error = PacketError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<AnyPacket>, PacketError> {
+        match self.frames.decode(src)? {
+            Some(frame) => Ok(Some(parse_packet(frame, &self.content_map, self.version)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<AnyPacket>, PacketError> {
+        match self.frames.decode_eof(src)? {
+            Some(frame) => Ok(Some(parse_packet(frame, &self.content_map, self.version)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+impl Encoder<Packet> for PacketCodec {
+    type Error = PacketError;
+
+    fn encode(&mut self, packet: Packet, dst: &mut BytesMut) -> Result<(), PacketError> {
+        for frame in write_packet_frames(packet, &mut self.next_stream_id)? {
+            dst.extend_from_slice(&frame);
+        }
+        Ok(())
+    }
+}
+
+impl Encoder<FrameworkPacket> for PacketCodec {
+    type Error = PacketError;
+
+    fn encode(&mut self, packet: FrameworkPacket, dst: &mut BytesMut) -> Result<(), PacketError> {
+        dst.extend_from_slice(&write_framework_frame(packet));
+        Ok(())
+    }
+}
@@ -0,0 +1,306 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Id of a block in the content header of a world
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BlockId(pub i16);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ItemId(pub i16);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct LiquidId(pub i16);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct UnitId(pub i16);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct StatusId(pub i16);
+
+// Generated by build.rs from the files in `src/data`
+include!(concat!(env!("OUT_DIR"), "/content_data.rs"));
+
+/// Names of the content types, indexed by the id used in content headers
+pub fn content_types() -> &'static [&'static str] {
+    CONTENT_TYPES
+}
+
+/// What the bundled block data says about a block
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlockInfo {
+    /// The class implementing the block, like `GenericCrafter`
+    pub class: &'static str,
+    /// Width and height in tiles
+    pub size: u8,
+    pub has_items: bool,
+    pub has_power: bool,
+    pub has_liquids: bool,
+}
+
+/// The fields of `block_params.json` this crate uses. Blocks only list the values their
+/// class does not set by default.
+struct BlockParams {
+    size: Option<u8>,
+    has_items: Option<bool>,
+    has_power: Option<bool>,
+    has_liquids: Option<bool>,
+}
+
+impl BlockInfo {
+    /// Looks up the block called `name`, `None` for blocks unknown to the bundled data
+    pub fn get(name: &str) -> Option<BlockInfo> {
+        let class = BLOCK_TYPES.get(name)?;
+        let params = BLOCK_PARAMS.get(name);
+        Some(BlockInfo {
+            class,
+            size: params.and_then(|p| p.size).unwrap_or(1),
+            has_items: params.and_then(|p| p.has_items).unwrap_or(false),
+            has_power: params.and_then(|p| p.has_power).unwrap_or(false),
+            has_liquids: params.and_then(|p| p.has_liquids).unwrap_or(false),
+        })
+    }
+}
+
+/// The content of a world by type and id, as sent in its content header.
+///
+/// Content types are named like in `content_types.json`, e.g. `block`, `item` or `unit`.
+/// Lookups return `None` for ids the world does not define, and blocks unknown to the
+/// bundled data have no `BlockInfo`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(
+    from = "HashMap<String, Vec<String>>",
+    into = "HashMap<String, Vec<String>>"
+)]
+pub struct ContentRegistry {
+    names: HashMap<String, Vec<String>>,
+    ids: HashMap<String, HashMap<String, i16>>,
+    blocks: Vec<Option<BlockInfo>>,
+}
+
+impl ContentRegistry {
+    pub fn new(names: HashMap<String, Vec<String>>) -> Self {
+        let ids = names
+            .iter()
+            .map(|(content_type, names)| {
+                let ids = names
+                    .iter()
+                    .enumerate()
+                    .map(|(id, name)| (name.clone(), id as i16))
+                    .collect();
+                (content_type.clone(), ids)
+            })
+            .collect();
+
+        let blocks = names
+            .get("block")
+            .map(|blocks| blocks.iter().map(|name| BlockInfo::get(name)).collect())
+            .unwrap_or_default();
+
+        ContentRegistry { names, ids, blocks }
+    }
+
+    /// A checksum of every content name, equal for worlds with the same content
+    pub fn content_hash(&self) -> u32 {
+        let mut types: Vec<_> = self.names.iter().collect();
+        types.sort();
+        let mut hasher = crc32fast::Hasher::new();
+        for (content_type, names) in types {
+            hasher.update(content_type.as_bytes());
+            hasher.update(&[0]);
+            for name in names {
+                hasher.update(name.as_bytes());
+                hasher.update(&[0]);
+            }
+        }
+        hasher.finalize()
+    }
+
+    /// Names of every content type, indexed by id
+    pub fn names(&self) -> &HashMap<String, Vec<String>> {
+        &self.names
+    }
+
+    pub fn name(&self, content_type: &str, id: i16) -> Option<&str> {
+        let names = self.names.get(content_type)?;
+        names.get(usize::try_from(id).ok()?).map(String::as_str)
+    }
+
+    pub fn id(&self, content_type: &str, name: &str) -> Option<i16> {
+        self.ids.get(content_type)?.get(name).copied()
+    }
+
+    pub fn block(&self, id: BlockId) -> Option<&str> {
+        self.name("block", id.0)
+    }
+
+    pub fn block_id(&self, name: &str) -> Option<BlockId> {
+        self.id("block", name).map(BlockId)
+    }
+
+    pub fn block_info(&self, id: BlockId) -> Option<&BlockInfo> {
+        self.blocks.get(usize::try_from(id.0).ok()?)?.as_ref()
+    }
+
+    pub fn item(&self, id: ItemId) -> Option<&str> {
+        self.name("item", id.0)
+    }
+
+    pub fn item_id(&self, name: &str) -> Option<ItemId> {
+        self.id("item", name).map(ItemId)
+    }
+
+    pub fn liquid(&self, id: LiquidId) -> Option<&str> {
+        self.name("liquid", id.0)
+    }
+
+    pub fn liquid_id(&self, name: &str) -> Option<LiquidId> {
+        self.id("liquid", name).map(LiquidId)
+    }
+
+    pub fn unit(&self, id: UnitId) -> Option<&str> {
+        self.name("unit", id.0)
+    }
+
+    pub fn unit_id(&self, name: &str) -> Option<UnitId> {
+        self.id("unit", name).map(UnitId)
+    }
+
+    pub fn status(&self, id: StatusId) -> Option<&str> {
+        self.name("status", id.0)
+    }
+
+    pub fn status_id(&self, name: &str) -> Option<StatusId> {
+        self.id("status", name).map(StatusId)
+    }
+}
+
+impl From<HashMap<String, Vec<String>>> for ContentRegistry {
+    fn from(names: HashMap<String, Vec<String>>) -> Self {
+        ContentRegistry::new(names)
+    }
+}
+
+impl From<ContentRegistry> for HashMap<String, Vec<String>> {
+    fn from(registry: ContentRegistry) -> Self {
+        registry.names
+    }
+}
+
+/// Caches the content of servers between connections, so packets referencing content
+/// can be decoded before the world of a server arrives
+pub trait ContentStore: Send + Sync {
+    /// The content last saved for the server at `address`
+    fn load(&self, address: &str) -> io::Result<Option<ContentRegistry>>;
+    fn save(&self, address: &str, content: &ContentRegistry) -> io::Result<()>;
+}
+
+/// Keeps content in memory, for the clients of a single process
+#[derive(Default)]
+pub struct MemoryContentStore {
+    servers: Mutex<HashMap<String, ContentRegistry>>,
+}
+
+impl MemoryContentStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ContentStore for MemoryContentStore {
+    fn load(&self, address: &str) -> io::Result<Option<ContentRegistry>> {
+        Ok(self.servers.lock().unwrap().get(address).cloned())
+    }
+
+    fn save(&self, address: &str, content: &ContentRegistry) -> io::Result<()> {
+        let mut servers = self.servers.lock().unwrap();
+        servers.insert(address.to_string(), content.clone());
+        Ok(())
+    }
+}
+
+/// Keeps content in a directory, as one `<hash>.json` file for each distinct content and
+/// a file in `servers/` for each server address holding the hash of its content. Servers
+/// running the same content share a file.
+///
+/// Several processes may share the directory. Every save only replaces the index file of
+/// its own server, so concurrent saves for different servers do not lose each other.
+pub struct FileContentStore {
+    dir: PathBuf,
+}
+
+impl FileContentStore {
+    /// Uses `dir`, which is created on the first save
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        FileContentStore {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    fn content_path(&self, hash: u32) -> PathBuf {
+        self.dir.join(format!("{hash:08x}.json"))
+    }
+
+    /// The index file of `address`, with every character that may not be valid in a file
+    /// name percent-encoded
+    fn server_path(&self, address: &str) -> PathBuf {
+        let mut name = String::new();
+        for byte in address.bytes() {
+            match byte {
+                b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'.' | b'-' => name.push(byte as char),
+                byte => name.push_str(&format!("%{byte:02x}")),
+            }
+        }
+        self.dir.join("servers").join(name)
+    }
+
+    fn read_hash(&self, address: &str) -> io::Result<Option<u32>> {
+        match fs::read_to_string(self.server_path(address)) {
+            Ok(data) => u32::from_str_radix(data.trim(), 16)
+                .map(Some)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Writes through a temporary file, so readers never see a partial file
+    fn write(&self, path: &Path, data: String) -> io::Result<()> {
+        let temp = path.with_extension(format!("{}.tmp", fastrand::u32(..)));
+        fs::write(&temp, data)?;
+        fs::rename(&temp, path)
+    }
+}
+
+impl ContentStore for FileContentStore {
+    fn load(&self, address: &str) -> io::Result<Option<ContentRegistry>> {
+        let Some(hash) = self.read_hash(address)? else {
+            return Ok(None);
+        };
+        let data = match fs::read_to_string(self.content_path(hash)) {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        serde_json::from_str(&data)
+            .map(Some)
+            .map_err(io::Error::other)
+    }
+
+    fn save(&self, address: &str, content: &ContentRegistry) -> io::Result<()> {
+        fs::create_dir_all(self.dir.join("servers"))?;
+        let hash = content.content_hash();
+        let path = self.content_path(hash);
+        if !path.exists() {
+            self.write(&path, serde_json::to_string(content)?)?;
+        }
+
+        if self.read_hash(address)? != Some(hash) {
+            self.write(&self.server_path(address), format!("{hash:08x}"))?;
+        }
+        Ok(())
+    }
+}
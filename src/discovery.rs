@@ -0,0 +1,200 @@
+use crate::packet::{FrameworkPacket, PacketError, write_framework_packet};
+use crate::type_io::{FieldContext, Reader, write_byte, write_int};
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+use std::fmt;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::time::{Duration, Instant};
+use tokio::net::{ToSocketAddrs, UdpSocket};
+use tokio::time;
+
+pub const DEFAULT_PORT: u16 = 6567;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, IntoPrimitive, TryFromPrimitive)]
+#[repr(u8)]
+pub enum Gamemode {
+    Survival = 0,
+    Sandbox = 1,
+    Attack = 2,
+    Pvp = 3,
+    Editor = 4,
+}
+
+/// A server's reply to a `DiscoverHost` ping
+#[derive(Debug, Clone, PartialEq)]
+pub struct HostInfo {
+    pub address: SocketAddr,
+    pub ping: Duration,
+    pub name: String,
+    pub map: String,
+    pub players: u32,
+    pub wave: u32,
+    pub version: i32,
+    pub version_type: String,
+    pub gamemode: Gamemode,
+    pub player_limit: u32,
+    pub description: String,
+    pub mode_name: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum DiscoveryError {
+    Io(std::io::Error),
+    Timeout,
+    Decode(PacketError),
+}
+impl fmt::Display for DiscoveryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiscoveryError::Io(e) => write!(f, "{e}"),
+            DiscoveryError::Timeout => write!(f, "Host did not respond in time"),
+            DiscoveryError::Decode(e) => write!(f, "{e}"),
+        }
+    }
+}
+impl std::error::Error for DiscoveryError {}
+impl From<std::io::Error> for DiscoveryError {
+    fn from(e: std::io::Error) -> Self {
+        DiscoveryError::Io(e)
+    }
+}
+
+/// Servers cut these strings at a byte limit, which can split a multibyte character
+fn read_server_string(reader: &mut Reader) -> Result<String, PacketError> {
+    let length = reader.byte()?;
+    let bytes = reader.bytes(length as usize)?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+fn write_server_string(buf: &mut Vec<u8>, string: &str, max_length: usize) {
+    let mut end = string.len().min(max_length);
+    while !string.is_char_boundary(end) {
+        end -= 1;
+    }
+    write_byte(buf, end as u8);
+    buf.extend_from_slice(&string.as_bytes()[..end]);
+}
+
+pub fn read_host_info(
+    reader: &mut Reader,
+    address: SocketAddr,
+    ping: Duration,
+) -> Result<HostInfo, PacketError> {
+    let name = read_server_string(reader).field("name")?;
+    let map = read_server_string(reader).field("map")?;
+    let players = reader.int().field("players")?;
+    let wave = reader.int().field("wave")?;
+    let version = reader.int().field("version")? as i32;
+    let version_type = read_server_string(reader).field("version_type")?;
+    let mode = reader.byte().field("gamemode")?;
+    let gamemode = Gamemode::try_from(mode)
+        .map_err(|_| reader.error(format!("unknown gamemode {mode}")))
+        .field("gamemode")?;
+    let player_limit = reader.int().field("player_limit")?;
+    let description = read_server_string(reader).field("description")?;
+    // Servers pad the reply to 500 bytes, so a missing mode name reads as empty
+    let mode_name = if reader.remaining() > 0 {
+        Some(read_server_string(reader).field("mode_name")?).filter(|name| !name.is_empty())
+    } else {
+        None
+    };
+
+    Ok(HostInfo {
+        address,
+        ping,
+        name,
+        map,
+        players,
+        wave,
+        version,
+        version_type,
+        gamemode,
+        player_limit,
+        description,
+        mode_name,
+    })
+}
+
+pub fn write_host_info(buf: &mut Vec<u8>, info: &HostInfo) {
+    write_server_string(buf, &info.name, 100);
+    write_server_string(buf, &info.map, 64);
+    write_int(buf, info.players);
+    write_int(buf, info.wave);
+    write_int(buf, info.version as u32);
+    write_server_string(buf, &info.version_type, 255);
+    write_byte(buf, info.gamemode.into());
+    write_int(buf, info.player_limit);
+    write_server_string(buf, &info.description, 100);
+    if let Some(mode_name) = &info.mode_name {
+        write_server_string(buf, mode_name, 50);
+    }
+}
+
+/// Pings a single server and waits for its reply
+pub async fn ping(
+    address: impl ToSocketAddrs,
+    timeout: Duration,
+) -> Result<HostInfo, DiscoveryError> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await?;
+    socket.connect(address).await?;
+    let address = socket.peer_addr()?;
+
+    let start = Instant::now();
+    socket
+        .send(&write_framework_packet(FrameworkPacket::DiscoverHost))
+        .await?;
+
+    let mut buf = [0u8; 1024];
+    let length = time::timeout(timeout, socket.recv(&mut buf))
+        .await
+        .map_err(|_| DiscoveryError::Timeout)??;
+
+    let mut reader = Reader::new(buf[..length].to_vec());
+    read_host_info(&mut reader, address, start.elapsed()).map_err(DiscoveryError::Decode)
+}
+
+/// Broadcasts a discovery ping on the local network and collects replies until the timeout
+pub async fn discover(timeout: Duration) -> Result<Vec<HostInfo>, DiscoveryError> {
+    discover_at((Ipv4Addr::BROADCAST, DEFAULT_PORT).into(), timeout).await
+}
+
+/// Sends a discovery ping to `target`, which may be a broadcast address, and collects
+/// every reply received before the timeout
+pub async fn discover_at(
+    target: SocketAddr,
+    timeout: Duration,
+) -> Result<Vec<HostInfo>, DiscoveryError> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await?;
+    socket.set_broadcast(true)?;
+
+    let start = Instant::now();
+    socket
+        .send_to(
+            &write_framework_packet(FrameworkPacket::DiscoverHost),
+            target,
+        )
+        .await?;
+
+    let deadline = time::Instant::now() + timeout;
+    let mut hosts: Vec<HostInfo> = vec![];
+    let mut buf = [0u8; 1024];
+    loop {
+        let (length, address) = match time::timeout_at(deadline, socket.recv_from(&mut buf)).await {
+            Ok(result) => result?,
+            Err(_) => break,
+        };
+        if hosts.iter().any(|host| host.address == address) {
+            continue;
+        }
+
+        let mut reader = Reader::new(buf[..length].to_vec());
+        match read_host_info(&mut reader, address, start.elapsed()) {
+            Ok(host) => {
+                tracing::debug!("Discovered '{}' at {address}", host.name);
+                hosts.push(host);
+            }
+            Err(e) => tracing::warn!("Ignoring malformed discovery reply from {address}: {e}"),
+        }
+    }
+
+    Ok(hosts)
+}
@@ -0,0 +1,68 @@
+use base64::{Engine, engine::general_purpose};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::net::IpAddr;
+use std::path::Path;
+
+/// The ids a client identifies itself with. Servers use the uuid to recognize players
+/// across sessions, e.g. for admins and bans, and the usid to tell apart installs
+/// sharing a uuid. Like the game, one uuid is used everywhere while a separate usid is
+/// generated for every server ip.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Identity {
+    pub uuid: String,
+    #[serde(default)]
+    pub usids: HashMap<String, String>,
+}
+
+/// 8 random bytes in base64, the format of both uuids and usids
+pub fn generate_id() -> String {
+    let mut bytes = [0u8; 8];
+    fastrand::fill(&mut bytes);
+    general_purpose::STANDARD.encode(bytes)
+}
+
+/// Whether `id` is in the format of `generate_id`
+pub fn is_valid_id(id: &str) -> bool {
+    general_purpose::STANDARD
+        .decode(id)
+        .is_ok_and(|bytes| bytes.len() == 8)
+}
+
+impl Identity {
+    pub fn generate() -> Identity {
+        Identity {
+            uuid: generate_id(),
+            usids: HashMap::new(),
+        }
+    }
+
+    /// Loads the identity stored at `path`, generating and saving a new one if the file
+    /// does not exist yet
+    pub fn load_or_create(path: impl AsRef<Path>) -> io::Result<Identity> {
+        let path = path.as_ref();
+        if path.exists() {
+            let data = fs::read_to_string(path)?;
+            Ok(serde_json::from_str(&data)?)
+        } else {
+            tracing::info!("Generating new identity at {}", path.display());
+            let identity = Identity::generate();
+            identity.save(path)?;
+            Ok(identity)
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+
+    /// The usid used for the server at `ip`, generated on first use
+    pub fn usid(&mut self, ip: IpAddr) -> String {
+        self.usids
+            .entry(ip.to_string())
+            .or_insert_with(generate_id)
+            .clone()
+    }
+}
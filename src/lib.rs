@@ -1,9 +1,18 @@
 pub mod packet;
-mod stream_builder;
+pub mod protocol;
+pub mod stream_builder;
 pub mod type_io;
 pub mod unit_io;
 
-mod arc_types;
+pub mod arc_types;
 pub mod block_io;
+pub mod capture;
 pub mod client;
+pub mod codec;
+pub mod content;
+pub mod discovery;
+pub mod identity;
+pub mod rules;
 pub mod save_io;
+pub mod server;
+pub mod version;
@@ -1,23 +1,40 @@
-use crate::save_io::{Map, read_content_header, read_map};
+use crate::block_io::{Block, read_block, read_block_items, write_block_items};
+use crate::content::ContentRegistry;
+use crate::rules::Rules;
+use crate::save_io::{
+    Map, MapInfo, MapTile, Marker, TeamPlan, lookup_block, read_content_header, read_custom_chunks,
+    read_map, read_markers, read_team_blocks, write_content_header, write_custom_chunks, write_map,
+    write_markers, write_team_blocks,
+};
 use crate::type_io::{
-    KickReason, Object, Reader, Tile, Unit, read_kick, read_object, read_prefixed_string,
-    read_string, read_string_map, read_tile, read_unit, write_byte, write_float, write_int,
-    write_string, write_unsigned_short,
+    AdminAction, Content, FieldContext, KickReason, Object, Reader, Tile, TraceInfo, Unit, Vec2,
+    read_admin_action, read_build_healths, read_command, read_content, read_int_seq, read_ints,
+    read_json, read_kick, read_object, read_prefixed_string, read_string, read_string_data,
+    read_string_map, read_strings, read_tile, read_tiles, read_trace_info, read_unit, read_vec2,
+    write_admin_action, write_bool, write_build_healths, write_byte, write_command, write_content,
+    write_double, write_float, write_int, write_int_seq, write_ints, write_json, write_kick,
+    write_long, write_object, write_prefixed_string, write_short, write_string, write_string_data,
+    write_string_map, write_strings, write_tile, write_tiles, write_trace_info, write_unit,
+    write_unsigned_short, write_utf, write_vec2,
+};
+use crate::unit_io::{
+    FullUnit, Plan, PlayerInfo, read_full_unit, read_plans_queue, read_player_info,
+    write_full_unit, write_plans_queue, write_player_info,
 };
-use crate::unit_io::{FullUnit, Plan, read_full_unit, write_plans};
+use crate::version::ProtocolVersion;
 use base64::Engine;
 use base64::engine::general_purpose;
+use flate2::Compression;
 use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
 use lz4::block::{compress, decompress};
 use num_enum::TryFromPrimitive;
 use std::collections::HashMap;
-use std::fs;
-use std::io::Read;
-use std::path::PathBuf;
+use std::fmt;
+use std::io::{Read, Write};
 use std::sync::Arc;
-use tokio::io::AsyncReadExt;
+use tokio::io::{AsyncRead, AsyncReadExt};
 use tokio::net::UdpSocket;
-use tokio::net::tcp::OwnedReadHalf;
 
 #[derive(Debug)]
 pub enum PacketError {
@@ -26,8 +43,90 @@ pub enum PacketError {
     UnknownFrameworkPacket,
     DecompressionFailed,
     WorldDataDecompressionFailed,
+    Io(std::io::Error),
+    /// The packet data did not match the expected layout
+    Decode {
+        packet_id: Option<u8>,
+        /// Byte offset into the (decompressed) data being read
+        offset: usize,
+        /// Path of the field that failed, e.g. `units.controller`
+        field: String,
+        reason: String,
+    },
+    /// A packet holds a value its layout can not express
+    Encode {
+        packet_id: Option<u8>,
+        field: String,
+        reason: String,
+    },
+}
+impl PacketError {
+    pub fn with_packet_id(self, id: u8) -> Self {
+        match self {
+            PacketError::Decode {
+                offset,
+                field,
+                reason,
+                ..
+            } => PacketError::Decode {
+                packet_id: Some(id),
+                offset,
+                field,
+                reason,
+            },
+            PacketError::Encode { field, reason, .. } => PacketError::Encode {
+                packet_id: Some(id),
+                field,
+                reason,
+            },
+            other => other,
+        }
+    }
+}
+impl fmt::Display for PacketError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PacketError::Decode {
+                packet_id,
+                offset,
+                field,
+                reason,
+            } => {
+                match packet_id {
+                    Some(id) => write!(f, "Failed to decode packet {id}")?,
+                    None => write!(f, "Failed to decode packet")?,
+                }
+                if !field.is_empty() {
+                    write!(f, " field `{field}`")?;
+                }
+                write!(f, " at offset {offset}: {reason}")
+            }
+            PacketError::Encode {
+                packet_id,
+                field,
+                reason,
+            } => {
+                match packet_id {
+                    Some(id) => write!(f, "Failed to encode packet {id}")?,
+                    None => write!(f, "Failed to encode packet")?,
+                }
+                if !field.is_empty() {
+                    write!(f, " field `{field}`")?;
+                }
+                write!(f, ": {reason}")
+            }
+            other => write!(f, "{other:?}"),
+        }
+    }
+}
+impl std::error::Error for PacketError {}
+impl From<std::io::Error> for PacketError {
+    fn from(e: std::io::Error) -> Self {
+        PacketError::Io(e)
+    }
 }
 
+#[derive(Debug)]
 pub enum AnyPacket {
     Framework(FrameworkPacket),
     Regular(Packet),
@@ -48,37 +147,148 @@ pub enum PacketId {
     StreamChunk = 1,
     WorldStream = 2,
     Connect = 3,
-
+    AdminRequestCall = 4,
+    AnnounceCall = 5,
+    AssemblerDroneSpawnedCall = 6,
+    AssemblerUnitSpawnedCall = 7,
+    AutoDoorToggleCall = 8,
     BeginBreak = 9,
     BeginPlace = 10,
     BlockSnapshot = 11,
-
+    BuildDestroyedCall = 12,
+    BuildHealthUpdateCall = 13,
+    BuildingControlSelectCall = 14,
+    ClearItemsCall = 15,
+    ClientPacketReliableCall = 16,
+    ClientPacketUnreliableCall = 17,
     ClientSnapshot = 18,
-
+    CommandBuildingCall = 19,
+    CommandUnitsCall = 20,
+    ConnectCall = 21,
     ConnectCallConfirm = 22,
     ConstructFinish = 23,
-
+    CreateBulletCall = 24,
+    CreateWeatherCall = 25,
+    DebugStatusClientCall = 26,
+    DebugStatusClientUnreliableCall = 27,
     DeconstructFinish = 28,
-
+    DeletePlansCall = 29,
+    DropItemCall = 30,
+    EffectCall = 31,
+    EffectCall2 = 32,
+    EffectReliableCall = 33,
     EntitySnapshot = 34,
-
+    FollowUpMenuCall = 35,
+    GameOverCall = 36,
+    HiddenSnapshotCall = 37,
+    HideFollowUpMenuCall = 38,
+    HideHudTextCall = 39,
+    InfoMessageCall = 40,
+    InfoPopupCall = 41,
+    InfoPopupReliableCall = 42,
+    InfoToastCall = 43,
     KickCall = 44,
     KickCall2 = 45,
-
+    LabelCall = 46,
+    LabelReliableCall = 47,
+    LogicExplosionCall = 48,
+    MenuCall = 49,
+    MenuChooseCall = 50,
+    ObjectiveCompletedCall = 51,
+    OpenUriCall = 52,
+    PayloadDroppedCall = 53,
+    PickedBuildPayloadCall = 54,
+    PickedUnitPayloadCall = 55,
+    PingCall = 56,
+    PingResponseCall = 57,
+    PlayerDisconnectCall = 58,
     SpawnCall = 59,
-
+    RemoveQueueBlockCall = 60,
+    RemoveTileCall = 61,
+    RemoveWorldLabelCall = 62,
+    RequestBuildPayloadCall = 63,
+    RequestDebugStatusCall = 64,
+    RequestDropPayloadCall = 65,
+    RequestItemCall = 66,
+    RequestUnitPayloadCall = 67,
+    ResearchedCall = 68,
     RotateBlockCall = 69,
-
+    SectorCaptureCall = 70,
     SendChatMessageCall = 71,
-
+    SendMessageCall = 72,
     SendMessageCall2 = 73,
-
+    ServerPacketReliableCall = 74,
+    ServerPacketUnreliableCall = 75,
+    SetCameraPositionCall = 76,
+    SetFlagCall = 77,
+    SetFloorCall = 78,
+    SetHudTextCall = 79,
+    SetHudTextReliableCall = 80,
+    SetItemCall = 81,
+    SetMapAreaCall = 82,
+    SetObjectivesCall = 83,
+    SetOverlayCall = 84,
+    SetPlayerTeamEditorCall = 85,
+    SetPositionCall = 86,
+    SetRulesCall = 87,
+    SetTeamCall = 88,
+    SetTileCall = 89,
+    SetUnitCommandCall = 90,
+    SoundCall = 91,
+    SoundAtCall = 92,
+    SpawnEffectCall = 93,
     StateSnapshot = 94,
-
+    SyncVariableCall = 95,
+    TakeItemsCall = 96,
+    TextInputCall = 97,
+    TextInputResultCall = 98,
     TileConfigCall = 99,
+    TileTapCall = 100,
+    TraceInfoCall = 101,
+    TransferInventoryCall = 102,
+    TransferItemToCall = 103,
+    TransferItemToUnitCall = 104,
+    UnitBlockSpawnCall = 105,
+    UnitBuildingControlSelectCall = 106,
+    UnitCapDeathCall = 107,
+    UnitClearCall = 108,
+    UnitControlCall = 109,
+    UnitDeathCall = 110,
+    UnitDestroyCall = 111,
+    UnitEnteredPayloadCall = 112,
+    UnitEnvDeathCall = 113,
+    UnitSafeDeathCall = 114,
+    UnitTetherBlockSpawnedCall = 115,
+    UpdateGameOverCall = 116,
+    WarningToastCall = 117,
+    WorldDataBeginCall = 118,
 }
 
-#[derive(Debug)]
+/// The world a server streams to a joining player
+#[derive(Debug, Clone, PartialEq)]
+pub struct World {
+    pub rules: Rules,
+    pub map_info: MapInfo,
+    pub wave: u32,
+    pub wave_time: f32,
+    pub tick: f64,
+    pub seed0: u64,
+    pub seed1: u64,
+    pub id: u32,
+    pub player: PlayerInfo,
+    pub content_map: ContentRegistry,
+    pub map: Map,
+    /// Rebuild plans of each team, by team id
+    pub team_plans: HashMap<u32, Vec<TeamPlan>>,
+    pub markers: HashMap<i32, Marker>,
+    pub custom_chunks: HashMap<String, Vec<u8>>,
+}
+
+/// Every packet of protocol version 146, numbered by packet id.
+///
+/// Calls are laid out the way the side that normally sends them writes them, so calls
+/// forwarded by the server (tile config, rotate block, ...) include the acting player.
+#[derive(Debug, Clone, PartialEq)]
 pub enum Packet {
     // [000] Stream begin
     StreamBegin {
@@ -92,17 +302,7 @@ pub enum Packet {
         data: Vec<u8>,
     },
     // [002] Completed world stream
-    WorldStream {
-        // TODO
-        /* rules */ /* map */ wave: u32,
-        wave_time: f32,
-        tick: f64,
-        seed0: u64,
-        seed1: u64,
-        id: u32,
-        content_map: HashMap<String, Vec<String>>, /* more */
-        map: Map,
-    },
+    WorldStream(Box<World>),
     // [003] Connect to server
     Connect {
         version: u32,
@@ -116,10 +316,29 @@ pub enum Packet {
         mods: Vec<String>,
     },
     // [004] Admin Request
+    AdminRequestCall {
+        other: u32,
+        action: AdminAction,
+        params: Object,
+    },
     // [005] Announce
+    AnnounceCall {
+        message: String,
+    },
     // [006] Assembler Drone Spawned
+    AssemblerDroneSpawnedCall {
+        tile: Tile,
+        id: u32,
+    },
     // [007] Assembler Unit Spawned
+    AssemblerUnitSpawnedCall {
+        tile: Tile,
+    },
     // [008] Auto Door Toggle
+    AutoDoorToggleCall {
+        tile: Tile,
+        open: bool,
+    },
     // [009] Begin Break
     BeginBreak {
         unit: Unit,
@@ -142,11 +361,32 @@ pub enum Packet {
         data: Vec<u8>,
     },
     // [012] Build Destroyed
+    BuildDestroyedCall {
+        build: Tile,
+    },
     // [013] Build Health Update
+    BuildHealthUpdateCall {
+        healths: Vec<(Tile, f32)>,
+    },
     // [014] Building Control Select
+    BuildingControlSelectCall {
+        player: u32,
+        build: Tile,
+    },
     // [015] Clear Items
+    ClearItemsCall {
+        build: Tile,
+    },
     // [016] Client Packet Reliable
+    ClientPacketReliableCall {
+        packet_type: String,
+        contents: String,
+    },
     // [017] Client Packet Unreliable
+    ClientPacketUnreliableCall {
+        packet_type: String,
+        contents: String,
+    },
     // [018] Client Snapshot
     ClientSnapshot {
         snapshot_id: u32,
@@ -173,8 +413,24 @@ pub enum Packet {
         view_height: f32,
     },
     // [019] Command Building
+    CommandBuildingCall {
+        player: u32,
+        buildings: Vec<u32>,
+        target: Vec2,
+    },
     // [020] Command Units
+    CommandUnitsCall {
+        player: u32,
+        unit_ids: Vec<u32>,
+        build_target: Tile,
+        unit_target: Unit,
+        pos_target: Vec2,
+    },
     // [021] Connect Call
+    ConnectCall {
+        ip: String,
+        port: u32,
+    },
     // [022] Connect Call Confirm
     ConnectCallConfirm,
     // [023] Construct Finish
@@ -186,34 +442,132 @@ pub enum Packet {
         team: u8,
         config: Object,
     },
-    // [024]
-    // [025]
-    // [026]
-    // [027]
+    // [024] Create Bullet
+    CreateBulletCall {
+        bullet_type: i16,
+        team: u8,
+        x: f32,
+        y: f32,
+        angle: f32,
+        damage: f32,
+        velocity_scale: f32,
+        lifetime_scale: f32,
+    },
+    // [025] Create Weather
+    CreateWeatherCall {
+        weather: i16,
+        intensity: f32,
+        duration: f32,
+        wind_x: f32,
+        wind_y: f32,
+    },
+    // [026] Debug Status Client
+    DebugStatusClientCall {
+        value: u32,
+        last_client_snapshot: u32,
+        snapshots_sent: u32,
+    },
+    // [027] Debug Status Client Unreliable
+    DebugStatusClientUnreliableCall {
+        value: u32,
+        last_client_snapshot: u32,
+        snapshots_sent: u32,
+    },
     // [028] Deconstruct Finish
     DeconstructFinish {
         tile: Tile,
         block: i16,
         builder: Unit,
     },
-    // [029]
-    // [030]
-    // [031]
-    // [032]
-    // [033]
+    // [029] Delete Plans
+    DeletePlansCall {
+        player: u32,
+        positions: Vec<Tile>,
+    },
+    // [030] Drop Item
+    DropItemCall {
+        player: u32,
+        angle: f32,
+    },
+    // [031] Effect
+    EffectCall {
+        effect: i16,
+        x: f32,
+        y: f32,
+        rotation: f32,
+        color: u32,
+    },
+    // [032] Effect with data
+    EffectCall2 {
+        effect: i16,
+        x: f32,
+        y: f32,
+        rotation: f32,
+        color: u32,
+        data: Object,
+    },
+    // [033] Effect Reliable
+    EffectReliableCall {
+        effect: i16,
+        x: f32,
+        y: f32,
+        rotation: f32,
+        color: u32,
+    },
     // [034] Entity Snapshot
     EntitySnapshot {
         units: HashMap<u32, FullUnit>,
     },
-    // [035]
-    // [036]
-    // [037]
-    // [038]
-    // [039]
-    // [040]
-    // [041]
-    // [042]
-    // [043]
+    // [035] Follow Up Menu
+    FollowUpMenuCall {
+        menu_id: u32,
+        title: String,
+        message: String,
+        options: Vec<Vec<String>>,
+    },
+    // [036] Game Over
+    GameOverCall {
+        winner: u8,
+    },
+    // [037] Hidden Snapshot
+    HiddenSnapshotCall {
+        ids: Vec<u32>,
+    },
+    // [038] Hide Follow Up Menu
+    HideFollowUpMenuCall {
+        menu_id: u32,
+    },
+    // [039] Hide Hud Text
+    HideHudTextCall,
+    // [040] Info Message
+    InfoMessageCall {
+        message: String,
+    },
+    // [041] Info Popup
+    InfoPopupCall {
+        message: String,
+        duration: f32,
+        align: u32,
+        top: u32,
+        left: u32,
+        bottom: u32,
+        right: u32,
+    },
+    // [042] Info Popup Reliable
+    InfoPopupReliableCall {
+        message: String,
+        duration: f32,
+        align: u32,
+        top: u32,
+        left: u32,
+        bottom: u32,
+        right: u32,
+    },
+    // [043] Info Toast
+    InfoToastCall {
+        message: String,
+        duration: f32,
+    },
     // [044] Kick with a custom message
     KickCall {
         reason: String,
@@ -222,72 +576,259 @@ pub enum Packet {
     KickCall2 {
         reason: KickReason,
     },
-    // [046]
-    // [047]
-    // [048]
-    // [049]
-    // [050]
-    // [051]
-    // [052]
-    // [053]
-    // [054]
-    // [055]
-    // [056]
-    // [057]
-    // [058]
+    // [046] Label
+    LabelCall {
+        message: String,
+        duration: f32,
+        world_x: f32,
+        world_y: f32,
+    },
+    // [047] Label Reliable
+    LabelReliableCall {
+        message: String,
+        duration: f32,
+        world_x: f32,
+        world_y: f32,
+    },
+    // [048] Logic Explosion
+    LogicExplosionCall {
+        team: u8,
+        x: f32,
+        y: f32,
+        radius: f32,
+        damage: f32,
+        air: bool,
+        ground: bool,
+        pierce: bool,
+    },
+    // [049] Menu
+    MenuCall {
+        menu_id: u32,
+        title: String,
+        message: String,
+        options: Vec<Vec<String>>,
+    },
+    // [050] Menu Choose
+    MenuChooseCall {
+        player: u32,
+        menu_id: u32,
+        option: u32,
+    },
+    // [051] Objective Completed
+    ObjectiveCompletedCall {
+        flags_removed: Vec<String>,
+        flags_added: Vec<String>,
+    },
+    // [052] Open URI
+    OpenUriCall {
+        uri: String,
+    },
+    // [053] Payload Dropped
+    PayloadDroppedCall {
+        unit: Unit,
+        x: f32,
+        y: f32,
+    },
+    // [054] Picked Build Payload
+    PickedBuildPayloadCall {
+        unit: Unit,
+        build: Tile,
+        on_ground: bool,
+    },
+    // [055] Picked Unit Payload
+    PickedUnitPayloadCall {
+        unit: Unit,
+        target: Unit,
+    },
+    // [056] Ping
+    PingCall {
+        time: u64,
+    },
+    // [057] Ping Response
+    PingResponseCall {
+        time: u64,
+    },
+    // [058] Player Disconnect
+    PlayerDisconnectCall {
+        player: u32,
+    },
     // [059] Spawn call
     SpawnCall {
         tile_x: i16,
         tile_y: i16,
         entity: u32,
     },
-    // [060]
-    // [061]
-    // [062]
-    // [063]
-    // [064]
-    // [065]
-    // [066]
-    // [067]
-    // [068]
+    // [060] Remove Queue Block
+    RemoveQueueBlockCall {
+        x: u32,
+        y: u32,
+        breaking: bool,
+    },
+    // [061] Remove Tile
+    RemoveTileCall {
+        tile: Tile,
+    },
+    // [062] Remove World Label
+    RemoveWorldLabelCall {
+        id: u32,
+    },
+    // [063] Request Build Payload
+    RequestBuildPayloadCall {
+        build: Tile,
+    },
+    // [064] Request Debug Status
+    RequestDebugStatusCall,
+    // [065] Request Drop Payload
+    RequestDropPayloadCall {
+        x: f32,
+        y: f32,
+    },
+    // [066] Request Item
+    RequestItemCall {
+        player: u32,
+        build: Tile,
+        item: i16,
+        amount: u32,
+    },
+    // [067] Request Unit Payload
+    RequestUnitPayloadCall {
+        target: Unit,
+    },
+    // [068] Researched
+    ResearchedCall {
+        content: Content,
+    },
     // [069] Rotate Block Call
     RotateBlockCall {
         entity: u32,
         tile: Tile,
         rotation: u8,
     },
-    // [070]
+    // [070] Sector Capture
+    SectorCaptureCall,
     // [071] Send a chat message to server
     SendChatMessageCall {
         message: String,
     },
-    // [072]
+    // [072] Received a chat message without a sender from server
+    SendMessageCall {
+        message: String,
+    },
     // [073] Received a chat message from server
     SendMessageCall2 {
         message: String,
         unformatted: Option<String>,
         sender: u32,
     },
-    // [074]
-    // [075]
-    // [076]
-    // [077]
-    // [078]
-    // [079]
-    // [080]
-    // [081]
-    // [082]
-    // [083]
-    // [084]
-    // [085]
+    // [074] Server Packet Reliable
+    ServerPacketReliableCall {
+        packet_type: String,
+        contents: String,
+    },
+    // [075] Server Packet Unreliable
+    ServerPacketUnreliableCall {
+        packet_type: String,
+        contents: String,
+    },
+    // [076] Set Camera Position
+    SetCameraPositionCall {
+        x: f32,
+        y: f32,
+    },
+    // [077] Set Flag
+    SetFlagCall {
+        flag: String,
+        add: bool,
+    },
+    // [078] Set Floor
+    SetFloorCall {
+        tile: Tile,
+        floor: i16,
+        overlay: i16,
+    },
+    // [079] Set Hud Text
+    SetHudTextCall {
+        message: String,
+    },
+    // [080] Set Hud Text Reliable
+    SetHudTextReliableCall {
+        message: String,
+    },
+    // [081] Set Item
+    SetItemCall {
+        build: Tile,
+        item: i16,
+        amount: u32,
+    },
+    // [082] Set Map Area
+    SetMapAreaCall {
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    },
+    // [083] Set Objectives
+    SetObjectivesCall {
+        objectives: String,
+    },
+    // [084] Set Overlay
+    SetOverlayCall {
+        tile: Tile,
+        overlay: i16,
+    },
+    // [085] Set Player Team Editor
+    SetPlayerTeamEditorCall {
+        team: u8,
+    },
     // [086] Set position call
-    // SetPositionCall { x: f32, y: f32 },
-    // [088]
-    // [089]
-    // [090]
-    // [091]
-    // [092]
-    // [093]
+    SetPositionCall {
+        x: f32,
+        y: f32,
+    },
+    // [087] Set Rules
+    SetRulesCall {
+        rules: String,
+    },
+    // [088] Set Team
+    SetTeamCall {
+        build: Tile,
+        team: u8,
+    },
+    // [089] Set Tile
+    SetTileCall {
+        tile: Tile,
+        block: i16,
+        team: u8,
+        rotation: u32,
+    },
+    // [090] Set Unit Command
+    SetUnitCommandCall {
+        player: u32,
+        unit_ids: Vec<u32>,
+        command: Option<u8>,
+    },
+    // [091] Sound
+    SoundCall {
+        sound: i16,
+        volume: f32,
+        pitch: f32,
+        pan: f32,
+    },
+    // [092] Sound At
+    SoundAtCall {
+        sound: i16,
+        x: f32,
+        y: f32,
+        volume: f32,
+        pitch: f32,
+    },
+    // [093] Spawn Effect
+    SpawnEffectCall {
+        x: f32,
+        y: f32,
+        rotation: f32,
+        unit_type: i16,
+    },
     // [094] StateSnapshot
     StateSnapshot {
         wave_time: f32,
@@ -299,49 +840,151 @@ pub enum Packet {
         tps: u8,
         rand0: u64,
         rand1: u64,
-        core_data: Vec<u8>,
+        /// Items in the first core of every team with cores, by team and item id
+        cores: HashMap<u8, HashMap<i16, u32>>,
+    },
+    // [095] Sync Variable
+    SyncVariableCall {
+        build: Tile,
+        variable: u32,
+        value: Object,
+    },
+    // [096] Take Items
+    TakeItemsCall {
+        build: Tile,
+        item: i16,
+        amount: u32,
+        to: Unit,
+    },
+    // [097] Text Input
+    TextInputCall {
+        text_input_id: u32,
+        title: String,
+        message: String,
+        text_length: u32,
+        default: String,
+        numeric: bool,
+    },
+    // [098] Text Input Result
+    TextInputResultCall {
+        text_input_id: u32,
+        text: Option<String>,
     },
-    // [095]
-    // [096]
-    // [097]
-    // [098]
     // [099] TileConfigCall
     TileConfigCall {
         player: u32,
         tile: Tile,
         value: Object,
     },
-    // [100]
-    // [101]
-    // [102]
-    // [103]
-    // [104]
-    // [105]
-    // [106]
-    // [107]
-    // [108]
-    // [109]
-    // [110]
-    // [111]
-    // [112]
-    // [113]
-    // [114]
-    // [115]
-    // [116]
-    // [117]
-    // [118]
+    // [100] Tile Tap
+    TileTapCall {
+        player: u32,
+        tile: Tile,
+    },
+    // [101] Trace Info
+    TraceInfoCall {
+        player: u32,
+        info: TraceInfo,
+    },
+    // [102] Transfer Inventory
+    TransferInventoryCall {
+        player: u32,
+        build: Tile,
+    },
+    // [103] Transfer Item To
+    TransferItemToCall {
+        unit: Unit,
+        item: i16,
+        amount: u32,
+        x: f32,
+        y: f32,
+        build: Tile,
+    },
+    // [104] Transfer Item To Unit
+    TransferItemToUnitCall {
+        item: i16,
+        x: f32,
+        y: f32,
+        to: u32,
+    },
+    // [105] Unit Block Spawn
+    UnitBlockSpawnCall {
+        tile: Tile,
+    },
+    // [106] Unit Building Control Select
+    UnitBuildingControlSelectCall {
+        unit: Unit,
+        build: Tile,
+    },
+    // [107] Unit Cap Death
+    UnitCapDeathCall {
+        unit: Unit,
+    },
+    // [108] Unit Clear
+    UnitClearCall {
+        player: u32,
+    },
+    // [109] Unit Control
+    UnitControlCall {
+        player: u32,
+        unit: Unit,
+    },
+    // [110] Unit Death
+    UnitDeathCall {
+        id: u32,
+    },
+    // [111] Unit Destroy
+    UnitDestroyCall {
+        id: u32,
+    },
+    // [112] Unit Entered Payload
+    UnitEnteredPayloadCall {
+        unit: Unit,
+        build: Tile,
+    },
+    // [113] Unit Env Death
+    UnitEnvDeathCall {
+        unit: Unit,
+    },
+    // [114] Unit Safe Death
+    UnitSafeDeathCall {
+        unit: Unit,
+    },
+    // [115] Unit Tether Block Spawned
+    UnitTetherBlockSpawnedCall {
+        tile: Tile,
+        id: u32,
+    },
+    // [116] Update Game Over
+    UpdateGameOverCall {
+        winner: u8,
+    },
+    // [117] Warning Toast
+    WarningToastCall {
+        unicode: u32,
+        text: String,
+    },
+    // [118] World Data Begin
+    WorldDataBeginCall,
     Other(u8),
 }
 
 pub async fn read_packet_tcp(
-    stream: &mut OwnedReadHalf,
-    content_map: &Option<HashMap<String, Vec<String>>>,
+    stream: &mut (impl AsyncRead + Unpin),
+    content_map: &Option<ContentRegistry>,
+    version: ProtocolVersion,
 ) -> Result<AnyPacket, PacketError> {
+    let buf = read_frame_tcp(stream).await?;
+    parse_packet(buf, content_map, version)
+}
+
+/// Reads the next length-prefixed frame without parsing it
+pub async fn read_frame_tcp(stream: &mut (impl AsyncRead + Unpin)) -> Result<Vec<u8>, PacketError> {
     let mut buf = [0u8; 2];
     let length = match stream.read_exact(&mut buf).await {
         Ok(_) => u16::from_be_bytes(buf),
         Err(e) => {
-            eprintln!("{}", e);
+            tracing::debug!("Failed to read frame: {e}");
             return Err(PacketError::FailedToReadLength);
         }
     };
@@ -350,72 +993,86 @@ pub async fn read_packet_tcp(
     match stream.read_exact(&mut buf).await {
         Ok(_) => {}
         Err(e) => {
-            eprintln!("{}", e);
+            tracing::debug!("Failed to read frame: {e}");
             return Err(PacketError::FailedToReadData);
         }
     }
 
-    parse_packet(buf, content_map)
+    Ok(buf)
 }
 
 pub async fn read_packet_udp(
     socket: &mut Arc<UdpSocket>,
-    content_map: &Option<HashMap<String, Vec<String>>>,
+    content_map: &Option<ContentRegistry>,
+    version: ProtocolVersion,
 ) -> Result<AnyPacket, PacketError> {
     let mut buf = [0u8; 32768];
-    let length = socket.recv(&mut buf).await.unwrap();
+    let length = socket.recv(&mut buf).await?;
     let data = &buf[..length];
 
-    parse_packet(Vec::from(data), content_map)
+    parse_packet(Vec::from(data), content_map, version)
 }
 
 pub fn parse_packet(
-    mut buf: Vec<u8>,
-    content_map: &Option<HashMap<String, Vec<String>>>,
+    buf: Vec<u8>,
+    content_map: &Option<ContentRegistry>,
+    version: ProtocolVersion,
 ) -> Result<AnyPacket, PacketError> {
-    let mut reader = Reader::new(buf);
+    let (packet, trailing) = parse_packet_checked(buf, content_map, version)?;
+    if trailing != 0 {
+        tracing::warn!("Packet {packet:?} has {trailing} remaining bytes");
+    }
+    Ok(packet)
+}
+
+/// Parses a frame like `parse_packet`, also returning how many bytes of the packet
+/// data were left unread
+pub fn parse_packet_checked(
+    mut buf: Vec<u8>,
+    content_map: &Option<ContentRegistry>,
+    version: ProtocolVersion,
+) -> Result<(AnyPacket, usize), PacketError> {
+    let mut reader = Reader::with_version(buf, version);
 
-    let id = reader.byte();
+    let id = reader.byte().field("id")?;
 
     if id == 254 {
-        Ok(AnyPacket::Framework(parse_framework_packet(reader)?))
+        let packet = parse_framework_packet(&mut reader)?;
+        Ok((AnyPacket::Framework(packet), reader.remaining()))
     } else {
-        let data_length = reader.short();
+        let data_length = reader.unsigned_short().field("length")?;
 
-        let compressed = reader.byte();
+        let compressed = reader.byte().field("compressed")?;
 
         if compressed == 1 {
-            buf = match decompress(&*reader.read_remaining(), Some(data_length as i32)) {
+            buf = match decompress(&reader.read_remaining(), Some(data_length as i32)) {
                 Ok(buf) => buf,
                 Err(e) => {
-                    eprintln!("{e}");
+                    tracing::warn!("Failed to decompress packet: {e}");
                     return Err(PacketError::DecompressionFailed);
                 }
             };
-            reader = Reader::new(buf);
+            reader = Reader::with_version(buf, version);
         }
-        Ok(AnyPacket::Regular(parse_regular_packet(
-            id,
-            reader,
-            content_map,
-        )?))
+        let packet = read_regular_packet(id, &mut reader, content_map)?;
+        Ok((AnyPacket::Regular(packet), reader.remaining()))
     }
 }
 
-fn parse_framework_packet(mut reader: Reader) -> Result<FrameworkPacket, PacketError> {
-    let id = reader.byte();
+fn parse_framework_packet(reader: &mut Reader) -> Result<FrameworkPacket, PacketError> {
+    let id = reader.byte().field("id")?;
     Ok(match id {
         1 => FrameworkPacket::DiscoverHost,
         2 => FrameworkPacket::KeepAlive,
         3 => {
-            let bytes = reader.bytes(4);
+            let bytes = reader.bytes(4).field("id")?;
             let mut data_buf = [0u8; 4];
             data_buf.copy_from_slice(&bytes);
             let data = u32::from_be_bytes(data_buf);
             FrameworkPacket::RegisterUDP(data)
         }
         4 => {
-            let bytes = reader.bytes(4);
+            let bytes = reader.bytes(4).field("id")?;
             let mut data_buf = [0u8; 4];
             data_buf.copy_from_slice(&bytes);
             let data = u32::from_be_bytes(data_buf);
@@ -428,7 +1085,26 @@ fn parse_framework_packet(mut reader: Reader) -> Result<FrameworkPacket, PacketE
 pub fn parse_regular_packet(
     id: u8,
     mut reader: Reader,
-    content_map: &Option<HashMap<String, Vec<String>>>,
+    content_map: &Option<ContentRegistry>,
+) -> Result<Packet, PacketError> {
+    let result = read_regular_packet(id, &mut reader, content_map)?;
+
+    if reader.remaining() != 0 {
+        tracing::warn!(
+            "Packet with id {id} has {} remaining bytes",
+            reader.remaining()
+        );
+    }
+
+    Ok(result)
+}
+
+/// Reads the data of a regular packet, leaving any bytes it does not use in the reader.
+/// Packets with unknown ids consume all of their data.
+pub fn read_regular_packet(
+    id: u8,
+    reader: &mut Reader,
+    content_map: &Option<ContentRegistry>,
 ) -> Result<Packet, PacketError> {
     let packet_id = match PacketId::try_from(id) {
         Ok(parsed_id) => {
@@ -437,15 +1113,24 @@ pub fn parse_regular_packet(
         }
         Err(_) => {
             tracing::debug!("Skipping packet {id}");
+            reader.read_remaining();
             return Ok(Packet::Other(id));
         }
     };
 
-    let result = match packet_id {
+    parse_packet_data(packet_id, reader, content_map).map_err(|error| error.with_packet_id(id))
+}
+
+fn parse_packet_data(
+    packet_id: PacketId,
+    reader: &mut Reader,
+    content_map: &Option<ContentRegistry>,
+) -> Result<Packet, PacketError> {
+    match packet_id {
         PacketId::StreamBegin => {
-            let id = reader.int();
-            let total = reader.int();
-            let stream_type = reader.byte();
+            let id = reader.int().field("id")?;
+            let total = reader.int().field("total")?;
+            let stream_type = reader.byte().field("stream_type")?;
             Ok(Packet::StreamBegin {
                 id,
                 total,
@@ -453,9 +1138,9 @@ pub fn parse_regular_packet(
             })
         }
         PacketId::StreamChunk => {
-            let id = reader.int();
-            let length = reader.short();
-            let data = reader.bytes(length as usize);
+            let id = reader.int().field("id")?;
+            let length = reader.short().field("length")?;
+            let data = reader.bytes(length as usize).field("data")?;
             Ok(Packet::StreamChunk { id, data })
         }
         PacketId::WorldStream => {
@@ -470,52 +1155,37 @@ pub fn parse_regular_packet(
                     return Err(PacketError::WorldDataDecompressionFailed);
                 }
             }
-            reader = Reader::new(data);
-
-            let rules_json = read_string(&mut reader); // TODO
-            let map = read_string_map(&mut reader); // TODO
-            let wave = reader.int();
-            let wave_time = reader.float();
-            let tick = reader.double();
-            let seed0 = reader.long();
-            let seed1 = reader.long();
-            let id = reader.int();
-
-            reader.short();
-            reader.byte();
-            reader.byte();
-            reader.int();
-            reader.byte();
-            reader.float();
-            reader.float();
-            read_prefixed_string(&mut reader);
-            reader.byte();
-            reader.byte();
-            reader.byte();
-            reader.byte();
-            reader.int();
-            reader.float();
-            reader.float();
-
-            let content_map = read_content_header(&mut reader);
-
-            let default_content_map_path = PathBuf::from("content-map.json");
-            let default_content_map_data = serde_json::to_string(&content_map).unwrap();
-            fs::write(&default_content_map_path, default_content_map_data).unwrap();
-
-            let map = read_map(&mut reader, &content_map);
-            //let team_blocks = read_team_blocks(&mut reader);
-            //println!("{team_blocks:?}");
-
-            //println!("{:?}", reader.read_remaining());
-
-            // let markers = read_markers(&mut reader);
-            // println!("{markers:?}");
-            // let custom_chunks = read_custom_chunks(&mut reader);
-            // println!("{custom_chunks:?}");
+            // The rest is read from the world data, so bytes it leaves unread count as
+            // trailing bytes of the packet
+            *reader = Reader::with_version(data, reader.version());
+
+            let rules_json = read_string(reader).field("rules")?.unwrap_or_default();
+            let rules = Rules::from_json_or_default(&rules_json);
+            let tags: HashMap<String, String> = read_string_map(reader)
+                .field("tags")?
+                .into_iter()
+                .map(|(key, value)| (key, value.unwrap_or_default()))
+                .collect();
+            let wave = reader.int().field("wave")?;
+            let wave_time = reader.float().field("wave_time")?;
+            let tick = reader.double().field("tick")?;
+            let seed0 = reader.long().field("seed0")?;
+            let seed1 = reader.long().field("seed1")?;
+            let id = reader.int().field("id")?;
+            let player = read_player_info(reader).field("player")?;
+
+            let content_map = read_content_header(reader).field("content_map")?;
+            let map = read_map(reader, &content_map).field("map")?;
+            let map_info = MapInfo::new(tags, &map);
+            let team_plans = read_team_blocks(reader).field("team_plans")?;
+            let markers = read_markers(reader).field("markers")?;
+            let custom_chunks = read_custom_chunks(reader).field("custom_chunks")?;
 
             tracing::debug!("World loaded!");
-            Ok(Packet::WorldStream {
+            Ok(Packet::WorldStream(Box::new(World {
+                rules,
+                map_info,
+                player,
                 wave,
                 wave_time,
                 tick,
@@ -524,35 +1194,166 @@ pub fn parse_regular_packet(
                 id,
                 content_map,
                 map,
+                team_plans,
+                markers,
+                custom_chunks,
+            })))
+        }
+        PacketId::Connect => {
+            let version = reader.int().field("version")?;
+            let client = read_prefixed_string(reader)
+                .field("client")?
+                .unwrap_or_default();
+            let name = read_prefixed_string(reader)
+                .field("name")?
+                .unwrap_or_default();
+            let lang = read_prefixed_string(reader)
+                .field("lang")?
+                .unwrap_or_default();
+            let usid = read_prefixed_string(reader)
+                .field("usid")?
+                .unwrap_or_default();
+            let uuid = general_purpose::STANDARD.encode(reader.bytes(8)?);
+            let _crc = reader.long().field("_crc")?;
+            let mobile = reader.bool().field("mobile")?;
+            let color = reader.bytes(4).field("color")?;
+            let mod_count = reader.byte().field("mod_count")?;
+            let mut mods = vec![];
+            for _ in 0..mod_count {
+                mods.push(read_prefixed_string(reader)?.unwrap_or_default());
+            }
+            Ok(Packet::Connect {
+                version,
+                client,
+                name,
+                lang,
+                usid,
+                uuid,
+                mobile,
+                color,
+                mods,
             })
         }
+        PacketId::AdminRequestCall => Ok(Packet::AdminRequestCall {
+            other: reader.int().field("other")?,
+            action: read_admin_action(reader).field("action")?,
+            params: read_object(reader).field("params")?,
+        }),
+        PacketId::AnnounceCall => Ok(Packet::AnnounceCall {
+            message: read_prefixed_string(reader)
+                .field("message")?
+                .unwrap_or_default(),
+        }),
+        PacketId::AssemblerDroneSpawnedCall => Ok(Packet::AssemblerDroneSpawnedCall {
+            tile: read_tile(reader).field("tile")?,
+            id: reader.int().field("id")?,
+        }),
+        PacketId::AssemblerUnitSpawnedCall => Ok(Packet::AssemblerUnitSpawnedCall {
+            tile: read_tile(reader).field("tile")?,
+        }),
+        PacketId::AutoDoorToggleCall => Ok(Packet::AutoDoorToggleCall {
+            tile: read_tile(reader).field("tile")?,
+            open: reader.bool().field("open")?,
+        }),
         PacketId::BeginBreak => Ok(Packet::BeginBreak {
-            unit: read_unit(&mut reader),
-            team: reader.byte(),
-            x: reader.int(),
-            y: reader.int(),
+            unit: read_unit(reader).field("unit")?,
+            team: reader.byte().field("team")?,
+            x: reader.int().field("x")?,
+            y: reader.int().field("y")?,
         }),
         PacketId::BeginPlace => Ok(Packet::BeginPlace {
-            unit: read_unit(&mut reader),
-            result: reader.unsigned_short(),
-            team: reader.byte(),
-            x: reader.int(),
-            y: reader.int(),
-            rotation: reader.int(),
+            unit: read_unit(reader).field("unit")?,
+            result: reader.unsigned_short().field("result")?,
+            team: reader.byte().field("team")?,
+            x: reader.int().field("x")?,
+            y: reader.int().field("y")?,
+            rotation: reader.int().field("rotation")?,
         }),
         PacketId::BlockSnapshot => {
-            let amount = reader.short();
-            let data_length = reader.short();
-            let data = reader.bytes(data_length as usize);
+            let amount = reader.short().field("amount")?;
+            let data_length = reader.short().field("data_length")?;
+            let data = reader.bytes(data_length as usize).field("data")?;
             Ok(Packet::BlockSnapshot { amount, data })
         }
+        PacketId::BuildDestroyedCall => Ok(Packet::BuildDestroyedCall {
+            build: read_tile(reader).field("build")?,
+        }),
+        PacketId::BuildHealthUpdateCall => Ok(Packet::BuildHealthUpdateCall {
+            healths: read_build_healths(reader).field("healths")?,
+        }),
+        PacketId::BuildingControlSelectCall => Ok(Packet::BuildingControlSelectCall {
+            player: reader.int().field("player")?,
+            build: read_tile(reader).field("build")?,
+        }),
+        PacketId::ClearItemsCall => Ok(Packet::ClearItemsCall {
+            build: read_tile(reader).field("build")?,
+        }),
+        PacketId::ClientPacketReliableCall => Ok(Packet::ClientPacketReliableCall {
+            packet_type: read_prefixed_string(reader)
+                .field("packet_type")?
+                .unwrap_or_default(),
+            contents: read_prefixed_string(reader)
+                .field("contents")?
+                .unwrap_or_default(),
+        }),
+        PacketId::ClientPacketUnreliableCall => Ok(Packet::ClientPacketUnreliableCall {
+            packet_type: read_prefixed_string(reader)
+                .field("packet_type")?
+                .unwrap_or_default(),
+            contents: read_prefixed_string(reader)
+                .field("contents")?
+                .unwrap_or_default(),
+        }),
+        PacketId::ClientSnapshot => Ok(Packet::ClientSnapshot {
+            snapshot_id: reader.int().field("snapshot_id")?,
+            unit_id: reader.int().field("unit_id")?,
+            dead: reader.bool().field("dead")?,
+            x: reader.float().field("x")?,
+            y: reader.float().field("y")?,
+            pointer_x: reader.float().field("pointer_x")?,
+            pointer_y: reader.float().field("pointer_y")?,
+            rotation: reader.float().field("rotation")?,
+            base_rotation: reader.float().field("base_rotation")?,
+            x_velocity: reader.float().field("x_velocity")?,
+            y_velocity: reader.float().field("y_velocity")?,
+            mining_x: reader.unsigned_short().field("mining_x")?,
+            mining_y: reader.unsigned_short().field("mining_y")?,
+            boosting: reader.bool().field("boosting")?,
+            shooting: reader.bool().field("shooting")?,
+            chatting: reader.bool().field("chatting")?,
+            building: reader.bool().field("building")?,
+            plans: read_plans_queue(reader).field("plans")?,
+            view_x: reader.float().field("view_x")?,
+            view_y: reader.float().field("view_y")?,
+            view_width: reader.float().field("view_width")?,
+            view_height: reader.float().field("view_height")?,
+        }),
+        PacketId::CommandBuildingCall => Ok(Packet::CommandBuildingCall {
+            player: reader.int().field("player")?,
+            buildings: read_ints(reader).field("buildings")?,
+            target: read_vec2(reader).field("target")?,
+        }),
+        PacketId::CommandUnitsCall => Ok(Packet::CommandUnitsCall {
+            player: reader.int().field("player")?,
+            unit_ids: read_ints(reader).field("unit_ids")?,
+            build_target: read_tile(reader).field("build_target")?,
+            unit_target: read_unit(reader).field("unit_target")?,
+            pos_target: read_vec2(reader).field("pos_target")?,
+        }),
+        PacketId::ConnectCall => Ok(Packet::ConnectCall {
+            ip: read_prefixed_string(reader)
+                .field("ip")?
+                .unwrap_or_default(),
+            port: reader.int().field("port")?,
+        }),
+        PacketId::ConnectCallConfirm => Ok(Packet::ConnectCallConfirm),
         PacketId::ConstructFinish => {
-            let tile = read_tile(&mut reader);
-            let block = reader.short();
-            let builder = read_unit(&mut reader);
-            let rotation = reader.byte();
-            let team = reader.byte();
-            let config = read_object(&mut reader);
+            let tile = read_tile(reader).field("tile")?;
+            let block = reader.short().field("block")?;
+            let builder = read_unit(reader).field("builder")?;
+            let rotation = reader.byte().field("rotation")?;
+            let team = reader.byte().field("team")?;
+            let config = read_object(reader).field("config")?;
             Ok(Packet::ConstructFinish {
                 tile,
                 block,
@@ -562,92 +1363,433 @@ pub fn parse_regular_packet(
                 config,
             })
         }
+        PacketId::CreateBulletCall => Ok(Packet::CreateBulletCall {
+            bullet_type: reader.short().field("bullet_type")?,
+            team: reader.byte().field("team")?,
+            x: reader.float().field("x")?,
+            y: reader.float().field("y")?,
+            angle: reader.float().field("angle")?,
+            damage: reader.float().field("damage")?,
+            velocity_scale: reader.float().field("velocity_scale")?,
+            lifetime_scale: reader.float().field("lifetime_scale")?,
+        }),
+        PacketId::CreateWeatherCall => Ok(Packet::CreateWeatherCall {
+            weather: reader.short().field("weather")?,
+            intensity: reader.float().field("intensity")?,
+            duration: reader.float().field("duration")?,
+            wind_x: reader.float().field("wind_x")?,
+            wind_y: reader.float().field("wind_y")?,
+        }),
+        PacketId::DebugStatusClientCall => Ok(Packet::DebugStatusClientCall {
+            value: reader.int().field("value")?,
+            last_client_snapshot: reader.int().field("last_client_snapshot")?,
+            snapshots_sent: reader.int().field("snapshots_sent")?,
+        }),
+        PacketId::DebugStatusClientUnreliableCall => Ok(Packet::DebugStatusClientUnreliableCall {
+            value: reader.int().field("value")?,
+            last_client_snapshot: reader.int().field("last_client_snapshot")?,
+            snapshots_sent: reader.int().field("snapshots_sent")?,
+        }),
         PacketId::DeconstructFinish => {
-            let tile = read_tile(&mut reader);
-            let block = reader.short();
-            let builder = read_unit(&mut reader);
+            let tile = read_tile(reader).field("tile")?;
+            let block = reader.short().field("block")?;
+            let builder = read_unit(reader).field("builder")?;
             Ok(Packet::DeconstructFinish {
                 tile,
                 block,
                 builder,
             })
         }
+        PacketId::DeletePlansCall => Ok(Packet::DeletePlansCall {
+            player: reader.int().field("player")?,
+            positions: read_tiles(reader).field("positions")?,
+        }),
+        PacketId::DropItemCall => Ok(Packet::DropItemCall {
+            player: reader.int().field("player")?,
+            angle: reader.float().field("angle")?,
+        }),
+        PacketId::EffectCall => Ok(Packet::EffectCall {
+            effect: reader.short().field("effect")?,
+            x: reader.float().field("x")?,
+            y: reader.float().field("y")?,
+            rotation: reader.float().field("rotation")?,
+            color: reader.int().field("color")?,
+        }),
+        PacketId::EffectCall2 => Ok(Packet::EffectCall2 {
+            effect: reader.short().field("effect")?,
+            x: reader.float().field("x")?,
+            y: reader.float().field("y")?,
+            rotation: reader.float().field("rotation")?,
+            color: reader.int().field("color")?,
+            data: read_object(reader).field("data")?,
+        }),
+        PacketId::EffectReliableCall => Ok(Packet::EffectReliableCall {
+            effect: reader.short().field("effect")?,
+            x: reader.float().field("x")?,
+            y: reader.float().field("y")?,
+            rotation: reader.float().field("rotation")?,
+            color: reader.int().field("color")?,
+        }),
         PacketId::EntitySnapshot => {
             let mut units = HashMap::new();
 
-            let amount = reader.short();
-            let byte_count = reader.short();
-            let data = reader.bytes(byte_count as usize);
+            let amount = reader.short().field("amount")?;
+            let byte_count = reader.short().field("byte_count")?;
+            let data = reader.bytes(byte_count as usize).field("data")?;
 
-            let mut unit_reader = Reader::new(data);
+            let mut unit_reader = Reader::with_version(data, reader.version());
 
             for _ in 0..amount {
-                let id = unit_reader.int();
-                let unit_type = unit_reader.byte();
+                let id = unit_reader.int().field("id")?;
+                let unit_type = unit_reader.byte().field("unit_type")?;
                 let content = match content_map {
                     Some(map) => map,
                     None => {
-                        println!("Received unit data before map was loaded, ignoring");
+                        tracing::warn!("Received unit data before map was loaded, ignoring");
                         return Ok(Packet::Other(34));
                     }
                 };
-                let unit = read_full_unit(&mut unit_reader, unit_type, false, content);
+                let unit =
+                    read_full_unit(&mut unit_reader, unit_type, false, content).field("unit")?;
                 units.insert(id, unit);
             }
 
             Ok(Packet::EntitySnapshot { units })
         }
+        PacketId::FollowUpMenuCall => Ok(Packet::FollowUpMenuCall {
+            menu_id: reader.int().field("menu_id")?,
+            title: read_prefixed_string(reader)
+                .field("title")?
+                .unwrap_or_default(),
+            message: read_prefixed_string(reader)
+                .field("message")?
+                .unwrap_or_default(),
+            options: read_string_data(reader).field("options")?,
+        }),
+        PacketId::GameOverCall => Ok(Packet::GameOverCall {
+            winner: reader.byte().field("winner")?,
+        }),
+        PacketId::HiddenSnapshotCall => Ok(Packet::HiddenSnapshotCall {
+            ids: read_int_seq(reader).field("ids")?,
+        }),
+        PacketId::HideFollowUpMenuCall => Ok(Packet::HideFollowUpMenuCall {
+            menu_id: reader.int().field("menu_id")?,
+        }),
+        PacketId::HideHudTextCall => Ok(Packet::HideHudTextCall),
+        PacketId::InfoMessageCall => Ok(Packet::InfoMessageCall {
+            message: read_prefixed_string(reader)
+                .field("message")?
+                .unwrap_or_default(),
+        }),
+        PacketId::InfoPopupCall => Ok(Packet::InfoPopupCall {
+            message: read_prefixed_string(reader)
+                .field("message")?
+                .unwrap_or_default(),
+            duration: reader.float().field("duration")?,
+            align: reader.int().field("align")?,
+            top: reader.int().field("top")?,
+            left: reader.int().field("left")?,
+            bottom: reader.int().field("bottom")?,
+            right: reader.int().field("right")?,
+        }),
+        PacketId::InfoPopupReliableCall => Ok(Packet::InfoPopupReliableCall {
+            message: read_prefixed_string(reader)
+                .field("message")?
+                .unwrap_or_default(),
+            duration: reader.float().field("duration")?,
+            align: reader.int().field("align")?,
+            top: reader.int().field("top")?,
+            left: reader.int().field("left")?,
+            bottom: reader.int().field("bottom")?,
+            right: reader.int().field("right")?,
+        }),
+        PacketId::InfoToastCall => Ok(Packet::InfoToastCall {
+            message: read_prefixed_string(reader)
+                .field("message")?
+                .unwrap_or_default(),
+            duration: reader.float().field("duration")?,
+        }),
         PacketId::KickCall => {
-            let reason = read_prefixed_string(&mut reader).unwrap();
+            let reason = read_prefixed_string(reader)
+                .field("reason")?
+                .unwrap_or_default();
             Ok(Packet::KickCall { reason })
         }
         PacketId::KickCall2 => {
-            let reason = read_kick(&mut reader).unwrap();
+            let reason = read_kick(reader).field("reason")?;
             Ok(Packet::KickCall2 { reason })
         }
+        PacketId::LabelCall => Ok(Packet::LabelCall {
+            message: read_prefixed_string(reader)
+                .field("message")?
+                .unwrap_or_default(),
+            duration: reader.float().field("duration")?,
+            world_x: reader.float().field("world_x")?,
+            world_y: reader.float().field("world_y")?,
+        }),
+        PacketId::LabelReliableCall => Ok(Packet::LabelReliableCall {
+            message: read_prefixed_string(reader)
+                .field("message")?
+                .unwrap_or_default(),
+            duration: reader.float().field("duration")?,
+            world_x: reader.float().field("world_x")?,
+            world_y: reader.float().field("world_y")?,
+        }),
+        PacketId::LogicExplosionCall => Ok(Packet::LogicExplosionCall {
+            team: reader.byte().field("team")?,
+            x: reader.float().field("x")?,
+            y: reader.float().field("y")?,
+            radius: reader.float().field("radius")?,
+            damage: reader.float().field("damage")?,
+            air: reader.bool().field("air")?,
+            ground: reader.bool().field("ground")?,
+            pierce: reader.bool().field("pierce")?,
+        }),
+        PacketId::MenuCall => Ok(Packet::MenuCall {
+            menu_id: reader.int().field("menu_id")?,
+            title: read_prefixed_string(reader)
+                .field("title")?
+                .unwrap_or_default(),
+            message: read_prefixed_string(reader)
+                .field("message")?
+                .unwrap_or_default(),
+            options: read_string_data(reader).field("options")?,
+        }),
+        PacketId::MenuChooseCall => Ok(Packet::MenuChooseCall {
+            player: reader.int().field("player")?,
+            menu_id: reader.int().field("menu_id")?,
+            option: reader.int().field("option")?,
+        }),
+        PacketId::ObjectiveCompletedCall => Ok(Packet::ObjectiveCompletedCall {
+            flags_removed: read_strings(reader).field("flags_removed")?,
+            flags_added: read_strings(reader).field("flags_added")?,
+        }),
+        PacketId::OpenUriCall => Ok(Packet::OpenUriCall {
+            uri: read_prefixed_string(reader)
+                .field("uri")?
+                .unwrap_or_default(),
+        }),
+        PacketId::PayloadDroppedCall => Ok(Packet::PayloadDroppedCall {
+            unit: read_unit(reader).field("unit")?,
+            x: reader.float().field("x")?,
+            y: reader.float().field("y")?,
+        }),
+        PacketId::PickedBuildPayloadCall => Ok(Packet::PickedBuildPayloadCall {
+            unit: read_unit(reader).field("unit")?,
+            build: read_tile(reader).field("build")?,
+            on_ground: reader.bool().field("on_ground")?,
+        }),
+        PacketId::PickedUnitPayloadCall => Ok(Packet::PickedUnitPayloadCall {
+            unit: read_unit(reader).field("unit")?,
+            target: read_unit(reader).field("target")?,
+        }),
+        PacketId::PingCall => Ok(Packet::PingCall {
+            time: reader.long().field("time")?,
+        }),
+        PacketId::PingResponseCall => Ok(Packet::PingResponseCall {
+            time: reader.long().field("time")?,
+        }),
+        PacketId::PlayerDisconnectCall => Ok(Packet::PlayerDisconnectCall {
+            player: reader.int().field("player")?,
+        }),
         PacketId::SpawnCall => {
-            let tile_x = reader.short();
-            let tile_y = reader.short();
-            let entity = reader.int();
+            let tile_x = reader.short().field("tile_x")?;
+            let tile_y = reader.short().field("tile_y")?;
+            let entity = reader.int().field("entity")?;
             Ok(Packet::SpawnCall {
                 tile_x,
                 tile_y,
                 entity,
             })
         }
+        PacketId::RemoveQueueBlockCall => Ok(Packet::RemoveQueueBlockCall {
+            x: reader.int().field("x")?,
+            y: reader.int().field("y")?,
+            breaking: reader.bool().field("breaking")?,
+        }),
+        PacketId::RemoveTileCall => Ok(Packet::RemoveTileCall {
+            tile: read_tile(reader).field("tile")?,
+        }),
+        PacketId::RemoveWorldLabelCall => Ok(Packet::RemoveWorldLabelCall {
+            id: reader.int().field("id")?,
+        }),
+        PacketId::RequestBuildPayloadCall => Ok(Packet::RequestBuildPayloadCall {
+            build: read_tile(reader).field("build")?,
+        }),
+        PacketId::RequestDebugStatusCall => Ok(Packet::RequestDebugStatusCall),
+        PacketId::RequestDropPayloadCall => Ok(Packet::RequestDropPayloadCall {
+            x: reader.float().field("x")?,
+            y: reader.float().field("y")?,
+        }),
+        PacketId::RequestItemCall => Ok(Packet::RequestItemCall {
+            player: reader.int().field("player")?,
+            build: read_tile(reader).field("build")?,
+            item: reader.short().field("item")?,
+            amount: reader.int().field("amount")?,
+        }),
+        PacketId::RequestUnitPayloadCall => Ok(Packet::RequestUnitPayloadCall {
+            target: read_unit(reader).field("target")?,
+        }),
+        PacketId::ResearchedCall => Ok(Packet::ResearchedCall {
+            content: read_content(reader).field("content")?,
+        }),
         PacketId::RotateBlockCall => {
-            let entity = reader.int();
-            let tile = read_tile(&mut reader);
-            let rotation = reader.byte();
+            let entity = reader.int().field("entity")?;
+            let tile = read_tile(reader).field("tile")?;
+            let rotation = reader.byte().field("rotation")?;
             Ok(Packet::RotateBlockCall {
                 entity,
                 tile,
                 rotation,
             })
         }
+        PacketId::SectorCaptureCall => Ok(Packet::SectorCaptureCall),
+        PacketId::SendChatMessageCall => Ok(Packet::SendChatMessageCall {
+            message: read_prefixed_string(reader)
+                .field("message")?
+                .unwrap_or_default(),
+        }),
+        PacketId::SendMessageCall => Ok(Packet::SendMessageCall {
+            message: read_prefixed_string(reader)
+                .field("message")?
+                .unwrap_or_default(),
+        }),
         PacketId::SendMessageCall2 => {
-            let message = read_prefixed_string(&mut reader).unwrap();
-            let unformatted = read_prefixed_string(&mut reader);
-            let sender = reader.int();
+            let message = read_prefixed_string(reader)
+                .field("message")?
+                .unwrap_or_default();
+            let unformatted = read_prefixed_string(reader).field("unformatted")?;
+            let sender = reader.int().field("sender")?;
             Ok(Packet::SendMessageCall2 {
                 message,
                 unformatted,
                 sender,
             })
         }
+        PacketId::ServerPacketReliableCall => Ok(Packet::ServerPacketReliableCall {
+            packet_type: read_prefixed_string(reader)
+                .field("packet_type")?
+                .unwrap_or_default(),
+            contents: read_prefixed_string(reader)
+                .field("contents")?
+                .unwrap_or_default(),
+        }),
+        PacketId::ServerPacketUnreliableCall => Ok(Packet::ServerPacketUnreliableCall {
+            packet_type: read_prefixed_string(reader)
+                .field("packet_type")?
+                .unwrap_or_default(),
+            contents: read_prefixed_string(reader)
+                .field("contents")?
+                .unwrap_or_default(),
+        }),
+        PacketId::SetCameraPositionCall => Ok(Packet::SetCameraPositionCall {
+            x: reader.float().field("x")?,
+            y: reader.float().field("y")?,
+        }),
+        PacketId::SetFlagCall => Ok(Packet::SetFlagCall {
+            flag: read_prefixed_string(reader)
+                .field("flag")?
+                .unwrap_or_default(),
+            add: reader.bool().field("add")?,
+        }),
+        PacketId::SetFloorCall => Ok(Packet::SetFloorCall {
+            tile: read_tile(reader).field("tile")?,
+            floor: reader.short().field("floor")?,
+            overlay: reader.short().field("overlay")?,
+        }),
+        PacketId::SetHudTextCall => Ok(Packet::SetHudTextCall {
+            message: read_prefixed_string(reader)
+                .field("message")?
+                .unwrap_or_default(),
+        }),
+        PacketId::SetHudTextReliableCall => Ok(Packet::SetHudTextReliableCall {
+            message: read_prefixed_string(reader)
+                .field("message")?
+                .unwrap_or_default(),
+        }),
+        PacketId::SetItemCall => Ok(Packet::SetItemCall {
+            build: read_tile(reader).field("build")?,
+            item: reader.short().field("item")?,
+            amount: reader.int().field("amount")?,
+        }),
+        PacketId::SetMapAreaCall => Ok(Packet::SetMapAreaCall {
+            x: reader.int().field("x")?,
+            y: reader.int().field("y")?,
+            width: reader.int().field("width")?,
+            height: reader.int().field("height")?,
+        }),
+        PacketId::SetObjectivesCall => Ok(Packet::SetObjectivesCall {
+            objectives: read_json(reader).field("objectives")?,
+        }),
+        PacketId::SetOverlayCall => Ok(Packet::SetOverlayCall {
+            tile: read_tile(reader).field("tile")?,
+            overlay: reader.short().field("overlay")?,
+        }),
+        PacketId::SetPlayerTeamEditorCall => Ok(Packet::SetPlayerTeamEditorCall {
+            team: reader.byte().field("team")?,
+        }),
+        PacketId::SetPositionCall => Ok(Packet::SetPositionCall {
+            x: reader.float().field("x")?,
+            y: reader.float().field("y")?,
+        }),
+        PacketId::SetRulesCall => Ok(Packet::SetRulesCall {
+            rules: read_json(reader).field("rules")?,
+        }),
+        PacketId::SetTeamCall => Ok(Packet::SetTeamCall {
+            build: read_tile(reader).field("build")?,
+            team: reader.byte().field("team")?,
+        }),
+        PacketId::SetTileCall => Ok(Packet::SetTileCall {
+            tile: read_tile(reader).field("tile")?,
+            block: reader.short().field("block")?,
+            team: reader.byte().field("team")?,
+            rotation: reader.int().field("rotation")?,
+        }),
+        PacketId::SetUnitCommandCall => Ok(Packet::SetUnitCommandCall {
+            player: reader.int().field("player")?,
+            unit_ids: read_ints(reader).field("unit_ids")?,
+            command: read_command(reader).field("command")?,
+        }),
+        PacketId::SoundCall => Ok(Packet::SoundCall {
+            sound: reader.short().field("sound")?,
+            volume: reader.float().field("volume")?,
+            pitch: reader.float().field("pitch")?,
+            pan: reader.float().field("pan")?,
+        }),
+        PacketId::SoundAtCall => Ok(Packet::SoundAtCall {
+            sound: reader.short().field("sound")?,
+            x: reader.float().field("x")?,
+            y: reader.float().field("y")?,
+            volume: reader.float().field("volume")?,
+            pitch: reader.float().field("pitch")?,
+        }),
+        PacketId::SpawnEffectCall => Ok(Packet::SpawnEffectCall {
+            x: reader.float().field("x")?,
+            y: reader.float().field("y")?,
+            rotation: reader.float().field("rotation")?,
+            unit_type: reader.short().field("unit_type")?,
+        }),
         PacketId::StateSnapshot => {
-            let wave_time = reader.float();
-            let wave = reader.int();
-            let enemies = reader.int();
-            let paused = reader.bool();
-            let game_over = reader.bool();
-            let time_data = reader.int();
-            let tps = reader.byte();
-            let rand0 = reader.long();
-            let rand1 = reader.long();
-
-            let length = reader.short();
-            let core_data = reader.bytes(length as usize);
+            let wave_time = reader.float().field("wave_time")?;
+            let wave = reader.int().field("wave")?;
+            let enemies = reader.int().field("enemies")?;
+            let paused = reader.bool().field("paused")?;
+            let game_over = reader.bool().field("game_over")?;
+            let time_data = reader.int().field("time_data")?;
+            let tps = reader.byte().field("tps")?;
+            let rand0 = reader.long().field("rand0")?;
+            let rand1 = reader.long().field("rand1")?;
+
+            let length = reader.short().field("length")?;
+            let core_data = reader.bytes(length as usize).field("core_data")?;
+            let mut core_reader = Reader::with_version(core_data, reader.version());
+            let team_count = core_reader.byte().field("core_data")?;
+            let mut cores = HashMap::new();
+            for _ in 0..team_count {
+                let team = core_reader.byte().field("core_data")?;
+                let items = read_block_items(&mut core_reader, false).field("core_data")?;
+                cores.insert(team, items);
+            }
 
             Ok(Packet::StateSnapshot {
                 wave_time,
@@ -659,40 +1801,191 @@ pub fn parse_regular_packet(
                 tps,
                 rand0,
                 rand1,
-                core_data,
+                cores,
             })
         }
+        PacketId::SyncVariableCall => Ok(Packet::SyncVariableCall {
+            build: read_tile(reader).field("build")?,
+            variable: reader.int().field("variable")?,
+            value: read_object(reader).field("value")?,
+        }),
+        PacketId::TakeItemsCall => Ok(Packet::TakeItemsCall {
+            build: read_tile(reader).field("build")?,
+            item: reader.short().field("item")?,
+            amount: reader.int().field("amount")?,
+            to: read_unit(reader).field("to")?,
+        }),
+        PacketId::TextInputCall => Ok(Packet::TextInputCall {
+            text_input_id: reader.int().field("text_input_id")?,
+            title: read_prefixed_string(reader)
+                .field("title")?
+                .unwrap_or_default(),
+            message: read_prefixed_string(reader)
+                .field("message")?
+                .unwrap_or_default(),
+            text_length: reader.int().field("text_length")?,
+            default: read_prefixed_string(reader)
+                .field("default")?
+                .unwrap_or_default(),
+            numeric: reader.bool().field("numeric")?,
+        }),
+        PacketId::TextInputResultCall => Ok(Packet::TextInputResultCall {
+            text_input_id: reader.int().field("text_input_id")?,
+            text: read_prefixed_string(reader).field("text")?,
+        }),
         PacketId::TileConfigCall => {
-            let player = reader.int();
-            let tile = read_tile(&mut reader);
-            let value = read_object(&mut reader);
-            println!("Config: {tile:?} {value:?}");
+            let player = reader.int().field("player")?;
+            let tile = read_tile(reader).field("tile")?;
+            let value = read_object(reader).field("value")?;
+            tracing::debug!("Config: {tile:?} {value:?}");
             Ok(Packet::TileConfigCall {
                 player,
                 tile,
                 value,
             })
         }
-        _ => Ok(Packet::Other(id)),
-    };
+        PacketId::TileTapCall => Ok(Packet::TileTapCall {
+            player: reader.int().field("player")?,
+            tile: read_tile(reader).field("tile")?,
+        }),
+        PacketId::TraceInfoCall => Ok(Packet::TraceInfoCall {
+            player: reader.int().field("player")?,
+            info: read_trace_info(reader).field("info")?,
+        }),
+        PacketId::TransferInventoryCall => Ok(Packet::TransferInventoryCall {
+            player: reader.int().field("player")?,
+            build: read_tile(reader).field("build")?,
+        }),
+        PacketId::TransferItemToCall => Ok(Packet::TransferItemToCall {
+            unit: read_unit(reader).field("unit")?,
+            item: reader.short().field("item")?,
+            amount: reader.int().field("amount")?,
+            x: reader.float().field("x")?,
+            y: reader.float().field("y")?,
+            build: read_tile(reader).field("build")?,
+        }),
+        PacketId::TransferItemToUnitCall => Ok(Packet::TransferItemToUnitCall {
+            item: reader.short().field("item")?,
+            x: reader.float().field("x")?,
+            y: reader.float().field("y")?,
+            to: reader.int().field("to")?,
+        }),
+        PacketId::UnitBlockSpawnCall => Ok(Packet::UnitBlockSpawnCall {
+            tile: read_tile(reader).field("tile")?,
+        }),
+        PacketId::UnitBuildingControlSelectCall => Ok(Packet::UnitBuildingControlSelectCall {
+            unit: read_unit(reader).field("unit")?,
+            build: read_tile(reader).field("build")?,
+        }),
+        PacketId::UnitCapDeathCall => Ok(Packet::UnitCapDeathCall {
+            unit: read_unit(reader).field("unit")?,
+        }),
+        PacketId::UnitClearCall => Ok(Packet::UnitClearCall {
+            player: reader.int().field("player")?,
+        }),
+        PacketId::UnitControlCall => Ok(Packet::UnitControlCall {
+            player: reader.int().field("player")?,
+            unit: read_unit(reader).field("unit")?,
+        }),
+        PacketId::UnitDeathCall => Ok(Packet::UnitDeathCall {
+            id: reader.int().field("id")?,
+        }),
+        PacketId::UnitDestroyCall => Ok(Packet::UnitDestroyCall {
+            id: reader.int().field("id")?,
+        }),
+        PacketId::UnitEnteredPayloadCall => Ok(Packet::UnitEnteredPayloadCall {
+            unit: read_unit(reader).field("unit")?,
+            build: read_tile(reader).field("build")?,
+        }),
+        PacketId::UnitEnvDeathCall => Ok(Packet::UnitEnvDeathCall {
+            unit: read_unit(reader).field("unit")?,
+        }),
+        PacketId::UnitSafeDeathCall => Ok(Packet::UnitSafeDeathCall {
+            unit: read_unit(reader).field("unit")?,
+        }),
+        PacketId::UnitTetherBlockSpawnedCall => Ok(Packet::UnitTetherBlockSpawnedCall {
+            tile: read_tile(reader).field("tile")?,
+            id: reader.int().field("id")?,
+        }),
+        PacketId::UpdateGameOverCall => Ok(Packet::UpdateGameOverCall {
+            winner: reader.byte().field("winner")?,
+        }),
+        PacketId::WarningToastCall => Ok(Packet::WarningToastCall {
+            unicode: reader.int().field("unicode")?,
+            text: read_prefixed_string(reader)
+                .field("text")?
+                .unwrap_or_default(),
+        }),
+        PacketId::WorldDataBeginCall => Ok(Packet::WorldDataBeginCall),
+    }
+}
 
-    if reader.remaining() != 0 {
-        tracing::warn!(
-            "Packet with id {id} has {} remaining bytes",
-            reader.remaining()
-        );
+/// Decodes the buildings sent in a `BlockSnapshot`. Every building has to already be on
+/// `map`, as its data is only readable when the class is known.
+///
+/// Buildings are written back to back without lengths, so decoding stops at the first
+/// one that fails. The buildings before it are returned along with the error.
+pub fn read_block_snapshot(
+    amount: i16,
+    data: Vec<u8>,
+    version: ProtocolVersion,
+    content_map: &ContentRegistry,
+    map: &Map,
+) -> (Vec<(Tile, Block)>, Option<PacketError>) {
+    let mut reader = Reader::with_version(data, version);
+    let mut buildings = vec![];
+
+    for i in 0..amount {
+        match read_snapshot_building(&mut reader, content_map, map)
+            .field(&format!("buildings[{i}]"))
+        {
+            Ok(building) => buildings.push(building),
+            Err(e) => return (buildings, Some(e)),
+        }
+    }
+
+    (buildings, None)
+}
+
+fn read_snapshot_building(
+    reader: &mut Reader,
+    content_map: &ContentRegistry,
+    map: &Map,
+) -> Result<(Tile, Block), PacketError> {
+    let tile = read_tile(reader).field("tile")?;
+    let block_id = reader.short().field("block_id")?;
+
+    match map.get(tile.x as u32, tile.y as u32) {
+        Some(MapTile {
+            block_id: Some(id),
+            block: Some(_),
+            ..
+        }) if *id != block_id => {
+            return Err(reader.error(format!("block {block_id} at {tile:?} is {id} locally")));
+        }
+        Some(MapTile { block: Some(_), .. }) => {}
+        _ => return Err(reader.error(format!("no building at {tile:?}"))),
     }
 
-    result
+    let (block_name, block_type) = lookup_block(reader, content_map, block_id)?;
+    // Snapshots are written with the current revision of the class, not the one the
+    // building was loaded with
+    let revision = reader.version().building_revision(&block_type);
+    let block = read_block(reader, block_name, block_type, revision, content_map)?;
+    Ok((tile, block))
 }
 
 pub fn write_framework_packet(packet: FrameworkPacket) -> Vec<u8> {
     let mut data: Vec<u8> = vec![];
 
     match packet {
-        FrameworkPacket::DiscoverHost => {} // TODO
+        FrameworkPacket::DiscoverHost => {
+            data.push(0xFE);
+            data.push(0x01);
+        }
         FrameworkPacket::KeepAlive => {
-            data.extend_from_slice(&vec![0x00, 0x06, 0xFE, 0x03, 0x00, 0x00, 0x00, 0x00]);
+            data.push(0xFE);
+            data.push(0x02);
         }
         FrameworkPacket::RegisterUDP(id) => {
             data.push(0xFE);
@@ -708,10 +2001,130 @@ pub fn write_framework_packet(packet: FrameworkPacket) -> Vec<u8> {
     data
 }
 
-pub fn write_packet(packet: Packet) -> Vec<u8> {
+/// Encodes a framework packet with the length prefix used over TCP
+pub fn write_framework_frame(packet: FrameworkPacket) -> Vec<u8> {
+    let data = write_framework_packet(packet);
+    let mut buf = vec![];
+    write_unsigned_short(&mut buf, data.len() as u16);
+    buf.extend_from_slice(&data);
+    buf
+}
+
+/// Largest packet body that fits into a single frame, bigger packets have to be streamed
+pub const MAX_FRAME_DATA: usize = u16::MAX as usize - 4;
+/// Size of the `StreamChunk`s a streamed packet is split into
+pub const STREAM_CHUNK_SIZE: usize = 1024;
+
+/// Encodes a packet into a single frame. Packets whose data is larger than
+/// `MAX_FRAME_DATA` can not be framed this way, use `write_packet_frames` for those.
+pub fn write_packet(packet: Packet) -> Result<Vec<u8>, PacketError> {
+    let (id, data) = write_packet_data(packet)?;
+    Ok(frame_packet(id, data))
+}
+
+/// Encodes a packet into frames, splitting it into a stream if it does not fit into a
+/// single frame. `next_stream_id` is only advanced when a stream is started.
+pub fn write_packet_frames(
+    packet: Packet,
+    next_stream_id: &mut u32,
+) -> Result<Vec<Vec<u8>>, PacketError> {
+    let (id, data) = write_packet_data(packet)?;
+    if data.len() <= MAX_FRAME_DATA {
+        return Ok(vec![frame_packet(id, data)]);
+    }
+
+    let stream_id = *next_stream_id;
+    *next_stream_id = next_stream_id.wrapping_add(1);
+    tracing::debug!(
+        "Streaming packet {id} ({} bytes) as stream {stream_id}",
+        data.len()
+    );
+    split_stream(stream_id, id, &data)
+        .into_iter()
+        .map(write_packet)
+        .collect()
+}
+
+/// Splits encoded packet data into a `StreamBegin` followed by its `StreamChunk`s
+pub fn split_stream(stream_id: u32, stream_type: u8, data: &[u8]) -> Vec<Packet> {
+    let mut packets = vec![Packet::StreamBegin {
+        id: stream_id,
+        total: data.len() as u32,
+        stream_type,
+    }];
+    for chunk in data.chunks(STREAM_CHUNK_SIZE) {
+        packets.push(Packet::StreamChunk {
+            id: stream_id,
+            data: chunk.to_vec(),
+        });
+    }
+    packets
+}
+
+/// Encodes a packet's data without the frame header, returning its id
+pub fn write_packet_data(packet: Packet) -> Result<(u8, Vec<u8>), PacketError> {
     let mut data: Vec<u8> = vec![];
 
     let id = match packet {
+        Packet::StreamBegin {
+            id,
+            total,
+            stream_type,
+        } => {
+            write_int(&mut data, id);
+            write_int(&mut data, total);
+            write_byte(&mut data, stream_type);
+            0
+        }
+        Packet::StreamChunk { id, data: chunk } => {
+            write_int(&mut data, id);
+            write_short(&mut data, chunk.len() as i16);
+            data.extend_from_slice(&chunk);
+            1
+        }
+        Packet::WorldStream(world) => {
+            let World {
+                rules,
+                map_info,
+                wave,
+                wave_time,
+                tick,
+                seed0,
+                seed1,
+                id,
+                player,
+                content_map,
+                map,
+                team_plans,
+                markers,
+                custom_chunks,
+            } = *world;
+            let mut world = vec![];
+            write_utf(&mut world, &rules.to_json()).field("rules")?;
+            let tags = map_info
+                .to_tags()
+                .into_iter()
+                .map(|(key, value)| (key, Some(value)))
+                .collect();
+            write_string_map(&mut world, tags).field("tags")?;
+            write_int(&mut world, wave);
+            write_float(&mut world, wave_time);
+            write_double(&mut world, tick);
+            write_long(&mut world, seed0);
+            write_long(&mut world, seed1);
+            write_int(&mut world, id);
+            write_player_info(&mut world, player).field("player")?;
+            write_content_header(&mut world, &content_map).field("content_map")?;
+            write_map(&mut world, &map).field("map")?;
+            write_team_blocks(&mut world, &team_plans).field("team_plans")?;
+            write_markers(&mut world, &markers);
+            write_custom_chunks(&mut world, &custom_chunks).field("custom_chunks")?;
+
+            let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+            encoder.write_all(&world).unwrap();
+            data.extend_from_slice(&encoder.finish().unwrap());
+            2
+        }
         Packet::Connect {
             version,
             client,
@@ -724,19 +2137,23 @@ pub fn write_packet(packet: Packet) -> Vec<u8> {
             mods,
         } => {
             write_int(&mut data, version);
-            write_string(&mut data, &client);
-            write_string(&mut data, &name);
-            write_string(&mut data, &lang);
-            write_string(&mut data, &usid);
-
-            let uuid_bytes = general_purpose::STANDARD
-                .decode(uuid)
-                .expect("Invalid base64 UUID");
-            data.extend_from_slice(&uuid_bytes);
+            write_string(&mut data, &client).field("client")?;
+            write_string(&mut data, &name).field("name")?;
+            write_string(&mut data, &lang).field("lang")?;
+            write_string(&mut data, &usid).field("usid")?;
 
-            // Should be equivalent to this, for some reason the js library just puts 0's
-            // buf.putLong(crc32(uuid_buf));
-            data.extend_from_slice(&[0x00; 8]);
+            let uuid_bytes = match general_purpose::STANDARD.decode(&uuid) {
+                Ok(bytes) if bytes.len() == 8 => bytes,
+                _ => {
+                    return Err(PacketError::Encode {
+                        packet_id: Some(3),
+                        field: "uuid".to_string(),
+                        reason: format!("{uuid:?} is not the base64 of 8 bytes"),
+                    });
+                }
+            };
+            data.extend_from_slice(&uuid_bytes);
+            write_long(&mut data, crc32fast::hash(&uuid_bytes) as u64);
 
             data.push(mobile as u8);
 
@@ -744,11 +2161,104 @@ pub fn write_packet(packet: Packet) -> Vec<u8> {
 
             data.push(mods.len() as u8);
             for entry in mods {
-                write_string(&mut data, &entry);
+                write_string(&mut data, &entry).field("entry")?;
             }
 
             3
         }
+        Packet::AdminRequestCall {
+            other,
+            action,
+            params,
+        } => {
+            write_int(&mut data, other);
+            write_admin_action(&mut data, action);
+            write_object(&mut data, params).field("params")?;
+            4
+        }
+        Packet::AnnounceCall { message } => {
+            write_prefixed_string(&mut data, Some(&message)).field("message")?;
+            5
+        }
+        Packet::AssemblerDroneSpawnedCall { tile, id } => {
+            write_tile(&mut data, tile);
+            write_int(&mut data, id);
+            6
+        }
+        Packet::AssemblerUnitSpawnedCall { tile } => {
+            write_tile(&mut data, tile);
+            7
+        }
+        Packet::AutoDoorToggleCall { tile, open } => {
+            write_tile(&mut data, tile);
+            write_bool(&mut data, open);
+            8
+        }
+        Packet::BeginBreak { unit, team, x, y } => {
+            write_unit(&mut data, unit);
+            write_byte(&mut data, team);
+            write_int(&mut data, x);
+            write_int(&mut data, y);
+            9
+        }
+        Packet::BeginPlace {
+            unit,
+            result,
+            team,
+            x,
+            y,
+            rotation,
+        } => {
+            write_unit(&mut data, unit);
+            write_unsigned_short(&mut data, result);
+            write_byte(&mut data, team);
+            write_int(&mut data, x);
+            write_int(&mut data, y);
+            write_int(&mut data, rotation);
+            10
+        }
+        Packet::BlockSnapshot {
+            amount,
+            data: snapshot,
+        } => {
+            write_short(&mut data, amount);
+            write_short(&mut data, snapshot.len() as i16);
+            data.extend_from_slice(&snapshot);
+            11
+        }
+        Packet::BuildDestroyedCall { build } => {
+            write_tile(&mut data, build);
+            12
+        }
+        Packet::BuildHealthUpdateCall { healths } => {
+            write_build_healths(&mut data, healths);
+            13
+        }
+        Packet::BuildingControlSelectCall { player, build } => {
+            write_int(&mut data, player);
+            write_tile(&mut data, build);
+            14
+        }
+        Packet::ClearItemsCall { build } => {
+            write_tile(&mut data, build);
+            15
+        }
+        Packet::ClientPacketReliableCall {
+            packet_type,
+            contents,
+        } => {
+            write_prefixed_string(&mut data, Some(&packet_type)).field("packet_type")?;
+            write_prefixed_string(&mut data, Some(&contents)).field("contents")?;
+            16
+        }
+        Packet::ClientPacketUnreliableCall {
+            packet_type,
+            contents,
+        } => {
+            write_prefixed_string(&mut data, Some(&packet_type)).field("packet_type")?;
+            write_prefixed_string(&mut data, Some(&contents)).field("contents")?;
+            17
+        }
         Packet::ClientSnapshot {
             snapshot_id,
             unit_id,
@@ -767,15 +2277,15 @@ pub fn write_packet(packet: Packet) -> Vec<u8> {
             shooting,
             chatting,
             building,
+            plans,
             view_x,
             view_y,
-            plans,
             view_width,
             view_height,
         } => {
             write_int(&mut data, snapshot_id);
             write_int(&mut data, unit_id);
-            write_byte(&mut data, dead as u8);
+            write_bool(&mut data, dead);
             write_float(&mut data, x);
             write_float(&mut data, y);
             write_float(&mut data, pointer_x);
@@ -786,25 +2296,809 @@ pub fn write_packet(packet: Packet) -> Vec<u8> {
             write_float(&mut data, y_velocity);
             write_unsigned_short(&mut data, mining_x);
             write_unsigned_short(&mut data, mining_y);
-            write_byte(&mut data, boosting as u8);
-            write_byte(&mut data, shooting as u8);
-            write_byte(&mut data, chatting as u8);
-            write_byte(&mut data, building as u8);
-            write_plans(&mut data, plans);
+            write_bool(&mut data, boosting);
+            write_bool(&mut data, shooting);
+            write_bool(&mut data, chatting);
+            write_bool(&mut data, building);
+            write_plans_queue(&mut data, plans).field("plans")?;
             write_float(&mut data, view_x);
             write_float(&mut data, view_y);
             write_float(&mut data, view_width);
             write_float(&mut data, view_height);
             18
         }
+        Packet::CommandBuildingCall {
+            player,
+            buildings,
+            target,
+        } => {
+            write_int(&mut data, player);
+            write_ints(&mut data, buildings);
+            write_vec2(&mut data, target);
+            19
+        }
+        Packet::CommandUnitsCall {
+            player,
+            unit_ids,
+            build_target,
+            unit_target,
+            pos_target,
+        } => {
+            write_int(&mut data, player);
+            write_ints(&mut data, unit_ids);
+            write_tile(&mut data, build_target);
+            write_unit(&mut data, unit_target);
+            write_vec2(&mut data, pos_target);
+            20
+        }
+        Packet::ConnectCall { ip, port } => {
+            write_prefixed_string(&mut data, Some(&ip)).field("ip")?;
+            write_int(&mut data, port);
+            21
+        }
         Packet::ConnectCallConfirm => 22,
+        Packet::ConstructFinish {
+            tile,
+            block,
+            builder,
+            rotation,
+            team,
+            config,
+        } => {
+            write_tile(&mut data, tile);
+            write_short(&mut data, block);
+            write_unit(&mut data, builder);
+            write_byte(&mut data, rotation);
+            write_byte(&mut data, team);
+            write_object(&mut data, config).field("config")?;
+            23
+        }
+        Packet::CreateBulletCall {
+            bullet_type,
+            team,
+            x,
+            y,
+            angle,
+            damage,
+            velocity_scale,
+            lifetime_scale,
+        } => {
+            write_short(&mut data, bullet_type);
+            write_byte(&mut data, team);
+            write_float(&mut data, x);
+            write_float(&mut data, y);
+            write_float(&mut data, angle);
+            write_float(&mut data, damage);
+            write_float(&mut data, velocity_scale);
+            write_float(&mut data, lifetime_scale);
+            24
+        }
+        Packet::CreateWeatherCall {
+            weather,
+            intensity,
+            duration,
+            wind_x,
+            wind_y,
+        } => {
+            write_short(&mut data, weather);
+            write_float(&mut data, intensity);
+            write_float(&mut data, duration);
+            write_float(&mut data, wind_x);
+            write_float(&mut data, wind_y);
+            25
+        }
+        Packet::DebugStatusClientCall {
+            value,
+            last_client_snapshot,
+            snapshots_sent,
+        } => {
+            write_int(&mut data, value);
+            write_int(&mut data, last_client_snapshot);
+            write_int(&mut data, snapshots_sent);
+            26
+        }
+        Packet::DebugStatusClientUnreliableCall {
+            value,
+            last_client_snapshot,
+            snapshots_sent,
+        } => {
+            write_int(&mut data, value);
+            write_int(&mut data, last_client_snapshot);
+            write_int(&mut data, snapshots_sent);
+            27
+        }
+        Packet::DeconstructFinish {
+            tile,
+            block,
+            builder,
+        } => {
+            write_tile(&mut data, tile);
+            write_short(&mut data, block);
+            write_unit(&mut data, builder);
+            28
+        }
+        Packet::DeletePlansCall { player, positions } => {
+            write_int(&mut data, player);
+            write_tiles(&mut data, positions);
+            29
+        }
+        Packet::DropItemCall { player, angle } => {
+            write_int(&mut data, player);
+            write_float(&mut data, angle);
+            30
+        }
+        Packet::EffectCall {
+            effect,
+            x,
+            y,
+            rotation,
+            color,
+        } => {
+            write_short(&mut data, effect);
+            write_float(&mut data, x);
+            write_float(&mut data, y);
+            write_float(&mut data, rotation);
+            write_int(&mut data, color);
+            31
+        }
+        Packet::EffectCall2 {
+            effect,
+            x,
+            y,
+            rotation,
+            color,
+            data: effect_data,
+        } => {
+            write_short(&mut data, effect);
+            write_float(&mut data, x);
+            write_float(&mut data, y);
+            write_float(&mut data, rotation);
+            write_int(&mut data, color);
+            write_object(&mut data, effect_data).field("effect_data")?;
+            32
+        }
+        Packet::EffectReliableCall {
+            effect,
+            x,
+            y,
+            rotation,
+            color,
+        } => {
+            write_short(&mut data, effect);
+            write_float(&mut data, x);
+            write_float(&mut data, y);
+            write_float(&mut data, rotation);
+            write_int(&mut data, color);
+            33
+        }
+        Packet::EntitySnapshot { units } => {
+            let mut unit_data = vec![];
+            for (id, unit) in units.iter() {
+                write_int(&mut unit_data, *id);
+                write_byte(&mut unit_data, unit.class_id());
+                write_full_unit(&mut unit_data, unit.clone())?;
+            }
+            write_short(&mut data, units.len() as i16);
+            write_short(&mut data, unit_data.len() as i16);
+            data.extend_from_slice(&unit_data);
+            34
+        }
+        Packet::FollowUpMenuCall {
+            menu_id,
+            title,
+            message,
+            options,
+        } => {
+            write_int(&mut data, menu_id);
+            write_prefixed_string(&mut data, Some(&title)).field("title")?;
+            write_prefixed_string(&mut data, Some(&message)).field("message")?;
+            write_string_data(&mut data, options).field("options")?;
+            35
+        }
+        Packet::GameOverCall { winner } => {
+            write_byte(&mut data, winner);
+            36
+        }
+        Packet::HiddenSnapshotCall { ids } => {
+            write_int_seq(&mut data, ids);
+            37
+        }
+        Packet::HideFollowUpMenuCall { menu_id } => {
+            write_int(&mut data, menu_id);
+            38
+        }
+        Packet::HideHudTextCall => 39,
+        Packet::InfoMessageCall { message } => {
+            write_prefixed_string(&mut data, Some(&message)).field("message")?;
+            40
+        }
+        Packet::InfoPopupCall {
+            message,
+            duration,
+            align,
+            top,
+            left,
+            bottom,
+            right,
+        } => {
+            write_prefixed_string(&mut data, Some(&message)).field("message")?;
+            write_float(&mut data, duration);
+            write_int(&mut data, align);
+            write_int(&mut data, top);
+            write_int(&mut data, left);
+            write_int(&mut data, bottom);
+            write_int(&mut data, right);
+            41
+        }
+        Packet::InfoPopupReliableCall {
+            message,
+            duration,
+            align,
+            top,
+            left,
+            bottom,
+            right,
+        } => {
+            write_prefixed_string(&mut data, Some(&message)).field("message")?;
+            write_float(&mut data, duration);
+            write_int(&mut data, align);
+            write_int(&mut data, top);
+            write_int(&mut data, left);
+            write_int(&mut data, bottom);
+            write_int(&mut data, right);
+            42
+        }
+        Packet::InfoToastCall { message, duration } => {
+            write_prefixed_string(&mut data, Some(&message)).field("message")?;
+            write_float(&mut data, duration);
+            43
+        }
+        Packet::KickCall { reason } => {
+            write_prefixed_string(&mut data, Some(&reason)).field("reason")?;
+            44
+        }
+        Packet::KickCall2 { reason } => {
+            write_kick(&mut data, reason);
+            45
+        }
+        Packet::LabelCall {
+            message,
+            duration,
+            world_x,
+            world_y,
+        } => {
+            write_prefixed_string(&mut data, Some(&message)).field("message")?;
+            write_float(&mut data, duration);
+            write_float(&mut data, world_x);
+            write_float(&mut data, world_y);
+            46
+        }
+        Packet::LabelReliableCall {
+            message,
+            duration,
+            world_x,
+            world_y,
+        } => {
+            write_prefixed_string(&mut data, Some(&message)).field("message")?;
+            write_float(&mut data, duration);
+            write_float(&mut data, world_x);
+            write_float(&mut data, world_y);
+            47
+        }
+        Packet::LogicExplosionCall {
+            team,
+            x,
+            y,
+            radius,
+            damage,
+            air,
+            ground,
+            pierce,
+        } => {
+            write_byte(&mut data, team);
+            write_float(&mut data, x);
+            write_float(&mut data, y);
+            write_float(&mut data, radius);
+            write_float(&mut data, damage);
+            write_bool(&mut data, air);
+            write_bool(&mut data, ground);
+            write_bool(&mut data, pierce);
+            48
+        }
+        Packet::MenuCall {
+            menu_id,
+            title,
+            message,
+            options,
+        } => {
+            write_int(&mut data, menu_id);
+            write_prefixed_string(&mut data, Some(&title)).field("title")?;
+            write_prefixed_string(&mut data, Some(&message)).field("message")?;
+            write_string_data(&mut data, options).field("options")?;
+            49
+        }
+        Packet::MenuChooseCall {
+            player,
+            menu_id,
+            option,
+        } => {
+            write_int(&mut data, player);
+            write_int(&mut data, menu_id);
+            write_int(&mut data, option);
+            50
+        }
+        Packet::ObjectiveCompletedCall {
+            flags_removed,
+            flags_added,
+        } => {
+            write_strings(&mut data, flags_removed).field("flags_removed")?;
+            write_strings(&mut data, flags_added).field("flags_added")?;
+            51
+        }
+        Packet::OpenUriCall { uri } => {
+            write_prefixed_string(&mut data, Some(&uri)).field("uri")?;
+            52
+        }
+        Packet::PayloadDroppedCall { unit, x, y } => {
+            write_unit(&mut data, unit);
+            write_float(&mut data, x);
+            write_float(&mut data, y);
+            53
+        }
+        Packet::PickedBuildPayloadCall {
+            unit,
+            build,
+            on_ground,
+        } => {
+            write_unit(&mut data, unit);
+            write_tile(&mut data, build);
+            write_bool(&mut data, on_ground);
+            54
+        }
+        Packet::PickedUnitPayloadCall { unit, target } => {
+            write_unit(&mut data, unit);
+            write_unit(&mut data, target);
+            55
+        }
+        Packet::PingCall { time } => {
+            write_long(&mut data, time);
+            56
+        }
+        Packet::PingResponseCall { time } => {
+            write_long(&mut data, time);
+            57
+        }
+        Packet::PlayerDisconnectCall { player } => {
+            write_int(&mut data, player);
+            58
+        }
+        Packet::SpawnCall {
+            tile_x,
+            tile_y,
+            entity,
+        } => {
+            write_short(&mut data, tile_x);
+            write_short(&mut data, tile_y);
+            write_int(&mut data, entity);
+            59
+        }
+        Packet::RemoveQueueBlockCall { x, y, breaking } => {
+            write_int(&mut data, x);
+            write_int(&mut data, y);
+            write_bool(&mut data, breaking);
+            60
+        }
+        Packet::RemoveTileCall { tile } => {
+            write_tile(&mut data, tile);
+            61
+        }
+        Packet::RemoveWorldLabelCall { id } => {
+            write_int(&mut data, id);
+            62
+        }
+        Packet::RequestBuildPayloadCall { build } => {
+            write_tile(&mut data, build);
+            63
+        }
+        Packet::RequestDebugStatusCall => 64,
+        Packet::RequestDropPayloadCall { x, y } => {
+            write_float(&mut data, x);
+            write_float(&mut data, y);
+            65
+        }
+        Packet::RequestItemCall {
+            player,
+            build,
+            item,
+            amount,
+        } => {
+            write_int(&mut data, player);
+            write_tile(&mut data, build);
+            write_short(&mut data, item);
+            write_int(&mut data, amount);
+            66
+        }
+        Packet::RequestUnitPayloadCall { target } => {
+            write_unit(&mut data, target);
+            67
+        }
+        Packet::ResearchedCall { content } => {
+            write_content(&mut data, content);
+            68
+        }
+        Packet::RotateBlockCall {
+            entity,
+            tile,
+            rotation,
+        } => {
+            write_int(&mut data, entity);
+            write_tile(&mut data, tile);
+            write_byte(&mut data, rotation);
+            69
+        }
+        Packet::SectorCaptureCall => 70,
         Packet::SendChatMessageCall { message } => {
-            write_string(&mut data, &message);
+            write_prefixed_string(&mut data, Some(&message)).field("message")?;
             71
         }
-        _ => 0,
+        Packet::SendMessageCall { message } => {
+            write_prefixed_string(&mut data, Some(&message)).field("message")?;
+            72
+        }
+        Packet::SendMessageCall2 {
+            message,
+            unformatted,
+            sender,
+        } => {
+            write_prefixed_string(&mut data, Some(&message)).field("message")?;
+            write_prefixed_string(&mut data, unformatted.as_deref()).field("unformatted")?;
+            write_int(&mut data, sender);
+            73
+        }
+        Packet::ServerPacketReliableCall {
+            packet_type,
+            contents,
+        } => {
+            write_prefixed_string(&mut data, Some(&packet_type)).field("packet_type")?;
+            write_prefixed_string(&mut data, Some(&contents)).field("contents")?;
+            74
+        }
+        Packet::ServerPacketUnreliableCall {
+            packet_type,
+            contents,
+        } => {
+            write_prefixed_string(&mut data, Some(&packet_type)).field("packet_type")?;
+            write_prefixed_string(&mut data, Some(&contents)).field("contents")?;
+            75
+        }
+        Packet::SetCameraPositionCall { x, y } => {
+            write_float(&mut data, x);
+            write_float(&mut data, y);
+            76
+        }
+        Packet::SetFlagCall { flag, add } => {
+            write_prefixed_string(&mut data, Some(&flag)).field("flag")?;
+            write_bool(&mut data, add);
+            77
+        }
+        Packet::SetFloorCall {
+            tile,
+            floor,
+            overlay,
+        } => {
+            write_tile(&mut data, tile);
+            write_short(&mut data, floor);
+            write_short(&mut data, overlay);
+            78
+        }
+        Packet::SetHudTextCall { message } => {
+            write_prefixed_string(&mut data, Some(&message)).field("message")?;
+            79
+        }
+        Packet::SetHudTextReliableCall { message } => {
+            write_prefixed_string(&mut data, Some(&message)).field("message")?;
+            80
+        }
+        Packet::SetItemCall {
+            build,
+            item,
+            amount,
+        } => {
+            write_tile(&mut data, build);
+            write_short(&mut data, item);
+            write_int(&mut data, amount);
+            81
+        }
+        Packet::SetMapAreaCall {
+            x,
+            y,
+            width,
+            height,
+        } => {
+            write_int(&mut data, x);
+            write_int(&mut data, y);
+            write_int(&mut data, width);
+            write_int(&mut data, height);
+            82
+        }
+        Packet::SetObjectivesCall { objectives } => {
+            write_json(&mut data, &objectives);
+            83
+        }
+        Packet::SetOverlayCall { tile, overlay } => {
+            write_tile(&mut data, tile);
+            write_short(&mut data, overlay);
+            84
+        }
+        Packet::SetPlayerTeamEditorCall { team } => {
+            write_byte(&mut data, team);
+            85
+        }
+        Packet::SetPositionCall { x, y } => {
+            write_float(&mut data, x);
+            write_float(&mut data, y);
+            86
+        }
+        Packet::SetRulesCall { rules } => {
+            write_json(&mut data, &rules);
+            87
+        }
+        Packet::SetTeamCall { build, team } => {
+            write_tile(&mut data, build);
+            write_byte(&mut data, team);
+            88
+        }
+        Packet::SetTileCall {
+            tile,
+            block,
+            team,
+            rotation,
+        } => {
+            write_tile(&mut data, tile);
+            write_short(&mut data, block);
+            write_byte(&mut data, team);
+            write_int(&mut data, rotation);
+            89
+        }
+        Packet::SetUnitCommandCall {
+            player,
+            unit_ids,
+            command,
+        } => {
+            write_int(&mut data, player);
+            write_ints(&mut data, unit_ids);
+            write_command(&mut data, command);
+            90
+        }
+        Packet::SoundCall {
+            sound,
+            volume,
+            pitch,
+            pan,
+        } => {
+            write_short(&mut data, sound);
+            write_float(&mut data, volume);
+            write_float(&mut data, pitch);
+            write_float(&mut data, pan);
+            91
+        }
+        Packet::SoundAtCall {
+            sound,
+            x,
+            y,
+            volume,
+            pitch,
+        } => {
+            write_short(&mut data, sound);
+            write_float(&mut data, x);
+            write_float(&mut data, y);
+            write_float(&mut data, volume);
+            write_float(&mut data, pitch);
+            92
+        }
+        Packet::SpawnEffectCall {
+            x,
+            y,
+            rotation,
+            unit_type,
+        } => {
+            write_float(&mut data, x);
+            write_float(&mut data, y);
+            write_float(&mut data, rotation);
+            write_short(&mut data, unit_type);
+            93
+        }
+        Packet::StateSnapshot {
+            wave_time,
+            wave,
+            enemies,
+            paused,
+            game_over,
+            time_data,
+            tps,
+            rand0,
+            rand1,
+            cores,
+        } => {
+            write_float(&mut data, wave_time);
+            write_int(&mut data, wave);
+            write_int(&mut data, enemies);
+            write_bool(&mut data, paused);
+            write_bool(&mut data, game_over);
+            write_int(&mut data, time_data);
+            write_byte(&mut data, tps);
+            write_long(&mut data, rand0);
+            write_long(&mut data, rand1);
+            let mut core_data = vec![];
+            write_byte(&mut core_data, cores.len() as u8);
+            for (team, items) in cores {
+                write_byte(&mut core_data, team);
+                write_block_items(&mut core_data, items, false);
+            }
+            write_short(&mut data, core_data.len() as i16);
+            data.extend_from_slice(&core_data);
+            94
+        }
+        Packet::SyncVariableCall {
+            build,
+            variable,
+            value,
+        } => {
+            write_tile(&mut data, build);
+            write_int(&mut data, variable);
+            write_object(&mut data, value).field("value")?;
+            95
+        }
+        Packet::TakeItemsCall {
+            build,
+            item,
+            amount,
+            to,
+        } => {
+            write_tile(&mut data, build);
+            write_short(&mut data, item);
+            write_int(&mut data, amount);
+            write_unit(&mut data, to);
+            96
+        }
+        Packet::TextInputCall {
+            text_input_id,
+            title,
+            message,
+            text_length,
+            default,
+            numeric,
+        } => {
+            write_int(&mut data, text_input_id);
+            write_prefixed_string(&mut data, Some(&title)).field("title")?;
+            write_prefixed_string(&mut data, Some(&message)).field("message")?;
+            write_int(&mut data, text_length);
+            write_prefixed_string(&mut data, Some(&default)).field("default")?;
+            write_bool(&mut data, numeric);
+            97
+        }
+        Packet::TextInputResultCall {
+            text_input_id,
+            text,
+        } => {
+            write_int(&mut data, text_input_id);
+            write_prefixed_string(&mut data, text.as_deref()).field("text")?;
+            98
+        }
+        Packet::TileConfigCall {
+            player,
+            tile,
+            value,
+        } => {
+            write_int(&mut data, player);
+            write_tile(&mut data, tile);
+            write_object(&mut data, value).field("value")?;
+            99
+        }
+        Packet::TileTapCall { player, tile } => {
+            write_int(&mut data, player);
+            write_tile(&mut data, tile);
+            100
+        }
+        Packet::TraceInfoCall { player, info } => {
+            write_int(&mut data, player);
+            write_trace_info(&mut data, info).field("info")?;
+            101
+        }
+        Packet::TransferInventoryCall { player, build } => {
+            write_int(&mut data, player);
+            write_tile(&mut data, build);
+            102
+        }
+        Packet::TransferItemToCall {
+            unit,
+            item,
+            amount,
+            x,
+            y,
+            build,
+        } => {
+            write_unit(&mut data, unit);
+            write_short(&mut data, item);
+            write_int(&mut data, amount);
+            write_float(&mut data, x);
+            write_float(&mut data, y);
+            write_tile(&mut data, build);
+            103
+        }
+        Packet::TransferItemToUnitCall { item, x, y, to } => {
+            write_short(&mut data, item);
+            write_float(&mut data, x);
+            write_float(&mut data, y);
+            write_int(&mut data, to);
+            104
+        }
+        Packet::UnitBlockSpawnCall { tile } => {
+            write_tile(&mut data, tile);
+            105
+        }
+        Packet::UnitBuildingControlSelectCall { unit, build } => {
+            write_unit(&mut data, unit);
+            write_tile(&mut data, build);
+            106
+        }
+        Packet::UnitCapDeathCall { unit } => {
+            write_unit(&mut data, unit);
+            107
+        }
+        Packet::UnitClearCall { player } => {
+            write_int(&mut data, player);
+            108
+        }
+        Packet::UnitControlCall { player, unit } => {
+            write_int(&mut data, player);
+            write_unit(&mut data, unit);
+            109
+        }
+        Packet::UnitDeathCall { id } => {
+            write_int(&mut data, id);
+            110
+        }
+        Packet::UnitDestroyCall { id } => {
+            write_int(&mut data, id);
+            111
+        }
+        Packet::UnitEnteredPayloadCall { unit, build } => {
+            write_unit(&mut data, unit);
+            write_tile(&mut data, build);
+            112
+        }
+        Packet::UnitEnvDeathCall { unit } => {
+            write_unit(&mut data, unit);
+            113
+        }
+        Packet::UnitSafeDeathCall { unit } => {
+            write_unit(&mut data, unit);
+            114
+        }
+        Packet::UnitTetherBlockSpawnedCall { tile, id } => {
+            write_tile(&mut data, tile);
+            write_int(&mut data, id);
+            115
+        }
+        Packet::UpdateGameOverCall { winner } => {
+            write_byte(&mut data, winner);
+            116
+        }
+        Packet::WarningToastCall { unicode, text } => {
+            write_int(&mut data, unicode);
+            write_prefixed_string(&mut data, Some(&text)).field("text")?;
+            117
+        }
+        Packet::WorldDataBeginCall => 118,
+        Packet::Other(id) => id,
     };
 
+    Ok((id, data))
+}
+
+/// Wraps encoded packet data in a TCP frame, compressing it when large enough
+pub fn frame_packet(id: u8, mut data: Vec<u8>) -> Vec<u8> {
     let mut buf: Vec<u8> = vec![];
     let length = data.len() as u16 + 4;
 
@@ -0,0 +1,701 @@
+use crate::block_io::{BaseBlockData, Block};
+use crate::client::{ClientEvent, ClientInfo, DisconnectCause, Kick, QueuedPacket, State};
+use crate::content::{BlockId, ContentRegistry, ItemId};
+use crate::packet::{
+    AnyPacket, FrameworkPacket, Packet, PacketError, World, parse_packet, read_block_snapshot,
+    write_framework_frame, write_framework_packet, write_packet_frames,
+};
+use crate::rules::Rules;
+use crate::stream_builder::StreamBuilder;
+use crate::type_io::Tile;
+use crate::unit_io::FullUnit;
+use crate::version::ProtocolVersion;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+const SNAPSHOT_INTERVAL: Duration = Duration::from_millis(200);
+/// How long the server may stay silent before the connection counts as dead
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(12);
+
+/// The client side of the protocol without any IO.
+///
+/// Frames are fed in through `receive_tcp`/`receive_udp` and time through
+/// `handle_timeout`; outgoing packets and events are collected with `poll_transmit`
+/// and `poll_event`. `poll_timeout` tells the driver when to call `handle_timeout` next.
+pub struct ClientProtocol {
+    pub state: State,
+    info: ClientInfo,
+    version: ProtocolVersion,
+    content_map: Option<ContentRegistry>,
+    streams: HashMap<u32, StreamBuilder>,
+    tcp_buffer: Vec<u8>,
+    transmits: VecDeque<QueuedPacket>,
+    events: VecDeque<ClientEvent>,
+    next_snapshot: Option<Instant>,
+    snapshot_id: u32,
+    next_stream_id: u32,
+    world_loaded: bool,
+    /// Between `WorldDataBegin` and the world stream, while no snapshots are sent
+    loading: bool,
+    kick: Option<Kick>,
+    timeout: Duration,
+    last_received: Option<Instant>,
+    closed: bool,
+}
+
+impl ClientProtocol {
+    /// Connects with the default `ClientInfo` for `username`
+    pub fn new(username: String, content_map: Option<ContentRegistry>) -> Self {
+        Self::with_info(ClientInfo::new(username), content_map)
+    }
+
+    pub fn with_info(info: ClientInfo, content_map: Option<ContentRegistry>) -> Self {
+        let version = ProtocolVersion::from_build(info.version).unwrap_or_else(|| {
+            tracing::warn!(
+                "Build {} is not supported, decoding as build {}",
+                info.version,
+                ProtocolVersion::OLDEST.build()
+            );
+            ProtocolVersion::OLDEST
+        });
+        ClientProtocol {
+            state: State::new(),
+            info,
+            version,
+            content_map,
+            streams: HashMap::new(),
+            tcp_buffer: vec![],
+            transmits: VecDeque::new(),
+            events: VecDeque::new(),
+            next_snapshot: None,
+            snapshot_id: 0,
+            next_stream_id: 0,
+            world_loaded: false,
+            loading: false,
+            kick: None,
+            timeout: DEFAULT_TIMEOUT,
+            last_received: None,
+            closed: false,
+        }
+    }
+
+    pub fn info(&self) -> &ClientInfo {
+        &self.info
+    }
+
+    /// The wire format matching the build the client connects with
+    pub fn version(&self) -> ProtocolVersion {
+        self.version
+    }
+
+    pub fn content_map(&self) -> &Option<ContentRegistry> {
+        &self.content_map
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Sets how long the server may stay silent before the connection times out
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Whether the connection is over, after which frames and timeouts are ignored
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /// Feeds bytes read from the TCP stream, which may contain partial frames
+    pub fn receive_tcp(&mut self, now: Instant, bytes: &[u8]) {
+        self.tcp_buffer.extend_from_slice(bytes);
+        while self.tcp_buffer.len() >= 2 {
+            let length = u16::from_be_bytes([self.tcp_buffer[0], self.tcp_buffer[1]]) as usize;
+            if self.tcp_buffer.len() < length + 2 {
+                break;
+            }
+            let frame = self.tcp_buffer[2..length + 2].to_vec();
+            self.tcp_buffer.drain(..length + 2);
+            self.receive_frame(now, frame);
+        }
+    }
+
+    pub fn receive_udp(&mut self, now: Instant, datagram: &[u8]) {
+        self.receive_frame(now, datagram.to_vec());
+    }
+
+    /// Tells the protocol the connection opened, which starts the timeout. A server that
+    /// accepts the connection but never sends a frame times out like a silent one.
+    pub fn handle_open(&mut self, now: Instant) {
+        if self.last_received.is_none() {
+            self.last_received = Some(now);
+        }
+    }
+
+    /// Tells the protocol the connection closed, `error` is why reading from it failed.
+    /// Snapshots stop and `Disconnected` is queued as the last event.
+    pub fn handle_close(&mut self, error: Option<String>) {
+        let cause = match (self.kick.take(), error) {
+            (Some(kick), _) => DisconnectCause::Kicked(kick),
+            (None, Some(error)) => DisconnectCause::Error(error),
+            (None, None) => DisconnectCause::Closed,
+        };
+        self.close(cause);
+    }
+
+    /// Ends the connection from the client side. The driver is expected to close the
+    /// sockets once `is_closed` returns true.
+    pub fn disconnect(&mut self) {
+        self.close(DisconnectCause::Requested);
+    }
+
+    fn close(&mut self, cause: DisconnectCause) {
+        if self.closed {
+            return;
+        }
+        tracing::info!("Disconnected: {cause:?}");
+        self.closed = true;
+        self.next_snapshot = None;
+        self.events.push_back(ClientEvent::Disconnected { cause });
+    }
+
+    /// Feeds a single frame without its TCP length prefix
+    pub fn receive_frame(&mut self, now: Instant, frame: Vec<u8>) {
+        if self.closed {
+            return;
+        }
+        self.last_received = Some(now);
+        match parse_packet(frame, &self.content_map, self.version) {
+            Ok(AnyPacket::Framework(packet)) => self.handle_framework_packet(now, packet),
+            Ok(AnyPacket::Regular(packet)) => self.handle_regular_packet(packet),
+            Err(e) => tracing::warn!("Dropping malformed packet: {e}"),
+        }
+    }
+
+    /// Sends the periodic snapshot and keepalives once they are due, and closes the
+    /// connection once the server has been silent for longer than the timeout
+    pub fn handle_timeout(&mut self, now: Instant) {
+        if let Some(deadline) = self.deadline()
+            && deadline <= now
+        {
+            self.close(DisconnectCause::TimedOut);
+        }
+        while let Some(due) = self.next_snapshot {
+            if due > now {
+                break;
+            }
+            self.send_snapshot();
+            self.next_snapshot = Some(due + SNAPSHOT_INTERVAL);
+        }
+    }
+
+    pub fn poll_timeout(&self) -> Option<Instant> {
+        match (self.next_snapshot, self.deadline()) {
+            (Some(snapshot), Some(deadline)) => Some(snapshot.min(deadline)),
+            (snapshot, deadline) => snapshot.or(deadline),
+        }
+    }
+
+    /// When the connection times out, counting from the last received frame or from
+    /// `handle_open`
+    fn deadline(&self) -> Option<Instant> {
+        match self.closed {
+            true => None,
+            false => self.last_received.map(|last| last + self.timeout),
+        }
+    }
+
+    pub fn poll_transmit(&mut self) -> Option<QueuedPacket> {
+        self.transmits.pop_front()
+    }
+
+    pub fn poll_event(&mut self) -> Option<ClientEvent> {
+        self.events.pop_front()
+    }
+
+    /// Queues a packet to be sent to the server. Packets too large for a single frame
+    /// are split into a stream, which is always sent over TCP.
+    pub fn send(&mut self, packet: Packet, reliable: bool) -> Result<(), PacketError> {
+        let frames = write_packet_frames(packet, &mut self.next_stream_id)?;
+        let reliable = reliable || frames.len() > 1;
+        for mut frame in frames {
+            if !reliable {
+                // Datagrams are not length prefixed
+                frame.drain(..2);
+            }
+            self.transmits.push_back(QueuedPacket {
+                reliable,
+                packet: frame,
+            });
+        }
+        Ok(())
+    }
+
+    /// Sends a packet built by the protocol itself, which always encodes
+    fn send_own(&mut self, packet: Packet, reliable: bool) {
+        if let Err(e) = self.send(packet, reliable) {
+            tracing::error!("Failed to encode packet: {e}");
+        }
+    }
+
+    fn handle_framework_packet(&mut self, now: Instant, packet: FrameworkPacket) {
+        match packet {
+            FrameworkPacket::KeepAlive => tracing::debug!("KeepAlive received!"),
+            FrameworkPacket::RegisterTCP(id) => {
+                tracing::debug!("TCP Registered, registering UDP...");
+                self.transmits.push_back(QueuedPacket {
+                    reliable: false,
+                    packet: write_framework_packet(FrameworkPacket::RegisterUDP(id)),
+                });
+            }
+            FrameworkPacket::RegisterUDP(..) => {
+                tracing::debug!("UDP registered, sending connect packet...");
+                if let Err(e) = self.send(self.info.connect_packet(), true) {
+                    tracing::error!("Can not connect: {e}");
+                    self.close(DisconnectCause::Error(e.to_string()));
+                    return;
+                }
+                self.send_own(Packet::ConnectCallConfirm, true);
+
+                tracing::debug!("Starting snapshots...");
+                self.next_snapshot = Some(now);
+                self.events.push_back(ClientEvent::Connected);
+            }
+            _ => tracing::debug!("Unhandled framework packet {packet:?}"),
+        }
+    }
+
+    fn send_snapshot(&mut self) {
+        self.snapshot_id += 1;
+        let i = self.snapshot_id;
+
+        let snapshot = Packet::ClientSnapshot {
+            snapshot_id: i,
+            unit_id: self.state.unit.id,
+            dead: false,
+            x: self.state.x,
+            y: self.state.y,
+            pointer_x: self.state.x,
+            pointer_y: self.state.y,
+            rotation: 0.0,
+            base_rotation: 0.0,
+            x_velocity: 0.0,
+            y_velocity: 0.0,
+            mining_x: 0,
+            mining_y: 0,
+            boosting: false,
+            shooting: false,
+            chatting: self.state.chatting,
+            building: true,
+            plans: self.state.plans.clone(),
+            view_x: 0.0,
+            view_y: 0.0,
+            view_width: 1920.0,
+            view_height: 1080.0,
+        };
+
+        // Keepalives continue while a world loads, there is no unit to report yet
+        if !self.loading {
+            tracing::debug!("Sending ClientSnapshot");
+            self.send_own(snapshot, true);
+        }
+
+        if i.is_multiple_of(5 * 5) {
+            tracing::debug!("Sending TCP KeepAlive");
+            self.transmits.push_back(QueuedPacket {
+                reliable: true,
+                packet: write_framework_frame(FrameworkPacket::KeepAlive),
+            });
+        }
+        if i.is_multiple_of(15 * 5) {
+            tracing::debug!("Sending UDP KeepAlive");
+            self.transmits.push_back(QueuedPacket {
+                reliable: false,
+                packet: write_framework_packet(FrameworkPacket::KeepAlive),
+            });
+        }
+    }
+
+    fn handle_regular_packet(&mut self, packet: Packet) {
+        match packet {
+            Packet::StreamBegin {
+                id,
+                stream_type,
+                total,
+            } => {
+                tracing::debug!("Receiving stream {id} ({total} bytes)");
+                self.streams
+                    .insert(id, StreamBuilder::new(id, stream_type, total));
+            }
+            Packet::StreamChunk { id, data } => {
+                let stream = match self.streams.get_mut(&id) {
+                    Some(stream) => stream,
+                    None => {
+                        tracing::error!("Stream {id} not found!");
+                        return;
+                    }
+                };
+                stream.add(data);
+                if stream.is_done() {
+                    tracing::debug!("Stream {id} completed");
+                    let stream = self.streams.remove(&id).unwrap();
+                    match stream.build(&self.content_map, self.version) {
+                        Ok(packet) => self.handle_regular_packet(packet),
+                        Err(e) => tracing::warn!("Dropping malformed stream {id}: {e}"),
+                    }
+                }
+            }
+            Packet::WorldStream(world) => {
+                let World {
+                    id,
+                    player,
+                    content_map: content,
+                    map,
+                    map_info,
+                    rules,
+                    team_plans,
+                    markers,
+                    custom_chunks,
+                    wave,
+                    wave_time,
+                    ..
+                } = *world;
+                let reloaded = self.world_loaded;
+                let current_state = &mut self.state;
+                current_state.player_id = id;
+                current_state.team = player.team;
+                current_state.admin = player.admin;
+                current_state.map = map;
+                current_state.map_info = map_info;
+                current_state.rules = rules;
+                current_state.team_plans = team_plans;
+                current_state.markers = markers;
+                current_state.custom_chunks = custom_chunks;
+                current_state.wave = wave;
+                current_state.wave_countdown = wave_time;
+                current_state.game_over = false;
+
+                // Block and item ids of the new world may differ from the old one
+                self.content_map = Some(content);
+
+                tracing::info!("World loaded!");
+                self.loading = false;
+                self.events.push_back(ClientEvent::WorldLoaded);
+                if reloaded {
+                    // The official client confirms every world it finishes loading
+                    self.send_own(Packet::ConnectCallConfirm, true);
+                    let map_info = self.state.map_info.clone();
+                    self.events.push_back(ClientEvent::MapChanged { map_info });
+                }
+                self.world_loaded = true;
+            }
+            Packet::WorldDataBeginCall => {
+                tracing::info!("Loading world...");
+                // Nothing of the old world carries over, not even streams still in flight
+                self.state = State::new();
+                self.streams.clear();
+                self.loading = true;
+            }
+            Packet::ConnectCall { ip, port } => {
+                tracing::info!("Redirected to {ip}:{port}");
+                self.close(DisconnectCause::Redirected { ip, port });
+            }
+            Packet::SetRulesCall { rules } => match Rules::from_json(&rules) {
+                Ok(rules) => self.state.rules = rules,
+                Err(e) => tracing::warn!("Ignoring invalid rules: {e}"),
+            },
+            Packet::BeginPlace {
+                x,
+                y,
+                rotation,
+                result,
+                team,
+                ..
+            } => {
+                let state = &mut self.state;
+                let map_tile = match state.map.get_mut(x, y) {
+                    Some(map_tile) => map_tile,
+                    None => {
+                        tracing::warn!("Received begin place before map was loaded, ignoring");
+                        return;
+                    }
+                };
+                map_tile.block_id = Some(result as i16);
+                // TODO improve
+                let new = Block {
+                    block_type: "Construct".to_string(),
+                    name: "Construct".to_string(),
+                    revision: 0,
+                    base: BaseBlockData {
+                        team,
+                        rotation: rotation as u8,
+                        version: 0,
+                        legacy: false,
+                        items: None,
+                        liquids: None,
+                        power: None,
+                        on: None,
+                        module_bitmask: 0,
+                        health: 1f32,
+                        efficiency: None,
+                        optional_efficiency: None,
+                        visible_flags: None,
+                    },
+                    specific: None,
+                };
+                let old = map_tile.block.replace(new.clone());
+
+                self.events.push_back(ClientEvent::BlockChanged {
+                    tile: Tile {
+                        x: x as i16,
+                        y: y as i16,
+                    },
+                    old: old.map(Box::new),
+                    new: Some(Box::new(new)),
+                });
+            }
+            Packet::ConstructFinish { tile, block, .. } => {
+                let Some(content_map) = &self.content_map else {
+                    tracing::warn!("Ignoring construct finish received before the world");
+                    return;
+                };
+                let Some(map_tile) = self.state.map.get_mut(tile.x as u32, tile.y as u32) else {
+                    tracing::warn!("Construct finish at {tile:?} is outside of the map");
+                    return;
+                };
+                map_tile.block_id = Some(block);
+
+                let block_id = BlockId(block);
+                let Some((block_name, block_type)) = content_map
+                    .block(block_id)
+                    .zip(content_map.block_info(block_id))
+                    .map(|(name, info)| (name.to_string(), info.class.to_string()))
+                else {
+                    tracing::warn!("Construct finish at {tile:?} has unknown block {block}");
+                    return;
+                };
+
+                let old = map_tile.block.clone();
+                if let Some(block) = &mut map_tile.block {
+                    block.revision = self.version.building_revision(&block_type);
+                    block.block_type = block_type;
+                    block.name = block_name;
+                    // TODO update config
+                } else {
+                    tracing::warn!("Construct block at {tile:?} missing!");
+                }
+
+                let new = map_tile.block.clone();
+                self.events.push_back(ClientEvent::BlockChanged {
+                    tile,
+                    old: old.map(Box::new),
+                    new: new.map(Box::new),
+                });
+            }
+            Packet::DeconstructFinish { tile, .. } => {
+                let Some(map_tile) = self.state.map.get_mut(tile.x as u32, tile.y as u32) else {
+                    tracing::warn!("Deconstruct finish at {tile:?} is outside of the map");
+                    return;
+                };
+                map_tile.block_id = None;
+                let old = map_tile.block.take();
+                self.events.push_back(ClientEvent::BlockChanged {
+                    tile,
+                    old: old.map(Box::new),
+                    new: None,
+                });
+            }
+            Packet::BlockSnapshot { amount, data } => {
+                let Some(content_map) = &self.content_map else {
+                    tracing::warn!("Ignoring block snapshot received before the world");
+                    return;
+                };
+                let (buildings, error) =
+                    read_block_snapshot(amount, data, self.version, content_map, &self.state.map);
+                if let Some(e) = error {
+                    tracing::warn!(
+                        "Dropping the rest of a block snapshot after {} buildings: {e}",
+                        buildings.len()
+                    );
+                }
+
+                for (tile, block) in buildings {
+                    if let Some(map_tile) = self.state.map.get_mut(tile.x as u32, tile.y as u32) {
+                        let old = map_tile.block.replace(block.clone());
+                        self.events.push_back(ClientEvent::BlockChanged {
+                            tile,
+                            old: old.map(Box::new),
+                            new: Some(Box::new(block)),
+                        });
+                    }
+                }
+            }
+            Packet::EntitySnapshot { units } => {
+                let state = &mut self.state;
+                for (id, unit) in &units {
+                    if let FullUnit::Player { name, .. } = unit
+                        && *id != state.player_id
+                        && !state.players.contains_key(id)
+                    {
+                        state.players.insert(*id, name.clone());
+                        self.events.push_back(ClientEvent::PlayerJoined {
+                            id: *id,
+                            name: name.clone(),
+                        });
+                    }
+                }
+
+                if let Some(FullUnit::Player { unit, x, y, .. }) = units.get(&state.player_id) {
+                    // The client moves itself, only the first known position is taken over
+                    if state.x == -1.0 && state.y == -1.0 && (*x != -1.0 || *y != -1.0) {
+                        state.x = *x;
+                        state.y = *y;
+                        self.events
+                            .push_back(ClientEvent::PositionChanged { x: *x, y: *y });
+                    }
+                    state.unit = unit.clone();
+                }
+
+                state.units = units;
+
+                self.events.push_back(ClientEvent::UnitSnapshot);
+            }
+            Packet::KickCall { reason } => {
+                tracing::warn!("Client was kicked: {reason}");
+                self.kick(Kick::Message(reason));
+            }
+            Packet::KickCall2 { reason } => {
+                tracing::warn!("Client was kicked: {reason:?}");
+                self.kick(Kick::Reason(reason));
+            }
+            Packet::SpawnCall {
+                tile_x,
+                tile_y,
+                entity,
+            } => {
+                let state = &mut self.state;
+                if state.player_id == entity {
+                    tracing::debug!("Spawned at {tile_x}/{tile_y}");
+                    state.x = (tile_x * 8) as f32;
+                    state.y = (tile_y * 8) as f32;
+                    self.events.push_back(ClientEvent::Spawned {
+                        tile: Tile {
+                            x: tile_x,
+                            y: tile_y,
+                        },
+                    });
+                    self.events.push_back(ClientEvent::PositionChanged {
+                        x: state.x,
+                        y: state.y,
+                    });
+                }
+            }
+            Packet::UnitDeathCall { id } | Packet::UnitDestroyCall { id } => self.unit_died(id),
+            Packet::UnitCapDeathCall { unit }
+            | Packet::UnitEnvDeathCall { unit }
+            | Packet::UnitSafeDeathCall { unit } => self.unit_died(unit.id),
+            Packet::PlayerDisconnectCall { player } => {
+                if let Some(name) = self.state.players.remove(&player) {
+                    self.events
+                        .push_back(ClientEvent::PlayerLeft { id: player, name });
+                }
+            }
+            Packet::RotateBlockCall { tile, rotation, .. } => {
+                let state = &mut self.state;
+                let Some(block) = state
+                    .map
+                    .get_mut(tile.x as u32, tile.y as u32)
+                    .and_then(|map_tile| map_tile.block.as_mut())
+                else {
+                    tracing::warn!("Rotated block at {tile:?} missing!");
+                    return;
+                };
+                let old = block.clone();
+                block.base.rotation = rotation;
+
+                self.events.push_back(ClientEvent::BlockChanged {
+                    tile,
+                    old: Some(Box::new(old)),
+                    new: Some(Box::new(block.clone())),
+                });
+            }
+            Packet::SendMessageCall2 {
+                message,
+                unformatted,
+                sender: author,
+            } => {
+                self.events.push_back(ClientEvent::ChatMessage {
+                    message,
+                    unformatted,
+                    sender: author,
+                });
+            }
+            Packet::StateSnapshot {
+                wave_time,
+                wave,
+                enemies,
+                paused,
+                game_over,
+                tps,
+                cores,
+                ..
+            } => {
+                let state = &mut self.state;
+                if wave != state.wave {
+                    state.wave = wave;
+                    self.events.push_back(ClientEvent::WaveChanged { wave });
+                }
+                if game_over && !state.game_over {
+                    self.events
+                        .push_back(ClientEvent::GameOver { winner: None });
+                }
+                state.wave_countdown = wave_time;
+                state.enemies = enemies;
+                state.paused = paused;
+                state.game_over = game_over;
+                state.tps = tps;
+
+                let content_map = self.content_map.as_ref();
+                state.cores = cores
+                    .into_iter()
+                    .map(|(team, core)| {
+                        let core = core
+                            .into_iter()
+                            .filter_map(|(id, amount)| {
+                                let name = content_map.and_then(|cm| cm.item(ItemId(id)));
+                                if name.is_none() {
+                                    tracing::debug!("Unknown item {id} in core of team {team}");
+                                }
+                                Some((name?.to_string(), amount))
+                            })
+                            .collect();
+                        (team, core)
+                    })
+                    .collect();
+
+                self.events.push_back(ClientEvent::StateUpdated);
+            }
+            Packet::GameOverCall { winner } if !self.state.game_over => {
+                self.state.game_over = true;
+                self.events.push_back(ClientEvent::GameOver {
+                    winner: Some(winner),
+                });
+            }
+            Packet::Other(id) => {
+                tracing::debug!("Unhandled packet: {id}");
+            }
+            _ => {}
+        }
+    }
+
+    fn kick(&mut self, reason: Kick) {
+        self.kick = Some(reason.clone());
+        self.events.push_back(ClientEvent::Kicked { reason });
+    }
+
+    fn unit_died(&mut self, id: u32) {
+        if self.state.unit.id != 0 && self.state.unit.id == id {
+            self.events.push_back(ClientEvent::Died);
+        }
+    }
+}
@@ -0,0 +1,338 @@
+use serde::de::Error as _;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Number, Value};
+use std::collections::{HashMap, HashSet};
+
+/// Per-team overrides of the game rules
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct TeamRule {
+    pub cheat: bool,
+    pub infinite_resources: bool,
+    pub infinite_ammo: bool,
+    pub build_ai: bool,
+    pub rts_ai: bool,
+    pub build_speed_multiplier: f32,
+    pub unit_build_speed_multiplier: f32,
+    pub unit_cost_multiplier: f32,
+    pub unit_damage_multiplier: f32,
+    pub block_health_multiplier: f32,
+    pub block_damage_multiplier: f32,
+    /// Fields not covered above, kept so the rules can be written back unchanged
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+impl Default for TeamRule {
+    fn default() -> Self {
+        TeamRule {
+            cheat: false,
+            infinite_resources: false,
+            infinite_ammo: false,
+            build_ai: false,
+            rts_ai: false,
+            build_speed_multiplier: 1.0,
+            unit_build_speed_multiplier: 1.0,
+            unit_cost_multiplier: 1.0,
+            unit_damage_multiplier: 1.0,
+            block_health_multiplier: 1.0,
+            block_damage_multiplier: 1.0,
+            other: Map::new(),
+        }
+    }
+}
+
+/// The game rules, as sent in the `WorldStream` and `SetRulesCall` JSON. The game leaves
+/// out fields that have their default value, so missing fields fall back to those.
+///
+/// Besides strict JSON, the "minimal" output of arc's `Json` is accepted, which is what
+/// the game writes by default: names and most strings are not quoted, like
+/// `{waves:true,bannedBlocks:[router]}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Rules {
+    pub pvp: bool,
+    pub attack_mode: bool,
+    pub waves: bool,
+    pub wave_timer: bool,
+    /// Ticks between waves
+    pub wave_spacing: f32,
+    pub initial_wave_spacing: f32,
+    /// Base unit cap, `unit_cap_variable` adds the cap of every core on top
+    pub unit_cap: i32,
+    pub unit_cap_variable: bool,
+    pub infinite_resources: bool,
+    pub build_cost_multiplier: f32,
+    pub build_speed_multiplier: f32,
+    pub deconstruct_refund_multiplier: f32,
+    pub unit_build_speed_multiplier: f32,
+    pub unit_cost_multiplier: f32,
+    pub unit_damage_multiplier: f32,
+    pub unit_health_multiplier: f32,
+    pub block_health_multiplier: f32,
+    pub block_damage_multiplier: f32,
+    /// Block names that can not be built
+    pub banned_blocks: HashSet<String>,
+    /// Unit names that can not be built
+    pub banned_units: HashSet<String>,
+    /// Overrides by team id
+    pub teams: HashMap<u8, TeamRule>,
+    pub fog: bool,
+    pub static_fog: bool,
+    pub lighting: bool,
+    /// Fields not covered above, kept so the rules can be written back unchanged
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            pvp: false,
+            attack_mode: false,
+            waves: false,
+            wave_timer: true,
+            wave_spacing: 2.0 * 60.0 * 60.0,
+            initial_wave_spacing: 0.0,
+            unit_cap: 0,
+            unit_cap_variable: true,
+            infinite_resources: false,
+            build_cost_multiplier: 1.0,
+            build_speed_multiplier: 1.0,
+            deconstruct_refund_multiplier: 0.5,
+            unit_build_speed_multiplier: 1.0,
+            unit_cost_multiplier: 1.0,
+            unit_damage_multiplier: 1.0,
+            unit_health_multiplier: 1.0,
+            block_health_multiplier: 1.0,
+            block_damage_multiplier: 1.0,
+            banned_blocks: HashSet::new(),
+            banned_units: HashSet::new(),
+            teams: HashMap::new(),
+            fog: false,
+            static_fog: true,
+            lighting: false,
+            other: Map::new(),
+        }
+    }
+}
+
+impl Rules {
+    pub fn from_json(json: &str) -> Result<Rules, serde_json::Error> {
+        serde_json::from_value(parse_json(json)?)
+    }
+
+    /// Parses rules like `from_json`, falling back to the defaults when they are empty or
+    /// invalid, so a bad rules string does not fail the whole world
+    pub fn from_json_or_default(json: &str) -> Rules {
+        if json.is_empty() {
+            return Rules::default();
+        }
+        Rules::from_json(json).unwrap_or_else(|e| {
+            tracing::warn!("Ignoring invalid rules: {e}");
+            Rules::default()
+        })
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    /// Replaces a single rule by its JSON field name, like the game's `SetRule` call. That call
+    /// is not decoded yet, since the packet ids of v8 builds are not mapped.
+    pub fn set_rule(&mut self, name: &str, json: &str) -> Result<(), serde_json::Error> {
+        let value = parse_json(json)?;
+        let mut rules = serde_json::to_value(&*self)?;
+        if let Value::Object(fields) = &mut rules {
+            fields.insert(name.to_string(), value);
+        }
+        *self = serde_json::from_value(rules)?;
+        Ok(())
+    }
+
+    /// The rules of a team, falling back to the defaults if it has no overrides
+    pub fn team(&self, team: u8) -> TeamRule {
+        self.teams.get(&team).cloned().unwrap_or_default()
+    }
+
+    pub fn is_banned_block(&self, name: &str) -> bool {
+        self.banned_blocks.contains(name)
+    }
+
+    pub fn is_banned_unit(&self, name: &str) -> bool {
+        self.banned_units.contains(name)
+    }
+
+    /// Build speed multiplier for `team`, combining the global and team rules
+    pub fn build_speed_multiplier(&self, team: u8) -> f32 {
+        self.build_speed_multiplier * self.team(team).build_speed_multiplier
+    }
+}
+
+/// How deep arrays and objects may nest, the same limit serde_json uses
+const MAX_DEPTH: usize = 128;
+
+/// Parses strict JSON, or the arc dialect if that fails
+fn parse_json(json: &str) -> Result<Value, serde_json::Error> {
+    match serde_json::from_str(json) {
+        Ok(value) => Ok(value),
+        Err(e) if e.is_syntax() || e.is_eof() => {
+            let mut parser = ArcJson { json, pos: 0 };
+            let value = parser.value(0)?;
+            parser.skip_whitespace();
+            match parser.pos == json.len() {
+                true => Ok(value),
+                false => Err(parser.error("trailing characters")),
+            }
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// A reader for the JSON written by arc's `JsonWriter` in its "minimal" output type
+struct ArcJson<'a> {
+    json: &'a str,
+    pos: usize,
+}
+
+impl ArcJson<'_> {
+    fn error(&self, reason: &str) -> serde_json::Error {
+        serde_json::Error::custom(format!("{reason} at offset {}", self.pos))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.json[self.pos..].chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek().filter(|c| c.is_whitespace()) {
+            self.pos += c.len_utf8();
+        }
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Value, serde_json::Error> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{' | '[') if depth >= MAX_DEPTH => Err(self.error("nested too deeply")),
+            Some('{') => self.object(depth),
+            Some('[') => self.array(depth),
+            Some('"') => Ok(Value::String(self.quoted()?)),
+            Some(_) => Ok(unquoted_value(self.unquoted(&[',', '}', ']'])?)),
+            None => Err(self.error("expected a value")),
+        }
+    }
+
+    fn object(&mut self, depth: usize) -> Result<Value, serde_json::Error> {
+        self.pos += 1;
+        let mut fields = Map::new();
+        loop {
+            self.skip_whitespace();
+            let name = match self.peek() {
+                Some('}') => {
+                    self.pos += 1;
+                    return Ok(Value::Object(fields));
+                }
+                Some(',') => {
+                    self.pos += 1;
+                    continue;
+                }
+                Some('"') => self.quoted()?,
+                Some(_) => self.unquoted(&[':'])?,
+                None => return Err(self.error("unterminated object")),
+            };
+            self.skip_whitespace();
+            if self.peek() != Some(':') {
+                return Err(self.error("expected ':'"));
+            }
+            self.pos += 1;
+            fields.insert(name, self.value(depth + 1)?);
+        }
+    }
+
+    fn array(&mut self, depth: usize) -> Result<Value, serde_json::Error> {
+        self.pos += 1;
+        let mut values = vec![];
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some(']') => {
+                    self.pos += 1;
+                    return Ok(Value::Array(values));
+                }
+                Some(',') => self.pos += 1,
+                Some(_) => values.push(self.value(depth + 1)?),
+                None => return Err(self.error("unterminated array")),
+            }
+        }
+    }
+
+    fn quoted(&mut self) -> Result<String, serde_json::Error> {
+        self.pos += 1;
+        let mut string = String::new();
+        loop {
+            let Some(c) = self.peek() else {
+                return Err(self.error("unterminated string"));
+            };
+            self.pos += c.len_utf8();
+            match c {
+                '"' => return Ok(string),
+                '\\' => {
+                    let escaped = self
+                        .peek()
+                        .ok_or_else(|| self.error("unterminated string"))?;
+                    self.pos += escaped.len_utf8();
+                    string.push(match escaped {
+                        'b' => '\u{8}',
+                        'f' => '\u{c}',
+                        'n' => '\n',
+                        'r' => '\r',
+                        't' => '\t',
+                        'u' => {
+                            let code = self
+                                .json
+                                .get(self.pos..self.pos + 4)
+                                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                                .ok_or_else(|| self.error("invalid unicode escape"))?;
+                            self.pos += 4;
+                            char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER)
+                        }
+                        other => other,
+                    });
+                }
+                c => string.push(c),
+            }
+        }
+    }
+
+    /// Reads up to the next character of `ends` or the end of the line
+    fn unquoted(&mut self, ends: &[char]) -> Result<String, serde_json::Error> {
+        let start = self.pos;
+        while let Some(c) = self
+            .peek()
+            .filter(|c| !ends.contains(c) && *c != '\n' && *c != '\r')
+        {
+            self.pos += c.len_utf8();
+        }
+        match self.json[start..self.pos].trim() {
+            "" => Err(self.error("expected a value")),
+            token => Ok(token.to_string()),
+        }
+    }
+}
+
+/// Unquoted values are numbers, booleans and null when they parse as such, else strings
+fn unquoted_value(token: String) -> Value {
+    match token.as_str() {
+        "true" => return Value::Bool(true),
+        "false" => return Value::Bool(false),
+        "null" => return Value::Null,
+        _ => {}
+    }
+    if let Ok(int) = token.parse::<i64>() {
+        return Value::from(int);
+    }
+    match token.parse::<f64>().ok().and_then(Number::from_f64) {
+        Some(number) => Value::Number(number),
+        None => Value::String(token),
+    }
+}
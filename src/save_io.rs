@@ -1,13 +1,37 @@
-use crate::block_io::{Block, read_block};
-use crate::type_io::{Object, Reader, read_object, read_string};
+use crate::block_io::{Block, read_block, write_block};
+use crate::content::{BlockId, ContentRegistry, content_types};
+use crate::packet::PacketError;
+use crate::rules::Rules;
+use crate::type_io::{
+    FieldContext, Object, Reader, Vec2, read_object, read_string, read_string_map, read_ubjson,
+    write_byte, write_int, write_object, write_short, write_ubjson, write_unsigned_short,
+    write_utf,
+};
+use crate::unit_io::{FullUnit, read_full_unit};
+use crate::version::ProtocolVersion;
 use colored::{Color, Colorize};
-use serde::Deserialize;
+use flate2::read::ZlibDecoder;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::collections::HashMap;
+use std::fs;
 use std::hash::{DefaultHasher, Hash, Hasher};
-
-pub fn load_block_types() -> HashMap<String, String> {
-    let data = include_str!("data/block_types.json");
-    serde_json::from_str(data).unwrap()
+use std::io::Read;
+use std::path::Path;
+
+/// Resolves a block id to its name and block type through the content map
+pub fn lookup_block(
+    reader: &Reader,
+    content_map: &ContentRegistry,
+    id: i16,
+) -> Result<(String, String), PacketError> {
+    let block_name = content_map
+        .block(BlockId(id))
+        .ok_or_else(|| reader.error(format!("unknown block id {id}")))?;
+    let info = content_map
+        .block_info(BlockId(id))
+        .ok_or_else(|| reader.error(format!("unknown block {block_name}")))?;
+    Ok((block_name.to_string(), info.class.to_string()))
 }
 
 fn color_from_number(num: i16) -> Color {
@@ -22,45 +46,100 @@ fn color_from_number(num: i16) -> Color {
     }
 }
 
-#[derive(Deserialize)]
-struct ContentTypes {
-    #[serde(rename = "contentTypes")]
-    content_types: Vec<String>,
-}
-
-fn load_content_types() -> Vec<String> {
-    let data = include_str!("data/content_types.json");
-    serde_json::from_str::<ContentTypes>(data)
-        .unwrap()
-        .content_types
-}
-
-pub fn read_content_header(reader: &mut Reader) -> HashMap<String, Vec<String>> {
+pub fn read_content_header(reader: &mut Reader) -> Result<ContentRegistry, PacketError> {
     let mut result = HashMap::new();
-    let content_types = load_content_types();
 
-    let mapped = reader.byte();
+    let mapped = reader.byte()?;
     for _ in 0..mapped {
-        let content_type_index = reader.byte();
-        let content_type = content_types
+        let content_type_index = reader.byte()?;
+        let content_type = content_types()
             .get(content_type_index as usize)
-            .unwrap()
-            .clone();
+            .ok_or_else(|| reader.error(format!("unknown content type {content_type_index}")))?
+            .to_string();
         let mut sub_result = vec![];
 
-        let count = reader.short();
+        let count = reader.short()?;
         for _ in 0..count {
-            let name = read_string(reader).unwrap();
+            let name = read_string(reader)?.unwrap_or_default();
             sub_result.push(name);
         }
 
         result.insert(content_type, sub_result);
     }
 
-    result
+    Ok(ContentRegistry::new(result))
+}
+
+pub fn write_content_header(
+    buf: &mut Vec<u8>,
+    content_map: &ContentRegistry,
+) -> Result<(), PacketError> {
+    let mut mapped: Vec<(usize, &Vec<String>)> = content_map
+        .names()
+        .iter()
+        .filter_map(|(content_type, names)| {
+            let index = content_types()
+                .iter()
+                .position(|name| name == content_type)?;
+            Some((index, names))
+        })
+        .collect();
+    mapped.sort_by_key(|(index, _)| *index);
+
+    write_byte(buf, mapped.len() as u8);
+    for (index, names) in mapped {
+        write_byte(buf, index as u8);
+        write_short(buf, names.len() as i16);
+        for name in names {
+            write_utf(buf, name)?;
+        }
+    }
+    Ok(())
+}
+
+/// Metadata of the loaded map, taken from its tags
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MapInfo {
+    pub name: Option<String>,
+    pub author: Option<String>,
+    pub description: Option<String>,
+    pub width: u32,
+    pub height: u32,
+    /// Every tag of the map, including the ones above
+    pub tags: HashMap<String, String>,
+}
+
+impl MapInfo {
+    /// Collects the tags of `map`, its size is taken from the map itself
+    pub fn new(tags: HashMap<String, String>, map: &Map) -> MapInfo {
+        MapInfo {
+            name: tags.get("name").cloned(),
+            author: tags.get("author").cloned(),
+            description: tags.get("description").cloned(),
+            width: map.width,
+            height: map.height,
+            tags,
+        }
+    }
+
+    /// The tags to write, with the fields above taking precedence
+    pub fn to_tags(&self) -> HashMap<String, String> {
+        let mut tags = self.tags.clone();
+        let fields = [
+            ("name", &self.name),
+            ("author", &self.author),
+            ("description", &self.description),
+        ];
+        for (key, value) in fields {
+            if let Some(value) = value {
+                tags.insert(key.to_string(), value.clone());
+            }
+        }
+        tags
+    }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct MapTile {
     pub floor: i16,
     pub ore: Option<i16>,
@@ -68,7 +147,7 @@ pub struct MapTile {
     pub block: Option<Block>,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Map {
     pub width: u32,
     pub height: u32,
@@ -142,7 +221,7 @@ impl Map {
                 if let Some(tile) = self.get(x, y) {
                     let color = color_from_number(tile.floor);
 
-                    if let Some(block) = tile.block_id {
+                    if tile.block_id.is_some() {
                         print!("{}", "  ".on_black());
                     } else if let Some(ore) = tile.ore {
                         let ore_color = color_from_number(ore);
@@ -157,14 +236,35 @@ impl Map {
     }
 }
 
-pub fn read_map(mut reader: &mut Reader, content_map: &HashMap<String, Vec<String>>) -> Map {
+/// Larger than any map the game can create or load
+const MAX_MAP_AREA: usize = 4096 * 4096;
+/// The floor, ore and count of a floor run plus the block, flags and count of a block run
+const MIN_TILE_RUN_LENGTH: usize = 9;
+
+pub fn read_map(reader: &mut Reader, content_map: &ContentRegistry) -> Result<Map, PacketError> {
     tracing::debug!("Loading map data...");
-    let width = reader.short() as u32;
-    let height = reader.short() as u32;
+    let width = reader.short().field("width")?;
+    if width <= 0 {
+        return Err(reader.error(format!("invalid width {width}"))).field("width");
+    }
+    let height = reader.short().field("height")?;
+    if height <= 0 {
+        return Err(reader.error(format!("invalid height {height}"))).field("height");
+    }
+    let (width, height) = (width as u32, height as u32);
+    // Every run of up to 256 tiles takes at least one floor and one block entry, so the
+    // size is checked against the data left before allocating the tiles
+    let area = width as usize * height as usize;
+    let min_length = area.div_ceil(256) * MIN_TILE_RUN_LENGTH;
+    if area > MAX_MAP_AREA || min_length > reader.remaining() {
+        return Err(reader.error(format!(
+            "map of {width} x {height} tiles does not fit in {} bytes",
+            reader.remaining()
+        )))
+        .field("height");
+    }
     tracing::debug!("Size: {width} x {height}");
 
-    let block_types = load_block_types();
-
     let mut map = Map::new(width, height);
 
     // Floors and ores
@@ -172,9 +272,9 @@ pub fn read_map(mut reader: &mut Reader, content_map: &HashMap<String, Vec<Strin
     while i < (width * height) {
         let x = i % width;
         let y = i / width;
-        let floor_id = reader.short();
-        let ore_id = reader.short();
-        let consecutive_count = reader.byte();
+        let floor_id = reader.short()?;
+        let ore_id = reader.short()?;
+        let consecutive_count = reader.byte()?;
         //if(content.block(floorid) == Blocks.air) floorid = Blocks.stone.id; TODO
 
         map.set_floor(x, y, floor_id);
@@ -205,81 +305,67 @@ pub fn read_map(mut reader: &mut Reader, content_map: &HashMap<String, Vec<Strin
         let x = i % width;
         let y = i / width;
 
-        let block_id = reader.short();
+        let block_id = reader.short()?;
         //Block block = content.block(stream.readShort());
         //Tile tile = context.tile(i);
         //if(block == null) block = Blocks.air;
         let mut is_center = true;
-        let packed_check = reader.byte();
+        let packed_check = reader.byte()?;
         let had_entity = (packed_check & 1) != 0;
-        let had_data = (packed_check & 4) != 0;
-
-        let mut tile_data = 0;
-        let mut floor_data = 0;
-        let mut overlay_data = 0;
-        let mut extra_data = 0;
+        // Builds before v8 only know the single data byte of non-building tiles
+        let had_data_old = (packed_check & 2) != 0;
+        let had_data = reader.version().extended_tile_data() && (packed_check & 4) != 0;
 
         if had_data {
-            tile_data = reader.byte();
-            floor_data = reader.byte();
-            overlay_data = reader.byte();
-            extra_data = reader.int();
+            // Tile data is not tracked by `Map`: data, floor data, overlay data, extra data
+            reader.bytes(3)?;
+            reader.int()?;
         }
 
         if had_entity {
-            is_center = reader.bool();
+            is_center = reader.bool()?;
         }
 
         //set block only if this is the center; otherwise, it's handled elsewhere
-        if is_center {
-            if block_id != 0 {
-                map.set_block_id(x, y, block_id);
-            }
-        }
-
-        //must be assigned after setBlock, because that can reset data
-        if had_data {
-            //tile.data = data;
-            //tile.floorData = floorData;
-            //tile.overlayData = overlayData;
-            //tile.extraData = extraData;
-            //context.onReadTileData();
+        if is_center && block_id != 0 {
+            map.set_block_id(x, y, block_id);
         }
 
         if had_entity {
             if is_center {
                 //only read entity for center blocks
-                let length = reader.unsigned_short();
+                let length = reader.unsigned_short()?;
 
                 let data_length_before = reader.remaining();
 
-                let version = reader.byte();
-                let block_name = content_map
-                    .get("block")
-                    .unwrap()
-                    .get(block_id as usize)
-                    .unwrap();
-                let block_type = block_types.get(block_name).unwrap();
+                let version = reader.byte()?;
+                let (block_name, block_type) = lookup_block(reader, content_map, block_id)?;
                 let block = read_block(
-                    &mut reader,
+                    reader,
                     block_name.clone(),
                     block_type.clone(),
                     version,
                     content_map,
-                );
+                )
+                .field(&format!("block[{x},{y}]"))?;
 
                 let data_read = (data_length_before - reader.remaining()) as u64;
                 if data_read != length as u64 {
-                    panic!(
-                        "Block parsing failed trying to parse {block_name} ({block_type}) at [{x},{y}]:\nread {data_read} bytes instead of {length}"
-                    )
+                    return Err(reader.error(format!(
+                        "read {data_read} bytes of {block_name} ({block_type}) instead of {length}"
+                    )))
+                    .field(&format!("block[{x},{y}]"));
                 }
 
                 map.set_block(x, y, block);
             }
-        } else if !had_data {
+        } else if had_data_old || had_data {
             //never read consecutive blocks if there's data
-            let consecutive_count = reader.byte();
+            if had_data_old {
+                reader.byte()?;
+            }
+        } else {
+            let consecutive_count = reader.byte()?;
             let mut j = i + 1;
             while j < i + 1 + consecutive_count as u32 {
                 let new_x = j % width;
@@ -298,10 +384,78 @@ pub fn read_map(mut reader: &mut Reader, content_map: &HashMap<String, Vec<Strin
 
     // map.visualize();
     tracing::debug!("Map loaded");
-    map
+    Ok(map)
 }
 
-#[derive(Debug)]
+/// Writes the map in the same format `read_map` reads it. Tile data and the non-center parts
+/// of multiblocks are not tracked by `Map`, so they are written as air.
+pub fn write_map(buf: &mut Vec<u8>, map: &Map) -> Result<(), PacketError> {
+    write_short(buf, map.width as i16);
+    write_short(buf, map.height as i16);
+
+    let size = map.width * map.height;
+    let tile_at = |i: u32| map.get(i % map.width, i / map.width).unwrap();
+
+    // Floors and ores
+    let mut i = 0;
+    while i < size {
+        let tile = tile_at(i);
+        let ore = tile.ore.unwrap_or(0);
+
+        let mut consecutive_count = 0;
+        while i + consecutive_count + 1 < size && consecutive_count < 255 {
+            let next = tile_at(i + consecutive_count + 1);
+            if next.floor != tile.floor || next.ore.unwrap_or(0) != ore {
+                break;
+            }
+            consecutive_count += 1;
+        }
+
+        write_short(buf, tile.floor);
+        write_short(buf, ore);
+        write_byte(buf, consecutive_count as u8);
+
+        i += consecutive_count + 1;
+    }
+
+    // Blocks
+    let mut i = 0;
+    while i < size {
+        let tile = tile_at(i);
+        let block_id = tile.block_id.unwrap_or(0);
+        write_short(buf, block_id);
+
+        if let Some(block) = &tile.block {
+            write_byte(buf, 1);
+            write_byte(buf, 1);
+
+            let mut block_data = vec![block.revision];
+            write_block(&mut block_data, block.clone())?;
+            write_unsigned_short(buf, block_data.len() as u16);
+            buf.extend_from_slice(&block_data);
+
+            i += 1;
+            continue;
+        }
+
+        write_byte(buf, 0);
+
+        let mut consecutive_count = 0;
+        while i + consecutive_count + 1 < size && consecutive_count < 255 {
+            let next = tile_at(i + consecutive_count + 1);
+            if next.block.is_some() || next.block_id.unwrap_or(0) != block_id {
+                break;
+            }
+            consecutive_count += 1;
+        }
+        write_byte(buf, consecutive_count as u8);
+
+        i += consecutive_count + 1;
+    }
+    Ok(())
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct TeamPlan {
     pub x: i16,
     pub y: i16,
@@ -310,21 +464,21 @@ pub struct TeamPlan {
     pub config: Object,
 }
 
-pub fn read_team_blocks(reader: &mut Reader) -> HashMap<u32, Vec<TeamPlan>> {
-    let team_count = reader.int();
+pub fn read_team_blocks(reader: &mut Reader) -> Result<HashMap<u32, Vec<TeamPlan>>, PacketError> {
+    let team_count = reader.int()?;
     let mut plans = HashMap::new();
 
     for _ in 0..team_count {
-        let team = reader.int();
+        let team = reader.int()?;
         let mut team_plans = vec![];
 
-        let block_count = reader.int();
+        let block_count = reader.int()?;
         for _ in 0..block_count {
-            let x = reader.short();
-            let y = reader.short();
-            let rotation = reader.short();
-            let block_id = reader.short();
-            let config = read_object(reader);
+            let x = reader.short()?;
+            let y = reader.short()?;
+            let rotation = reader.short()?;
+            let block_id = reader.short()?;
+            let config = read_object(reader)?;
             team_plans.push(TeamPlan {
                 x,
                 y,
@@ -337,10 +491,340 @@ pub fn read_team_blocks(reader: &mut Reader) -> HashMap<u32, Vec<TeamPlan>> {
         plans.insert(team, team_plans);
     }
 
-    plans
+    Ok(plans)
+}
+
+pub fn write_team_blocks(
+    buf: &mut Vec<u8>,
+    plans: &HashMap<u32, Vec<TeamPlan>>,
+) -> Result<(), PacketError> {
+    write_int(buf, plans.len() as u32);
+    for (team, team_plans) in plans {
+        write_int(buf, *team);
+        write_int(buf, team_plans.len() as u32);
+        for plan in team_plans {
+            write_short(buf, plan.x);
+            write_short(buf, plan.y);
+            write_short(buf, plan.rotation);
+            write_short(buf, plan.block_id);
+            write_object(buf, plan.config.clone())?;
+        }
+    }
+    Ok(())
+}
+
+/// The kind of an objective marker, taken from the class tag Arc's `Json` adds to it
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum MarkerKind {
+    #[default]
+    ShapeText,
+    Point,
+    Shape,
+    Text,
+    Line,
+    Texture,
+    /// A marker type this crate does not know about, with its class tag
+    Other(String),
+}
+
+impl From<String> for MarkerKind {
+    fn from(tag: String) -> MarkerKind {
+        // Unregistered classes are written with their full name
+        let name = tag.rsplit(['.', '$']).next().unwrap_or(&tag);
+        let name = name.strip_suffix("Marker").unwrap_or(name);
+        match name.to_ascii_lowercase().as_str() {
+            "shapetext" => MarkerKind::ShapeText,
+            "point" | "minimap" => MarkerKind::Point,
+            "shape" => MarkerKind::Shape,
+            "text" => MarkerKind::Text,
+            "line" => MarkerKind::Line,
+            "texture" => MarkerKind::Texture,
+            _ => MarkerKind::Other(tag),
+        }
+    }
+}
+
+impl From<MarkerKind> for String {
+    fn from(kind: MarkerKind) -> String {
+        match kind {
+            MarkerKind::ShapeText => "ShapeText".to_string(),
+            MarkerKind::Point => "Point".to_string(),
+            MarkerKind::Shape => "Shape".to_string(),
+            MarkerKind::Text => "Text".to_string(),
+            MarkerKind::Line => "Line".to_string(),
+            MarkerKind::Texture => "Texture".to_string(),
+            MarkerKind::Other(tag) => tag,
+        }
+    }
+}
+
+/// An objective marker placed by the map or a world processor
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Marker {
+    #[serde(rename = "class")]
+    pub kind: MarkerKind,
+    /// In tiles
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pos: Option<Vec2>,
+    /// Where a line marker ends, in tiles
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_pos: Option<Vec2>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub texture_name: Option<String>,
+    /// Every other field, such as colors, radius or rotation
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, Value>,
+}
+
+/// Reads the markers of the map by their id. They are sent as UBJSON, either as an object
+/// keyed by id or as a plain array.
+pub fn read_markers(reader: &mut Reader) -> Result<HashMap<i32, Marker>, PacketError> {
+    let entries: Vec<(i32, Value)> = match read_ubjson(reader)? {
+        Value::Null => vec![],
+        Value::Array(values) => (0..).zip(values).collect(),
+        Value::Object(values) => values
+            .into_iter()
+            .map(|(id, value)| match id.parse() {
+                Ok(id) => Ok((id, value)),
+                Err(_) => Err(reader.error(format!("invalid marker id {id:?}"))),
+            })
+            .collect::<Result<_, _>>()?,
+        other => return Err(reader.error(format!("invalid markers: {other}"))),
+    };
+
+    let mut markers = HashMap::new();
+    for (id, value) in entries {
+        let marker = serde_json::from_value(value)
+            .map_err(|e| reader.error(format!("invalid marker {id}: {e}")))?;
+        markers.insert(id, marker);
+    }
+    Ok(markers)
+}
+
+pub fn write_markers(buf: &mut Vec<u8>, markers: &HashMap<i32, Marker>) {
+    let values = markers
+        .iter()
+        .map(|(id, marker)| {
+            let value = serde_json::to_value(marker).expect("markers are valid JSON");
+            (id.to_string(), value)
+        })
+        .collect();
+    write_ubjson(buf, &Value::Object(values));
+}
+
+/// Reads the custom chunks mods and plugins store in the save, by name
+pub fn read_custom_chunks(reader: &mut Reader) -> Result<HashMap<String, Vec<u8>>, PacketError> {
+    let count = reader.int()?;
+    let mut chunks = HashMap::new();
+    for _ in 0..count {
+        let name = read_string(reader).field("name")?.unwrap_or_default();
+        let length = reader.int()?;
+        let data = reader.bytes(length as usize).field(&name)?;
+        chunks.insert(name, data);
+    }
+    Ok(chunks)
 }
 
-pub fn read_markers(reader: &mut Reader) {
-    let _type = reader.byte();
-    println!("Type: {}", _type);
+pub fn write_custom_chunks(
+    buf: &mut Vec<u8>,
+    chunks: &HashMap<String, Vec<u8>>,
+) -> Result<(), PacketError> {
+    write_int(buf, chunks.len() as u32);
+    for (name, data) in chunks {
+        write_utf(buf, name)?;
+        write_int(buf, data.len() as u32);
+        buf.extend_from_slice(data);
+    }
+    Ok(())
+}
+
+/// Save format versions that added regions
+const MARKERS_VERSION: u32 = 8;
+const PREVIEW_MAP_VERSION: u32 = 11;
+
+/// An entity stored in a save, like a unit, fire or puddle
+#[derive(Clone, Debug, PartialEq)]
+pub struct SaveEntity {
+    pub id: u32,
+    pub class_id: u8,
+    /// `FullUnit::Unknown` for classes this crate cannot decode, like the ones added by mods
+    pub entity: FullUnit,
+}
+
+/// A save or map file, as written by the game
+#[derive(Clone, Debug, PartialEq)]
+pub struct SaveFile {
+    /// Version of the save format
+    pub version: u32,
+    pub rules: Rules,
+    /// Metadata of the save, `map_info.tags` holds every tag like `build`, `wave` or `saved`
+    pub map_info: MapInfo,
+    pub content_map: ContentRegistry,
+    pub map: Map,
+    pub team_plans: HashMap<u32, Vec<TeamPlan>>,
+    pub entities: Vec<SaveEntity>,
+    pub markers: HashMap<i32, Marker>,
+    pub custom_chunks: HashMap<String, Vec<u8>>,
+}
+
+/// Reads a `.msav` save or map file
+pub fn read_save(path: impl AsRef<Path>) -> Result<SaveFile, PacketError> {
+    read_save_data(&fs::read(path)?)
+}
+
+/// Reads a save from the zlib compressed contents of a `.msav` file
+pub fn read_save_data(data: &[u8]) -> Result<SaveFile, PacketError> {
+    let mut decoder = ZlibDecoder::new(data);
+    let mut save = Vec::new();
+    if let Err(e) = decoder.read_to_end(&mut save) {
+        tracing::error!("Error decompressing save: {e}");
+        return Err(PacketError::WorldDataDecompressionFailed);
+    }
+    let mut reader = Reader::new(save);
+
+    if reader.bytes(4).field("header")? != b"MSAV" {
+        return Err(reader.error("not a save file")).field("header");
+    }
+    let version = reader.int().field("version")?;
+    tracing::debug!("Loading save version {version}");
+
+    let tags: HashMap<String, String> = read_region(&mut reader, "meta", read_string_map)?
+        .into_iter()
+        .map(|(key, value)| (key, value.unwrap_or_default()))
+        .collect();
+
+    // The rest of the save is laid out like the build that wrote it. Custom builds save
+    // as build -1.
+    let protocol = match tags
+        .get("build")
+        .and_then(|build| build.parse::<u32>().ok())
+    {
+        Some(build) => ProtocolVersion::from_build(build)
+            .ok_or_else(|| reader.error(format!("saves of build {build} are not supported")))
+            .field("meta")?,
+        None => ProtocolVersion::default(),
+    };
+    let rules =
+        Rules::from_json_or_default(tags.get("rules").map(String::as_str).unwrap_or_default());
+    let mut reader = Reader::with_version(reader.read_remaining(), protocol);
+    let reader = &mut reader;
+
+    let content_map = read_region(reader, "content", read_content_header)?;
+    if version >= PREVIEW_MAP_VERSION {
+        // A copy of the map for previews, `map` below has the same tiles
+        let length = reader.int().field("preview_map")?;
+        reader.bytes(length as usize).field("preview_map")?;
+    }
+    let map = read_region(reader, "map", |reader| read_map(reader, &content_map))?;
+    let map_info = MapInfo::new(tags, &map);
+    let (team_plans, entities) = read_region(reader, "entities", |reader| {
+        let mod_classes = read_entity_mapping(reader).field("mapping")?;
+        let team_plans = read_team_blocks(reader).field("team_plans")?;
+        let entities = read_entities(reader, &mod_classes, &content_map)?;
+        Ok((team_plans, entities))
+    })?;
+    let markers = if version >= MARKERS_VERSION {
+        read_region(reader, "markers", read_markers)?
+    } else {
+        HashMap::new()
+    };
+    let custom_chunks = read_region(reader, "custom", read_custom_chunks)?;
+
+    tracing::debug!("Save loaded");
+    Ok(SaveFile {
+        version,
+        rules,
+        map_info,
+        content_map,
+        map,
+        team_plans,
+        entities,
+        markers,
+        custom_chunks,
+    })
+}
+
+/// Reads a region of a save, which is prefixed with its length
+fn read_region<T>(
+    reader: &mut Reader,
+    name: &str,
+    read: impl FnOnce(&mut Reader) -> Result<T, PacketError>,
+) -> Result<T, PacketError> {
+    let length = reader.int().field(name)? as usize;
+    let remaining_before = reader.remaining();
+    let value = read(reader).field(name)?;
+
+    let data_read = remaining_before - reader.remaining();
+    if data_read != length {
+        return Err(reader.error(format!(
+            "read {data_read} bytes of region {name} instead of {length}"
+        )))
+        .field(name);
+    }
+    Ok(value)
+}
+
+/// Reads the entity classes mods registered by id, their ids do not match the classes of
+/// the game
+fn read_entity_mapping(reader: &mut Reader) -> Result<HashMap<i16, String>, PacketError> {
+    let mut classes = HashMap::new();
+    let mapped = reader.short()?;
+    for _ in 0..mapped {
+        let class_id = reader.short()?;
+        let name = read_string(reader)?.unwrap_or_default();
+        classes.insert(class_id, name);
+    }
+    Ok(classes)
+}
+
+fn read_entities(
+    reader: &mut Reader,
+    mod_classes: &HashMap<i16, String>,
+    content_map: &ContentRegistry,
+) -> Result<Vec<SaveEntity>, PacketError> {
+    let count = reader.int().field("entities")?;
+    let mut entities = vec![];
+    for i in 0..count {
+        let field = format!("entities[{i}]");
+        let length = reader.unsigned_short().field(&field)? as usize;
+        let remaining_before = reader.remaining();
+
+        let class_id = reader.byte().field(&field)?;
+        let id = reader.int().field(&field)?;
+        let known = !mod_classes.contains_key(&(class_id as i16))
+            && reader
+                .version()
+                .entity_class(class_id)
+                .is_some_and(|class| !class.is_empty());
+        let entity = if known {
+            read_full_unit(reader, class_id, true, content_map).field(&field)?
+        } else {
+            // The length includes the class and entity id
+            let rest = length
+                .checked_sub(5)
+                .ok_or_else(|| reader.error(format!("entity of {length} bytes")))
+                .field(&field)?;
+            reader.bytes(rest).field(&field)?;
+            FullUnit::Unknown
+        };
+
+        let data_read = remaining_before - reader.remaining();
+        if data_read != length {
+            return Err(reader.error(format!(
+                "read {data_read} bytes of entity class {class_id} instead of {length}"
+            )))
+            .field(&field);
+        }
+        entities.push(SaveEntity {
+            id,
+            class_id,
+            entity,
+        });
+    }
+
+    Ok(entities)
 }
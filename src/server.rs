@@ -0,0 +1,403 @@
+use crate::codec::PacketCodec;
+use crate::content::ContentRegistry;
+use crate::packet::{
+    AnyPacket, FrameworkPacket, Packet, PacketError, World, parse_packet, split_stream,
+    write_packet, write_packet_data,
+};
+use crate::rules::Rules;
+use crate::save_io::{Map, MapInfo};
+use crate::stream_builder::StreamBuilder;
+use crate::type_io::Unit;
+use crate::unit_io::PlayerInfo;
+use crate::version::ProtocolVersion;
+use futures_util::{SinkExt, StreamExt};
+use std::collections::HashMap;
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs, UdpSocket};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tokio::time;
+use tokio_util::codec::{FramedRead, FramedWrite};
+
+/// How long clients get to register their UDP channel after the TCP one by default
+pub const DEFAULT_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Handshakes waiting for the `RegisterUDP` packet of their connection id
+type Registrations = Arc<Mutex<HashMap<u32, oneshot::Sender<SocketAddr>>>>;
+
+#[derive(Debug)]
+pub enum ServerError {
+    Io(std::io::Error),
+    Packet(PacketError),
+    /// The client sent something unexpected during the handshake
+    Handshake(String),
+}
+impl fmt::Display for ServerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServerError::Io(e) => write!(f, "{e}"),
+            ServerError::Packet(e) => write!(f, "{e}"),
+            ServerError::Handshake(reason) => write!(f, "Handshake failed: {reason}"),
+        }
+    }
+}
+impl std::error::Error for ServerError {}
+impl From<std::io::Error> for ServerError {
+    fn from(e: std::io::Error) -> Self {
+        ServerError::Io(e)
+    }
+}
+impl From<PacketError> for ServerError {
+    fn from(e: PacketError) -> Self {
+        ServerError::Packet(e)
+    }
+}
+
+/// A minimal headless server speaking the Mindustry protocol, meant for testing clients
+pub struct Server {
+    listener: TcpListener,
+    udp: Arc<UdpSocket>,
+    map: Map,
+    content_map: ContentRegistry,
+    rules: Rules,
+    next_id: u32,
+    handshake_timeout: Duration,
+    registrations: Registrations,
+    udp_task: JoinHandle<()>,
+}
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.udp_task.abort();
+    }
+}
+
+/// A client whose TCP connection was accepted, but which has not registered its UDP
+/// channel or received the world yet
+struct Handshake {
+    id: u32,
+    tcp: TcpStream,
+    address: SocketAddr,
+    udp: Arc<UdpSocket>,
+    registration: oneshot::Receiver<SocketAddr>,
+    registrations: Registrations,
+    timeout: Duration,
+    map: Map,
+    content_map: ContentRegistry,
+    rules: Rules,
+}
+
+/// A client that completed the handshake and received the world
+pub struct Connection {
+    pub id: u32,
+    pub name: String,
+    pub uuid: String,
+    pub version: u32,
+    read: FramedRead<OwnedReadHalf, PacketCodec>,
+    write: FramedWrite<OwnedWriteHalf, PacketCodec>,
+    udp: Arc<UdpSocket>,
+    udp_address: SocketAddr,
+    streams: HashMap<u32, StreamBuilder>,
+}
+
+impl Server {
+    /// Listens for TCP and UDP on the same address
+    pub async fn bind(
+        address: impl ToSocketAddrs,
+        map: Map,
+        content_map: ContentRegistry,
+    ) -> Result<Server, ServerError> {
+        let listener = TcpListener::bind(address).await?;
+        let udp = Arc::new(UdpSocket::bind(listener.local_addr()?).await?);
+        tracing::info!("Server listening on {}", listener.local_addr()?);
+
+        let registrations = Registrations::default();
+        let udp_task = tokio::spawn(receive_udp(Arc::clone(&udp), Arc::clone(&registrations)));
+        Ok(Server {
+            listener,
+            udp,
+            map,
+            content_map,
+            rules: Rules::default(),
+            next_id: 1,
+            handshake_timeout: DEFAULT_HANDSHAKE_TIMEOUT,
+            registrations,
+            udp_task,
+        })
+    }
+
+    /// Rules sent to clients along with the world
+    pub fn set_rules(&mut self, rules: Rules) {
+        self.rules = rules;
+    }
+
+    /// How long clients get to register their UDP channel before they are dropped
+    pub fn set_handshake_timeout(&mut self, timeout: Duration) {
+        self.handshake_timeout = timeout;
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, ServerError> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Accepts the next client, registers its TCP and UDP channels, waits for its
+    /// `Connect` packet and streams the world to it
+    pub async fn accept(&mut self) -> Result<Connection, ServerError> {
+        self.accept_tcp().await?.finish().await
+    }
+
+    async fn accept_tcp(&mut self) -> Result<Handshake, ServerError> {
+        let (tcp, address) = self.listener.accept().await?;
+        let id = self.next_id;
+        self.next_id += 1;
+        tracing::debug!("Accepted {address} as connection {id}");
+
+        let (sender, registration) = oneshot::channel();
+        self.registrations.lock().unwrap().insert(id, sender);
+        Ok(Handshake {
+            id,
+            tcp,
+            address,
+            udp: Arc::clone(&self.udp),
+            registration,
+            registrations: Arc::clone(&self.registrations),
+            timeout: self.handshake_timeout,
+            map: self.map.clone(),
+            content_map: self.content_map.clone(),
+            rules: self.rules.clone(),
+        })
+    }
+
+    /// Accepts clients forever, sending each of them the scripted packets once it
+    /// has loaded the world. Every handshake runs in its own task, so a client that
+    /// stalls does not hold up the others.
+    pub async fn serve(mut self, script: Vec<Packet>) -> Result<(), ServerError> {
+        loop {
+            let handshake = self.accept_tcp().await?;
+            let script = script.clone();
+            tokio::spawn(async move {
+                let mut connection = match handshake.finish().await {
+                    Ok(connection) => connection,
+                    Err(e) => {
+                        tracing::warn!("Rejected client: {e}");
+                        return;
+                    }
+                };
+                for packet in script {
+                    if let Err(e) = connection.send(packet).await {
+                        tracing::warn!("Connection {} closed: {e}", connection.id);
+                        return;
+                    }
+                }
+                while connection.recv().await.is_ok() {}
+                tracing::info!("Connection {} closed", connection.id);
+            });
+        }
+    }
+}
+
+impl Handshake {
+    /// Registers the UDP channel, waits for the `Connect` packet and streams the world
+    async fn finish(self) -> Result<Connection, ServerError> {
+        let Handshake {
+            id,
+            tcp,
+            address,
+            udp,
+            registration,
+            registrations,
+            timeout,
+            map,
+            content_map,
+            rules,
+        } = self;
+        let (read, write) = tcp.into_split();
+        let mut read = FramedRead::new(read, PacketCodec::default());
+        let mut write = FramedWrite::new(write, PacketCodec::default());
+
+        let registered = async {
+            write.send(FrameworkPacket::RegisterTCP(id)).await?;
+            registration
+                .await
+                .map_err(|_| ServerError::Handshake("stopped receiving UDP packets".to_string()))
+        };
+        let udp_address = time::timeout(timeout, registered).await;
+        registrations.lock().unwrap().remove(&id);
+        let udp_address = udp_address.map_err(|_| {
+            ServerError::Handshake(format!(
+                "{address} did not register its UDP channel within {timeout:?}"
+            ))
+        })??;
+        write.send(FrameworkPacket::RegisterUDP(id)).await?;
+
+        let packet = loop {
+            match next_packet(&mut read).await? {
+                AnyPacket::Framework(_) => {}
+                AnyPacket::Regular(packet) => break packet,
+            }
+        };
+        let Packet::Connect {
+            version,
+            name,
+            uuid,
+            ..
+        } = packet
+        else {
+            return Err(ServerError::Handshake(format!(
+                "expected Connect, got {packet:?}"
+            )));
+        };
+        let Some(protocol_version) = ProtocolVersion::from_build(version) else {
+            return Err(ServerError::Handshake(format!(
+                "unsupported build {version}"
+            )));
+        };
+        read.decoder_mut().version = protocol_version;
+        tracing::info!("'{name}' connected as {id}");
+
+        let mut connection = Connection {
+            id,
+            name: name.clone(),
+            uuid,
+            version,
+            read,
+            write,
+            udp,
+            udp_address,
+            streams: HashMap::new(),
+        };
+        connection.send(Packet::WorldDataBeginCall).await?;
+        connection
+            .send_stream(Packet::WorldStream(Box::new(World {
+                rules,
+                map_info: MapInfo::new(HashMap::new(), &map),
+                wave: 1,
+                wave_time: 0.0,
+                tick: 0.0,
+                seed0: 0,
+                seed1: 0,
+                id,
+                player: PlayerInfo {
+                    revision: 0,
+                    admin: false,
+                    boosting: false,
+                    color: 0xffa108ff,
+                    mouse_x: 0.0,
+                    mouse_y: 0.0,
+                    name: Some(name),
+                    shooting: false,
+                    team: 1,
+                    typing: false,
+                    unit: Unit {
+                        unit_type: 0,
+                        id: 0,
+                    },
+                    x: 0.0,
+                    y: 0.0,
+                },
+                content_map,
+                map,
+                team_plans: HashMap::new(),
+                markers: HashMap::new(),
+                custom_chunks: HashMap::new(),
+            })))
+            .await?;
+
+        Ok(connection)
+    }
+}
+
+impl Connection {
+    pub async fn send(&mut self, packet: Packet) -> Result<(), ServerError> {
+        self.write.send(packet).await?;
+        Ok(())
+    }
+
+    /// Sends a packet over UDP, like the server does for snapshots
+    pub async fn send_unreliable(&self, packet: Packet) -> Result<(), ServerError> {
+        let frame = write_packet(packet)?;
+        self.udp.send_to(&frame[2..], self.udp_address).await?;
+        Ok(())
+    }
+
+    /// Splits a packet into `StreamBegin` and `StreamChunk` packets, even if it would fit
+    /// into a single frame
+    pub async fn send_stream(&mut self, packet: Packet) -> Result<(), ServerError> {
+        let (stream_type, data) = write_packet_data(packet)?;
+        let id = self.write.encoder_mut().next_stream_id();
+        for packet in split_stream(id, stream_type, &data) {
+            self.send(packet).await?;
+        }
+        Ok(())
+    }
+
+    /// Waits for the next regular packet from the client, reassembling streamed packets
+    pub async fn recv(&mut self) -> Result<Packet, ServerError> {
+        loop {
+            let AnyPacket::Regular(packet) = next_packet(&mut self.read).await? else {
+                continue;
+            };
+            match packet {
+                Packet::StreamBegin {
+                    id,
+                    total,
+                    stream_type,
+                } => {
+                    self.streams
+                        .insert(id, StreamBuilder::new(id, stream_type, total));
+                }
+                Packet::StreamChunk { id, data } => {
+                    let Some(stream) = self.streams.get_mut(&id) else {
+                        tracing::warn!("Chunk for unknown stream {id} from connection {}", self.id);
+                        continue;
+                    };
+                    stream.add(data);
+                    if stream.is_done() {
+                        let stream = self.streams.remove(&id).unwrap();
+                        return Ok(stream.build(&None, self.read.decoder().version)?);
+                    }
+                }
+                packet => return Ok(packet),
+            }
+        }
+    }
+}
+
+async fn next_packet(
+    read: &mut FramedRead<OwnedReadHalf, PacketCodec>,
+) -> Result<AnyPacket, ServerError> {
+    match read.next().await {
+        Some(packet) => Ok(packet?),
+        None => Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into()),
+    }
+}
+
+/// Hands the `RegisterUDP` packets of clients to their handshakes. Other UDP packets from
+/// clients are not used by this server.
+async fn receive_udp(udp: Arc<UdpSocket>, registrations: Registrations) {
+    let mut buf = vec![0u8; u16::MAX as usize];
+    loop {
+        let (length, from) = match udp.recv_from(&mut buf).await {
+            Ok(received) => received,
+            Err(e) => {
+                tracing::warn!("Failed to receive UDP packet: {e}");
+                continue;
+            }
+        };
+        match parse_packet(buf[..length].to_vec(), &None, ProtocolVersion::default()) {
+            Ok(AnyPacket::Framework(FrameworkPacket::RegisterUDP(id))) => {
+                match registrations.lock().unwrap().remove(&id) {
+                    Some(sender) => {
+                        let _ = sender.send(from);
+                    }
+                    None => tracing::debug!("Ignoring UDP registration of {id} from {from}"),
+                }
+            }
+            Ok(packet) => tracing::debug!("Ignoring UDP packet from {from}: {packet:?}"),
+            Err(e) => tracing::warn!("Dropping malformed UDP packet from {from}: {e}"),
+        }
+    }
+}
@@ -1,6 +1,7 @@
-use crate::packet::{Packet, PacketError, parse_regular_packet};
+use crate::content::ContentRegistry;
+use crate::packet::{Packet, PacketError, parse_regular_packet, read_regular_packet};
 use crate::type_io::Reader;
-use std::collections::HashMap;
+use crate::version::ProtocolVersion;
 
 pub struct StreamBuilder {
     pub id: u32,
@@ -29,9 +30,22 @@ impl StreamBuilder {
 
     pub fn build(
         self,
-        content_map: &Option<HashMap<String, Vec<String>>>,
+        content_map: &Option<ContentRegistry>,
+        version: ProtocolVersion,
     ) -> Result<Packet, PacketError> {
-        let reader = Reader::new(self.data);
+        let reader = Reader::with_version(self.data, version);
         parse_regular_packet(self.stream_type, reader, content_map)
     }
+
+    /// Decodes the packet like `build`, also returning how many bytes of its data were
+    /// left unread
+    pub fn build_checked(
+        self,
+        content_map: &Option<ContentRegistry>,
+        version: ProtocolVersion,
+    ) -> Result<(Packet, usize), PacketError> {
+        let mut reader = Reader::with_version(self.data, version);
+        let packet = read_regular_packet(self.stream_type, &mut reader, content_map)?;
+        Ok((packet, reader.remaining()))
+    }
 }
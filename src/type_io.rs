@@ -1,67 +1,98 @@
 use num_enum::{IntoPrimitive, TryFromPrimitive};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::collections::HashMap;
 
 use crate::arc_types::Point2;
+use crate::packet::PacketError;
+use crate::version::ProtocolVersion;
 
 #[derive(Debug)]
 pub struct Reader {
     buf: Vec<u8>,
     pos: usize,
+    version: ProtocolVersion,
 }
 impl Reader {
+    /// Reads data in the format of the oldest supported build
     pub fn new(buf: Vec<u8>) -> Reader {
-        Reader { buf, pos: 0 }
+        Self::with_version(buf, ProtocolVersion::default())
     }
 
-    pub fn byte(&mut self) -> u8 {
-        let b = self.buf[self.pos];
-        self.pos += 1;
-        b
+    pub fn with_version(buf: Vec<u8>, version: ProtocolVersion) -> Reader {
+        Reader {
+            buf,
+            pos: 0,
+            version,
+        }
     }
 
-    pub fn bool(&mut self) -> bool {
-        self.byte() == 1
+    /// The build format being read, nested readers should be created with the same one
+    pub fn version(&self) -> ProtocolVersion {
+        self.version
     }
 
-    pub fn bytes(&mut self, n: usize) -> Vec<u8> {
-        let end = (self.pos + n).min(self.buf.len());
-        let bytes = self.buf[self.pos..end].to_vec();
-        self.pos = end;
-        bytes
+    /// Creates a decode error at the current position
+    pub fn error(&self, reason: impl Into<String>) -> PacketError {
+        PacketError::Decode {
+            packet_id: None,
+            offset: self.pos,
+            field: String::new(),
+            reason: reason.into(),
+        }
+    }
+
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], PacketError> {
+        let bytes = self.bytes(N)?;
+        Ok(bytes.try_into().unwrap())
+    }
+
+    pub fn byte(&mut self) -> Result<u8, PacketError> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    pub fn bool(&mut self) -> Result<bool, PacketError> {
+        Ok(self.byte()? == 1)
+    }
+
+    pub fn bytes(&mut self, n: usize) -> Result<Vec<u8>, PacketError> {
+        if self.remaining() < n {
+            return Err(self.error(format!(
+                "expected {n} bytes, only {} remaining",
+                self.remaining()
+            )));
+        }
+        let bytes = self.buf[self.pos..self.pos + n].to_vec();
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    pub fn unsigned_short(&mut self) -> Result<u16, PacketError> {
+        Ok(u16::from_be_bytes(self.take()?))
     }
 
-    pub fn unsigned_short(&mut self) -> u16 {
-        let bytes = self.bytes(2);
-        u16::from_be_bytes([bytes[0], bytes[1]])
+    pub fn short(&mut self) -> Result<i16, PacketError> {
+        Ok(i16::from_be_bytes(self.take()?))
     }
 
-    pub fn short(&mut self) -> i16 {
-        let bytes = self.bytes(2);
-        i16::from_be_bytes([bytes[0], bytes[1]])
+    pub fn int(&mut self) -> Result<u32, PacketError> {
+        Ok(u32::from_be_bytes(self.take()?))
     }
 
-    pub fn int(&mut self) -> u32 {
-        let bytes = self.bytes(4);
-        u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    pub fn long(&mut self) -> Result<u64, PacketError> {
+        Ok(u64::from_be_bytes(self.take()?))
     }
 
-    pub fn long(&mut self) -> u64 {
-        let bytes = self.bytes(8);
-        u64::from_be_bytes([
-            bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7],
-        ])
+    pub fn float(&mut self) -> Result<f32, PacketError> {
+        Ok(f32::from_be_bytes(self.take()?))
     }
 
-    pub fn float(&mut self) -> f32 {
-        let bytes = self.bytes(4);
-        f32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    pub fn double(&mut self) -> Result<f64, PacketError> {
+        Ok(f64::from_be_bytes(self.take()?))
     }
 
-    pub fn double(&mut self) -> f64 {
-        let bytes = self.bytes(8);
-        f64::from_be_bytes([
-            bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7],
-        ])
+    pub fn position(&self) -> usize {
+        self.pos
     }
 
     pub fn remaining(&self) -> usize {
@@ -75,6 +106,46 @@ impl Reader {
     }
 }
 
+/// Adds the name of the field being decoded to decode errors
+pub trait FieldContext {
+    fn field(self, name: &str) -> Self;
+}
+impl<T> FieldContext for Result<T, PacketError> {
+    fn field(self, name: &str) -> Self {
+        self.map_err(|error| match error {
+            PacketError::Decode {
+                packet_id,
+                offset,
+                field,
+                reason,
+            } => PacketError::Decode {
+                packet_id,
+                offset,
+                field: if field.is_empty() {
+                    name.to_string()
+                } else {
+                    format!("{name}.{field}")
+                },
+                reason,
+            },
+            PacketError::Encode {
+                packet_id,
+                field,
+                reason,
+            } => PacketError::Encode {
+                packet_id,
+                field: if field.is_empty() {
+                    name.to_string()
+                } else {
+                    format!("{name}.{field}")
+                },
+                reason,
+            },
+            other => other,
+        })
+    }
+}
+
 pub fn write_byte(buf: &mut Vec<u8>, value: u8) {
     buf.push(value);
 }
@@ -106,217 +177,173 @@ pub fn write_double(buf: &mut Vec<u8>, data: f64) {
     buf.extend_from_slice(&data.to_be_bytes());
 }
 
-pub fn read_prefixed_string(reader: &mut Reader) -> Option<String> {
-    if reader.remaining() == 0 {
-        return None;
-    }
-
-    let has_string = reader.byte() != 0;
+pub fn read_prefixed_string(reader: &mut Reader) -> Result<Option<String>, PacketError> {
+    let has_string = reader.byte()? != 0;
     if !has_string {
-        return None;
+        return Ok(None);
     }
 
-    if reader.remaining() < 2 {
-        return None;
-    }
-    let length = reader.unsigned_short() as usize;
-
-    if reader.remaining() < length {
-        return None;
-    }
-    let string_bytes: Vec<u8> = reader.bytes(length);
-    String::from_utf8(string_bytes).ok()
+    let length = reader.unsigned_short()? as usize;
+    let string_bytes: Vec<u8> = reader.bytes(length)?;
+    utf8(reader, string_bytes).map(Some)
 }
 
-pub fn read_string(reader: &mut Reader) -> Option<String> {
-    let length = reader.unsigned_short();
+pub fn read_string(reader: &mut Reader) -> Result<Option<String>, PacketError> {
+    let length = reader.unsigned_short()?;
     if length == 0 {
-        return None;
+        return Ok(None);
     }
-    let string_bytes: Vec<u8> = reader.bytes(length as usize);
-    String::from_utf8(string_bytes).ok()
+    let string_bytes: Vec<u8> = reader.bytes(length as usize)?;
+    utf8(reader, string_bytes).map(Some)
 }
 
-pub fn write_string(buf: &mut Vec<u8>, string: &str) {
+fn utf8(reader: &Reader, bytes: Vec<u8>) -> Result<String, PacketError> {
+    String::from_utf8(bytes).map_err(|e| reader.error(format!("invalid UTF-8: {e}")))
+}
+
+pub fn write_string(buf: &mut Vec<u8>, string: &str) -> Result<(), PacketError> {
     if !string.is_empty() {
         buf.push(1);
-        let encoded = string.as_bytes();
-        let length = encoded.len();
-
-        // Length should fit in 2 bytes
-        assert!(length <= u16::MAX as usize, "String too long");
-
-        buf.extend_from_slice(&(length as u16).to_be_bytes());
-        buf.extend_from_slice(encoded);
+        write_utf(buf, string)
     } else {
         buf.push(0);
+        Ok(())
     }
 }
 
-// TODO
-#[derive(Debug, Clone)]
+/// Counterpart to `read_prefixed_string`, unlike `write_string` this keeps empty strings
+pub fn write_prefixed_string(buf: &mut Vec<u8>, string: Option<&str>) -> Result<(), PacketError> {
+    match string {
+        Some(string) => {
+            buf.push(1);
+            write_utf(buf, string)
+        }
+        None => {
+            buf.push(0);
+            Ok(())
+        }
+    }
+}
+
+/// Counterpart to `read_string`. Fails for strings longer than the 65535 bytes their
+/// length prefix can hold, like `DataOutput.writeUTF` does.
+pub fn write_utf(buf: &mut Vec<u8>, string: &str) -> Result<(), PacketError> {
+    let encoded = string.as_bytes();
+    let length = u16::try_from(encoded.len()).map_err(|_| PacketError::Encode {
+        packet_id: None,
+        field: String::new(),
+        reason: format!("string of {} bytes is longer than 65535", encoded.len()),
+    })?;
+    write_unsigned_short(buf, length);
+    buf.extend_from_slice(encoded);
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Object {
     Null,
     Int(u32),
     Long(u64),
     Float(f32),
     String(Option<String>),
-    // Content
+    Content(Content),
     IntSequence(Vec<u32>),
     Point2(Point2),
     Point2Array(Vec<Point2>),
-    // TechNode
+    TechNode(Content),
     Boolean(bool),
     Double(f64),
-    // Building
-    // LAccess
+    Building(Tile),
+    LAccess(i16),
     ByteArray(Vec<u8>),
     BooleanArray(Vec<bool>),
-    // Unit
+    Unit(u32),
     Vec2Array(Vec<Vec2>),
     Vec2(Vec2),
-    // Team
-    // int[]
-    // Object[]
-    // UnitCommand
-    Unknown,
-
-    NotImplemented,
+    Team(u8),
+    IntArray(Vec<u32>),
+    ObjectArray(Vec<Object>),
+    UnitCommand(u16),
 }
 
-pub fn read_object_boxed(reader: &mut Reader, box_: bool) -> Object {
+pub fn read_object_boxed(reader: &mut Reader, _box: bool) -> Result<Object, PacketError> {
     read_object(reader)
 }
 
-// TODO
-pub fn read_object(reader: &mut Reader) -> Object {
-    let object_type = reader.byte();
+pub fn read_object(reader: &mut Reader) -> Result<Object, PacketError> {
+    let object_type = reader.byte()?;
 
-    match object_type {
+    Ok(match object_type {
         0 => Object::Null,
-        1 => Object::Int(reader.int()),
-        2 => Object::Long(reader.long()),
-        3 => Object::Float(reader.float()),
-        4 => Object::String(read_prefixed_string(reader)),
-        5 => {
-            reader.byte();
-            reader.short();
-            Object::NotImplemented
-        }
+        1 => Object::Int(reader.int()?),
+        2 => Object::Long(reader.long()?),
+        3 => Object::Float(reader.float()?),
+        4 => Object::String(read_prefixed_string(reader)?),
+        5 => Object::Content(read_content(reader)?),
         6 => {
-            let length = reader.short();
+            let length = reader.short()?;
             let mut values = vec![];
             for _ in 0..length {
-                values.push(reader.int());
+                values.push(reader.int()?);
             }
             Object::IntSequence(values)
         }
         7 => {
-            let x = reader.int() as i16;
-            let y = reader.int() as i16;
+            let x = reader.int()? as i16;
+            let y = reader.int()? as i16;
             Object::Point2(Point2 { x, y })
         }
         8 => {
-            let length = reader.byte();
+            let length = reader.byte()?;
             let mut values = vec![];
             for _ in 0..length {
-                values.push(Point2::unpack(reader.int()));
+                values.push(Point2::unpack(reader.int()?));
             }
             Object::Point2Array(values)
         }
-        9 => {
-            reader.byte();
-            reader.short();
-            Object::NotImplemented
-        }
-        10 => {
-            let value = reader.bool();
-            Object::Boolean(value)
-        }
-        11 => {
-            let value = reader.double();
-            Object::Double(value)
-        }
-        12 => {
-            reader.int();
-            Object::NotImplemented
-        }
-        13 => {
-            reader.short();
-            Object::NotImplemented
-        }
+        9 => Object::TechNode(read_content(reader)?),
+        10 => Object::Boolean(reader.bool()?),
+        11 => Object::Double(reader.double()?),
+        12 => Object::Building(read_tile(reader)?),
+        13 => Object::LAccess(reader.short()?),
         14 => {
-            let length = reader.int();
-            let mut values = vec![];
-            for _ in 0..length {
-                values.push(reader.byte());
-            }
-            Object::ByteArray(values)
+            let length = reader.int()?;
+            Object::ByteArray(reader.bytes(length as usize)?)
         }
-        //15 => {
-        //    reader.byte();
-        //    Object::NotImplemented
-        //}
         16 => {
-            let length = reader.short();
+            let length = reader.int()?;
             let mut values = vec![];
             for _ in 0..length {
-                values.push(reader.bool());
+                values.push(reader.bool()?);
             }
             Object::BooleanArray(values)
         }
-        17 => {
-            reader.int();
-            Object::NotImplemented
-        }
+        17 => Object::Unit(reader.int()?),
         18 => {
-            let length = reader.short();
+            let length = reader.short()?;
             let mut values = vec![];
             for _ in 0..length {
-                let x = reader.float();
-                let y = reader.float();
-                values.push(Vec2 { x, y });
+                values.push(read_vec2(reader)?);
             }
             Object::Vec2Array(values)
         }
-        19 => {
-            let x = reader.float();
-            let y = reader.float();
-            Object::Vec2(Vec2 { x, y })
-        }
-        20 => {
-            reader.byte();
-            Object::NotImplemented
-        }
-        21 => {
-            let length = reader.short();
-            let mut values = vec![];
-            for _ in 0..length {
-                values.push(reader.int());
-            }
-            Object::NotImplemented
-        }
+        19 => Object::Vec2(read_vec2(reader)?),
+        20 => Object::Team(reader.byte()?),
+        21 => Object::IntArray(read_ints(reader)?),
         22 => {
-            let length = reader.short();
+            let length = reader.int()?;
             let mut values = vec![];
             for _ in 0..length {
-                values.push(read_object(reader));
+                values.push(read_object(reader)?);
             }
-            Object::NotImplemented
-        }
-        23 => {
-            reader.byte();
-            Object::NotImplemented
-        }
-        other => {
-            eprintln!("Unknown object: {other}");
-            Object::Unknown
+            Object::ObjectArray(values)
         }
-    }
+        23 => Object::UnitCommand(reader.unsigned_short()?),
+        other => return Err(reader.error(format!("unknown object type {other}"))),
+    })
 }
 
-// TODO
-pub fn write_object(buf: &mut Vec<u8>, object: Object) {
+pub fn write_object(buf: &mut Vec<u8>, object: Object) -> Result<(), PacketError> {
     match object {
         Object::Null => {
             write_byte(buf, 0u8);
@@ -335,9 +362,12 @@ pub fn write_object(buf: &mut Vec<u8>, object: Object) {
         }
         Object::String(value) => {
             write_byte(buf, 4u8);
-            write_string(buf, &*value.unwrap());
+            write_prefixed_string(buf, value.as_deref())?;
+        }
+        Object::Content(value) => {
+            write_byte(buf, 5u8);
+            write_content(buf, value);
         }
-        // Content
         Object::IntSequence(values) => {
             write_byte(buf, 6u8);
             write_short(buf, values.len() as i16);
@@ -357,7 +387,10 @@ pub fn write_object(buf: &mut Vec<u8>, object: Object) {
                 write_int(buf, value.pack() as u32);
             }
         }
-        // TechNode
+        Object::TechNode(value) => {
+            write_byte(buf, 9u8);
+            write_content(buf, value);
+        }
         Object::Boolean(value) => {
             write_byte(buf, 10u8);
             write_bool(buf, value);
@@ -366,14 +399,18 @@ pub fn write_object(buf: &mut Vec<u8>, object: Object) {
             write_byte(buf, 11u8);
             write_double(buf, value);
         }
-        // BuildingBox
-        // LAccess
+        Object::Building(value) => {
+            write_byte(buf, 12u8);
+            write_tile(buf, value);
+        }
+        Object::LAccess(value) => {
+            write_byte(buf, 13u8);
+            write_short(buf, value);
+        }
         Object::ByteArray(values) => {
             write_byte(buf, 14u8);
             write_int(buf, values.len() as u32);
-            for value in values {
-                write_byte(buf, value);
-            }
+            buf.extend_from_slice(&values);
         }
         Object::BooleanArray(values) => {
             write_byte(buf, 16u8);
@@ -382,40 +419,69 @@ pub fn write_object(buf: &mut Vec<u8>, object: Object) {
                 write_bool(buf, value);
             }
         }
-        // Unit
+        Object::Unit(value) => {
+            write_byte(buf, 17u8);
+            write_int(buf, value);
+        }
         Object::Vec2Array(values) => {
             write_byte(buf, 18u8);
             write_short(buf, values.len() as i16);
             for value in values {
-                write_float(buf, value.x);
-                write_float(buf, value.y);
+                write_vec2(buf, value);
             }
         }
         Object::Vec2(value) => {
             write_byte(buf, 19u8);
-            write_float(buf, value.x);
-            write_float(buf, value.y);
+            write_vec2(buf, value);
+        }
+        Object::Team(value) => {
+            write_byte(buf, 20u8);
+            write_byte(buf, value);
+        }
+        Object::IntArray(values) => {
+            write_byte(buf, 21u8);
+            write_ints(buf, values);
+        }
+        Object::ObjectArray(values) => {
+            write_byte(buf, 22u8);
+            write_int(buf, values.len() as u32);
+            for value in values {
+                write_object(buf, value)?;
+            }
+        }
+        Object::UnitCommand(value) => {
+            write_byte(buf, 23u8);
+            write_unsigned_short(buf, value);
         }
-        // Team
-        // int[]
-        // Object[]
-        // UnitCommand
-        Object::NotImplemented => {}
-        Object::Unknown => {}
     }
+    Ok(())
 }
 
-pub fn read_string_map(reader: &mut Reader) -> HashMap<String, Option<String>> {
+pub fn read_string_map(
+    reader: &mut Reader,
+) -> Result<HashMap<String, Option<String>>, PacketError> {
     let mut data = HashMap::new();
 
-    let size = reader.short();
+    let size = reader.short()?;
     for _ in 0..size {
-        let key = read_string(reader).unwrap();
-        let value = read_string(reader);
+        let key = read_string(reader)?.unwrap_or_default();
+        let value = read_string(reader)?;
         data.insert(key, value);
     }
 
-    data
+    Ok(data)
+}
+
+pub fn write_string_map(
+    buf: &mut Vec<u8>,
+    data: HashMap<String, Option<String>>,
+) -> Result<(), PacketError> {
+    write_short(buf, data.len() as i16);
+    for (key, value) in data {
+        write_utf(buf, &key).field(&key)?;
+        write_utf(buf, value.as_deref().unwrap_or_default()).field(&key)?;
+    }
+    Ok(())
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, IntoPrimitive, TryFromPrimitive)]
@@ -439,24 +505,435 @@ pub enum KickReason {
     ServerRestarting = 15,
 }
 
-pub fn read_kick(reader: &mut Reader) -> Option<KickReason> {
-    KickReason::try_from(reader.byte()).ok()
+pub fn read_kick(reader: &mut Reader) -> Result<KickReason, PacketError> {
+    let value = reader.byte()?;
+    KickReason::try_from(value).map_err(|_| reader.error(format!("unknown kick reason {value}")))
 }
 
 pub fn write_kick(buf: &mut Vec<u8>, reason: KickReason) {
     buf.push(reason as u8);
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, IntoPrimitive, TryFromPrimitive)]
+#[repr(u8)]
+pub enum AdminAction {
+    Kick = 0,
+    Ban = 1,
+    Trace = 2,
+    Wave = 3,
+    SwitchTeam = 4,
+}
+
+pub fn read_admin_action(reader: &mut Reader) -> Result<AdminAction, PacketError> {
+    let value = reader.byte()?;
+    AdminAction::try_from(value).map_err(|_| reader.error(format!("unknown admin action {value}")))
+}
+
+pub fn write_admin_action(buf: &mut Vec<u8>, action: AdminAction) {
+    buf.push(action as u8);
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Content {
+    pub content_type: u8,
+    pub id: i16,
+}
+
+pub fn read_content(reader: &mut Reader) -> Result<Content, PacketError> {
+    let content_type = reader.byte()?;
+    let id = reader.short()?;
+    Ok(Content { content_type, id })
+}
+
+pub fn write_content(buf: &mut Vec<u8>, content: Content) {
+    write_byte(buf, content.content_type);
+    write_short(buf, content.id);
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceInfo {
+    pub ip: Option<String>,
+    pub uuid: Option<String>,
+    pub locale: Option<String>,
+    pub modded: bool,
+    pub mobile: bool,
+    pub times_joined: u32,
+    pub times_kicked: u32,
+    pub ips: Vec<String>,
+    pub names: Vec<String>,
+}
+
+pub fn read_trace_info(reader: &mut Reader) -> Result<TraceInfo, PacketError> {
+    Ok(TraceInfo {
+        ip: read_prefixed_string(reader)?,
+        uuid: read_prefixed_string(reader)?,
+        locale: read_prefixed_string(reader)?,
+        modded: reader.bool()?,
+        mobile: reader.bool()?,
+        times_joined: reader.int()?,
+        times_kicked: reader.int()?,
+        ips: read_strings(reader)?,
+        names: read_strings(reader)?,
+    })
+}
+
+pub fn write_trace_info(buf: &mut Vec<u8>, info: TraceInfo) -> Result<(), PacketError> {
+    write_prefixed_string(buf, info.ip.as_deref()).field("ip")?;
+    write_prefixed_string(buf, info.uuid.as_deref()).field("uuid")?;
+    write_prefixed_string(buf, info.locale.as_deref()).field("locale")?;
+    write_bool(buf, info.modded);
+    write_bool(buf, info.mobile);
+    write_int(buf, info.times_joined);
+    write_int(buf, info.times_kicked);
+    write_strings(buf, info.ips).field("ips")?;
+    write_strings(buf, info.names).field("names")
+}
+
+/// Reads an `int[]`, prefixed with a short length
+pub fn read_ints(reader: &mut Reader) -> Result<Vec<u32>, PacketError> {
+    let length = reader.short()?;
+    let mut values = vec![];
+    for _ in 0..length {
+        values.push(reader.int()?);
+    }
+    Ok(values)
+}
+
+pub fn write_ints(buf: &mut Vec<u8>, values: Vec<u32>) {
+    write_short(buf, values.len() as i16);
+    for value in values {
+        write_int(buf, value);
+    }
+}
+
+/// Reads an `IntSeq`, prefixed with an int length
+pub fn read_int_seq(reader: &mut Reader) -> Result<Vec<u32>, PacketError> {
+    let length = reader.int()?;
+    let mut values = vec![];
+    for _ in 0..length {
+        values.push(reader.int()?);
+    }
+    Ok(values)
+}
+
+pub fn write_int_seq(buf: &mut Vec<u8>, values: Vec<u32>) {
+    write_int(buf, values.len() as u32);
+    for value in values {
+        write_int(buf, value);
+    }
+}
+
+/// Reads a `byte[]`, prefixed with a short length
+pub fn read_bytes(reader: &mut Reader) -> Result<Vec<u8>, PacketError> {
+    let length = reader.short()?;
+    reader.bytes(length as usize)
+}
+
+pub fn write_bytes(buf: &mut Vec<u8>, values: &[u8]) {
+    write_short(buf, values.len() as i16);
+    buf.extend_from_slice(values);
+}
+
+pub fn read_strings(reader: &mut Reader) -> Result<Vec<String>, PacketError> {
+    let length = reader.byte()?;
+    let mut values = vec![];
+    for _ in 0..length {
+        values.push(read_prefixed_string(reader)?.unwrap_or_default());
+    }
+    Ok(values)
+}
+
+pub fn write_strings(buf: &mut Vec<u8>, values: Vec<String>) -> Result<(), PacketError> {
+    write_byte(buf, values.len() as u8);
+    for value in values {
+        write_prefixed_string(buf, Some(&value))?;
+    }
+    Ok(())
+}
+
+/// Reads a `String[][]`, as used by menu options
+pub fn read_string_data(reader: &mut Reader) -> Result<Vec<Vec<String>>, PacketError> {
+    let rows = reader.byte()?;
+    let mut values = vec![];
+    for _ in 0..rows {
+        values.push(read_strings(reader)?);
+    }
+    Ok(values)
+}
+
+pub fn write_string_data(buf: &mut Vec<u8>, values: Vec<Vec<String>>) -> Result<(), PacketError> {
+    write_byte(buf, values.len() as u8);
+    for row in values {
+        write_strings(buf, row)?;
+    }
+    Ok(())
+}
+
+/// Reads a JSON document prefixed with an int length, as used for rules and objectives
+pub fn read_json(reader: &mut Reader) -> Result<String, PacketError> {
+    let length = reader.int()?;
+    let bytes = reader.bytes(length as usize)?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+pub fn write_json(buf: &mut Vec<u8>, json: &str) {
+    write_int(buf, json.len() as u32);
+    buf.extend_from_slice(json.as_bytes());
+}
+
+/// How deep UBJSON arrays and objects may nest, deeper data is rejected instead of
+/// overflowing the stack
+const MAX_UBJSON_DEPTH: usize = 64;
+
+/// Reads a single value in Arc's UBJSON format, as written by `JsonIO.writeBytes`
+pub fn read_ubjson(reader: &mut Reader) -> Result<Value, PacketError> {
+    let value_type = reader.byte()?;
+    read_ubjson_value(reader, value_type, 0)
+}
+
+fn read_ubjson_value(
+    reader: &mut Reader,
+    value_type: u8,
+    depth: usize,
+) -> Result<Value, PacketError> {
+    if matches!(value_type, b'[' | b'{') && depth >= MAX_UBJSON_DEPTH {
+        return Err(reader.error(format!(
+            "UBJSON nested deeper than {MAX_UBJSON_DEPTH} levels"
+        )));
+    }
+    Ok(match value_type {
+        b'Z' => Value::Null,
+        b'T' => Value::Bool(true),
+        b'F' => Value::Bool(false),
+        b'i' => (reader.byte()? as i8).into(),
+        b'U' => reader.byte()?.into(),
+        b'I' => reader.short()?.into(),
+        b'C' => reader.unsigned_short()?.into(),
+        b'l' => (reader.int()? as i32).into(),
+        b'L' => (reader.long()? as i64).into(),
+        b'd' => (reader.float()? as f64).into(),
+        b'D' => reader.double()?.into(),
+        b'S' | b's' => Value::String(read_ubjson_string(reader, value_type)?),
+        b'[' => {
+            let mut values = vec![];
+            match read_ubjson_count(reader)? {
+                UbjsonCount::Counted(value_type, count) => {
+                    for _ in 0..count {
+                        let value_type = match value_type {
+                            Some(value_type) => value_type,
+                            None => reader.byte()?,
+                        };
+                        values.push(read_ubjson_value(reader, value_type, depth + 1)?);
+                    }
+                }
+                UbjsonCount::Open(mut next) => {
+                    while next != b']' {
+                        values.push(read_ubjson_value(reader, next, depth + 1)?);
+                        next = reader.byte()?;
+                    }
+                }
+            }
+            Value::Array(values)
+        }
+        b'{' => {
+            let mut values = serde_json::Map::new();
+            match read_ubjson_count(reader)? {
+                UbjsonCount::Counted(value_type, count) => {
+                    for _ in 0..count {
+                        let key_type = reader.byte()?;
+                        let key = read_ubjson_string(reader, key_type)?;
+                        let value_type = match value_type {
+                            Some(value_type) => value_type,
+                            None => reader.byte()?,
+                        };
+                        values.insert(key, read_ubjson_value(reader, value_type, depth + 1)?);
+                    }
+                }
+                UbjsonCount::Open(mut next) => {
+                    while next != b'}' {
+                        let key = read_ubjson_string(reader, next)?;
+                        let value_type = reader.byte()?;
+                        values.insert(key, read_ubjson_value(reader, value_type, depth + 1)?);
+                        next = reader.byte()?;
+                    }
+                }
+            }
+            Value::Object(values)
+        }
+        other => return Err(reader.error(format!("unknown UBJSON type {other}"))),
+    })
+}
+
+enum UbjsonCount {
+    /// The optimized form, with an optional type shared by every value
+    Counted(Option<u8>, usize),
+    /// Entries until the closing marker, starting with the given byte
+    Open(u8),
+}
+
+fn read_ubjson_count(reader: &mut Reader) -> Result<UbjsonCount, PacketError> {
+    let mut next = reader.byte()?;
+    let mut value_type = None;
+    if next == b'$' {
+        value_type = Some(reader.byte()?);
+        next = reader.byte()?;
+    }
+    if next == b'#' {
+        let size_type = reader.byte()?;
+        let count = read_ubjson_size(reader, size_type)?;
+        return Ok(UbjsonCount::Counted(value_type, count));
+    }
+    if value_type.is_some() {
+        return Err(reader.error("UBJSON container has a type but no count"));
+    }
+    Ok(UbjsonCount::Open(next))
+}
+
+fn read_ubjson_size(reader: &mut Reader, size_type: u8) -> Result<usize, PacketError> {
+    Ok(match size_type {
+        b'i' | b'U' => reader.byte()? as usize,
+        b'I' => reader.unsigned_short()? as usize,
+        b'l' => reader.int()? as usize,
+        b'L' => reader.long()? as usize,
+        other => return Err(reader.error(format!("invalid UBJSON size type {other}"))),
+    })
+}
+
+fn read_ubjson_string(reader: &mut Reader, string_type: u8) -> Result<String, PacketError> {
+    let length = match string_type {
+        b's' => reader.byte()? as usize,
+        b'S' => {
+            let size_type = reader.byte()?;
+            read_ubjson_size(reader, size_type)?
+        }
+        // Object keys leave out the `S`
+        size_type => read_ubjson_size(reader, size_type)?,
+    };
+    let bytes = reader.bytes(length)?;
+    utf8(reader, bytes)
+}
+
+/// Counterpart to `read_ubjson`. Integers are written as ints or longs and floats as floats
+/// when that keeps their value, the same types Arc's `Json` writes for fields.
+pub fn write_ubjson(buf: &mut Vec<u8>, value: &Value) {
+    match value {
+        Value::Null => write_byte(buf, b'Z'),
+        Value::Bool(value) => write_byte(buf, if *value { b'T' } else { b'F' }),
+        Value::Number(number) => {
+            if let Some(int) = number.as_i64() {
+                if let Ok(int) = i32::try_from(int) {
+                    write_byte(buf, b'l');
+                    write_int(buf, int as u32);
+                } else {
+                    write_byte(buf, b'L');
+                    write_long(buf, int as u64);
+                }
+            } else if let Some(long) = number.as_u64() {
+                write_byte(buf, b'L');
+                write_long(buf, long);
+            } else {
+                let double = number.as_f64().unwrap_or_default();
+                if double as f32 as f64 == double {
+                    write_byte(buf, b'd');
+                    write_float(buf, double as f32);
+                } else {
+                    write_byte(buf, b'D');
+                    write_double(buf, double);
+                }
+            }
+        }
+        Value::String(string) => {
+            write_byte(buf, b'S');
+            write_ubjson_name(buf, string);
+        }
+        Value::Array(values) => {
+            write_byte(buf, b'[');
+            for value in values {
+                write_ubjson(buf, value);
+            }
+            write_byte(buf, b']');
+        }
+        Value::Object(values) => {
+            write_byte(buf, b'{');
+            for (key, value) in values {
+                write_ubjson_name(buf, key);
+                write_ubjson(buf, value);
+            }
+            write_byte(buf, b'}');
+        }
+    }
+}
+
+/// Writes a string with its length, without the `S` marker
+fn write_ubjson_name(buf: &mut Vec<u8>, string: &str) {
+    let bytes = string.as_bytes();
+    if bytes.len() <= i8::MAX as usize {
+        write_byte(buf, b'i');
+        write_byte(buf, bytes.len() as u8);
+    } else if bytes.len() <= i16::MAX as usize {
+        write_byte(buf, b'I');
+        write_short(buf, bytes.len() as i16);
+    } else {
+        write_byte(buf, b'l');
+        write_int(buf, bytes.len() as u32);
+    }
+    buf.extend_from_slice(bytes);
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Tile {
     pub x: i16,
     pub y: i16,
 }
+impl Tile {
+    pub fn unpack(pos: u32) -> Self {
+        let point = Point2::unpack(pos);
+        Tile {
+            x: point.x,
+            y: point.y,
+        }
+    }
+
+    pub fn pack(&self) -> u32 {
+        Point2 {
+            x: self.x,
+            y: self.y,
+        }
+        .pack() as u32
+    }
+}
+
+pub fn read_tile(reader: &mut Reader) -> Result<Tile, PacketError> {
+    let x = reader.short()?;
+    let y = reader.short()?;
+    Ok(Tile { x, y })
+}
+
+/// Reads an `int[]` of packed tile positions
+pub fn read_tiles(reader: &mut Reader) -> Result<Vec<Tile>, PacketError> {
+    Ok(read_ints(reader)?.into_iter().map(Tile::unpack).collect())
+}
+
+pub fn write_tiles(buf: &mut Vec<u8>, tiles: Vec<Tile>) {
+    write_ints(buf, tiles.iter().map(Tile::pack).collect());
+}
+
+/// Reads the `IntSeq` of building positions and health bits sent by `buildHealthUpdate`
+pub fn read_build_healths(reader: &mut Reader) -> Result<Vec<(Tile, f32)>, PacketError> {
+    Ok(read_int_seq(reader)?
+        .chunks_exact(2)
+        .map(|pair| (Tile::unpack(pair[0]), f32::from_bits(pair[1])))
+        .collect())
+}
 
-pub fn read_tile(reader: &mut Reader) -> Tile {
-    let x = reader.short();
-    let y = reader.short();
-    Tile { x, y }
+pub fn write_build_healths(buf: &mut Vec<u8>, healths: Vec<(Tile, f32)>) {
+    write_int_seq(
+        buf,
+        healths
+            .into_iter()
+            .flat_map(|(tile, health)| [tile.pack(), health.to_bits()])
+            .collect(),
+    );
 }
 
 pub fn write_tile(buf: &mut Vec<u8>, tile: Tile) {
@@ -464,62 +941,76 @@ pub fn write_tile(buf: &mut Vec<u8>, tile: Tile) {
     write_short(buf, tile.y);
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Unit {
     pub unit_type: u8,
     pub id: u32,
 }
 
-pub fn read_unit(reader: &mut Reader) -> Unit {
-    let unit_type = reader.byte();
-    let id = reader.int();
-    Unit { unit_type, id }
+pub fn read_unit(reader: &mut Reader) -> Result<Unit, PacketError> {
+    let unit_type = reader.byte()?;
+    let id = reader.int()?;
+    Ok(Unit { unit_type, id })
 }
 
 pub fn write_unit(buf: &mut Vec<u8>, unit: Unit) {
-    write_int(buf, unit.id);
     write_byte(buf, unit.unit_type);
+    write_int(buf, unit.id);
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Items {
     pub id: i16,
     pub count: u32,
 }
 
-pub fn read_items(reader: &mut Reader) -> Items {
-    let id = reader.short();
-    let count = reader.int();
-    Items { id, count }
+pub fn read_items(reader: &mut Reader) -> Result<Items, PacketError> {
+    let id = reader.short()?;
+    let count = reader.int()?;
+    Ok(Items { id, count })
 }
 
-#[derive(Debug, Clone)]
+pub fn write_items(buf: &mut Vec<u8>, items: Items) {
+    write_short(buf, items.id);
+    write_int(buf, items.count);
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Vec2 {
-    x: f32,
-    y: f32,
+    pub x: f32,
+    pub y: f32,
+}
+
+pub fn read_vec2(reader: &mut Reader) -> Result<Vec2, PacketError> {
+    let x = reader.float()?;
+    let y = reader.float()?;
+    Ok(Vec2 { x, y })
 }
 
-pub fn read_vec2(reader: &mut Reader) -> Vec2 {
-    let x = reader.float();
-    let y = reader.float();
-    Vec2 { x, y }
+pub fn write_vec2(buf: &mut Vec<u8>, vec: Vec2) {
+    write_float(buf, vec.x);
+    write_float(buf, vec.y);
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Vec2Nullable {
     pub x: f32,
     pub y: f32,
 }
 
-pub fn read_vec2_nullable(reader: &mut Reader) -> Vec2 {
+pub fn read_vec2_nullable(reader: &mut Reader) -> Result<Vec2, PacketError> {
     // TODO  (isNaN(x) || isNaN(y)) ? null : {x, y}
     // How does NaN even work
-    let x = reader.float();
-    let y = reader.float();
-    Vec2 { x, y }
+    let x = reader.float()?;
+    let y = reader.float()?;
+    Ok(Vec2 { x, y })
+}
+
+pub fn read_command(reader: &mut Reader) -> Result<Option<u8>, PacketError> {
+    let value = reader.byte()?;
+    Ok(if value == 255 { None } else { Some(value) })
 }
 
-pub fn read_command(reader: &mut Reader) -> Option<u8> {
-    let value = reader.byte();
-    if value == 255 { None } else { Some(value) }
+pub fn write_command(buf: &mut Vec<u8>, command: Option<u8>) {
+    write_byte(buf, command.unwrap_or(255));
 }
@@ -1,63 +1,32 @@
-use crate::block_io::read_block;
-use crate::save_io::load_block_types;
+use crate::block_io::{Block, read_block, write_block};
+use crate::content::ContentRegistry;
+use crate::packet::PacketError;
+use crate::save_io::lookup_block;
 use crate::type_io::{
-    Items, Object, Reader, Tile, Unit, Vec2, read_items, read_object, read_prefixed_string,
-    read_tile, read_unit, read_vec2, write_byte, write_int, write_object, write_short, write_tile,
-};
-use phf::phf_map;
-use std::collections::HashMap;
-
-static UNIT_MAP: phf::Map<u8, &'static str> = phf_map! {
-    0u8 => "UnitEntity",
-    2u8 => "BlockUnitUnit",
-    3u8 => "UnitEntity",
-    4u8 => "MechUnit",
-    5u8 => "PayloadUnit",
-    6u8 => "",
-    7u8 => "",
-    8u8 => "",
-    9u8 => "",
-    10u8 => "Fire",
-    11u8 => "",
-    12u8 => "Player",
-    13u8 => "Puddle",
-    14u8 => "WeatherState",
-    15u8 => "",
-    16u8 => "UnitEntity",
-    17u8 => "MechUnit",
-    18u8 => "UnitEntity",
-    19u8 => "MechUnit",
-    20u8 => "UnitWaterMove",
-    21u8 => "LegsUnit",
-    23u8 => "PayloadUnit",
-    24u8 => "LegsUnit",
-    26u8 => "PayloadUnit",
-    28u8 => "",
-    29u8 => "LegsUnit",
-    30u8 => "UnitEntity",
-    31u8 => "UnitEntity",
-    32u8 => "MechUnit",
-    33u8 => "LegsUnit",
-    35u8 => "WorldLabel",
-    36u8 => "BuildingTetherPayloadUnit",
-    39u8 => "TimedKillUnit",
-    42u8 => "",
-    43u8 => "TankUnit",
-    45u8 => "ElevationMoveUnit",
-    46u8 => "CrawlUnit",
+    FieldContext, Items, Object, Reader, Tile, Unit, Vec2, read_items, read_object,
+    read_prefixed_string, read_tile, read_unit, read_vec2, write_bool, write_byte, write_double,
+    write_float, write_int, write_items, write_object, write_prefixed_string, write_short,
+    write_tile, write_unit, write_vec2,
 };
 
-pub fn read_abilities(reader: &mut Reader) -> Vec<f32> {
-    let length = reader.byte();
+pub fn read_abilities(reader: &mut Reader) -> Result<Vec<f32>, PacketError> {
+    let length = reader.byte()?;
     let mut abilities = vec![];
     for _ in 0..length {
-        let data = reader.float();
+        let data = reader.float()?;
         abilities.push(data);
     }
-    abilities
+    Ok(abilities)
 }
 
-#[derive(Debug, Clone)]
+pub fn write_abilities(buf: &mut Vec<u8>, abilities: Vec<f32>) {
+    write_byte(buf, abilities.len() as u8);
+    for ability in abilities {
+        write_float(buf, ability);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Plan {
     pub plan_type: u8, // TODO this might be a boolean for deconstruction
     pub position: Tile,
@@ -67,268 +36,482 @@ pub struct Plan {
     pub config: Option<Object>,
 }
 
-pub fn read_plan(reader: &mut Reader) -> Plan {
-    let plan_type = reader.byte();
-    let position = read_tile(reader);
+pub fn read_plan(reader: &mut Reader) -> Result<Plan, PacketError> {
+    let plan_type = reader.byte()?;
+    let position = read_tile(reader)?;
 
     if plan_type == 1 {
-        Plan {
+        Ok(Plan {
             plan_type,
             position,
             block: None,
             rotation: None,
             has_config: None,
             config: None,
-        }
+        })
     } else {
-        let block = reader.short();
-        let rotation = reader.byte();
-        let has_config = reader.byte() != 0;
-        let config = read_object(reader);
-        Plan {
+        let block = reader.short()?;
+        let rotation = reader.byte()?;
+        let has_config = reader.byte()? != 0;
+        let config = read_object(reader)?;
+        Ok(Plan {
             plan_type,
             position,
             block: Some(block),
             rotation: Some(rotation),
             has_config: Some(has_config),
             config: Some(config),
-        }
+        })
     }
 }
 
-pub fn read_plans(reader: &mut Reader) -> Vec<Plan> {
+pub fn read_plans(reader: &mut Reader) -> Result<Vec<Plan>, PacketError> {
     let mut plans = vec![];
-    let plan_count = reader.short();
+    let plan_count = reader.short()?;
     for _ in 0..plan_count {
-        plans.push(read_plan(reader));
+        plans.push(read_plan(reader)?);
     }
-    plans
+    Ok(plans)
 }
 
-pub fn read_plans_queue(reader: &mut Reader) -> Vec<Plan> {
+pub fn read_plans_queue(reader: &mut Reader) -> Result<Vec<Plan>, PacketError> {
     let mut plans = vec![];
-    let plan_count = reader.int();
+    // A missing queue is sent as -1
+    let plan_count = reader.int()? as i32;
     for _ in 0..plan_count {
-        plans.push(read_plan(reader));
+        plans.push(read_plan(reader)?);
     }
-    plans
+    Ok(plans)
 }
 
-pub fn write_plan(buf: &mut Vec<u8>, plan: Plan) {
+/// Counterpart to `read_plan`. Every plan but a deconstruction (type 1) needs its block,
+/// rotation and config.
+pub fn write_plan(buf: &mut Vec<u8>, plan: Plan) -> Result<(), PacketError> {
     write_byte(buf, plan.plan_type);
     write_tile(buf, plan.position);
 
-    if plan.plan_type == 0 {
-        write_short(buf, plan.block.unwrap());
-        write_byte(buf, plan.rotation.unwrap());
-        write_byte(buf, plan.has_config.unwrap() as u8);
-        write_object(buf, plan.config.unwrap());
+    if plan.plan_type != 1 {
+        let missing = |field: &str| PacketError::Encode {
+            packet_id: None,
+            field: field.to_string(),
+            reason: format!("plan of type {} without a {field}", plan.plan_type),
+        };
+        write_short(buf, plan.block.ok_or_else(|| missing("block"))?);
+        write_byte(buf, plan.rotation.ok_or_else(|| missing("rotation"))?);
+        write_bool(buf, plan.has_config.ok_or_else(|| missing("has_config"))?);
+        write_object(buf, plan.config.ok_or_else(|| missing("config"))?).field("config")?;
+    }
+    Ok(())
+}
+
+pub fn write_plans(buf: &mut Vec<u8>, plans: Vec<Plan>) -> Result<(), PacketError> {
+    write_short(buf, plans.len() as i16);
+    for plan in plans {
+        write_plan(buf, plan)?;
     }
+    Ok(())
 }
 
-pub fn write_plans(buf: &mut Vec<u8>, plans: Vec<Plan>) {
+pub fn write_plans_queue(buf: &mut Vec<u8>, plans: Vec<Plan>) -> Result<(), PacketError> {
     write_int(buf, plans.len() as u32);
     for plan in plans {
-        write_plan(buf, plan);
+        write_plan(buf, plan)?;
     }
+    Ok(())
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Status {
-    id: i16,
-    time: f32,
+    pub id: i16,
+    pub time: f32,
 }
 
-pub fn read_status(reader: &mut Reader) -> Status {
-    let id = reader.short();
-    let time = reader.float();
-    Status { id, time }
+pub fn read_status(reader: &mut Reader) -> Result<Status, PacketError> {
+    let id = reader.short()?;
+    let time = reader.float()?;
+    Ok(Status { id, time })
 }
 
-pub fn read_statuses(reader: &mut Reader) -> Vec<Status> {
+pub fn read_statuses(reader: &mut Reader) -> Result<Vec<Status>, PacketError> {
     let mut statuses = vec![];
-    let status_count = reader.int();
+    let status_count = reader.int()?;
     for _ in 0..status_count {
-        let status = read_status(reader);
+        let status = read_status(reader)?;
         statuses.push(status);
     }
-    statuses
+    Ok(statuses)
+}
+
+pub fn write_statuses(buf: &mut Vec<u8>, statuses: Vec<Status>) {
+    write_int(buf, statuses.len() as u32);
+    for status in statuses {
+        write_short(buf, status.id);
+        write_float(buf, status.time);
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Mount {
-    state: u8,
-    x: f32,
-    y: f32,
+    pub state: u8,
+    pub x: f32,
+    pub y: f32,
 }
 
-pub fn read_mounts(reader: &mut Reader) -> Vec<Mount> {
+pub fn read_mounts(reader: &mut Reader) -> Result<Vec<Mount>, PacketError> {
     let mut mounts = vec![];
-    let amount = reader.byte() as usize;
+    let amount = reader.byte()? as usize;
     for _ in 0..amount {
-        let state = reader.byte();
-        let x = reader.float();
-        let y = reader.float();
+        let state = reader.byte()?;
+        let x = reader.float()?;
+        let y = reader.float()?;
         mounts.push(Mount { state, x, y });
     }
-    mounts
+    Ok(mounts)
 }
 
-// TODO
-#[derive(Debug, Clone)]
-pub struct Controller {}
+pub fn write_mounts(buf: &mut Vec<u8>, mounts: Vec<Mount>) {
+    write_byte(buf, mounts.len() as u8);
+    for mount in mounts {
+        write_byte(buf, mount.state);
+        write_float(buf, mount.x);
+        write_float(buf, mount.y);
+    }
+}
 
-// TODO
-pub fn read_controller(reader: &mut Reader) -> Controller {
-    let controller_type = reader.byte();
+/// What a commanded unit attacks, only buildings and units can be attacked
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttackTarget {
+    Building(Tile),
+    Unit(u32),
+}
 
-    match controller_type {
-        0 => {
-            reader.int();
-        }
-        1 => {
-            reader.bytes(4);
-        }
-        3 => {
-            reader.int();
-        }
+#[derive(Debug, Clone, PartialEq)]
+pub enum CommandTarget {
+    Building(Tile),
+    Unit(u32),
+    Position(Vec2),
+    None(u8),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Controller {
+    Player {
+        id: u32,
+    },
+    Formation {
+        leader: u32,
+    },
+    Logic {
+        controller: Tile,
+    },
+    Command {
+        controller_type: u8,
+        attack: Option<AttackTarget>,
+        position: Option<Vec2>,
+        command: Option<u8>,
+        queue: Vec<CommandTarget>,
+        stance: Option<u8>,
+    },
+    /// Controllers without any synced state, such as the default AI
+    Other(u8),
+}
+
+pub fn read_controller(reader: &mut Reader) -> Result<Controller, PacketError> {
+    let controller_type = reader.byte()?;
+
+    Ok(match controller_type {
+        0 => Controller::Player { id: reader.int()? },
+        1 => Controller::Formation {
+            leader: reader.int()?,
+        },
+        3 => Controller::Logic {
+            controller: read_tile(reader)?,
+        },
         4 | 6 | 7 | 8 => {
-            let has_attack = reader.byte() != 0;
-            let has_pos = reader.byte() != 0;
-
-            let mut x = None;
-            let mut y = None;
-            if has_pos {
-                x = Some(reader.float());
-                y = Some(reader.float());
+            let has_attack = reader.bool()?;
+            let has_pos = reader.bool()?;
+
+            let position = if has_pos {
+                Some(read_vec2(reader)?)
+            } else {
+                None
+            };
+
+            let attack = if has_attack {
+                let entity_type = reader.byte()?;
+                let id = reader.int()?;
+                Some(if entity_type == 1 {
+                    AttackTarget::Building(Tile::unpack(id))
+                } else {
+                    AttackTarget::Unit(id)
+                })
+            } else {
+                None
+            };
+
+            let command = if controller_type >= 6 {
+                Some(reader.byte()?)
+            } else {
+                None
+            };
+
+            let mut queue = vec![];
+            if controller_type == 7 || controller_type == 8 {
+                let length = reader.byte()?;
+                for _ in 0..length {
+                    let target_type = reader.byte()?;
+                    queue.push(match target_type {
+                        0 => CommandTarget::Building(read_tile(reader)?),
+                        1 => CommandTarget::Unit(reader.int()?),
+                        2 => CommandTarget::Position(read_vec2(reader)?),
+                        other => CommandTarget::None(other),
+                    });
+                }
             }
 
-            let mut entity_type = None;
-            let mut attack = None;
-            if has_attack {
-                entity_type = Some(reader.byte());
-                attack = Some(reader.int());
+            let stance = if controller_type == 8 {
+                Some(reader.byte()?)
+            } else {
+                None
+            };
+
+            Controller::Command {
+                controller_type,
+                attack,
+                position,
+                command,
+                queue,
+                stance,
             }
+        }
+        other => Controller::Other(other),
+    })
+}
 
-            let mut id = None;
-            if controller_type == 6 || controller_type == 7 || controller_type == 8 {
-                id = Some(reader.byte());
+pub fn write_controller(buf: &mut Vec<u8>, controller: Controller) {
+    match controller {
+        Controller::Player { id } => {
+            write_byte(buf, 0);
+            write_int(buf, id);
+        }
+        Controller::Formation { leader } => {
+            write_byte(buf, 1);
+            write_int(buf, leader);
+        }
+        Controller::Logic { controller } => {
+            write_byte(buf, 3);
+            write_tile(buf, controller);
+        }
+        Controller::Command {
+            controller_type,
+            attack,
+            position,
+            command,
+            queue,
+            stance,
+        } => {
+            write_byte(buf, controller_type);
+            write_bool(buf, attack.is_some());
+            write_bool(buf, position.is_some());
+            if let Some(position) = position {
+                write_vec2(buf, position);
+            }
+            match attack {
+                Some(AttackTarget::Building(tile)) => {
+                    write_byte(buf, 1);
+                    write_tile(buf, tile);
+                }
+                Some(AttackTarget::Unit(id)) => {
+                    write_byte(buf, 0);
+                    write_int(buf, id);
+                }
+                None => {}
+            }
+            if controller_type >= 6 {
+                write_byte(buf, command.unwrap_or(255));
             }
-
-            let mut attack_info_build = None;
-            let mut attack_info_unit = None;
-            let mut attack_info_vec_x = None;
-            let mut attack_info_vec_y = None;
-            let mut final_controller_type = vec![];
             if controller_type == 7 || controller_type == 8 {
-                let length = reader.byte();
-                for _ in 0..length {
-                    let controller_type2 = reader.byte();
-                    final_controller_type.push(controller_type2);
-
-                    if controller_type2 == 0 {
-                        attack_info_build = Some(reader.int());
-                    } else if controller_type2 == 1 {
-                        attack_info_unit = Some(reader.int());
-                    } else if controller_type2 == 2 {
-                        attack_info_vec_x = Some(reader.float());
-                        attack_info_vec_y = Some(reader.float());
+                write_byte(buf, queue.len() as u8);
+                for target in queue {
+                    match target {
+                        CommandTarget::Building(tile) => {
+                            write_byte(buf, 0);
+                            write_tile(buf, tile);
+                        }
+                        CommandTarget::Unit(id) => {
+                            write_byte(buf, 1);
+                            write_int(buf, id);
+                        }
+                        CommandTarget::Position(position) => {
+                            write_byte(buf, 2);
+                            write_vec2(buf, position);
+                        }
+                        CommandTarget::None(target_type) => write_byte(buf, target_type),
                     }
                 }
             }
-
-            let mut stance = None;
             if controller_type == 8 {
-                let byte = reader.byte();
-                if byte == 255 {
-                    stance = Some(None)
-                } else {
-                    stance = Some(Some(byte))
-                }
+                write_byte(buf, stance.unwrap_or(255));
             }
-
-            /*Controller {
-              entity_type,
-              length2,
-              ctype,
-              has_attack,
-              has_pos,
-              pos,
-              attackinfo,
-              attack,
-              id,
-              stance
-            }*/
         }
-        _ => {}
-    };
-    Controller {}
+        Controller::Other(controller_type) => write_byte(buf, controller_type),
+    }
 }
 
-// TODO
-#[derive(Debug, Clone)]
-pub struct Payload {}
+#[derive(Debug, Clone, PartialEq)]
+pub enum Payload {
+    Block { block_id: i16, block: Box<Block> },
+    Unit(Box<FullUnit>),
+}
 
-// TODO
 pub fn read_payload(
     reader: &mut Reader,
-    content_map: &HashMap<String, Vec<String>>,
-) -> Option<Payload> {
-    let ex = reader.bool();
+    content_map: &ContentRegistry,
+) -> Result<Option<Payload>, PacketError> {
+    let ex = reader.bool()?;
     if !ex {
-        return None;
+        return Ok(None);
     }
 
-    let payload_type = reader.byte();
+    let payload_type = reader.byte()?;
     if payload_type == 1 {
-        let block_types = load_block_types();
-
-        let id = reader.short();
-        let version = reader.byte();
-        let block_name = content_map.get("block").unwrap().get(id as usize).unwrap();
-        let block_type = block_types.get(block_name).unwrap();
-        let block = read_block(
-            reader,
-            block_name.clone(),
-            block_type.clone(),
-            version,
-            content_map,
-        );
-        //return [id, block]
+        let id = reader.short()?;
+        let (block_name, block_type) = lookup_block(reader, content_map, id)?;
+        let version = reader.byte()?;
+        let block = read_block(reader, block_name, block_type, version, content_map)?;
+        Ok(Some(Payload::Block {
+            block_id: id,
+            block: Box::new(block),
+        }))
     } else {
-        let unit_id = reader.byte();
-        let unit = read_full_unit(reader, unit_id, true, content_map);
-        //return unit
+        let unit_id = reader.byte()?;
+        let unit = read_full_unit(reader, unit_id, true, content_map)?;
+        Ok(Some(Payload::Unit(Box::new(unit))))
     }
+}
 
-    Some(Payload {})
+pub fn write_payload(buf: &mut Vec<u8>, payload: Option<Payload>) -> Result<(), PacketError> {
+    let payload = match payload {
+        Some(payload) => payload,
+        None => {
+            write_bool(buf, false);
+            return Ok(());
+        }
+    };
+    write_bool(buf, true);
+
+    match payload {
+        Payload::Block { block_id, block } => {
+            write_byte(buf, 1);
+            write_short(buf, block_id);
+            write_byte(buf, block.revision);
+            write_block(buf, *block)?;
+        }
+        Payload::Unit(unit) => {
+            write_byte(buf, 0);
+            write_byte(buf, unit.class_id());
+            write_full_unit(buf, *unit)?;
+        }
+    }
+    Ok(())
 }
 
 pub fn read_payloads(
     reader: &mut Reader,
-    content_map: &HashMap<String, Vec<String>>,
-) -> Vec<Payload> {
+    content_map: &ContentRegistry,
+) -> Result<Vec<Payload>, PacketError> {
     let mut payloads = vec![];
 
-    let amount = reader.int();
+    let amount = reader.int()?;
     for _ in 0..amount {
-        let payload = read_payload(reader, content_map).unwrap();
+        let payload = read_payload(reader, content_map)?
+            .ok_or_else(|| reader.error("missing payload in payload list"))?;
         payloads.push(payload)
     }
 
-    payloads
+    Ok(payloads)
+}
+
+pub fn write_payloads(buf: &mut Vec<u8>, payloads: Vec<Payload>) -> Result<(), PacketError> {
+    write_int(buf, payloads.len() as u32);
+    for payload in payloads {
+        write_payload(buf, Some(payload))?;
+    }
+    Ok(())
+}
+
+/// The local player, as written into the `WorldStream` right after the player id
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlayerInfo {
+    pub revision: i16,
+    pub admin: bool,
+    pub boosting: bool,
+    /// RGBA
+    pub color: u32,
+    pub mouse_x: f32,
+    pub mouse_y: f32,
+    pub name: Option<String>,
+    pub shooting: bool,
+    pub team: u8,
+    pub typing: bool,
+    pub unit: Unit,
+    pub x: f32,
+    pub y: f32,
+}
+
+pub fn read_player_info(reader: &mut Reader) -> Result<PlayerInfo, PacketError> {
+    let revision = reader.short().field("revision")?;
+    let admin = reader.bool().field("admin")?;
+    let boosting = reader.bool().field("boosting")?;
+    let color = reader.int().field("color")?;
+    // Not part of the synced player, always written as 0
+    reader.byte()?;
+    Ok(PlayerInfo {
+        revision,
+        admin,
+        boosting,
+        color,
+        mouse_x: reader.float().field("mouse_x")?,
+        mouse_y: reader.float().field("mouse_y")?,
+        name: read_prefixed_string(reader).field("name")?,
+        shooting: reader.bool().field("shooting")?,
+        team: reader.byte().field("team")?,
+        typing: reader.bool().field("typing")?,
+        unit: read_unit(reader).field("unit")?,
+        x: reader.float().field("x")?,
+        y: reader.float().field("y")?,
+    })
+}
+
+pub fn write_player_info(buf: &mut Vec<u8>, player: PlayerInfo) -> Result<(), PacketError> {
+    write_short(buf, player.revision);
+    write_bool(buf, player.admin);
+    write_bool(buf, player.boosting);
+    write_int(buf, player.color);
+    write_byte(buf, 0);
+    write_float(buf, player.mouse_x);
+    write_float(buf, player.mouse_y);
+    write_prefixed_string(buf, player.name.as_deref())?;
+    write_bool(buf, player.shooting);
+    write_byte(buf, player.team);
+    write_bool(buf, player.typing);
+    write_unit(buf, player.unit);
+    write_float(buf, player.x);
+    write_float(buf, player.y);
+    Ok(())
 }
 
 // TODO
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum FullUnit {
     GenericUnit {
+        class_id: u8,
         revision: Option<i16>,
         abilities: Vec<f32>,
         ammo: f32,
         building: Option<u32>,
         base_rotation: Option<f32>,
-        controller: Controller,
+        controller: Box<Controller>,
         elevation: f32,
         flag: f64,
         health: f32,
@@ -408,14 +591,17 @@ pub fn read_full_unit(
     reader: &mut Reader,
     type_id: u8,
     has_revision: bool,
-    content_map: &HashMap<String, Vec<String>>,
-) -> FullUnit {
+    content_map: &ContentRegistry,
+) -> Result<FullUnit, PacketError> {
     let mut revision = None;
     if has_revision {
-        revision = Some(reader.short());
+        revision = Some(reader.short()?);
     }
 
-    let unit_type = UNIT_MAP.get(&type_id).unwrap();
+    let unit_type = &reader
+        .version()
+        .entity_class(type_id)
+        .ok_or_else(|| reader.error(format!("unknown entity class {type_id}")))?;
 
     if unit_type == &"MechUnit"
         || unit_type == &"CrawlUnit"
@@ -429,58 +615,59 @@ pub fn read_full_unit(
         || unit_type == &"PayloadUnit"
         || unit_type == &"BuildingTetherPayloadUnit"
     {
-        let abilities = read_abilities(reader);
-        let ammo = reader.float();
+        let abilities = read_abilities(reader)?;
+        let ammo = reader.float()?;
 
         let mut building = None;
         if unit_type == &"BuildingTetherPayloadUnit" {
-            building = Some(reader.int());
+            building = Some(reader.int()?);
         }
 
         let mut base_rotation = None;
         if unit_type == &"MechUnit" {
-            base_rotation = Some(reader.float());
+            base_rotation = Some(reader.float()?);
         }
 
-        let controller = read_controller(reader);
-        let elevation = reader.float(); // TODO check if 'elv' really is elevation
-        let flag = reader.double();
-        let health = reader.float();
-        let shooting = reader.byte() != 0;
+        let controller = Box::new(read_controller(reader)?);
+        let elevation = reader.float()?; // TODO check if 'elv' really is elevation
+        let flag = reader.double()?;
+        let health = reader.float()?;
+        let shooting = reader.byte()? != 0;
 
         let mut lifetime = None;
         if unit_type == &"TimedKillUnit" {
-            lifetime = Some(reader.float());
+            lifetime = Some(reader.float()?);
         }
 
-        let mining_position = read_tile(reader);
-        let mounts = read_mounts(reader);
+        let mining_position = read_tile(reader)?;
+        let mounts = read_mounts(reader)?;
 
         let mut payloads = None;
         if unit_type == &"PayloadUnit" || unit_type == &"BuildingTetherPayloadUnit" {
-            payloads = Some(read_payloads(reader, &content_map.clone()));
+            payloads = Some(read_payloads(reader, content_map)?);
         }
 
-        let plans = read_plans_queue(reader);
-        let rotation = reader.float();
-        let shield = reader.float();
-        let spawned_by_core = reader.byte() != 0; // TODO check if 'spbycore' really is spawned_by_core
-        let items = read_items(reader);
-        let statuses = read_statuses(reader);
-        let team = reader.byte();
+        let plans = read_plans_queue(reader)?;
+        let rotation = reader.float()?;
+        let shield = reader.float()?;
+        let spawned_by_core = reader.byte()? != 0; // TODO check if 'spbycore' really is spawned_by_core
+        let items = read_items(reader)?;
+        let statuses = read_statuses(reader)?;
+        let team = reader.byte()?;
 
         let mut time = None;
         if unit_type == &"TimedKillUnit" {
-            time = Some(reader.float());
+            time = Some(reader.float()?);
         }
 
-        let unit_type = reader.short(); // TODO check if this is what 'utype' actually is
-        let upgrade_building = reader.byte(); // TODO check what 'updbuilding' actually is
-        let velocity = read_vec2(reader);
-        let x = reader.float();
-        let y = reader.float();
+        let unit_type = reader.short()?; // TODO check if this is what 'utype' actually is
+        let upgrade_building = reader.byte()?; // TODO check what 'updbuilding' actually is
+        let velocity = read_vec2(reader)?;
+        let x = reader.float()?;
+        let y = reader.float()?;
 
-        return FullUnit::GenericUnit {
+        return Ok(FullUnit::GenericUnit {
+            class_id: type_id,
             revision,
             abilities,
             ammo,
@@ -508,63 +695,254 @@ pub fn read_full_unit(
             velocity,
             x,
             y,
-        };
+        });
     } else if unit_type == &"Fire" {
-        return FullUnit::Fire {
+        return Ok(FullUnit::Fire {
             revision,
-            lifetime: reader.float(),
-            tile: read_tile(reader),
-            time: reader.float(),
-            x: reader.float(),
-            y: reader.float(),
-        };
+            lifetime: reader.float()?,
+            tile: read_tile(reader)?,
+            time: reader.float()?,
+            x: reader.float()?,
+            y: reader.float()?,
+        });
     } else if unit_type == &"Puddle" {
-        return FullUnit::Puddle {
+        return Ok(FullUnit::Puddle {
             revision,
-            amount: reader.float(),
-            liquid: reader.short(),
-            tile: read_tile(reader),
-            x: reader.float(),
-            y: reader.float(),
-        };
+            amount: reader.float()?,
+            liquid: reader.short()?,
+            tile: read_tile(reader)?,
+            x: reader.float()?,
+            y: reader.float()?,
+        });
     } else if unit_type == &"Player" {
-        return FullUnit::Player {
+        return Ok(FullUnit::Player {
             revision,
-            admin: reader.byte() != 0,
-            boosting: reader.byte() != 0,
-            color: reader.int(),
-            mouse_x: reader.float(),
-            mouse_y: reader.float(),
-            name: read_prefixed_string(reader),
-            shooting: reader.byte() != 0,
-            team: reader.byte(),
-            typing: reader.byte() != 0,
-            unit: read_unit(reader),
-            x: reader.float(),
-            y: reader.float(),
-        };
+            admin: reader.byte()? != 0,
+            boosting: reader.byte()? != 0,
+            color: reader.int()?,
+            mouse_x: reader.float()?,
+            mouse_y: reader.float()?,
+            name: read_prefixed_string(reader)?,
+            shooting: reader.byte()? != 0,
+            team: reader.byte()?,
+            typing: reader.byte()? != 0,
+            unit: read_unit(reader)?,
+            x: reader.float()?,
+            y: reader.float()?,
+        });
     } else if unit_type == &"WeatherState" {
-        return FullUnit::WeatherState {
+        return Ok(FullUnit::WeatherState {
             revision,
-            effect_timer: Some(reader.float()),
-            intensity: reader.float(),
-            life: reader.float(),
-            opacity: reader.float(),
-            weather: reader.short(),
-            wind: Some(read_vec2(reader)),
-            x: reader.float(),
-            y: reader.float(),
-        };
+            effect_timer: Some(reader.float()?),
+            intensity: reader.float()?,
+            life: reader.float()?,
+            opacity: reader.float()?,
+            weather: reader.short()?,
+            wind: Some(read_vec2(reader)?),
+            x: reader.float()?,
+            y: reader.float()?,
+        });
     } else if unit_type == &"WorldLabel" {
-        return FullUnit::WorldLabel {
+        return Ok(FullUnit::WorldLabel {
             revision,
-            flags: reader.byte(),
-            fonts: reader.float(),
-            str: read_prefixed_string(reader).unwrap(),
-            x: reader.float(),
-            y: reader.float(),
-        };
+            flags: reader.byte()?,
+            fonts: reader.float()?,
+            str: read_prefixed_string(reader)?.unwrap_or_default(),
+            x: reader.float()?,
+            y: reader.float()?,
+        });
+    }
+
+    Err(reader.error(format!("unsupported entity class {type_id}")))
+}
+
+impl FullUnit {
+    /// The entity class id this unit is serialized with
+    pub fn class_id(&self) -> u8 {
+        match self {
+            FullUnit::GenericUnit { class_id, .. } => *class_id,
+            FullUnit::Fire { .. } => 10,
+            FullUnit::Player { .. } => 12,
+            FullUnit::Puddle { .. } => 13,
+            FullUnit::WeatherState { .. } => 14,
+            FullUnit::WorldLabel { .. } => 35,
+            FullUnit::Unknown => 255,
+        }
     }
+}
+
+fn write_revision(buf: &mut Vec<u8>, revision: Option<i16>) {
+    if let Some(revision) = revision {
+        write_short(buf, revision);
+    }
+}
 
-    FullUnit::Unknown
+pub fn write_full_unit(buf: &mut Vec<u8>, unit: FullUnit) -> Result<(), PacketError> {
+    match unit {
+        FullUnit::GenericUnit {
+            class_id: _,
+            revision,
+            abilities,
+            ammo,
+            building,
+            base_rotation,
+            controller,
+            elevation,
+            flag,
+            health,
+            shooting,
+            lifetime,
+            mining_position,
+            mounts,
+            payloads,
+            plans,
+            rotation,
+            shield,
+            spawned_by_core,
+            items,
+            statuses,
+            team,
+            time,
+            unit_type,
+            upgrade_building,
+            velocity,
+            x,
+            y,
+        } => {
+            write_revision(buf, revision);
+            write_abilities(buf, abilities);
+            write_float(buf, ammo);
+            if let Some(building) = building {
+                write_int(buf, building);
+            }
+            if let Some(base_rotation) = base_rotation {
+                write_float(buf, base_rotation);
+            }
+            write_controller(buf, *controller);
+            write_float(buf, elevation);
+            write_double(buf, flag);
+            write_float(buf, health);
+            write_bool(buf, shooting);
+            if let Some(lifetime) = lifetime {
+                write_float(buf, lifetime);
+            }
+            write_tile(buf, mining_position);
+            write_mounts(buf, mounts);
+            if let Some(payloads) = payloads {
+                write_payloads(buf, payloads)?;
+            }
+            write_plans_queue(buf, plans)?;
+            write_float(buf, rotation);
+            write_float(buf, shield);
+            write_bool(buf, spawned_by_core);
+            write_items(buf, items);
+            write_statuses(buf, statuses);
+            write_byte(buf, team);
+            if let Some(time) = time {
+                write_float(buf, time);
+            }
+            write_short(buf, unit_type);
+            write_byte(buf, upgrade_building);
+            write_vec2(buf, velocity);
+            write_float(buf, x);
+            write_float(buf, y);
+        }
+        FullUnit::Fire {
+            revision,
+            lifetime,
+            tile,
+            time,
+            x,
+            y,
+        } => {
+            write_revision(buf, revision);
+            write_float(buf, lifetime);
+            write_tile(buf, tile);
+            write_float(buf, time);
+            write_float(buf, x);
+            write_float(buf, y);
+        }
+        FullUnit::Puddle {
+            revision,
+            amount,
+            liquid,
+            tile,
+            x,
+            y,
+        } => {
+            write_revision(buf, revision);
+            write_float(buf, amount);
+            write_short(buf, liquid);
+            write_tile(buf, tile);
+            write_float(buf, x);
+            write_float(buf, y);
+        }
+        FullUnit::Player {
+            revision,
+            admin,
+            boosting,
+            color,
+            mouse_x,
+            mouse_y,
+            name,
+            shooting,
+            team,
+            typing,
+            unit,
+            x,
+            y,
+        } => {
+            write_revision(buf, revision);
+            write_bool(buf, admin);
+            write_bool(buf, boosting);
+            write_int(buf, color);
+            write_float(buf, mouse_x);
+            write_float(buf, mouse_y);
+            write_prefixed_string(buf, name.as_deref())?;
+            write_bool(buf, shooting);
+            write_byte(buf, team);
+            write_bool(buf, typing);
+            write_unit(buf, unit);
+            write_float(buf, x);
+            write_float(buf, y);
+        }
+        FullUnit::WeatherState {
+            revision,
+            effect_timer,
+            intensity,
+            life,
+            opacity,
+            weather,
+            wind,
+            x,
+            y,
+        } => {
+            write_revision(buf, revision);
+            write_float(buf, effect_timer.unwrap_or_default());
+            write_float(buf, intensity);
+            write_float(buf, life);
+            write_float(buf, opacity);
+            write_short(buf, weather);
+            write_vec2(buf, wind.unwrap_or(Vec2 { x: 0.0, y: 0.0 }));
+            write_float(buf, x);
+            write_float(buf, y);
+        }
+        FullUnit::WorldLabel {
+            revision,
+            flags,
+            fonts,
+            str,
+            x,
+            y,
+        } => {
+            write_revision(buf, revision);
+            write_byte(buf, flags);
+            write_float(buf, fonts);
+            write_prefixed_string(buf, Some(&str))?;
+            write_float(buf, x);
+            write_float(buf, y);
+        }
+        FullUnit::Unknown => {}
+    }
+    Ok(())
 }
@@ -0,0 +1,119 @@
+mod common;
+
+use common::{content_map, player, stone_map};
+use mindustry_net::capture::{
+    Capture, CaptureFrame, Direction, Transport, read_capture, read_capture_frame, replay,
+    write_capture_frame,
+};
+use mindustry_net::client::{Client, ClientEvent, ClientInfo, EventKind, Subscription};
+use mindustry_net::packet::{Packet, write_packet};
+use mindustry_net::server::Server;
+use mindustry_net::type_io::Reader;
+use mindustry_net::version::ProtocolVersion;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::time;
+
+#[test]
+fn frame_round_trip() {
+    let frame = CaptureFrame {
+        timestamp: Duration::from_micros(1500),
+        transport: Transport::Udp,
+        direction: Direction::Outbound,
+        data: vec![0xFE, 0x03, 0, 0, 0, 1],
+    };
+    let mut buf = vec![];
+    write_capture_frame(&mut buf, &frame);
+
+    assert_eq!(read_capture_frame(&mut Reader::new(buf)).unwrap(), frame);
+}
+
+#[test]
+fn replay_uses_recorded_build() {
+    let mut info = ClientInfo::new("bot".to_string());
+    info.version = 149;
+    let connect = CaptureFrame {
+        timestamp: Duration::ZERO,
+        transport: Transport::Tcp,
+        direction: Direction::Outbound,
+        data: write_packet(info.connect_packet()).unwrap()[2..].to_vec(),
+    };
+    let mut capture = Capture {
+        started: 0,
+        frames: vec![connect],
+    };
+    assert_eq!(replay(&capture).0.version(), ProtocolVersion::V149);
+
+    capture.frames.clear();
+    assert_eq!(replay(&capture).0.version(), ProtocolVersion::V146);
+}
+
+async fn wait_for(receiver: &mut Subscription, kind: EventKind) {
+    loop {
+        let event = time::timeout(Duration::from_secs(5), receiver.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        if event.kind() == kind {
+            return;
+        }
+    }
+}
+
+#[tokio::test]
+async fn record_and_replay() {
+    let path = std::env::temp_dir().join(format!("mindustry-net-{}.mcap", std::process::id()));
+    let mut server = Server::bind("127.0.0.1:0", stone_map(20, 10), content_map())
+        .await
+        .unwrap();
+    let address = server.local_addr().unwrap();
+
+    let mut client = Client::new_recording(address.to_string(), "bot".to_string(), &path)
+        .await
+        .unwrap();
+    let protocol = client.protocol.clone();
+    let mut receiver = client.subscribe();
+    tokio::spawn(async move { client.handle_packets().await });
+
+    let connection = server.accept().await.unwrap();
+    // Entity snapshots can only be decoded with the content of the world
+    wait_for(&mut receiver, EventKind::WorldLoaded).await;
+    let mut units = HashMap::new();
+    units.insert(connection.id, player(8.0, 16.0));
+    connection
+        .send_unreliable(Packet::EntitySnapshot { units })
+        .await
+        .unwrap();
+    wait_for(&mut receiver, EventKind::UnitSnapshot).await;
+
+    let capture: Capture = read_capture(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let has = |transport, direction| {
+        capture
+            .frames
+            .iter()
+            .any(|frame| frame.transport == transport && frame.direction == direction)
+    };
+    assert!(has(Transport::Tcp, Direction::Inbound));
+    assert!(has(Transport::Udp, Direction::Inbound));
+    assert!(has(Transport::Tcp, Direction::Outbound));
+    assert!(has(Transport::Udp, Direction::Outbound));
+    assert!(
+        capture
+            .frames
+            .windows(2)
+            .all(|pair| pair[0].timestamp <= pair[1].timestamp)
+    );
+
+    let (replayed, events) = replay(&capture);
+    assert!(
+        events
+            .iter()
+            .any(|event| matches!(event, ClientEvent::WorldLoaded))
+    );
+    let live = &protocol.lock().await.state;
+    let replayed = &replayed.state;
+    assert_eq!(replayed.player_id, live.player_id);
+    assert_eq!(replayed.map, live.map);
+    assert_eq!(replayed.units, live.units);
+}
@@ -0,0 +1,111 @@
+use bytes::BytesMut;
+use futures_util::{SinkExt, StreamExt};
+use mindustry_net::codec::{FrameCodec, PacketCodec};
+use mindustry_net::packet::{AnyPacket, FrameworkPacket, Packet, PacketError, write_packet};
+use tokio::io::{AsyncWriteExt, duplex};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_util::codec::{Decoder, Framed};
+
+#[tokio::test]
+async fn packets_over_duplex() {
+    let (client, server) = duplex(64);
+    let mut client = Framed::new(client, PacketCodec::default());
+    let mut server = Framed::new(server, PacketCodec::default());
+
+    let message = Packet::AnnounceCall {
+        message: "hello ".repeat(20),
+    };
+    let sent = message.clone();
+    tokio::spawn(async move {
+        client.send(FrameworkPacket::RegisterTCP(7)).await.unwrap();
+        client.send(sent).await.unwrap();
+    });
+
+    match server.next().await.unwrap().unwrap() {
+        AnyPacket::Framework(FrameworkPacket::RegisterTCP(id)) => assert_eq!(id, 7),
+        other => panic!("unexpected packet {other:?}"),
+    }
+    match server.next().await.unwrap().unwrap() {
+        AnyPacket::Regular(packet) => assert_eq!(packet, message),
+        other => panic!("unexpected packet {other:?}"),
+    }
+    assert!(server.next().await.is_none());
+}
+
+#[tokio::test]
+async fn packets_over_tcp() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (server, _) = listener.accept().await.unwrap();
+        let mut server = Framed::new(server, PacketCodec::default());
+        server.send(FrameworkPacket::RegisterTCP(3)).await.unwrap();
+    });
+
+    let client = TcpStream::connect(address).await.unwrap();
+    let mut client: Framed<TcpStream, PacketCodec> = Framed::new(client, PacketCodec::default());
+    match client.next().await.unwrap().unwrap() {
+        AnyPacket::Framework(FrameworkPacket::RegisterTCP(id)) => assert_eq!(id, 3),
+        other => panic!("unexpected packet {other:?}"),
+    }
+    assert!(client.next().await.is_none());
+}
+
+#[tokio::test]
+async fn split_writes() {
+    let (mut client, server) = duplex(1024);
+    let mut server = Framed::new(server, PacketCodec::default());
+
+    let packet = Packet::AnnounceCall {
+        message: "split".to_string(),
+    };
+    let mut frames = write_packet(packet.clone()).unwrap();
+    frames.extend(write_packet(packet.clone()).unwrap());
+    tokio::spawn(async move {
+        for byte in frames {
+            client.write_all(&[byte]).await.unwrap();
+            tokio::task::yield_now().await;
+        }
+    });
+
+    for _ in 0..2 {
+        match server.next().await.unwrap().unwrap() {
+            AnyPacket::Regular(parsed) => assert_eq!(parsed, packet),
+            other => panic!("unexpected packet {other:?}"),
+        }
+    }
+}
+
+#[tokio::test]
+async fn truncated_stream() {
+    let (mut client, server) = duplex(1024);
+    let mut server = Framed::new(server, FrameCodec);
+
+    client.write_all(&[0x00, 0x05, 0x01]).await.unwrap();
+    drop(client);
+
+    match server.next().await.unwrap() {
+        Err(PacketError::Io(e)) => assert_eq!(e.kind(), std::io::ErrorKind::UnexpectedEof),
+        other => panic!("unexpected result {other:?}"),
+    }
+}
+
+#[test]
+fn malformed_frame_is_skipped() {
+    let packet = Packet::AnnounceCall {
+        message: "after".to_string(),
+    };
+    let mut src = BytesMut::new();
+    // StreamBegin with a truncated body
+    src.extend_from_slice(&[0x00, 0x05, 0x00, 0x00, 0x02, 0x00, 0x00]);
+    src.extend_from_slice(&write_packet(packet.clone()).unwrap());
+
+    let mut codec = PacketCodec::default();
+    assert!(codec.decode(&mut src).is_err());
+    match codec.decode(&mut src).unwrap() {
+        Some(AnyPacket::Regular(parsed)) => assert_eq!(parsed, packet),
+        other => panic!("unexpected packet {other:?}"),
+    }
+    assert!(codec.decode(&mut src).unwrap().is_none());
+    assert!(src.is_empty());
+}
@@ -0,0 +1,56 @@
+//! Fixtures shared by the integration tests
+#![allow(dead_code)]
+
+use mindustry_net::content::ContentRegistry;
+use mindustry_net::save_io::Map;
+use mindustry_net::type_io::Unit;
+use mindustry_net::unit_io::FullUnit;
+use std::collections::HashMap;
+
+/// Air, stone and copper ore blocks and the copper item
+pub fn content_map() -> ContentRegistry {
+    ContentRegistry::new(HashMap::from([
+        (
+            "block".to_string(),
+            vec![
+                "air".to_string(),
+                "stone".to_string(),
+                "ore-copper".to_string(),
+            ],
+        ),
+        ("item".to_string(), vec!["copper".to_string()]),
+    ]))
+}
+
+/// A map with stone floor everywhere
+pub fn stone_map(width: u32, height: u32) -> Map {
+    let mut map = Map::new(width, height);
+    for x in 0..width {
+        for y in 0..height {
+            map.set_floor(x, y, 1);
+        }
+    }
+    map
+}
+
+/// The player unit of a client named "bot" at `(x, y)`, aiming at its own position
+pub fn player(x: f32, y: f32) -> FullUnit {
+    FullUnit::Player {
+        revision: None,
+        admin: false,
+        boosting: false,
+        color: 0xffa108ff,
+        mouse_x: x,
+        mouse_y: y,
+        name: Some("bot".to_string()),
+        shooting: false,
+        team: 1,
+        typing: false,
+        unit: Unit {
+            unit_type: 2,
+            id: 12,
+        },
+        x,
+        y,
+    }
+}
@@ -0,0 +1,206 @@
+use mindustry_net::block_io::{BaseBlockData, Block, SpecificBlockData};
+use mindustry_net::content::{
+    BlockId, ContentRegistry, ContentStore, FileContentStore, ItemId, LiquidId, MemoryContentStore,
+    StatusId, UnitId,
+};
+use mindustry_net::packet::PacketError;
+use mindustry_net::save_io::{Map, read_content_header, read_map, write_content_header, write_map};
+use mindustry_net::type_io::Reader;
+use std::collections::HashMap;
+use std::fs;
+
+fn names(names: &[&str]) -> Vec<String> {
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+fn registry() -> ContentRegistry {
+    ContentRegistry::new(HashMap::from([
+        (
+            "block".to_string(),
+            names(&["air", "multi-press", "unloader", "modded-block"]),
+        ),
+        ("item".to_string(), names(&["copper", "lead"])),
+        ("liquid".to_string(), names(&["water"])),
+        ("unit".to_string(), names(&["dagger", "alpha"])),
+        ("status".to_string(), names(&["none", "burning"])),
+    ]))
+}
+
+#[test]
+fn lookups() {
+    let registry = registry();
+    assert_eq!(registry.block(BlockId(1)), Some("multi-press"));
+    assert_eq!(registry.block_id("unloader"), Some(BlockId(2)));
+    assert_eq!(registry.item(ItemId(1)), Some("lead"));
+    assert_eq!(registry.item_id("copper"), Some(ItemId(0)));
+    assert_eq!(registry.liquid(LiquidId(0)), Some("water"));
+    assert_eq!(registry.unit_id("alpha"), Some(UnitId(1)));
+    assert_eq!(registry.status(StatusId(1)), Some("burning"));
+    assert_eq!(registry.name("item", 0), Some("copper"));
+    assert_eq!(registry.id("unit", "dagger"), Some(0));
+
+    // Missing entries are not an error until something needs them
+    assert_eq!(registry.block(BlockId(4)), None);
+    assert_eq!(registry.item(ItemId(-1)), None);
+    assert_eq!(registry.item_id("titanium"), None);
+    assert_eq!(registry.name("planet", 0), None);
+}
+
+#[test]
+fn block_info() {
+    let registry = registry();
+    let press = registry.block_info(BlockId(1)).unwrap();
+    assert_eq!(press.class, "GenericCrafter");
+    assert_eq!(press.size, 3);
+    assert!(press.has_items);
+    assert!(press.has_power);
+
+    let air = registry.block_info(BlockId(0)).unwrap();
+    assert_eq!(air.class, "AirBlock");
+    assert_eq!(air.size, 1);
+
+    // Blocks the bundled data does not know have no info
+    assert_eq!(registry.block_info(BlockId(3)), None);
+}
+
+#[test]
+fn content_header() {
+    let registry = registry();
+    let mut buf = vec![];
+    write_content_header(&mut buf, &registry).unwrap();
+    let parsed = read_content_header(&mut Reader::new(buf)).unwrap();
+    assert_eq!(parsed, registry);
+
+    let json = serde_json::to_string(&registry).unwrap();
+    let parsed: ContentRegistry = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed.block_id("multi-press"), Some(BlockId(1)));
+}
+
+#[test]
+fn unknown_block_in_map() {
+    let mut map = Map::new(2, 2);
+    map.set_block_id(1, 1, 2);
+    map.set_block(
+        1,
+        1,
+        Block {
+            name: "unloader".to_string(),
+            block_type: "Unloader".to_string(),
+            revision: 1,
+            base: BaseBlockData {
+                health: 70.0,
+                rotation: 0,
+                version: 3,
+                legacy: false,
+                on: Some(1),
+                team: 1,
+                module_bitmask: 1,
+                items: Some(HashMap::new()),
+                liquids: None,
+                power: None,
+                efficiency: Some(255),
+                optional_efficiency: Some(0),
+                visible_flags: None,
+            },
+            specific: Some(SpecificBlockData::Unloader { item_id: -1 }),
+        },
+    );
+    let mut buf = vec![];
+    write_map(&mut buf, &map).unwrap();
+
+    assert_eq!(
+        read_map(&mut Reader::new(buf.clone()), &registry()).unwrap(),
+        map
+    );
+
+    // A world without the block fails to decode instead of panicking
+    let registry = ContentRegistry::new(HashMap::from([("block".to_string(), names(&["air"]))]));
+    match read_map(&mut Reader::new(buf), &registry) {
+        Err(PacketError::Decode { reason, .. }) => assert_eq!(reason, "unknown block id 2"),
+        result => panic!("unexpected result {result:?}"),
+    }
+}
+
+#[test]
+fn content_hash() {
+    let mut other = registry().names().clone();
+    assert_eq!(
+        ContentRegistry::new(other.clone()).content_hash(),
+        registry().content_hash()
+    );
+    other.get_mut("item").unwrap().push("titanium".to_string());
+    assert_ne!(
+        ContentRegistry::new(other).content_hash(),
+        registry().content_hash()
+    );
+}
+
+#[test]
+fn memory_store() {
+    let store = MemoryContentStore::new();
+    assert_eq!(store.load("127.0.0.1:6567").unwrap(), None);
+    store.save("127.0.0.1:6567", &registry()).unwrap();
+    assert_eq!(store.load("127.0.0.1:6567").unwrap(), Some(registry()));
+    assert_eq!(store.load("127.0.0.1:6568").unwrap(), None);
+}
+
+#[test]
+fn file_store() {
+    let dir = std::env::temp_dir().join(format!("mindustry-net-{}-content", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let store = FileContentStore::new(&dir);
+    assert_eq!(store.load("127.0.0.1:6567").unwrap(), None);
+
+    store.save("127.0.0.1:6567", &registry()).unwrap();
+    store.save("10.0.0.2:6567", &registry()).unwrap();
+    let vanilla = ContentRegistry::new(HashMap::from([("block".to_string(), names(&["air"]))]));
+    store.save("10.0.0.3:6567", &vanilla).unwrap();
+
+    // A new store sees what the first one saved
+    let store = FileContentStore::new(&dir);
+    assert_eq!(store.load("127.0.0.1:6567").unwrap(), Some(registry()));
+    assert_eq!(store.load("10.0.0.2:6567").unwrap(), Some(registry()));
+    assert_eq!(store.load("10.0.0.3:6567").unwrap(), Some(vanilla));
+
+    // Servers with the same content share a file, next to the index
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 3);
+    assert_eq!(fs::read_dir(dir.join("servers")).unwrap().count(), 3);
+    assert!(dir.join("servers/127.0.0.1%3a6567").exists());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn file_store_concurrent_saves() {
+    let dir = std::env::temp_dir().join(format!(
+        "mindustry-net-{}-concurrent-content",
+        std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&dir);
+
+    // Like several bots sharing one directory, each with its own store
+    let threads: Vec<_> = (0..16)
+        .map(|i| {
+            let dir = dir.clone();
+            std::thread::spawn(move || {
+                let store = FileContentStore::new(&dir);
+                for port in 0..8 {
+                    store
+                        .save(&format!("10.0.0.{i}:{port}"), &registry())
+                        .unwrap();
+                }
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    let store = FileContentStore::new(&dir);
+    for i in 0..16 {
+        for port in 0..8 {
+            let address = format!("10.0.0.{i}:{port}");
+            assert_eq!(store.load(&address).unwrap(), Some(registry()));
+        }
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,115 @@
+use mindustry_net::discovery::{
+    DiscoveryError, Gamemode, HostInfo, discover_at, ping, read_host_info, write_host_info,
+};
+use mindustry_net::type_io::Reader;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+
+fn host_info(address: SocketAddr) -> HostInfo {
+    HostInfo {
+        address,
+        ping: Duration::ZERO,
+        name: "Test server".into(),
+        map: "Ground Zero".into(),
+        players: 3,
+        wave: 12,
+        version: 146,
+        version_type: "official".into(),
+        gamemode: Gamemode::Survival,
+        player_limit: 20,
+        description: "A server for tests".into(),
+        mode_name: None,
+    }
+}
+
+/// Answers discovery pings with `reply`, padded like a real server
+async fn responder(reply: Vec<u8>) -> SocketAddr {
+    let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let address = socket.local_addr().unwrap();
+    tokio::spawn(async move {
+        let mut buf = [0u8; 64];
+        loop {
+            let (length, from) = socket.recv_from(&mut buf).await.unwrap();
+            assert_eq!(&buf[..length], &[0xFE, 0x01]);
+            let mut data = reply.clone();
+            data.resize(500, 0);
+            socket.send_to(&data, from).await.unwrap();
+        }
+    });
+    address
+}
+
+fn encode(info: &HostInfo) -> Vec<u8> {
+    let mut buf = vec![];
+    write_host_info(&mut buf, info);
+    buf
+}
+
+#[test]
+fn host_info_round_trip() {
+    let address = "127.0.0.1:6567".parse().unwrap();
+    let mut info = host_info(address);
+    info.mode_name = Some("Custom".into());
+
+    let decoded = read_host_info(&mut Reader::new(encode(&info)), address, Duration::ZERO);
+    assert_eq!(decoded.unwrap(), info);
+}
+
+#[test]
+fn truncated_multibyte_name() {
+    let address = "127.0.0.1:6567".parse().unwrap();
+    let mut info = host_info(address);
+    info.name = String::new();
+    // Servers cut names at 100 bytes, here in the middle of the last character
+    let mut data = vec![100];
+    data.extend_from_slice(&format!("{}é", "a".repeat(99)).as_bytes()[..100]);
+    data.extend_from_slice(&encode(&info)[1..]);
+
+    let decoded = read_host_info(&mut Reader::new(data), address, Duration::ZERO);
+    assert_eq!(decoded.unwrap().name, format!("{}\u{FFFD}", "a".repeat(99)));
+}
+
+#[tokio::test]
+async fn ping_single_host() {
+    let address = responder(encode(&host_info("0.0.0.0:0".parse().unwrap()))).await;
+
+    let mut info = ping(address, Duration::from_secs(1)).await.unwrap();
+    info.ping = Duration::ZERO;
+    assert_eq!(info, host_info(address));
+}
+
+#[tokio::test]
+async fn discover_collects_replies() {
+    let address = responder(encode(&host_info("0.0.0.0:0".parse().unwrap()))).await;
+
+    let hosts = discover_at(address, Duration::from_millis(200))
+        .await
+        .unwrap();
+    assert_eq!(hosts.len(), 1);
+    assert_eq!(hosts[0].address, address);
+    assert_eq!(hosts[0].name, "Test server");
+    assert_eq!(hosts[0].gamemode, Gamemode::Survival);
+}
+
+#[tokio::test]
+async fn discover_skips_malformed_replies() {
+    // Empty strings and counts, then an unknown gamemode
+    let mut reply = vec![0, 0];
+    reply.extend_from_slice(&[0; 12]);
+    reply.extend_from_slice(&[0, 0xFF]);
+    let address = responder(reply).await;
+
+    let hosts = discover_at(address, Duration::from_millis(200))
+        .await
+        .unwrap();
+    assert!(hosts.is_empty());
+}
+
+#[tokio::test]
+async fn ping_times_out() {
+    let silent = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+
+    let result = ping(silent.local_addr().unwrap(), Duration::from_millis(100)).await;
+    assert!(matches!(result, Err(DiscoveryError::Timeout)));
+}
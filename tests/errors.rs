@@ -0,0 +1,151 @@
+use mindustry_net::content::ContentRegistry;
+use mindustry_net::packet::{
+    AnyPacket, Packet, PacketError, frame_packet, parse_packet, parse_packet_checked,
+    parse_regular_packet, write_packet_data,
+};
+use mindustry_net::save_io::read_map;
+use mindustry_net::type_io::{Reader, read_object, read_ubjson};
+use mindustry_net::version::ProtocolVersion;
+
+fn parse_error(id: u8, data: Vec<u8>) -> PacketError {
+    parse_regular_packet(id, Reader::new(data), &None).unwrap_err()
+}
+
+#[test]
+fn truncated_field() {
+    let error = parse_error(0, vec![0x00, 0x00, 0x00, 0x07, 0x00, 0x00]);
+    match error {
+        PacketError::Decode {
+            packet_id,
+            offset,
+            field,
+            ..
+        } => {
+            assert_eq!(packet_id, Some(0));
+            assert_eq!(offset, 4);
+            assert_eq!(field, "total");
+        }
+        other => panic!("unexpected error {other:?}"),
+    }
+}
+
+#[test]
+fn truncated_nested_field() {
+    // AdminRequestCall with an object param cut off after the type byte
+    let error = parse_error(4, vec![0x00, 0x00, 0x00, 0x01, 0x00, 0x01]);
+    match error {
+        PacketError::Decode {
+            packet_id,
+            offset,
+            field,
+            ..
+        } => {
+            assert_eq!(packet_id, Some(4));
+            assert_eq!(offset, 6);
+            assert_eq!(field, "params");
+        }
+        other => panic!("unexpected error {other:?}"),
+    }
+}
+
+#[test]
+fn unknown_object_type() {
+    let error = read_object(&mut Reader::new(vec![200])).unwrap_err();
+    assert!(matches!(error, PacketError::Decode { offset: 1, .. }));
+}
+
+#[test]
+fn deeply_nested_ubjson() {
+    // Nesting like this would overflow the stack if it was followed
+    let data = vec![b'['; 100_000];
+    match read_ubjson(&mut Reader::new(data)) {
+        Err(PacketError::Decode { reason, .. }) => {
+            assert_eq!(reason, "UBJSON nested deeper than 64 levels")
+        }
+        result => panic!("unexpected result {result:?}"),
+    }
+
+    let mut data = vec![b'['; 64];
+    data.extend([b']'; 64]);
+    assert!(read_ubjson(&mut Reader::new(data)).is_ok());
+}
+
+#[test]
+fn unknown_admin_action() {
+    let error = parse_error(4, vec![0x00, 0x00, 0x00, 0x01, 0x7f, 0x00]);
+    assert!(matches!(
+        error,
+        PacketError::Decode {
+            packet_id: Some(4),
+            ..
+        }
+    ));
+}
+
+#[test]
+fn truncated_frame_header() {
+    let Err(error) = parse_packet(vec![71, 0x00], &None, ProtocolVersion::default()) else {
+        panic!("expected an error");
+    };
+    assert!(matches!(error, PacketError::Decode { .. }));
+}
+
+#[test]
+fn error_display() {
+    let error = parse_error(0, vec![0x00, 0x00]);
+    assert_eq!(
+        error.to_string(),
+        "Failed to decode packet 0 field `id` at offset 0: expected 4 bytes, only 2 remaining"
+    );
+}
+
+#[test]
+fn trailing_bytes() {
+    let (id, mut data) = write_packet_data(Packet::GameOverCall { winner: 1 }).unwrap();
+    data.extend_from_slice(&[0xAA, 0xBB]);
+    let frame = frame_packet(id, data);
+
+    let (packet, trailing) =
+        parse_packet_checked(frame[2..].to_vec(), &None, ProtocolVersion::default()).unwrap();
+    assert!(matches!(
+        packet,
+        AnyPacket::Regular(Packet::GameOverCall { winner: 1 })
+    ));
+    assert_eq!(trailing, 2);
+}
+
+#[test]
+fn unknown_packets_have_no_trailing_bytes() {
+    let frame = frame_packet(200, vec![1, 2, 3]);
+
+    let (packet, trailing) =
+        parse_packet_checked(frame[2..].to_vec(), &None, ProtocolVersion::default()).unwrap();
+    assert!(matches!(packet, AnyPacket::Regular(Packet::Other(200))));
+    assert_eq!(trailing, 0);
+}
+
+#[test]
+fn invalid_map_size() {
+    let map_error =
+        |data: Vec<u8>| match read_map(&mut Reader::new(data), &ContentRegistry::default()) {
+            Err(PacketError::Decode { field, reason, .. }) => (field, reason),
+            result => panic!("unexpected result {result:?}"),
+        };
+
+    // A hostile header would allocate a billion tiles
+    let (field, reason) = map_error(vec![0x7f, 0xff, 0x7f, 0xff, 0, 1, 0, 0, 255]);
+    assert_eq!(field, "height");
+    assert_eq!(reason, "map of 32767 x 32767 tiles does not fit in 5 bytes");
+
+    let (field, reason) = map_error(vec![0xff, 0xff, 0, 4]);
+    assert_eq!(field, "width");
+    assert_eq!(reason, "invalid width -1");
+
+    let (field, reason) = map_error(vec![0, 4, 0, 0]);
+    assert_eq!(field, "height");
+    assert_eq!(reason, "invalid height 0");
+
+    // Truncated in the middle of the header
+    let (field, _) = map_error(vec![0, 4, 0]);
+    assert_eq!(field, "height");
+}
@@ -0,0 +1,133 @@
+use base64::{Engine, engine::general_purpose};
+use mindustry_net::client::{Client, ClientError, ClientEvent, ClientInfo};
+use mindustry_net::identity::{Identity, generate_id, is_valid_id};
+use mindustry_net::packet::{PacketError, write_packet_data};
+use mindustry_net::save_io::Map;
+use mindustry_net::server::Server;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::time;
+
+fn temp_path(name: &str) -> PathBuf {
+    let path =
+        std::env::temp_dir().join(format!("mindustry-net-{}-{name}.json", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+#[test]
+fn generated_ids() {
+    let id = generate_id();
+    assert_eq!(general_purpose::STANDARD.decode(&id).unwrap().len(), 8);
+    assert_ne!(generate_id(), id);
+    assert!(is_valid_id(&id));
+    assert!(!is_valid_id("AAAAAA=="));
+    assert!(!is_valid_id("not base64"));
+}
+
+#[test]
+fn usid_per_server() {
+    let mut identity = Identity::generate();
+    let first: IpAddr = "10.0.0.1".parse().unwrap();
+    let second: IpAddr = "10.0.0.2".parse().unwrap();
+
+    let usid = identity.usid(first);
+    assert_eq!(identity.usid(first), usid);
+    assert_ne!(identity.usid(second), usid);
+}
+
+#[test]
+fn identity_persists() {
+    let path = temp_path("persists");
+    let mut identity = Identity::load_or_create(&path).unwrap();
+    let usid = identity.usid("10.0.0.1".parse().unwrap());
+    identity.save(&path).unwrap();
+
+    let mut loaded = Identity::load_or_create(&path).unwrap();
+    assert_eq!(loaded.uuid, identity.uuid);
+    assert_eq!(loaded.usid("10.0.0.1".parse().unwrap()), usid);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn connect_checksum() {
+    let info = ClientInfo::new("bot".to_string());
+    let uuid = general_purpose::STANDARD.decode(&info.uuid).unwrap();
+    let (id, data) = write_packet_data(info.connect_packet()).unwrap();
+    assert_eq!(id, 3);
+
+    // version, then client, name, lang and usid strings
+    let mut offset = 4;
+    for _ in 0..4 {
+        offset += 3 + u16::from_be_bytes([data[offset + 1], data[offset + 2]]) as usize;
+    }
+    assert_eq!(&data[offset..offset + 8], uuid.as_slice());
+    let crc = u64::from_be_bytes(data[offset + 8..offset + 16].try_into().unwrap());
+    assert_eq!(crc, crc32fast::hash(&uuid) as u64);
+}
+
+#[tokio::test]
+async fn rejects_invalid_uuid() {
+    let mut info = ClientInfo::new("bot".to_string());
+    info.uuid = "AAAAAA==".to_string();
+    match write_packet_data(info.connect_packet()) {
+        Err(PacketError::Encode {
+            packet_id, field, ..
+        }) => {
+            assert_eq!(packet_id, Some(3));
+            assert_eq!(field, "uuid");
+        }
+        result => panic!("unexpected result {result:?}"),
+    }
+
+    // Rejected before connecting, the address is never used
+    let result = Client::builder("127.0.0.1:1".to_string(), "bot".to_string())
+        .uuid("not base64".to_string())
+        .connect()
+        .await;
+    assert!(matches!(result, Err(ClientError::InvalidUuid(uuid)) if uuid == "not base64"));
+}
+
+#[tokio::test]
+async fn builder_uses_identity() {
+    let mut content_map = HashMap::new();
+    content_map.insert("block".to_string(), vec!["air".to_string()]);
+    let mut server = Server::bind("127.0.0.1:0", Map::new(10, 10), content_map.into())
+        .await
+        .unwrap();
+    let address = server.local_addr().unwrap();
+    let path = temp_path("builder");
+
+    let mut ids = vec![];
+    for _ in 0..2 {
+        let mut client = Client::builder(address.to_string(), "bot".to_string())
+            .lang("de".to_string())
+            .color(0x11223344)
+            .mods(vec!["example-mod".to_string()])
+            .identity(&path)
+            .connect()
+            .await
+            .unwrap();
+        let mut receiver = client.subscribe();
+        let task = tokio::spawn(async move { client.handle_packets().await });
+
+        let connection = server.accept().await.unwrap();
+        assert!(matches!(
+            time::timeout(Duration::from_secs(5), receiver.recv()).await,
+            Ok(Some(ClientEvent::Connected))
+        ));
+        assert!(matches!(
+            time::timeout(Duration::from_secs(5), receiver.recv()).await,
+            Ok(Some(ClientEvent::WorldLoaded))
+        ));
+        ids.push(connection.uuid.clone());
+        task.abort();
+    }
+
+    let identity = Identity::load_or_create(&path).unwrap();
+    assert_eq!(ids, vec![identity.uuid.clone(), identity.uuid]);
+    assert_eq!(identity.usids.len(), 1);
+    std::fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,1904 @@
+use mindustry_net::content::ContentRegistry;
+use mindustry_net::packet::{Packet, parse_regular_packet};
+use mindustry_net::type_io::{
+    AdminAction, Content, KickReason, Object, Reader, Tile, TraceInfo, Unit, Vec2,
+};
+use mindustry_net::unit_io::{FullUnit, Plan};
+use std::collections::HashMap;
+
+fn parse(id: u8, data: Vec<u8>) -> Packet {
+    parse_regular_packet(id, Reader::new(data), &None).unwrap()
+}
+
+#[test]
+fn stream_begin() {
+    let packet = parse(
+        0,
+        vec![0x00, 0x00, 0x00, 0x07, 0x00, 0x00, 0x03, 0xe8, 0x02],
+    );
+    assert_eq!(
+        packet,
+        Packet::StreamBegin {
+            id: 7,
+            total: 1000,
+            stream_type: 2
+        }
+    );
+}
+
+#[test]
+fn stream_chunk() {
+    let packet = parse(
+        1,
+        vec![0x00, 0x00, 0x00, 0x07, 0x00, 0x03, 0x01, 0x02, 0x03],
+    );
+    assert_eq!(
+        packet,
+        Packet::StreamChunk {
+            id: 7,
+            data: vec![1, 2, 3]
+        }
+    );
+}
+
+#[test]
+fn connect() {
+    let packet = parse(
+        3,
+        vec![
+            0x00, 0x00, 0x00, 0x92, 0x01, 0x00, 0x08, 0x6f, 0x66, 0x66, 0x69, 0x63, 0x69, 0x61,
+            0x6c, 0x01, 0x00, 0x06, 0x50, 0x6c, 0x61, 0x79, 0x65, 0x72, 0x01, 0x00, 0x02, 0x65,
+            0x6e, 0x01, 0x00, 0x0c, 0x55, 0x53, 0x49, 0x47, 0x41, 0x41, 0x41, 0x41, 0x41, 0x41,
+            0x41, 0x3d, 0x51, 0x42, 0x06, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0xa1, 0x08, 0xff, 0x01, 0x01, 0x00, 0x0b, 0x65,
+            0x78, 0x61, 0x6d, 0x70, 0x6c, 0x65, 0x2d, 0x6d, 0x6f, 0x64,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::Connect {
+            version: 146,
+            client: "official".to_string(),
+            name: "Player".to_string(),
+            lang: "en".to_string(),
+            usid: "USIGAAAAAAA=".to_string(),
+            uuid: "UUIGAAAAAAA=".to_string(),
+            mobile: false,
+            color: vec![0xff, 0xa1, 0x08, 0xff],
+            mods: vec!["example-mod".to_string()],
+        }
+    );
+}
+
+#[test]
+fn admin_request_call() {
+    let packet = parse(
+        4,
+        vec![0x00, 0x00, 0x00, 0x65, 0x02, 0x01, 0x00, 0x00, 0x00, 0x03],
+    );
+    assert_eq!(
+        packet,
+        Packet::AdminRequestCall {
+            other: 101,
+            action: AdminAction::Trace,
+            params: Object::Int(3),
+        }
+    );
+}
+
+#[test]
+fn announce_call() {
+    let packet = parse(5, vec![0x01, 0x00, 0x05, 0x74, 0x65, 0x78, 0x74, 0x31]);
+    assert_eq!(
+        packet,
+        Packet::AnnounceCall {
+            message: "text1".to_string(),
+        }
+    );
+}
+
+#[test]
+fn assembler_drone_spawned_call() {
+    let packet = parse(6, vec![0x00, 0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 0x66]);
+    assert_eq!(
+        packet,
+        Packet::AssemblerDroneSpawnedCall {
+            tile: Tile { x: 1, y: 2 },
+            id: 102,
+        }
+    );
+}
+
+#[test]
+fn assembler_unit_spawned_call() {
+    let packet = parse(7, vec![0x00, 0x01, 0x00, 0x02]);
+    assert_eq!(
+        packet,
+        Packet::AssemblerUnitSpawnedCall {
+            tile: Tile { x: 1, y: 2 },
+        }
+    );
+}
+
+#[test]
+fn auto_door_toggle_call() {
+    let packet = parse(8, vec![0x00, 0x01, 0x00, 0x02, 0x01]);
+    assert_eq!(
+        packet,
+        Packet::AutoDoorToggleCall {
+            tile: Tile { x: 1, y: 2 },
+            open: true,
+        }
+    );
+}
+
+#[test]
+fn begin_break() {
+    let packet = parse(
+        9,
+        vec![
+            0x02, 0x00, 0x00, 0x00, 0x0c, 0x01, 0x00, 0x00, 0x00, 0x1e, 0x00, 0x00, 0x00, 0x28,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::BeginBreak {
+            unit: Unit {
+                unit_type: 2,
+                id: 12
+            },
+            team: 1,
+            x: 30,
+            y: 40,
+        }
+    );
+}
+
+#[test]
+fn begin_place() {
+    let packet = parse(
+        10,
+        vec![
+            0x02, 0x00, 0x00, 0x00, 0x0c, 0x01, 0x01, 0x01, 0x00, 0x00, 0x00, 0x1e, 0x00, 0x00,
+            0x00, 0x28, 0x00, 0x00, 0x00, 0x03,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::BeginPlace {
+            unit: Unit {
+                unit_type: 2,
+                id: 12
+            },
+            result: 257,
+            team: 1,
+            x: 30,
+            y: 40,
+            rotation: 3,
+        }
+    );
+}
+
+#[test]
+fn block_snapshot() {
+    let packet = parse(11, vec![0x00, 0x02, 0x00, 0x03, 0x01, 0x02, 0x03]);
+    assert_eq!(
+        packet,
+        Packet::BlockSnapshot {
+            amount: 2,
+            data: vec![1, 2, 3]
+        }
+    );
+}
+
+#[test]
+fn build_destroyed_call() {
+    let packet = parse(12, vec![0x00, 0x01, 0x00, 0x02]);
+    assert_eq!(
+        packet,
+        Packet::BuildDestroyedCall {
+            build: Tile { x: 1, y: 2 },
+        }
+    );
+}
+
+#[test]
+fn build_health_update_call() {
+    let packet = parse(
+        13,
+        vec![
+            0x00, 0x00, 0x00, 0x02, 0x00, 0x05, 0x00, 0x06, 0x42, 0xa0, 0x00, 0x00,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::BuildHealthUpdateCall {
+            healths: vec![(Tile { x: 5, y: 6 }, 80.0)],
+        }
+    );
+}
+
+#[test]
+fn building_control_select_call() {
+    let packet = parse(14, vec![0x00, 0x00, 0x00, 0x65, 0x00, 0x02, 0x00, 0x03]);
+    assert_eq!(
+        packet,
+        Packet::BuildingControlSelectCall {
+            player: 101,
+            build: Tile { x: 2, y: 3 },
+        }
+    );
+}
+
+#[test]
+fn clear_items_call() {
+    let packet = parse(15, vec![0x00, 0x01, 0x00, 0x02]);
+    assert_eq!(
+        packet,
+        Packet::ClearItemsCall {
+            build: Tile { x: 1, y: 2 },
+        }
+    );
+}
+
+#[test]
+fn client_packet_reliable_call() {
+    let packet = parse(
+        16,
+        vec![
+            0x01, 0x00, 0x05, 0x74, 0x65, 0x78, 0x74, 0x31, 0x01, 0x00, 0x05, 0x74, 0x65, 0x78,
+            0x74, 0x32,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::ClientPacketReliableCall {
+            packet_type: "text1".to_string(),
+            contents: "text2".to_string(),
+        }
+    );
+}
+
+#[test]
+fn client_packet_unreliable_call() {
+    let packet = parse(
+        17,
+        vec![
+            0x01, 0x00, 0x05, 0x74, 0x65, 0x78, 0x74, 0x31, 0x01, 0x00, 0x05, 0x74, 0x65, 0x78,
+            0x74, 0x32,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::ClientPacketUnreliableCall {
+            packet_type: "text1".to_string(),
+            contents: "text2".to_string(),
+        }
+    );
+}
+
+#[test]
+fn client_snapshot() {
+    let packet = parse(
+        18,
+        vec![
+            0x00, 0x00, 0x00, 0x05, 0x00, 0x00, 0x00, 0x0c, 0x00, 0x42, 0xa0, 0x00, 0x00, 0x42,
+            0xc0, 0x00, 0x00, 0x42, 0xa2, 0x00, 0x00, 0x42, 0xc2, 0x00, 0x00, 0x42, 0xb4, 0x00,
+            0x00, 0x42, 0x34, 0x00, 0x00, 0x3f, 0x00, 0x00, 0x00, 0xbf, 0x00, 0x00, 0x00, 0x00,
+            0x0a, 0x00, 0x0c, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x01, 0x00, 0x0a,
+            0x00, 0x0b, 0x41, 0x00, 0x00, 0x00, 0x41, 0x80, 0x00, 0x00, 0x44, 0xf0, 0x00, 0x00,
+            0x44, 0x87, 0x00, 0x00,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::ClientSnapshot {
+            snapshot_id: 5,
+            unit_id: 12,
+            dead: false,
+            x: 80.0,
+            y: 96.0,
+            pointer_x: 81.0,
+            pointer_y: 97.0,
+            rotation: 90.0,
+            base_rotation: 45.0,
+            x_velocity: 0.5,
+            y_velocity: -0.5,
+            mining_x: 10,
+            mining_y: 12,
+            boosting: false,
+            shooting: true,
+            chatting: false,
+            building: true,
+            plans: vec![Plan {
+                plan_type: 1,
+                position: Tile { x: 10, y: 11 },
+                block: None,
+                rotation: None,
+                has_config: None,
+                config: None,
+            }],
+            view_x: 8.0,
+            view_y: 16.0,
+            view_width: 1920.0,
+            view_height: 1080.0,
+        }
+    );
+}
+
+#[test]
+fn command_building_call() {
+    let packet = parse(
+        19,
+        vec![
+            0x00, 0x00, 0x00, 0x65, 0x00, 0x02, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x03,
+            0x3f, 0xc0, 0x00, 0x00, 0xc0, 0x00, 0x00, 0x00,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::CommandBuildingCall {
+            player: 101,
+            buildings: vec![2, 3],
+            target: Vec2 { x: 1.5, y: -2.0 },
+        }
+    );
+}
+
+#[test]
+fn command_units_call() {
+    let packet = parse(
+        20,
+        vec![
+            0x00, 0x00, 0x00, 0x65, 0x00, 0x02, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x03,
+            0x00, 0x03, 0x00, 0x04, 0x02, 0x00, 0x00, 0x00, 0xcc, 0x3f, 0xc0, 0x00, 0x00, 0xc0,
+            0x00, 0x00, 0x00,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::CommandUnitsCall {
+            player: 101,
+            unit_ids: vec![2, 3],
+            build_target: Tile { x: 3, y: 4 },
+            unit_target: Unit {
+                unit_type: 2,
+                id: 204
+            },
+            pos_target: Vec2 { x: 1.5, y: -2.0 },
+        }
+    );
+}
+
+#[test]
+fn connect_call() {
+    let packet = parse(
+        21,
+        vec![
+            0x01, 0x00, 0x05, 0x74, 0x65, 0x78, 0x74, 0x31, 0x00, 0x00, 0x00, 0x66,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::ConnectCall {
+            ip: "text1".to_string(),
+            port: 102,
+        }
+    );
+}
+
+#[test]
+fn connect_call_confirm() {
+    let packet = parse(22, vec![]);
+    assert_eq!(packet, Packet::ConnectCallConfirm);
+}
+
+#[test]
+fn construct_finish() {
+    let packet = parse(
+        23,
+        vec![
+            0x00, 0x03, 0x00, 0x04, 0x01, 0x01, 0x02, 0x00, 0x00, 0x00, 0x0c, 0x01, 0x01, 0x0a,
+            0x01,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::ConstructFinish {
+            tile: Tile { x: 3, y: 4 },
+            block: 257,
+            builder: Unit {
+                unit_type: 2,
+                id: 12
+            },
+            rotation: 1,
+            team: 1,
+            config: Object::Boolean(true),
+        }
+    );
+}
+
+#[test]
+fn create_bullet_call() {
+    let packet = parse(
+        24,
+        vec![
+            0x00, 0x0b, 0x02, 0x40, 0x60, 0x00, 0x00, 0x40, 0x90, 0x00, 0x00, 0x40, 0xb0, 0x00,
+            0x00, 0x40, 0xd0, 0x00, 0x00, 0x40, 0xf0, 0x00, 0x00, 0x41, 0x08, 0x00, 0x00,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::CreateBulletCall {
+            bullet_type: 11,
+            team: 2,
+            x: 3.5,
+            y: 4.5,
+            angle: 5.5,
+            damage: 6.5,
+            velocity_scale: 7.5,
+            lifetime_scale: 8.5,
+        }
+    );
+}
+
+#[test]
+fn create_weather_call() {
+    let packet = parse(
+        25,
+        vec![
+            0x00, 0x0b, 0x40, 0x20, 0x00, 0x00, 0x40, 0x60, 0x00, 0x00, 0x40, 0x90, 0x00, 0x00,
+            0x40, 0xb0, 0x00, 0x00,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::CreateWeatherCall {
+            weather: 11,
+            intensity: 2.5,
+            duration: 3.5,
+            wind_x: 4.5,
+            wind_y: 5.5,
+        }
+    );
+}
+
+#[test]
+fn debug_status_client_call() {
+    let packet = parse(
+        26,
+        vec![
+            0x00, 0x00, 0x00, 0x65, 0x00, 0x00, 0x00, 0x66, 0x00, 0x00, 0x00, 0x67,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::DebugStatusClientCall {
+            value: 101,
+            last_client_snapshot: 102,
+            snapshots_sent: 103,
+        }
+    );
+}
+
+#[test]
+fn debug_status_client_unreliable_call() {
+    let packet = parse(
+        27,
+        vec![
+            0x00, 0x00, 0x00, 0x65, 0x00, 0x00, 0x00, 0x66, 0x00, 0x00, 0x00, 0x67,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::DebugStatusClientUnreliableCall {
+            value: 101,
+            last_client_snapshot: 102,
+            snapshots_sent: 103,
+        }
+    );
+}
+
+#[test]
+fn deconstruct_finish() {
+    let packet = parse(
+        28,
+        vec![
+            0x00, 0x03, 0x00, 0x04, 0x01, 0x01, 0x02, 0x00, 0x00, 0x00, 0x0c,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::DeconstructFinish {
+            tile: Tile { x: 3, y: 4 },
+            block: 257,
+            builder: Unit {
+                unit_type: 2,
+                id: 12
+            },
+        }
+    );
+}
+
+#[test]
+fn delete_plans_call() {
+    let packet = parse(
+        29,
+        vec![
+            0x00, 0x00, 0x00, 0x65, 0x00, 0x02, 0x00, 0x01, 0x00, 0x02, 0x00, 0x03, 0x00, 0x04,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::DeletePlansCall {
+            player: 101,
+            positions: vec![Tile { x: 1, y: 2 }, Tile { x: 3, y: 4 }],
+        }
+    );
+}
+
+#[test]
+fn drop_item_call() {
+    let packet = parse(30, vec![0x00, 0x00, 0x00, 0x65, 0x40, 0x20, 0x00, 0x00]);
+    assert_eq!(
+        packet,
+        Packet::DropItemCall {
+            player: 101,
+            angle: 2.5,
+        }
+    );
+}
+
+#[test]
+fn effect_call() {
+    let packet = parse(
+        31,
+        vec![
+            0x00, 0x0b, 0x40, 0x20, 0x00, 0x00, 0x40, 0x60, 0x00, 0x00, 0x40, 0x90, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x69,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::EffectCall {
+            effect: 11,
+            x: 2.5,
+            y: 3.5,
+            rotation: 4.5,
+            color: 105,
+        }
+    );
+}
+
+#[test]
+fn effect_call2() {
+    let packet = parse(
+        32,
+        vec![
+            0x00, 0x0b, 0x40, 0x20, 0x00, 0x00, 0x40, 0x60, 0x00, 0x00, 0x40, 0x90, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x69, 0x01, 0x00, 0x00, 0x00, 0x06,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::EffectCall2 {
+            effect: 11,
+            x: 2.5,
+            y: 3.5,
+            rotation: 4.5,
+            color: 105,
+            data: Object::Int(6),
+        }
+    );
+}
+
+#[test]
+fn effect_reliable_call() {
+    let packet = parse(
+        33,
+        vec![
+            0x00, 0x0b, 0x40, 0x20, 0x00, 0x00, 0x40, 0x60, 0x00, 0x00, 0x40, 0x90, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x69,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::EffectReliableCall {
+            effect: 11,
+            x: 2.5,
+            y: 3.5,
+            rotation: 4.5,
+            color: 105,
+        }
+    );
+}
+
+#[test]
+fn entity_snapshot() {
+    let content_map = Some(ContentRegistry::default());
+    let data = vec![
+        0x00, 0x01, 0x00, 0x2c, 0x00, 0x00, 0x00, 0x03, 0x0c, 0x01, 0x00, 0xff, 0xa1, 0x08, 0xff,
+        0x41, 0x00, 0x00, 0x00, 0x41, 0x80, 0x00, 0x00, 0x01, 0x00, 0x06, 0x50, 0x6c, 0x61, 0x79,
+        0x65, 0x72, 0x00, 0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 0x0c, 0x41, 0x00, 0x00, 0x00, 0x41,
+        0x80, 0x00, 0x00,
+    ];
+    let packet = parse_regular_packet(34, Reader::new(data), &content_map).unwrap();
+
+    let mut units = HashMap::new();
+    units.insert(
+        3,
+        FullUnit::Player {
+            revision: None,
+            admin: true,
+            boosting: false,
+            color: 0xffa108ff,
+            mouse_x: 8.0,
+            mouse_y: 16.0,
+            name: Some("Player".to_string()),
+            shooting: false,
+            team: 1,
+            typing: false,
+            unit: Unit {
+                unit_type: 2,
+                id: 12,
+            },
+            x: 8.0,
+            y: 16.0,
+        },
+    );
+    assert_eq!(packet, Packet::EntitySnapshot { units });
+}
+
+#[test]
+fn follow_up_menu_call() {
+    let packet = parse(
+        35,
+        vec![
+            0x00, 0x00, 0x00, 0x65, 0x01, 0x00, 0x05, 0x74, 0x65, 0x78, 0x74, 0x32, 0x01, 0x00,
+            0x05, 0x74, 0x65, 0x78, 0x74, 0x33, 0x02, 0x02, 0x01, 0x00, 0x03, 0x59, 0x65, 0x73,
+            0x01, 0x00, 0x02, 0x4e, 0x6f, 0x01, 0x01, 0x00, 0x05, 0x43, 0x6c, 0x6f, 0x73, 0x65,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::FollowUpMenuCall {
+            menu_id: 101,
+            title: "text2".to_string(),
+            message: "text3".to_string(),
+            options: vec![
+                vec!["Yes".to_string(), "No".to_string()],
+                vec!["Close".to_string()],
+            ],
+        }
+    );
+}
+
+#[test]
+fn game_over_call() {
+    let packet = parse(36, vec![0x01]);
+    assert_eq!(packet, Packet::GameOverCall { winner: 1 });
+}
+
+#[test]
+fn hidden_snapshot_call() {
+    let packet = parse(
+        37,
+        vec![
+            0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x02,
+        ],
+    );
+    assert_eq!(packet, Packet::HiddenSnapshotCall { ids: vec![1, 2] });
+}
+
+#[test]
+fn hide_follow_up_menu_call() {
+    let packet = parse(38, vec![0x00, 0x00, 0x00, 0x65]);
+    assert_eq!(packet, Packet::HideFollowUpMenuCall { menu_id: 101 });
+}
+
+#[test]
+fn hide_hud_text_call() {
+    let packet = parse(39, vec![]);
+    assert_eq!(packet, Packet::HideHudTextCall);
+}
+
+#[test]
+fn info_message_call() {
+    let packet = parse(40, vec![0x01, 0x00, 0x05, 0x74, 0x65, 0x78, 0x74, 0x31]);
+    assert_eq!(
+        packet,
+        Packet::InfoMessageCall {
+            message: "text1".to_string(),
+        }
+    );
+}
+
+#[test]
+fn info_popup_call() {
+    let packet = parse(
+        41,
+        vec![
+            0x01, 0x00, 0x05, 0x74, 0x65, 0x78, 0x74, 0x31, 0x40, 0x20, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x67, 0x00, 0x00, 0x00, 0x68, 0x00, 0x00, 0x00, 0x69, 0x00, 0x00, 0x00, 0x6a,
+            0x00, 0x00, 0x00, 0x6b,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::InfoPopupCall {
+            message: "text1".to_string(),
+            duration: 2.5,
+            align: 103,
+            top: 104,
+            left: 105,
+            bottom: 106,
+            right: 107,
+        }
+    );
+}
+
+#[test]
+fn info_popup_reliable_call() {
+    let packet = parse(
+        42,
+        vec![
+            0x01, 0x00, 0x05, 0x74, 0x65, 0x78, 0x74, 0x31, 0x40, 0x20, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x67, 0x00, 0x00, 0x00, 0x68, 0x00, 0x00, 0x00, 0x69, 0x00, 0x00, 0x00, 0x6a,
+            0x00, 0x00, 0x00, 0x6b,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::InfoPopupReliableCall {
+            message: "text1".to_string(),
+            duration: 2.5,
+            align: 103,
+            top: 104,
+            left: 105,
+            bottom: 106,
+            right: 107,
+        }
+    );
+}
+
+#[test]
+fn info_toast_call() {
+    let packet = parse(
+        43,
+        vec![
+            0x01, 0x00, 0x05, 0x74, 0x65, 0x78, 0x74, 0x31, 0x40, 0x20, 0x00, 0x00,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::InfoToastCall {
+            message: "text1".to_string(),
+            duration: 2.5,
+        }
+    );
+}
+
+#[test]
+fn kick_call() {
+    let packet = parse(
+        44,
+        vec![
+            0x01, 0x00, 0x11, 0x53, 0x65, 0x72, 0x76, 0x65, 0x72, 0x20, 0x72, 0x65, 0x73, 0x74,
+            0x61, 0x72, 0x74, 0x69, 0x6e, 0x67,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::KickCall {
+            reason: "Server restarting".to_string()
+        }
+    );
+}
+
+#[test]
+fn kick_call2() {
+    let packet = parse(45, vec![0x06]);
+    assert_eq!(
+        packet,
+        Packet::KickCall2 {
+            reason: KickReason::NameInUse
+        }
+    );
+}
+
+#[test]
+fn label_call() {
+    let packet = parse(
+        46,
+        vec![
+            0x01, 0x00, 0x05, 0x74, 0x65, 0x78, 0x74, 0x31, 0x40, 0x20, 0x00, 0x00, 0x40, 0x60,
+            0x00, 0x00, 0x40, 0x90, 0x00, 0x00,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::LabelCall {
+            message: "text1".to_string(),
+            duration: 2.5,
+            world_x: 3.5,
+            world_y: 4.5,
+        }
+    );
+}
+
+#[test]
+fn label_reliable_call() {
+    let packet = parse(
+        47,
+        vec![
+            0x01, 0x00, 0x05, 0x74, 0x65, 0x78, 0x74, 0x31, 0x40, 0x20, 0x00, 0x00, 0x40, 0x60,
+            0x00, 0x00, 0x40, 0x90, 0x00, 0x00,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::LabelReliableCall {
+            message: "text1".to_string(),
+            duration: 2.5,
+            world_x: 3.5,
+            world_y: 4.5,
+        }
+    );
+}
+
+#[test]
+fn logic_explosion_call() {
+    let packet = parse(
+        48,
+        vec![
+            0x01, 0x40, 0x20, 0x00, 0x00, 0x40, 0x60, 0x00, 0x00, 0x40, 0x90, 0x00, 0x00, 0x40,
+            0xb0, 0x00, 0x00, 0x01, 0x01, 0x01,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::LogicExplosionCall {
+            team: 1,
+            x: 2.5,
+            y: 3.5,
+            radius: 4.5,
+            damage: 5.5,
+            air: true,
+            ground: true,
+            pierce: true,
+        }
+    );
+}
+
+#[test]
+fn menu_call() {
+    let packet = parse(
+        49,
+        vec![
+            0x00, 0x00, 0x00, 0x65, 0x01, 0x00, 0x05, 0x74, 0x65, 0x78, 0x74, 0x32, 0x01, 0x00,
+            0x05, 0x74, 0x65, 0x78, 0x74, 0x33, 0x02, 0x02, 0x01, 0x00, 0x03, 0x59, 0x65, 0x73,
+            0x01, 0x00, 0x02, 0x4e, 0x6f, 0x01, 0x01, 0x00, 0x05, 0x43, 0x6c, 0x6f, 0x73, 0x65,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::MenuCall {
+            menu_id: 101,
+            title: "text2".to_string(),
+            message: "text3".to_string(),
+            options: vec![
+                vec!["Yes".to_string(), "No".to_string()],
+                vec!["Close".to_string()],
+            ],
+        }
+    );
+}
+
+#[test]
+fn menu_choose_call() {
+    let packet = parse(
+        50,
+        vec![
+            0x00, 0x00, 0x00, 0x65, 0x00, 0x00, 0x00, 0x66, 0x00, 0x00, 0x00, 0x67,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::MenuChooseCall {
+            player: 101,
+            menu_id: 102,
+            option: 103,
+        }
+    );
+}
+
+#[test]
+fn objective_completed_call() {
+    let packet = parse(
+        51,
+        vec![
+            0x02, 0x01, 0x00, 0x01, 0x61, 0x01, 0x00, 0x01, 0x62, 0x02, 0x01, 0x00, 0x01, 0x61,
+            0x01, 0x00, 0x01, 0x62,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::ObjectiveCompletedCall {
+            flags_removed: vec!["a".to_string(), "b".to_string()],
+            flags_added: vec!["a".to_string(), "b".to_string()],
+        }
+    );
+}
+
+#[test]
+fn open_uri_call() {
+    let packet = parse(52, vec![0x01, 0x00, 0x05, 0x74, 0x65, 0x78, 0x74, 0x31]);
+    assert_eq!(
+        packet,
+        Packet::OpenUriCall {
+            uri: "text1".to_string(),
+        }
+    );
+}
+
+#[test]
+fn payload_dropped_call() {
+    let packet = parse(
+        53,
+        vec![
+            0x02, 0x00, 0x00, 0x00, 0xc9, 0x40, 0x20, 0x00, 0x00, 0x40, 0x60, 0x00, 0x00,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::PayloadDroppedCall {
+            unit: Unit {
+                unit_type: 2,
+                id: 201
+            },
+            x: 2.5,
+            y: 3.5,
+        }
+    );
+}
+
+#[test]
+fn picked_build_payload_call() {
+    let packet = parse(
+        54,
+        vec![0x02, 0x00, 0x00, 0x00, 0xc9, 0x00, 0x02, 0x00, 0x03, 0x01],
+    );
+    assert_eq!(
+        packet,
+        Packet::PickedBuildPayloadCall {
+            unit: Unit {
+                unit_type: 2,
+                id: 201
+            },
+            build: Tile { x: 2, y: 3 },
+            on_ground: true,
+        }
+    );
+}
+
+#[test]
+fn picked_unit_payload_call() {
+    let packet = parse(
+        55,
+        vec![0x02, 0x00, 0x00, 0x00, 0xc9, 0x02, 0x00, 0x00, 0x00, 0xca],
+    );
+    assert_eq!(
+        packet,
+        Packet::PickedUnitPayloadCall {
+            unit: Unit {
+                unit_type: 2,
+                id: 201
+            },
+            target: Unit {
+                unit_type: 2,
+                id: 202
+            },
+        }
+    );
+}
+
+#[test]
+fn ping_call() {
+    let packet = parse(56, vec![0x00, 0x00, 0x01, 0x8b, 0xcf, 0xe5, 0x68, 0x01]);
+    assert_eq!(
+        packet,
+        Packet::PingCall {
+            time: 1700000000001,
+        }
+    );
+}
+
+#[test]
+fn ping_response_call() {
+    let packet = parse(57, vec![0x00, 0x00, 0x01, 0x8b, 0xcf, 0xe5, 0x68, 0x01]);
+    assert_eq!(
+        packet,
+        Packet::PingResponseCall {
+            time: 1700000000001,
+        }
+    );
+}
+
+#[test]
+fn player_disconnect_call() {
+    let packet = parse(58, vec![0x00, 0x00, 0x00, 0x65]);
+    assert_eq!(packet, Packet::PlayerDisconnectCall { player: 101 });
+}
+
+#[test]
+fn spawn_call() {
+    let packet = parse(59, vec![0x00, 0x0a, 0x00, 0x0b, 0x00, 0x00, 0x00, 0x03]);
+    assert_eq!(
+        packet,
+        Packet::SpawnCall {
+            tile_x: 10,
+            tile_y: 11,
+            entity: 3
+        }
+    );
+}
+
+#[test]
+fn remove_queue_block_call() {
+    let packet = parse(
+        60,
+        vec![0x00, 0x00, 0x00, 0x65, 0x00, 0x00, 0x00, 0x66, 0x01],
+    );
+    assert_eq!(
+        packet,
+        Packet::RemoveQueueBlockCall {
+            x: 101,
+            y: 102,
+            breaking: true,
+        }
+    );
+}
+
+#[test]
+fn remove_tile_call() {
+    let packet = parse(61, vec![0x00, 0x01, 0x00, 0x02]);
+    assert_eq!(
+        packet,
+        Packet::RemoveTileCall {
+            tile: Tile { x: 1, y: 2 },
+        }
+    );
+}
+
+#[test]
+fn remove_world_label_call() {
+    let packet = parse(62, vec![0x00, 0x00, 0x00, 0x65]);
+    assert_eq!(packet, Packet::RemoveWorldLabelCall { id: 101 });
+}
+
+#[test]
+fn request_build_payload_call() {
+    let packet = parse(63, vec![0x00, 0x01, 0x00, 0x02]);
+    assert_eq!(
+        packet,
+        Packet::RequestBuildPayloadCall {
+            build: Tile { x: 1, y: 2 },
+        }
+    );
+}
+
+#[test]
+fn request_debug_status_call() {
+    let packet = parse(64, vec![]);
+    assert_eq!(packet, Packet::RequestDebugStatusCall);
+}
+
+#[test]
+fn request_drop_payload_call() {
+    let packet = parse(65, vec![0x3f, 0xc0, 0x00, 0x00, 0x40, 0x20, 0x00, 0x00]);
+    assert_eq!(packet, Packet::RequestDropPayloadCall { x: 1.5, y: 2.5 });
+}
+
+#[test]
+fn request_item_call() {
+    let packet = parse(
+        66,
+        vec![
+            0x00, 0x00, 0x00, 0x65, 0x00, 0x02, 0x00, 0x03, 0x00, 0x0d, 0x00, 0x00, 0x00, 0x68,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::RequestItemCall {
+            player: 101,
+            build: Tile { x: 2, y: 3 },
+            item: 13,
+            amount: 104,
+        }
+    );
+}
+
+#[test]
+fn request_unit_payload_call() {
+    let packet = parse(67, vec![0x02, 0x00, 0x00, 0x00, 0xc9]);
+    assert_eq!(
+        packet,
+        Packet::RequestUnitPayloadCall {
+            target: Unit {
+                unit_type: 2,
+                id: 201
+            },
+        }
+    );
+}
+
+#[test]
+fn researched_call() {
+    let packet = parse(68, vec![0x01, 0x00, 0x2a]);
+    assert_eq!(
+        packet,
+        Packet::ResearchedCall {
+            content: Content {
+                content_type: 1,
+                id: 42
+            },
+        }
+    );
+}
+
+#[test]
+fn rotate_block_call() {
+    let packet = parse(
+        69,
+        vec![0x00, 0x00, 0x00, 0x03, 0x00, 0x05, 0x00, 0x06, 0x01],
+    );
+    assert_eq!(
+        packet,
+        Packet::RotateBlockCall {
+            entity: 3,
+            tile: Tile { x: 5, y: 6 },
+            rotation: 1,
+        }
+    );
+}
+
+#[test]
+fn sector_capture_call() {
+    let packet = parse(70, vec![]);
+    assert_eq!(packet, Packet::SectorCaptureCall);
+}
+
+#[test]
+fn send_chat_message_call() {
+    let packet = parse(71, vec![0x01, 0x00, 0x02, 0x68, 0x69]);
+    assert_eq!(
+        packet,
+        Packet::SendChatMessageCall {
+            message: "hi".to_string()
+        }
+    );
+}
+
+#[test]
+fn send_message_call() {
+    let packet = parse(72, vec![0x01, 0x00, 0x05, 0x74, 0x65, 0x78, 0x74, 0x31]);
+    assert_eq!(
+        packet,
+        Packet::SendMessageCall {
+            message: "text1".to_string(),
+        }
+    );
+}
+
+#[test]
+fn send_message_call2() {
+    let packet = parse(
+        73,
+        vec![
+            0x01, 0x00, 0x14, 0x5b, 0x63, 0x6f, 0x72, 0x61, 0x6c, 0x5d, 0x5b, 0x5b, 0x50, 0x6c,
+            0x61, 0x79, 0x65, 0x72, 0x5d, 0x3a, 0x20, 0x68, 0x69, 0x01, 0x00, 0x02, 0x68, 0x69,
+            0x00, 0x00, 0x00, 0x03,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::SendMessageCall2 {
+            message: "[coral][[Player]: hi".to_string(),
+            unformatted: Some("hi".to_string()),
+            sender: 3,
+        }
+    );
+}
+
+#[test]
+fn server_packet_reliable_call() {
+    let packet = parse(
+        74,
+        vec![
+            0x01, 0x00, 0x05, 0x74, 0x65, 0x78, 0x74, 0x31, 0x01, 0x00, 0x05, 0x74, 0x65, 0x78,
+            0x74, 0x32,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::ServerPacketReliableCall {
+            packet_type: "text1".to_string(),
+            contents: "text2".to_string(),
+        }
+    );
+}
+
+#[test]
+fn server_packet_unreliable_call() {
+    let packet = parse(
+        75,
+        vec![
+            0x01, 0x00, 0x05, 0x74, 0x65, 0x78, 0x74, 0x31, 0x01, 0x00, 0x05, 0x74, 0x65, 0x78,
+            0x74, 0x32,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::ServerPacketUnreliableCall {
+            packet_type: "text1".to_string(),
+            contents: "text2".to_string(),
+        }
+    );
+}
+
+#[test]
+fn set_camera_position_call() {
+    let packet = parse(76, vec![0x3f, 0xc0, 0x00, 0x00, 0x40, 0x20, 0x00, 0x00]);
+    assert_eq!(packet, Packet::SetCameraPositionCall { x: 1.5, y: 2.5 });
+}
+
+#[test]
+fn set_flag_call() {
+    let packet = parse(
+        77,
+        vec![0x01, 0x00, 0x05, 0x74, 0x65, 0x78, 0x74, 0x31, 0x01],
+    );
+    assert_eq!(
+        packet,
+        Packet::SetFlagCall {
+            flag: "text1".to_string(),
+            add: true,
+        }
+    );
+}
+
+#[test]
+fn set_floor_call() {
+    let packet = parse(78, vec![0x00, 0x01, 0x00, 0x02, 0x00, 0x0c, 0x00, 0x0d]);
+    assert_eq!(
+        packet,
+        Packet::SetFloorCall {
+            tile: Tile { x: 1, y: 2 },
+            floor: 12,
+            overlay: 13,
+        }
+    );
+}
+
+#[test]
+fn set_hud_text_call() {
+    let packet = parse(79, vec![0x01, 0x00, 0x05, 0x74, 0x65, 0x78, 0x74, 0x31]);
+    assert_eq!(
+        packet,
+        Packet::SetHudTextCall {
+            message: "text1".to_string(),
+        }
+    );
+}
+
+#[test]
+fn set_hud_text_reliable_call() {
+    let packet = parse(80, vec![0x01, 0x00, 0x05, 0x74, 0x65, 0x78, 0x74, 0x31]);
+    assert_eq!(
+        packet,
+        Packet::SetHudTextReliableCall {
+            message: "text1".to_string(),
+        }
+    );
+}
+
+#[test]
+fn set_item_call() {
+    let packet = parse(
+        81,
+        vec![0x00, 0x01, 0x00, 0x02, 0x00, 0x0c, 0x00, 0x00, 0x00, 0x67],
+    );
+    assert_eq!(
+        packet,
+        Packet::SetItemCall {
+            build: Tile { x: 1, y: 2 },
+            item: 12,
+            amount: 103,
+        }
+    );
+}
+
+#[test]
+fn set_map_area_call() {
+    let packet = parse(
+        82,
+        vec![
+            0x00, 0x00, 0x00, 0x65, 0x00, 0x00, 0x00, 0x66, 0x00, 0x00, 0x00, 0x67, 0x00, 0x00,
+            0x00, 0x68,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::SetMapAreaCall {
+            x: 101,
+            y: 102,
+            width: 103,
+            height: 104,
+        }
+    );
+}
+
+#[test]
+fn set_objectives_call() {
+    let packet = parse(
+        83,
+        vec![
+            0x00, 0x00, 0x00, 0x0c, 0x7b, 0x22, 0x70, 0x76, 0x70, 0x22, 0x3a, 0x74, 0x72, 0x75,
+            0x65, 0x7d,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::SetObjectivesCall {
+            objectives: r#"{"pvp":true}"#.to_string(),
+        }
+    );
+}
+
+#[test]
+fn set_overlay_call() {
+    let packet = parse(84, vec![0x00, 0x01, 0x00, 0x02, 0x00, 0x0c]);
+    assert_eq!(
+        packet,
+        Packet::SetOverlayCall {
+            tile: Tile { x: 1, y: 2 },
+            overlay: 12,
+        }
+    );
+}
+
+#[test]
+fn set_player_team_editor_call() {
+    let packet = parse(85, vec![0x01]);
+    assert_eq!(packet, Packet::SetPlayerTeamEditorCall { team: 1 });
+}
+
+#[test]
+fn set_position_call() {
+    let packet = parse(86, vec![0x3f, 0xc0, 0x00, 0x00, 0x40, 0x20, 0x00, 0x00]);
+    assert_eq!(packet, Packet::SetPositionCall { x: 1.5, y: 2.5 });
+}
+
+#[test]
+fn set_rules_call() {
+    let packet = parse(
+        87,
+        vec![
+            0x00, 0x00, 0x00, 0x0c, 0x7b, 0x22, 0x70, 0x76, 0x70, 0x22, 0x3a, 0x74, 0x72, 0x75,
+            0x65, 0x7d,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::SetRulesCall {
+            rules: r#"{"pvp":true}"#.to_string(),
+        }
+    );
+}
+
+#[test]
+fn set_team_call() {
+    let packet = parse(88, vec![0x00, 0x01, 0x00, 0x02, 0x02]);
+    assert_eq!(
+        packet,
+        Packet::SetTeamCall {
+            build: Tile { x: 1, y: 2 },
+            team: 2,
+        }
+    );
+}
+
+#[test]
+fn set_tile_call() {
+    let packet = parse(
+        89,
+        vec![
+            0x00, 0x01, 0x00, 0x02, 0x00, 0x0c, 0x03, 0x00, 0x00, 0x00, 0x68,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::SetTileCall {
+            tile: Tile { x: 1, y: 2 },
+            block: 12,
+            team: 3,
+            rotation: 104,
+        }
+    );
+}
+
+#[test]
+fn set_unit_command_call() {
+    let packet = parse(
+        90,
+        vec![
+            0x00, 0x00, 0x00, 0x65, 0x00, 0x02, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x03,
+            0x03,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::SetUnitCommandCall {
+            player: 101,
+            unit_ids: vec![2, 3],
+            command: Some(3),
+        }
+    );
+}
+
+#[test]
+fn sound_call() {
+    let packet = parse(
+        91,
+        vec![
+            0x00, 0x0b, 0x40, 0x20, 0x00, 0x00, 0x40, 0x60, 0x00, 0x00, 0x40, 0x90, 0x00, 0x00,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::SoundCall {
+            sound: 11,
+            volume: 2.5,
+            pitch: 3.5,
+            pan: 4.5,
+        }
+    );
+}
+
+#[test]
+fn sound_at_call() {
+    let packet = parse(
+        92,
+        vec![
+            0x00, 0x0b, 0x40, 0x20, 0x00, 0x00, 0x40, 0x60, 0x00, 0x00, 0x40, 0x90, 0x00, 0x00,
+            0x40, 0xb0, 0x00, 0x00,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::SoundAtCall {
+            sound: 11,
+            x: 2.5,
+            y: 3.5,
+            volume: 4.5,
+            pitch: 5.5,
+        }
+    );
+}
+
+#[test]
+fn spawn_effect_call() {
+    let packet = parse(
+        93,
+        vec![
+            0x3f, 0xc0, 0x00, 0x00, 0x40, 0x20, 0x00, 0x00, 0x40, 0x60, 0x00, 0x00, 0x00, 0x0e,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::SpawnEffectCall {
+            x: 1.5,
+            y: 2.5,
+            rotation: 3.5,
+            unit_type: 14,
+        }
+    );
+}
+
+#[test]
+fn state_snapshot() {
+    let packet = parse(
+        94,
+        vec![
+            0x42, 0xf0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x0c, 0x00, 0x00,
+            0x00, 0x00, 0x0e, 0x10, 0x3c, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, // rand
+            0x00, 0x0a, 0x01, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x03, 0xe8, // cores
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::StateSnapshot {
+            wave_time: 120.0,
+            wave: 4,
+            enemies: 12,
+            paused: false,
+            game_over: false,
+            time_data: 3600,
+            tps: 60,
+            rand0: 1,
+            rand1: 2,
+            cores: HashMap::from([(1, HashMap::from([(0, 1000)]))]),
+        }
+    );
+}
+
+#[test]
+fn sync_variable_call() {
+    let packet = parse(
+        95,
+        vec![
+            0x00, 0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 0x66, 0x01, 0x00, 0x00, 0x00, 0x03,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::SyncVariableCall {
+            build: Tile { x: 1, y: 2 },
+            variable: 102,
+            value: Object::Int(3),
+        }
+    );
+}
+
+#[test]
+fn take_items_call() {
+    let packet = parse(
+        96,
+        vec![
+            0x00, 0x01, 0x00, 0x02, 0x00, 0x0c, 0x00, 0x00, 0x00, 0x67, 0x02, 0x00, 0x00, 0x00,
+            0xcc,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::TakeItemsCall {
+            build: Tile { x: 1, y: 2 },
+            item: 12,
+            amount: 103,
+            to: Unit {
+                unit_type: 2,
+                id: 204
+            },
+        }
+    );
+}
+
+#[test]
+fn text_input_call() {
+    let packet = parse(
+        97,
+        vec![
+            0x00, 0x00, 0x00, 0x65, 0x01, 0x00, 0x05, 0x74, 0x65, 0x78, 0x74, 0x32, 0x01, 0x00,
+            0x05, 0x74, 0x65, 0x78, 0x74, 0x33, 0x00, 0x00, 0x00, 0x68, 0x01, 0x00, 0x05, 0x74,
+            0x65, 0x78, 0x74, 0x35, 0x01,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::TextInputCall {
+            text_input_id: 101,
+            title: "text2".to_string(),
+            message: "text3".to_string(),
+            text_length: 104,
+            default: "text5".to_string(),
+            numeric: true,
+        }
+    );
+}
+
+#[test]
+fn text_input_result_call() {
+    let packet = parse(
+        98,
+        vec![
+            0x00, 0x00, 0x00, 0x65, 0x01, 0x00, 0x05, 0x74, 0x65, 0x78, 0x74, 0x32,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::TextInputResultCall {
+            text_input_id: 101,
+            text: Some("text2".to_string()),
+        }
+    );
+}
+
+#[test]
+fn tile_config_call() {
+    let packet = parse(
+        99,
+        vec![
+            0x00, 0x00, 0x00, 0x03, 0x00, 0x05, 0x00, 0x06, 0x01, 0x00, 0x00, 0x00, 0x07,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::TileConfigCall {
+            player: 3,
+            tile: Tile { x: 5, y: 6 },
+            value: Object::Int(7),
+        }
+    );
+}
+
+#[test]
+fn tile_tap_call() {
+    let packet = parse(100, vec![0x00, 0x00, 0x00, 0x65, 0x00, 0x02, 0x00, 0x03]);
+    assert_eq!(
+        packet,
+        Packet::TileTapCall {
+            player: 101,
+            tile: Tile { x: 2, y: 3 },
+        }
+    );
+}
+
+#[test]
+fn trace_info_call() {
+    let packet = parse(
+        101,
+        vec![
+            0x00, 0x00, 0x00, 0x65, 0x01, 0x00, 0x09, 0x31, 0x32, 0x37, 0x2e, 0x30, 0x2e, 0x30,
+            0x2e, 0x31, 0x01, 0x00, 0x0c, 0x41, 0x41, 0x41, 0x41, 0x41, 0x41, 0x41, 0x41, 0x41,
+            0x41, 0x41, 0x3d, 0x01, 0x00, 0x02, 0x65, 0x6e, 0x00, 0x01, 0x00, 0x00, 0x00, 0x03,
+            0x00, 0x00, 0x00, 0x01, 0x01, 0x01, 0x00, 0x09, 0x31, 0x32, 0x37, 0x2e, 0x30, 0x2e,
+            0x30, 0x2e, 0x31, 0x01, 0x01, 0x00, 0x06, 0x50, 0x6c, 0x61, 0x79, 0x65, 0x72,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::TraceInfoCall {
+            player: 101,
+            info: TraceInfo {
+                ip: Some("127.0.0.1".to_string()),
+                uuid: Some("AAAAAAAAAAA=".to_string()),
+                locale: Some("en".to_string()),
+                modded: false,
+                mobile: true,
+                times_joined: 3,
+                times_kicked: 1,
+                ips: vec!["127.0.0.1".to_string()],
+                names: vec!["Player".to_string()],
+            },
+        }
+    );
+}
+
+#[test]
+fn transfer_inventory_call() {
+    let packet = parse(102, vec![0x00, 0x00, 0x00, 0x65, 0x00, 0x02, 0x00, 0x03]);
+    assert_eq!(
+        packet,
+        Packet::TransferInventoryCall {
+            player: 101,
+            build: Tile { x: 2, y: 3 },
+        }
+    );
+}
+
+#[test]
+fn transfer_item_to_call() {
+    let packet = parse(
+        103,
+        vec![
+            0x02, 0x00, 0x00, 0x00, 0xc9, 0x00, 0x0c, 0x00, 0x00, 0x00, 0x67, 0x40, 0x90, 0x00,
+            0x00, 0x40, 0xb0, 0x00, 0x00, 0x00, 0x06, 0x00, 0x07,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::TransferItemToCall {
+            unit: Unit {
+                unit_type: 2,
+                id: 201
+            },
+            item: 12,
+            amount: 103,
+            x: 4.5,
+            y: 5.5,
+            build: Tile { x: 6, y: 7 },
+        }
+    );
+}
+
+#[test]
+fn transfer_item_to_unit_call() {
+    let packet = parse(
+        104,
+        vec![
+            0x00, 0x0b, 0x40, 0x20, 0x00, 0x00, 0x40, 0x60, 0x00, 0x00, 0x00, 0x00, 0x00, 0x68,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::TransferItemToUnitCall {
+            item: 11,
+            x: 2.5,
+            y: 3.5,
+            to: 104,
+        }
+    );
+}
+
+#[test]
+fn unit_block_spawn_call() {
+    let packet = parse(105, vec![0x00, 0x01, 0x00, 0x02]);
+    assert_eq!(
+        packet,
+        Packet::UnitBlockSpawnCall {
+            tile: Tile { x: 1, y: 2 },
+        }
+    );
+}
+
+#[test]
+fn unit_building_control_select_call() {
+    let packet = parse(
+        106,
+        vec![0x02, 0x00, 0x00, 0x00, 0xc9, 0x00, 0x02, 0x00, 0x03],
+    );
+    assert_eq!(
+        packet,
+        Packet::UnitBuildingControlSelectCall {
+            unit: Unit {
+                unit_type: 2,
+                id: 201
+            },
+            build: Tile { x: 2, y: 3 },
+        }
+    );
+}
+
+#[test]
+fn unit_cap_death_call() {
+    let packet = parse(107, vec![0x02, 0x00, 0x00, 0x00, 0xc9]);
+    assert_eq!(
+        packet,
+        Packet::UnitCapDeathCall {
+            unit: Unit {
+                unit_type: 2,
+                id: 201
+            },
+        }
+    );
+}
+
+#[test]
+fn unit_clear_call() {
+    let packet = parse(108, vec![0x00, 0x00, 0x00, 0x65]);
+    assert_eq!(packet, Packet::UnitClearCall { player: 101 });
+}
+
+#[test]
+fn unit_control_call() {
+    let packet = parse(
+        109,
+        vec![0x00, 0x00, 0x00, 0x65, 0x02, 0x00, 0x00, 0x00, 0xca],
+    );
+    assert_eq!(
+        packet,
+        Packet::UnitControlCall {
+            player: 101,
+            unit: Unit {
+                unit_type: 2,
+                id: 202
+            },
+        }
+    );
+}
+
+#[test]
+fn unit_death_call() {
+    let packet = parse(110, vec![0x00, 0x00, 0x00, 0x65]);
+    assert_eq!(packet, Packet::UnitDeathCall { id: 101 });
+}
+
+#[test]
+fn unit_destroy_call() {
+    let packet = parse(111, vec![0x00, 0x00, 0x00, 0x65]);
+    assert_eq!(packet, Packet::UnitDestroyCall { id: 101 });
+}
+
+#[test]
+fn unit_entered_payload_call() {
+    let packet = parse(
+        112,
+        vec![0x02, 0x00, 0x00, 0x00, 0xc9, 0x00, 0x02, 0x00, 0x03],
+    );
+    assert_eq!(
+        packet,
+        Packet::UnitEnteredPayloadCall {
+            unit: Unit {
+                unit_type: 2,
+                id: 201
+            },
+            build: Tile { x: 2, y: 3 },
+        }
+    );
+}
+
+#[test]
+fn unit_env_death_call() {
+    let packet = parse(113, vec![0x02, 0x00, 0x00, 0x00, 0xc9]);
+    assert_eq!(
+        packet,
+        Packet::UnitEnvDeathCall {
+            unit: Unit {
+                unit_type: 2,
+                id: 201
+            },
+        }
+    );
+}
+
+#[test]
+fn unit_safe_death_call() {
+    let packet = parse(114, vec![0x02, 0x00, 0x00, 0x00, 0xc9]);
+    assert_eq!(
+        packet,
+        Packet::UnitSafeDeathCall {
+            unit: Unit {
+                unit_type: 2,
+                id: 201
+            },
+        }
+    );
+}
+
+#[test]
+fn unit_tether_block_spawned_call() {
+    let packet = parse(115, vec![0x00, 0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 0x66]);
+    assert_eq!(
+        packet,
+        Packet::UnitTetherBlockSpawnedCall {
+            tile: Tile { x: 1, y: 2 },
+            id: 102,
+        }
+    );
+}
+
+#[test]
+fn update_game_over_call() {
+    let packet = parse(116, vec![0x01]);
+    assert_eq!(packet, Packet::UpdateGameOverCall { winner: 1 });
+}
+
+#[test]
+fn warning_toast_call() {
+    let packet = parse(
+        117,
+        vec![
+            0x00, 0x00, 0x00, 0x65, 0x01, 0x00, 0x05, 0x74, 0x65, 0x78, 0x74, 0x32,
+        ],
+    );
+    assert_eq!(
+        packet,
+        Packet::WarningToastCall {
+            unicode: 101,
+            text: "text2".to_string(),
+        }
+    );
+}
+
+#[test]
+fn world_data_begin_call() {
+    let packet = parse(118, vec![]);
+    assert_eq!(packet, Packet::WorldDataBeginCall);
+}
+
+#[test]
+fn unknown_packet() {
+    let packet = parse(200, vec![0x01, 0x02, 0x03]);
+    assert_eq!(packet, Packet::Other(200));
+}
@@ -0,0 +1,842 @@
+use mindustry_net::block_io::{BaseBlockData, Block, SpecificBlockData, write_block};
+use mindustry_net::client::{ClientEvent, DisconnectCause, Kick, QueuedPacket};
+use mindustry_net::content::ContentRegistry;
+use mindustry_net::packet::{
+    AnyPacket, FrameworkPacket, Packet, World, parse_packet, write_framework_packet, write_packet,
+    write_packet_data,
+};
+use mindustry_net::protocol::{ClientProtocol, DEFAULT_TIMEOUT};
+use mindustry_net::rules::Rules;
+use mindustry_net::save_io::{Map, MapInfo, Marker, MarkerKind, TeamPlan};
+use mindustry_net::type_io::{KickReason, Object, Tile, Unit, Vec2, write_unsigned_short};
+use mindustry_net::unit_io::{FullUnit, PlayerInfo};
+use mindustry_net::version::ProtocolVersion;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+fn framework_frame(packet: FrameworkPacket) -> Vec<u8> {
+    let data = write_framework_packet(packet);
+    let mut frame = vec![];
+    write_unsigned_short(&mut frame, data.len() as u16);
+    frame.extend_from_slice(&data);
+    frame
+}
+
+fn decode(transmit: QueuedPacket) -> AnyPacket {
+    let frame = match transmit.reliable {
+        true => transmit.packet[2..].to_vec(),
+        false => transmit.packet,
+    };
+    parse_packet(frame, &None, ProtocolVersion::default()).unwrap()
+}
+
+fn transmits(protocol: &mut ClientProtocol) -> Vec<(bool, AnyPacket)> {
+    let mut transmits = vec![];
+    while let Some(transmit) = protocol.poll_transmit() {
+        transmits.push((transmit.reliable, decode(transmit)));
+    }
+    transmits
+}
+
+/// Sends encoded packet data as a stream of small chunks
+fn receive_stream(protocol: &mut ClientProtocol, now: Instant, stream_type: u8, data: &[u8]) {
+    let mut bytes = write_packet(Packet::StreamBegin {
+        id: 0,
+        total: data.len() as u32,
+        stream_type,
+    })
+    .unwrap();
+    for chunk in data.chunks(16) {
+        bytes.extend(
+            write_packet(Packet::StreamChunk {
+                id: 0,
+                data: chunk.to_vec(),
+            })
+            .unwrap(),
+        );
+    }
+    protocol.receive_tcp(now, &bytes);
+}
+
+fn player(admin: bool, team: u8) -> PlayerInfo {
+    PlayerInfo {
+        revision: 0,
+        admin,
+        boosting: false,
+        color: 0xffa108ff,
+        mouse_x: 0.0,
+        mouse_y: 0.0,
+        name: Some("bot".to_string()),
+        shooting: false,
+        team,
+        typing: false,
+        unit: Unit {
+            unit_type: 0,
+            id: 0,
+        },
+        x: 0.0,
+        y: 0.0,
+    }
+}
+
+/// Runs the handshake, discarding the packets it sends
+fn connect(protocol: &mut ClientProtocol, now: Instant) {
+    protocol.receive_tcp(now, &framework_frame(FrameworkPacket::RegisterTCP(7)));
+    protocol.receive_tcp(now, &framework_frame(FrameworkPacket::RegisterUDP(7)));
+    transmits(protocol);
+    assert!(matches!(
+        protocol.poll_event(),
+        Some(ClientEvent::Connected)
+    ));
+}
+
+#[test]
+fn handshake() {
+    let mut protocol = ClientProtocol::new("bot".to_string(), None);
+    let now = Instant::now();
+
+    // Split across reads to exercise framing
+    let frame = framework_frame(FrameworkPacket::RegisterTCP(7));
+    protocol.receive_tcp(now, &frame[..3]);
+    assert!(protocol.poll_transmit().is_none());
+    protocol.receive_tcp(now, &frame[3..]);
+
+    let sent = transmits(&mut protocol);
+    assert_eq!(sent.len(), 1);
+    assert!(matches!(
+        sent[0],
+        (false, AnyPacket::Framework(FrameworkPacket::RegisterUDP(7)))
+    ));
+    // Only the dead server timeout is pending before snapshots start
+    assert_eq!(protocol.poll_timeout(), Some(now + DEFAULT_TIMEOUT));
+
+    protocol.receive_tcp(now, &framework_frame(FrameworkPacket::RegisterUDP(7)));
+    let sent = transmits(&mut protocol);
+    assert_eq!(sent.len(), 2);
+    match &sent[0] {
+        (true, AnyPacket::Regular(Packet::Connect { name, version, .. })) => {
+            assert_eq!(name, "bot");
+            assert_eq!(*version, 146);
+        }
+        other => panic!("unexpected transmit {other:?}"),
+    }
+    assert!(matches!(
+        sent[1],
+        (true, AnyPacket::Regular(Packet::ConnectCallConfirm))
+    ));
+    assert_eq!(protocol.poll_timeout(), Some(now));
+}
+
+#[test]
+fn snapshots_and_keepalives() {
+    let mut protocol = ClientProtocol::new("bot".to_string(), None);
+    let start = Instant::now();
+    connect(&mut protocol, start);
+
+    protocol.handle_timeout(start);
+    let sent = transmits(&mut protocol);
+    assert!(matches!(
+        sent[..],
+        [(
+            true,
+            AnyPacket::Regular(Packet::ClientSnapshot { snapshot_id: 1, .. })
+        )]
+    ));
+    assert_eq!(
+        protocol.poll_timeout(),
+        Some(start + Duration::from_millis(200))
+    );
+
+    // Nothing is due yet
+    protocol.handle_timeout(start + Duration::from_millis(199));
+    assert!(protocol.poll_transmit().is_none());
+
+    // Catching up after five seconds sends every missed snapshot and a keepalive
+    protocol.handle_timeout(start + Duration::from_secs(5));
+    let sent = transmits(&mut protocol);
+    let snapshots = sent
+        .iter()
+        .filter(|(_, packet)| matches!(packet, AnyPacket::Regular(Packet::ClientSnapshot { .. })))
+        .count();
+    assert_eq!(snapshots, 25);
+    assert!(
+        sent.iter()
+            .any(|(_, packet)| matches!(packet, AnyPacket::Framework(_)))
+    );
+}
+
+#[test]
+fn world_stream() {
+    let mut protocol = ClientProtocol::new("bot".to_string(), None);
+    let now = Instant::now();
+    connect(&mut protocol, now);
+
+    let content_map = ContentRegistry::new(HashMap::from([(
+        "block".to_string(),
+        vec!["air".to_string()],
+    )]));
+    let rules = Rules {
+        pvp: true,
+        ..Default::default()
+    };
+    let tags = HashMap::from([("name".to_string(), "Frozen Forest".to_string())]);
+    let map_info = MapInfo::new(tags, &Map::new(4, 3));
+    let (stream_type, data) = write_packet_data(Packet::WorldStream(Box::new(World {
+        rules: rules.clone(),
+        map_info: map_info.clone(),
+        wave: 1,
+        wave_time: 0.0,
+        tick: 0.0,
+        seed0: 0,
+        seed1: 0,
+        id: 7,
+        player: player(true, 2),
+        content_map: content_map.clone(),
+        map: Map::new(4, 3),
+        team_plans: HashMap::from([(
+            1,
+            vec![TeamPlan {
+                x: 1,
+                y: 2,
+                rotation: 0,
+                block_id: 0,
+                config: Object::Null,
+            }],
+        )]),
+        markers: HashMap::from([(
+            0,
+            Marker {
+                kind: MarkerKind::Text,
+                pos: Some(Vec2 { x: 2.0, y: 1.0 }),
+                text: Some("Go here".to_string()),
+                ..Default::default()
+            },
+        )]),
+        custom_chunks: HashMap::new(),
+    })))
+    .unwrap();
+
+    receive_stream(&mut protocol, now, stream_type, &data);
+
+    assert!(matches!(
+        protocol.poll_event(),
+        Some(ClientEvent::WorldLoaded)
+    ));
+    assert!(protocol.poll_event().is_none());
+    assert_eq!(protocol.state.player_id, 7);
+    assert_eq!(protocol.state.team, 2);
+    assert!(protocol.state.admin);
+    assert_eq!(
+        protocol.state.map_info.name.as_deref(),
+        Some("Frozen Forest")
+    );
+    assert_eq!(protocol.state.map_info, map_info);
+    assert_eq!(protocol.state.team_plans[&1][0].x, 1);
+    assert_eq!(protocol.state.markers[&0].text.as_deref(), Some("Go here"));
+    assert_eq!(protocol.state.map, Map::new(4, 3));
+    assert_eq!(protocol.content_map(), &Some(content_map));
+    assert_eq!(protocol.state.rules, rules);
+
+    let update = write_packet(Packet::SetRulesCall {
+        rules: r#"{"bannedBlocks":["router"],"buildCostMultiplier":2}"#.to_string(),
+    })
+    .unwrap();
+    protocol.receive_tcp(now, &update);
+    assert!(protocol.state.rules.is_banned_block("router"));
+    assert_eq!(protocol.state.rules.build_cost_multiplier, 2.0);
+    assert!(!protocol.state.rules.pvp);
+}
+
+fn unloader(item_id: i16) -> Block {
+    Block {
+        name: "unloader".to_string(),
+        block_type: "Unloader".to_string(),
+        revision: 1,
+        base: BaseBlockData {
+            health: 70.0,
+            rotation: 0,
+            version: 3,
+            legacy: false,
+            on: Some(1),
+            team: 1,
+            module_bitmask: 1,
+            items: Some(HashMap::new()),
+            liquids: None,
+            power: None,
+            efficiency: Some(255),
+            optional_efficiency: Some(0),
+            visible_flags: None,
+        },
+        specific: Some(SpecificBlockData::Unloader { item_id }),
+    }
+}
+
+/// Loads a world with an unloader at (2, 1)
+fn load_world(protocol: &mut ClientProtocol, now: Instant) {
+    let mut map = Map::new(4, 3);
+    map.set_block_id(2, 1, 1);
+    map.set_block(2, 1, unloader(-1));
+    let content_map = ContentRegistry::new(HashMap::from([
+        (
+            "block".to_string(),
+            vec!["air".to_string(), "unloader".to_string()],
+        ),
+        (
+            "item".to_string(),
+            vec!["copper".to_string(), "lead".to_string()],
+        ),
+    ]));
+    let (stream_type, data) = write_packet_data(Packet::WorldStream(Box::new(World {
+        rules: Rules::default(),
+        map_info: MapInfo::new(HashMap::new(), &map),
+        wave: 1,
+        wave_time: 0.0,
+        tick: 0.0,
+        seed0: 0,
+        seed1: 0,
+        id: 7,
+        player: player(false, 1),
+        content_map,
+        map,
+        team_plans: HashMap::new(),
+        markers: HashMap::new(),
+        custom_chunks: HashMap::new(),
+    })))
+    .unwrap();
+    receive_stream(protocol, now, stream_type, &data);
+    assert!(matches!(
+        protocol.poll_event(),
+        Some(ClientEvent::WorldLoaded)
+    ));
+}
+
+#[test]
+fn block_snapshot() {
+    let mut protocol = ClientProtocol::new("bot".to_string(), None);
+    let now = Instant::now();
+    connect(&mut protocol, now);
+    load_world(&mut protocol, now);
+
+    // An unloader switched to item 5 with 3 coal inside, as sent by a build 146 server.
+    // Its data is written for building revision 1, not the base data version 3.
+    #[rustfmt::skip]
+    let data = vec![
+        0x00, 0x02, 0x00, 0x01, // tile
+        0x00, 0x01, // block id
+        0x42, 0x8c, 0x00, 0x00, 0x80, 0x01, 0x03, 0x01, 0x01, // health, rotation, team, version, on, modules
+        0x00, 0x01, 0x00, 0x05, 0x00, 0x00, 0x00, 0x03, // items
+        0xff, 0x00, // efficiency
+        0x00, 0x05, // sort item
+    ];
+    protocol.receive_tcp(
+        now,
+        &write_packet(Packet::BlockSnapshot { amount: 1, data }).unwrap(),
+    );
+
+    let mut expected = unloader(5);
+    expected.base.items = Some(HashMap::from([(5, 3)]));
+    match protocol.poll_event() {
+        Some(ClientEvent::BlockChanged { tile, old, new }) => {
+            assert_eq!(tile, Tile { x: 2, y: 1 });
+            assert_eq!(old.as_deref(), Some(&unloader(-1)));
+            assert_eq!(new.as_deref(), Some(&expected));
+        }
+        event => panic!("unexpected event {event:?}"),
+    }
+    let block = protocol.state.map.get(2, 1).unwrap().block.clone().unwrap();
+    assert_eq!(block, expected);
+}
+
+/// A snapshot entry for an unloader set to `item`, as a build 146 server writes it
+fn unloader_snapshot(x: u8, y: u8, item: u8) -> Vec<u8> {
+    #[rustfmt::skip]
+    let data = vec![
+        0x00, x, 0x00, y, // tile
+        0x00, 0x01, // block id
+        0x42, 0x8c, 0x00, 0x00, 0x80, 0x01, 0x03, 0x01, 0x01, // health, rotation, team, version, on, modules
+        0x00, 0x00, // items
+        0xff, 0x00, // efficiency
+        0x00, item, // sort item
+    ];
+    data
+}
+
+#[test]
+fn block_snapshot_for_placed_building() {
+    let mut protocol = ClientProtocol::new("bot".to_string(), None);
+    let now = Instant::now();
+    connect(&mut protocol, now);
+    load_world(&mut protocol, now);
+
+    let builder = Unit {
+        unit_type: 2,
+        id: 12,
+    };
+    for packet in [
+        Packet::BeginPlace {
+            unit: builder.clone(),
+            result: 1,
+            team: 1,
+            x: 1,
+            y: 1,
+            rotation: 0,
+        },
+        Packet::ConstructFinish {
+            tile: Tile { x: 1, y: 1 },
+            block: 1,
+            builder,
+            rotation: 0,
+            team: 1,
+            config: Object::Null,
+        },
+    ] {
+        protocol.receive_tcp(now, &write_packet(packet).unwrap());
+    }
+    while protocol.poll_event().is_some() {}
+
+    // The placed building and the one of the world are decoded, the entry for the empty
+    // tile after them stops decoding
+    let mut data = unloader_snapshot(1, 1, 4);
+    data.extend(unloader_snapshot(2, 1, 5));
+    data.extend(unloader_snapshot(0, 0, 6));
+    protocol.receive_tcp(
+        now,
+        &write_packet(Packet::BlockSnapshot { amount: 3, data }).unwrap(),
+    );
+
+    for (x, item) in [(1, 4), (2, 5)] {
+        let mut expected = unloader(item);
+        expected.base.items = Some(HashMap::new());
+        match protocol.poll_event() {
+            Some(ClientEvent::BlockChanged { tile, new, .. }) => {
+                assert_eq!(tile, Tile { x, y: 1 });
+                assert_eq!(new.as_deref(), Some(&expected));
+            }
+            event => panic!("unexpected event {event:?}"),
+        }
+        let block = protocol.state.map.get(x as u32, 1).unwrap().block.clone();
+        assert_eq!(block, Some(expected));
+    }
+    assert!(protocol.poll_event().is_none());
+}
+
+#[test]
+fn block_snapshot_for_missing_building() {
+    let mut protocol = ClientProtocol::new("bot".to_string(), None);
+    let now = Instant::now();
+    connect(&mut protocol, now);
+    load_world(&mut protocol, now);
+
+    let mut data = vec![0x00, 0x01, 0x00, 0x01, 0x00, 0x01];
+    write_block(&mut data, unloader(5)).unwrap();
+    protocol.receive_tcp(
+        now,
+        &write_packet(Packet::BlockSnapshot { amount: 1, data }).unwrap(),
+    );
+
+    assert!(protocol.poll_event().is_none());
+    assert_eq!(
+        protocol.state.map.get(2, 1).unwrap().block,
+        Some(unloader(-1))
+    );
+}
+
+fn state_snapshot(wave: u32, game_over: bool) -> Vec<u8> {
+    write_packet(Packet::StateSnapshot {
+        wave_time: 600.0,
+        wave,
+        enemies: 3,
+        paused: false,
+        game_over,
+        time_data: 0,
+        tps: 60,
+        rand0: 0,
+        rand1: 0,
+        cores: HashMap::from([(1, HashMap::from([(0, 500), (1, 20)]))]),
+    })
+    .unwrap()
+}
+
+#[test]
+fn state_snapshots() {
+    let mut protocol = ClientProtocol::new("bot".to_string(), None);
+    let now = Instant::now();
+    connect(&mut protocol, now);
+    load_world(&mut protocol, now);
+
+    protocol.receive_tcp(now, &state_snapshot(1, false));
+    assert!(matches!(
+        protocol.poll_event(),
+        Some(ClientEvent::StateUpdated)
+    ));
+    assert!(protocol.poll_event().is_none());
+    assert_eq!(protocol.state.enemies, 3);
+    assert_eq!(protocol.state.wave_countdown, 600.0);
+    assert_eq!(protocol.state.tps, 60);
+    assert_eq!(protocol.state.cores[&1]["copper"], 500);
+    assert_eq!(protocol.state.cores[&1]["lead"], 20);
+
+    protocol.receive_tcp(now, &state_snapshot(2, false));
+    assert!(matches!(
+        protocol.poll_event(),
+        Some(ClientEvent::WaveChanged { wave: 2 })
+    ));
+    assert!(matches!(
+        protocol.poll_event(),
+        Some(ClientEvent::StateUpdated)
+    ));
+
+    protocol.receive_tcp(
+        now,
+        &write_packet(Packet::GameOverCall { winner: 2 }).unwrap(),
+    );
+    protocol.receive_tcp(now, &state_snapshot(2, true));
+    assert!(matches!(
+        protocol.poll_event(),
+        Some(ClientEvent::GameOver { winner: Some(2) })
+    ));
+    assert!(matches!(
+        protocol.poll_event(),
+        Some(ClientEvent::StateUpdated)
+    ));
+    assert!(protocol.poll_event().is_none());
+    assert!(protocol.state.game_over);
+}
+
+#[test]
+fn map_changed() {
+    let mut protocol = ClientProtocol::new("bot".to_string(), None);
+    let now = Instant::now();
+    connect(&mut protocol, now);
+    load_world(&mut protocol, now);
+    assert!(protocol.poll_event().is_none());
+
+    load_world(&mut protocol, now);
+    match protocol.poll_event() {
+        Some(ClientEvent::MapChanged { map_info }) => assert_eq!(map_info.width, 4),
+        event => panic!("unexpected event {event:?}"),
+    }
+}
+
+#[test]
+fn world_reload() {
+    let mut protocol = ClientProtocol::new("bot".to_string(), None);
+    let now = Instant::now();
+    connect(&mut protocol, now);
+    load_world(&mut protocol, now);
+    transmits(&mut protocol);
+    protocol.state.x = 16.0;
+    protocol.state.y = 24.0;
+    protocol.state.players.insert(3, Some("other".to_string()));
+
+    // A stream of the old world that never finishes
+    protocol.receive_tcp(
+        now,
+        &write_packet(Packet::StreamBegin {
+            id: 5,
+            total: 100,
+            stream_type: 2,
+        })
+        .unwrap(),
+    );
+    protocol.receive_tcp(now, &write_packet(Packet::WorldDataBeginCall).unwrap());
+    assert_eq!(protocol.state.x, -1.0);
+    assert_eq!(protocol.state.y, -1.0);
+    assert!(protocol.state.players.is_empty());
+    assert_eq!(protocol.state.map.width, 0);
+
+    // No snapshots while loading, but keepalives continue
+    protocol.handle_timeout(now + Duration::from_secs(5));
+    let sent = transmits(&mut protocol);
+    assert!(!sent.is_empty());
+    assert!(
+        sent.iter()
+            .all(|(_, packet)| matches!(packet, AnyPacket::Framework(FrameworkPacket::KeepAlive)))
+    );
+
+    load_world(&mut protocol, now + Duration::from_secs(5));
+    assert!(matches!(
+        protocol.poll_event(),
+        Some(ClientEvent::MapChanged { .. })
+    ));
+    assert_eq!(protocol.state.map.width, 4);
+    let sent = transmits(&mut protocol);
+    assert!(matches!(
+        sent[..],
+        [(true, AnyPacket::Regular(Packet::ConnectCallConfirm))]
+    ));
+    protocol.handle_timeout(now + Duration::from_millis(5200));
+    assert!(
+        transmits(&mut protocol)
+            .iter()
+            .any(|(_, packet)| matches!(packet, AnyPacket::Regular(Packet::ClientSnapshot { .. })))
+    );
+}
+
+#[test]
+fn redirect() {
+    let mut protocol = ClientProtocol::new("bot".to_string(), None);
+    let now = Instant::now();
+    connect(&mut protocol, now);
+    load_world(&mut protocol, now);
+
+    let redirect = write_packet(Packet::ConnectCall {
+        ip: "10.0.0.2".to_string(),
+        port: 6568,
+    })
+    .unwrap();
+    protocol.receive_tcp(now, &redirect);
+    match protocol.poll_event() {
+        Some(ClientEvent::Disconnected {
+            cause: DisconnectCause::Redirected { ip, port },
+        }) => assert_eq!((ip.as_str(), port), ("10.0.0.2", 6568)),
+        event => panic!("unexpected event {event:?}"),
+    }
+    assert!(protocol.is_closed());
+}
+
+#[test]
+fn spawn_and_death() {
+    let mut protocol = ClientProtocol::new("bot".to_string(), None);
+    let now = Instant::now();
+    connect(&mut protocol, now);
+    load_world(&mut protocol, now);
+
+    let spawn = write_packet(Packet::SpawnCall {
+        tile_x: 3,
+        tile_y: 4,
+        entity: 7,
+    })
+    .unwrap();
+    protocol.receive_tcp(now, &spawn);
+    assert!(matches!(
+        protocol.poll_event(),
+        Some(ClientEvent::Spawned {
+            tile: Tile { x: 3, y: 4 }
+        })
+    ));
+    assert!(matches!(
+        protocol.poll_event(),
+        Some(ClientEvent::PositionChanged { x: 24.0, y: 32.0 })
+    ));
+
+    protocol.state.unit = Unit {
+        unit_type: 2,
+        id: 42,
+    };
+    protocol.receive_tcp(
+        now,
+        &write_packet(Packet::UnitDeathCall { id: 41 }).unwrap(),
+    );
+    assert!(protocol.poll_event().is_none());
+    protocol.receive_tcp(
+        now,
+        &write_packet(Packet::UnitDeathCall { id: 42 }).unwrap(),
+    );
+    assert!(matches!(protocol.poll_event(), Some(ClientEvent::Died)));
+}
+
+#[test]
+fn players_join_and_leave() {
+    let mut protocol = ClientProtocol::new("bot".to_string(), None);
+    let now = Instant::now();
+    connect(&mut protocol, now);
+    load_world(&mut protocol, now);
+
+    let other = FullUnit::Player {
+        revision: None,
+        admin: false,
+        boosting: false,
+        color: 0xffffffff,
+        mouse_x: 0.0,
+        mouse_y: 0.0,
+        name: Some("other".to_string()),
+        shooting: false,
+        team: 1,
+        typing: false,
+        unit: Unit {
+            unit_type: 0,
+            id: 0,
+        },
+        x: 0.0,
+        y: 0.0,
+    };
+    let snapshot = write_packet(Packet::EntitySnapshot {
+        units: HashMap::from([(9, other)]),
+    })
+    .unwrap();
+    protocol.receive_tcp(now, &snapshot);
+    protocol.receive_tcp(now, &snapshot);
+    match protocol.poll_event() {
+        Some(ClientEvent::PlayerJoined { id, name }) => {
+            assert_eq!(id, 9);
+            assert_eq!(name.as_deref(), Some("other"));
+        }
+        event => panic!("unexpected event {event:?}"),
+    }
+    assert!(matches!(
+        protocol.poll_event(),
+        Some(ClientEvent::UnitSnapshot)
+    ));
+    assert!(matches!(
+        protocol.poll_event(),
+        Some(ClientEvent::UnitSnapshot)
+    ));
+
+    protocol.receive_tcp(
+        now,
+        &write_packet(Packet::PlayerDisconnectCall { player: 9 }).unwrap(),
+    );
+    assert!(matches!(
+        protocol.poll_event(),
+        Some(ClientEvent::PlayerLeft { id: 9, .. })
+    ));
+    assert!(protocol.state.players.is_empty());
+}
+
+#[test]
+fn kicked_and_disconnected() {
+    let mut protocol = ClientProtocol::new("bot".to_string(), None);
+    let now = Instant::now();
+    connect(&mut protocol, now);
+
+    let kick = write_packet(Packet::KickCall2 {
+        reason: KickReason::Banned,
+    })
+    .unwrap();
+    protocol.receive_tcp(now, &kick);
+    protocol.handle_close(None);
+    assert!(matches!(
+        protocol.poll_event(),
+        Some(ClientEvent::Kicked {
+            reason: Kick::Reason(KickReason::Banned)
+        })
+    ));
+    assert!(matches!(
+        protocol.poll_event(),
+        Some(ClientEvent::Disconnected {
+            cause: DisconnectCause::Kicked(Kick::Reason(KickReason::Banned))
+        })
+    ));
+
+    // No snapshots are sent after the connection closed
+    protocol.handle_timeout(now + Duration::from_secs(1));
+    assert!(protocol.poll_transmit().is_none());
+
+    let mut protocol = ClientProtocol::new("bot".to_string(), None);
+    connect(&mut protocol, now);
+    protocol.handle_close(Some("connection reset".to_string()));
+    match protocol.poll_event() {
+        Some(ClientEvent::Disconnected {
+            cause: DisconnectCause::Error(error),
+        }) => assert_eq!(error, "connection reset"),
+        event => panic!("unexpected event {event:?}"),
+    }
+}
+
+#[test]
+fn times_out_without_data() {
+    let mut protocol = ClientProtocol::new("bot".to_string(), None);
+    protocol.set_timeout(Duration::from_secs(5));
+    let now = Instant::now();
+    connect(&mut protocol, now);
+
+    // Keepalives from the server push the deadline back
+    let later = now + Duration::from_secs(4);
+    protocol.receive_tcp(later, &framework_frame(FrameworkPacket::KeepAlive));
+    protocol.handle_timeout(now + Duration::from_secs(6));
+    transmits(&mut protocol);
+    assert!(protocol.poll_event().is_none());
+    assert!(!protocol.is_closed());
+
+    protocol.handle_timeout(later + Duration::from_secs(5));
+    assert!(matches!(
+        protocol.poll_event(),
+        Some(ClientEvent::Disconnected {
+            cause: DisconnectCause::TimedOut
+        })
+    ));
+    assert!(protocol.is_closed());
+    assert_eq!(protocol.poll_timeout(), None);
+
+    // Closing the sockets afterwards does not report a second disconnect
+    protocol.handle_close(None);
+    assert!(protocol.poll_event().is_none());
+}
+
+#[test]
+fn times_out_without_any_frame() {
+    let mut protocol = ClientProtocol::new("bot".to_string(), None);
+    protocol.set_timeout(Duration::from_secs(5));
+    assert_eq!(protocol.poll_timeout(), None);
+
+    let now = Instant::now();
+    protocol.handle_open(now);
+    assert_eq!(protocol.poll_timeout(), Some(now + Duration::from_secs(5)));
+    protocol.handle_timeout(now + Duration::from_secs(5));
+    assert!(matches!(
+        protocol.poll_event(),
+        Some(ClientEvent::Disconnected {
+            cause: DisconnectCause::TimedOut
+        })
+    ));
+}
+
+#[test]
+fn kicks_without_reconnect() {
+    for reason in [
+        KickReason::Kick,
+        KickReason::RecentKick,
+        KickReason::Vote,
+        KickReason::Banned,
+        KickReason::Whitelist,
+        KickReason::ClientOutdated,
+        KickReason::ServerOutdated,
+        KickReason::CustomClient,
+        KickReason::TypeMismatch,
+        KickReason::NameEmpty,
+    ] {
+        let cause = DisconnectCause::Kicked(Kick::Reason(reason));
+        assert!(!cause.allows_reconnect(), "{reason:?}");
+    }
+}
+
+#[test]
+fn kicks_with_reconnect() {
+    for reason in [
+        KickReason::GameOver,
+        KickReason::NameInUse,
+        KickReason::IdInUse,
+        KickReason::ServerClose,
+        KickReason::PlayerLimit,
+        KickReason::ServerRestarting,
+    ] {
+        let cause = DisconnectCause::Kicked(Kick::Reason(reason));
+        assert!(cause.allows_reconnect(), "{reason:?}");
+    }
+}
+
+#[test]
+fn disconnect() {
+    let mut protocol = ClientProtocol::new("bot".to_string(), None);
+    let now = Instant::now();
+    connect(&mut protocol, now);
+
+    protocol.disconnect();
+    let cause = match protocol.poll_event() {
+        Some(ClientEvent::Disconnected { cause }) => cause,
+        event => panic!("unexpected event {event:?}"),
+    };
+    assert_eq!(cause, DisconnectCause::Requested);
+    assert!(!cause.allows_reconnect());
+    assert!(!DisconnectCause::Kicked(Kick::Reason(KickReason::Banned)).allows_reconnect());
+    assert!(DisconnectCause::Kicked(Kick::Reason(KickReason::ServerRestarting)).allows_reconnect());
+    assert!(DisconnectCause::TimedOut.allows_reconnect());
+
+    // Snapshots stop and frames are ignored
+    protocol.handle_timeout(now + Duration::from_secs(1));
+    assert!(protocol.poll_transmit().is_none());
+    protocol.receive_tcp(now, &framework_frame(FrameworkPacket::RegisterUDP(7)));
+    assert!(protocol.poll_transmit().is_none());
+    assert!(protocol.poll_event().is_none());
+}
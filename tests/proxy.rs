@@ -0,0 +1,120 @@
+use mindustry_net::client::{Client, ClientEvent, Subscription};
+use mindustry_net::packet::Packet;
+use mindustry_net::save_io::Map;
+use mindustry_net::server::Server;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader};
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time;
+
+struct KillOnDrop(Child);
+impl Drop for KillOnDrop {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+#[tokio::test]
+async fn proxy_forwards_and_decodes() {
+    let mut content_map = HashMap::new();
+    content_map.insert("block".to_string(), vec!["air".to_string()]);
+    let mut server = Server::bind("127.0.0.1:0", Map::new(10, 10), content_map.into())
+        .await
+        .unwrap();
+    let upstream = server.local_addr().unwrap();
+
+    let mut proxy = KillOnDrop(
+        Command::new(env!("CARGO_BIN_EXE_mindustry-proxy"))
+            .args(["127.0.0.1:0", &upstream.to_string(), "--dump", "73"])
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap(),
+    );
+    let stdout = BufReader::new(proxy.0.stdout.take().unwrap());
+    let (line_sender, mut lines) = mpsc::unbounded_channel();
+    std::thread::spawn(move || {
+        for line in stdout.lines().map_while(Result::ok) {
+            if line_sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    let banner = lines.recv().await.unwrap();
+    let address = banner
+        .strip_prefix("Proxying ")
+        .and_then(|rest| rest.split(' ').next())
+        .unwrap()
+        .to_string();
+
+    let mut client = Client::new(address, "bot".to_string()).await.unwrap();
+    let mut receiver = client.subscribe();
+    tokio::spawn(async move { client.handle_packets().await });
+
+    let mut connection = server.accept().await.unwrap();
+    assert_eq!(connection.name, "bot");
+    let next_event = async |receiver: &mut Subscription| {
+        time::timeout(Duration::from_secs(5), receiver.recv())
+            .await
+            .unwrap()
+            .unwrap()
+    };
+    assert!(matches!(
+        next_event(&mut receiver).await,
+        ClientEvent::Connected
+    ));
+    assert!(matches!(
+        next_event(&mut receiver).await,
+        ClientEvent::WorldLoaded
+    ));
+    connection
+        .send(Packet::SendMessageCall2 {
+            message: "hello".to_string(),
+            unformatted: None,
+            sender: 0,
+        })
+        .await
+        .unwrap();
+    assert!(matches!(
+        next_event(&mut receiver).await,
+        ClientEvent::ChatMessage { .. }
+    ));
+
+    let mut output = vec![];
+    while !output
+        .iter()
+        .any(|line: &String| line.contains("SendMessageCall2"))
+    {
+        let line = time::timeout(Duration::from_secs(5), lines.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        output.push(line);
+    }
+    let output = output.join("\n");
+    assert!(output.contains("S->C tcp 254 RegisterTCP"));
+    assert!(output.contains("C->S udp 254 RegisterUDP"));
+    assert!(output.contains("C->S tcp   3 Connect"));
+    assert!(output.contains("stream 0: WorldStream"));
+    assert!(output.contains("S->C tcp  73 SendMessageCall2"));
+    assert!(!output.contains("!!"));
+}
+
+#[test]
+fn proxy_exits_when_bind_fails() {
+    let taken = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = taken.local_addr().unwrap().to_string();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_mindustry-proxy"))
+        .args([&address, "127.0.0.1:6567"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.starts_with(&format!("Failed to listen on {address}: ")),
+        "{stderr}"
+    );
+}
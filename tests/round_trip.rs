@@ -0,0 +1,1513 @@
+use mindustry_net::arc_types::Point2;
+use mindustry_net::block_io::{
+    BaseBlockData, Block, ConveyorItem, DirectionalItemBuffer, SpecificBlockData,
+};
+use mindustry_net::content::ContentRegistry;
+use mindustry_net::packet::{AnyPacket, Packet, PacketError, World, parse_packet, write_packet};
+use mindustry_net::rules::Rules;
+use mindustry_net::save_io::{Map, MapInfo, Marker, MarkerKind, TeamPlan, read_markers};
+use mindustry_net::type_io::{
+    AdminAction, Content, Items, KickReason, Object, Reader, Tile, TraceInfo, Unit, Vec2,
+};
+use mindustry_net::unit_io::{
+    AttackTarget, CommandTarget, Controller, FullUnit, Payload, Plan, PlayerInfo, read_plan,
+    write_plan,
+};
+use mindustry_net::version::ProtocolVersion;
+use std::collections::HashMap;
+
+fn content_map() -> ContentRegistry {
+    let mut content_map = HashMap::new();
+    content_map.insert(
+        "block".to_string(),
+        vec![
+            "air".to_string(),
+            "conveyor".to_string(),
+            "sorter".to_string(),
+            "core-shard".to_string(),
+        ],
+    );
+    content_map.insert("item".to_string(), vec!["copper".to_string()]);
+    content_map.into()
+}
+
+/// Encodes the packet, decodes the resulting frame and checks that nothing changed
+fn round_trip(packet: Packet) {
+    let frame = write_packet(packet.clone()).unwrap();
+    let length = u16::from_be_bytes([frame[0], frame[1]]) as usize;
+    assert_eq!(length, frame.len() - 2);
+
+    match parse_packet(
+        frame[2..].to_vec(),
+        &Some(content_map()),
+        ProtocolVersion::default(),
+    )
+    .unwrap()
+    {
+        AnyPacket::Regular(parsed) => assert_eq!(parsed, packet),
+        AnyPacket::Framework(parsed) => panic!("Expected {packet:?}, got {parsed:?}"),
+    }
+}
+
+fn block(name: &str, block_type: &str, specific: Option<SpecificBlockData>) -> Block {
+    let mut items = HashMap::new();
+    items.insert(0, 25);
+
+    Block {
+        name: name.to_string(),
+        block_type: block_type.to_string(),
+        revision: 1,
+        base: BaseBlockData {
+            health: 45.0,
+            rotation: 2,
+            version: 3,
+            legacy: false,
+            on: Some(1),
+            team: 1,
+            module_bitmask: 1 | 8,
+            items: Some(items),
+            liquids: None,
+            power: None,
+            efficiency: Some(255),
+            optional_efficiency: Some(0),
+            visible_flags: None,
+        },
+        specific,
+    }
+}
+
+fn generic_unit(
+    revision: Option<i16>,
+    controller: Controller,
+    payloads: Option<Vec<Payload>>,
+) -> FullUnit {
+    FullUnit::GenericUnit {
+        class_id: if payloads.is_some() { 5 } else { 0 },
+        revision,
+        abilities: vec![1.0],
+        ammo: 10.0,
+        building: None,
+        base_rotation: None,
+        controller: Box::new(controller),
+        elevation: 1.0,
+        flag: 0.0,
+        health: 200.0,
+        shooting: false,
+        lifetime: None,
+        mining_position: Tile { x: -1, y: -1 },
+        mounts: vec![],
+        payloads,
+        plans: vec![],
+        rotation: 90.0,
+        shield: 0.0,
+        spawned_by_core: true,
+        items: Items { id: 0, count: 0 },
+        statuses: vec![],
+        team: 1,
+        time: None,
+        unit_type: 1,
+        upgrade_building: 0,
+        velocity: Vec2 { x: 0.5, y: -0.5 },
+        x: 80.0,
+        y: 96.0,
+    }
+}
+
+#[test]
+fn stream_begin() {
+    round_trip(Packet::StreamBegin {
+        id: 7,
+        total: 1000,
+        stream_type: 2,
+    });
+}
+
+#[test]
+fn stream_chunk() {
+    round_trip(Packet::StreamChunk {
+        id: 7,
+        data: vec![1, 2, 3],
+    });
+}
+
+#[test]
+fn connect() {
+    round_trip(Packet::Connect {
+        version: 146,
+        client: "official".to_string(),
+        name: "Player".to_string(),
+        lang: "en".to_string(),
+        usid: "USIGAAAAAAA=".to_string(),
+        uuid: "UUIGAAAAAAA=".to_string(),
+        mobile: false,
+        color: vec![0xff, 0xa1, 0x08, 0xff],
+        mods: vec!["example-mod".to_string()],
+    });
+}
+
+#[test]
+fn admin_request_call() {
+    round_trip(Packet::AdminRequestCall {
+        other: 101,
+        action: AdminAction::Trace,
+        params: Object::Int(3),
+    });
+}
+
+#[test]
+fn announce_call() {
+    round_trip(Packet::AnnounceCall {
+        message: "text1".to_string(),
+    });
+}
+
+#[test]
+fn assembler_drone_spawned_call() {
+    round_trip(Packet::AssemblerDroneSpawnedCall {
+        tile: Tile { x: 1, y: 2 },
+        id: 102,
+    });
+}
+
+#[test]
+fn assembler_unit_spawned_call() {
+    round_trip(Packet::AssemblerUnitSpawnedCall {
+        tile: Tile { x: 1, y: 2 },
+    });
+}
+
+#[test]
+fn auto_door_toggle_call() {
+    round_trip(Packet::AutoDoorToggleCall {
+        tile: Tile { x: 1, y: 2 },
+        open: true,
+    });
+}
+
+#[test]
+fn begin_break() {
+    round_trip(Packet::BeginBreak {
+        unit: Unit {
+            unit_type: 2,
+            id: 12,
+        },
+        team: 1,
+        x: 30,
+        y: 40,
+    });
+}
+
+#[test]
+fn begin_place() {
+    round_trip(Packet::BeginPlace {
+        unit: Unit {
+            unit_type: 2,
+            id: 12,
+        },
+        result: 257,
+        team: 1,
+        x: 30,
+        y: 40,
+        rotation: 3,
+    });
+}
+
+#[test]
+fn block_snapshot() {
+    round_trip(Packet::BlockSnapshot {
+        amount: 2,
+        data: vec![1, 2, 3],
+    });
+}
+
+#[test]
+fn build_destroyed_call() {
+    round_trip(Packet::BuildDestroyedCall {
+        build: Tile { x: 1, y: 2 },
+    });
+}
+
+#[test]
+fn build_health_update_call() {
+    round_trip(Packet::BuildHealthUpdateCall {
+        healths: vec![(Tile { x: 5, y: 6 }, 80.0)],
+    });
+}
+
+#[test]
+fn building_control_select_call() {
+    round_trip(Packet::BuildingControlSelectCall {
+        player: 101,
+        build: Tile { x: 2, y: 3 },
+    });
+}
+
+#[test]
+fn clear_items_call() {
+    round_trip(Packet::ClearItemsCall {
+        build: Tile { x: 1, y: 2 },
+    });
+}
+
+#[test]
+fn client_packet_reliable_call() {
+    round_trip(Packet::ClientPacketReliableCall {
+        packet_type: "text1".to_string(),
+        contents: "text2".to_string(),
+    });
+}
+
+#[test]
+fn client_packet_unreliable_call() {
+    round_trip(Packet::ClientPacketUnreliableCall {
+        packet_type: "text1".to_string(),
+        contents: "text2".to_string(),
+    });
+}
+
+#[test]
+fn client_snapshot() {
+    round_trip(Packet::ClientSnapshot {
+        snapshot_id: 5,
+        unit_id: 12,
+        dead: false,
+        x: 80.0,
+        y: 96.0,
+        pointer_x: 81.0,
+        pointer_y: 97.0,
+        rotation: 90.0,
+        base_rotation: 45.0,
+        x_velocity: 0.5,
+        y_velocity: -0.5,
+        mining_x: 10,
+        mining_y: 12,
+        boosting: false,
+        shooting: true,
+        chatting: false,
+        building: true,
+        plans: vec![Plan {
+            plan_type: 1,
+            position: Tile { x: 10, y: 11 },
+            block: None,
+            rotation: None,
+            has_config: None,
+            config: None,
+        }],
+        view_x: 8.0,
+        view_y: 16.0,
+        view_width: 1920.0,
+        view_height: 1080.0,
+    });
+}
+
+#[test]
+fn plan_types() {
+    for plan_type in 0..=u8::MAX {
+        let plan = if plan_type == 1 {
+            Plan {
+                plan_type,
+                position: Tile { x: 3, y: 4 },
+                block: None,
+                rotation: None,
+                has_config: None,
+                config: None,
+            }
+        } else {
+            Plan {
+                plan_type,
+                position: Tile { x: 3, y: 4 },
+                block: Some(2),
+                rotation: Some(3),
+                has_config: Some(true),
+                config: Some(Object::Int(7)),
+            }
+        };
+        let mut buf = vec![];
+        write_plan(&mut buf, plan.clone()).unwrap();
+        let mut reader = Reader::new(buf);
+        assert_eq!(read_plan(&mut reader).unwrap(), plan);
+        assert_eq!(reader.remaining(), 0);
+    }
+
+    // A build plan without its block cannot be encoded
+    let plan = Plan {
+        plan_type: 0,
+        position: Tile { x: 3, y: 4 },
+        block: None,
+        rotation: Some(0),
+        has_config: Some(false),
+        config: Some(Object::Null),
+    };
+    match write_plan(&mut vec![], plan) {
+        Err(PacketError::Encode { field, reason, .. }) => {
+            assert_eq!(field, "block");
+            assert_eq!(reason, "plan of type 0 without a block");
+        }
+        result => panic!("unexpected result {result:?}"),
+    }
+}
+
+#[test]
+fn command_building_call() {
+    round_trip(Packet::CommandBuildingCall {
+        player: 101,
+        buildings: vec![2, 3],
+        target: Vec2 { x: 1.5, y: -2.0 },
+    });
+}
+
+#[test]
+fn command_units_call() {
+    round_trip(Packet::CommandUnitsCall {
+        player: 101,
+        unit_ids: vec![2, 3],
+        build_target: Tile { x: 3, y: 4 },
+        unit_target: Unit {
+            unit_type: 2,
+            id: 204,
+        },
+        pos_target: Vec2 { x: 1.5, y: -2.0 },
+    });
+}
+
+#[test]
+fn connect_call() {
+    round_trip(Packet::ConnectCall {
+        ip: "text1".to_string(),
+        port: 102,
+    });
+}
+
+#[test]
+fn connect_call_confirm() {
+    round_trip(Packet::ConnectCallConfirm);
+}
+
+#[test]
+fn construct_finish() {
+    round_trip(Packet::ConstructFinish {
+        tile: Tile { x: 3, y: 4 },
+        block: 257,
+        builder: Unit {
+            unit_type: 2,
+            id: 12,
+        },
+        rotation: 1,
+        team: 1,
+        config: Object::Boolean(true),
+    });
+}
+
+#[test]
+fn create_bullet_call() {
+    round_trip(Packet::CreateBulletCall {
+        bullet_type: 11,
+        team: 2,
+        x: 3.5,
+        y: 4.5,
+        angle: 5.5,
+        damage: 6.5,
+        velocity_scale: 7.5,
+        lifetime_scale: 8.5,
+    });
+}
+
+#[test]
+fn create_weather_call() {
+    round_trip(Packet::CreateWeatherCall {
+        weather: 11,
+        intensity: 2.5,
+        duration: 3.5,
+        wind_x: 4.5,
+        wind_y: 5.5,
+    });
+}
+
+#[test]
+fn debug_status_client_call() {
+    round_trip(Packet::DebugStatusClientCall {
+        value: 101,
+        last_client_snapshot: 102,
+        snapshots_sent: 103,
+    });
+}
+
+#[test]
+fn debug_status_client_unreliable_call() {
+    round_trip(Packet::DebugStatusClientUnreliableCall {
+        value: 101,
+        last_client_snapshot: 102,
+        snapshots_sent: 103,
+    });
+}
+
+#[test]
+fn deconstruct_finish() {
+    round_trip(Packet::DeconstructFinish {
+        tile: Tile { x: 3, y: 4 },
+        block: 257,
+        builder: Unit {
+            unit_type: 2,
+            id: 12,
+        },
+    });
+}
+
+#[test]
+fn delete_plans_call() {
+    round_trip(Packet::DeletePlansCall {
+        player: 101,
+        positions: vec![Tile { x: 1, y: 2 }, Tile { x: 3, y: 4 }],
+    });
+}
+
+#[test]
+fn drop_item_call() {
+    round_trip(Packet::DropItemCall {
+        player: 101,
+        angle: 2.5,
+    });
+}
+
+#[test]
+fn effect_call() {
+    round_trip(Packet::EffectCall {
+        effect: 11,
+        x: 2.5,
+        y: 3.5,
+        rotation: 4.5,
+        color: 105,
+    });
+}
+
+#[test]
+fn effect_call2() {
+    round_trip(Packet::EffectCall2 {
+        effect: 11,
+        x: 2.5,
+        y: 3.5,
+        rotation: 4.5,
+        color: 105,
+        data: Object::Int(6),
+    });
+}
+
+#[test]
+fn effect_reliable_call() {
+    round_trip(Packet::EffectReliableCall {
+        effect: 11,
+        x: 2.5,
+        y: 3.5,
+        rotation: 4.5,
+        color: 105,
+    });
+}
+
+#[test]
+fn entity_snapshot() {
+    let mut units = HashMap::new();
+    units.insert(
+        3,
+        FullUnit::Player {
+            revision: None,
+            admin: true,
+            boosting: false,
+            color: 0xffa108ff,
+            mouse_x: 8.0,
+            mouse_y: 16.0,
+            name: Some("Player".to_string()),
+            shooting: false,
+            team: 1,
+            typing: false,
+            unit: Unit {
+                unit_type: 2,
+                id: 12,
+            },
+            x: 8.0,
+            y: 16.0,
+        },
+    );
+    round_trip(Packet::EntitySnapshot { units });
+}
+
+#[test]
+fn follow_up_menu_call() {
+    round_trip(Packet::FollowUpMenuCall {
+        menu_id: 101,
+        title: "text2".to_string(),
+        message: "text3".to_string(),
+        options: vec![
+            vec!["Yes".to_string(), "No".to_string()],
+            vec!["Close".to_string()],
+        ],
+    });
+}
+
+#[test]
+fn game_over_call() {
+    round_trip(Packet::GameOverCall { winner: 1 });
+}
+
+#[test]
+fn hidden_snapshot_call() {
+    round_trip(Packet::HiddenSnapshotCall { ids: vec![1, 2] });
+}
+
+#[test]
+fn hide_follow_up_menu_call() {
+    round_trip(Packet::HideFollowUpMenuCall { menu_id: 101 });
+}
+
+#[test]
+fn hide_hud_text_call() {
+    round_trip(Packet::HideHudTextCall);
+}
+
+#[test]
+fn info_message_call() {
+    round_trip(Packet::InfoMessageCall {
+        message: "text1".to_string(),
+    });
+}
+
+#[test]
+fn info_popup_call() {
+    round_trip(Packet::InfoPopupCall {
+        message: "text1".to_string(),
+        duration: 2.5,
+        align: 103,
+        top: 104,
+        left: 105,
+        bottom: 106,
+        right: 107,
+    });
+}
+
+#[test]
+fn info_popup_reliable_call() {
+    round_trip(Packet::InfoPopupReliableCall {
+        message: "text1".to_string(),
+        duration: 2.5,
+        align: 103,
+        top: 104,
+        left: 105,
+        bottom: 106,
+        right: 107,
+    });
+}
+
+#[test]
+fn info_toast_call() {
+    round_trip(Packet::InfoToastCall {
+        message: "text1".to_string(),
+        duration: 2.5,
+    });
+}
+
+#[test]
+fn kick_call() {
+    round_trip(Packet::KickCall {
+        reason: "Server restarting".to_string(),
+    });
+}
+
+#[test]
+fn kick_call2() {
+    round_trip(Packet::KickCall2 {
+        reason: KickReason::NameInUse,
+    });
+}
+
+#[test]
+fn label_call() {
+    round_trip(Packet::LabelCall {
+        message: "text1".to_string(),
+        duration: 2.5,
+        world_x: 3.5,
+        world_y: 4.5,
+    });
+}
+
+#[test]
+fn label_reliable_call() {
+    round_trip(Packet::LabelReliableCall {
+        message: "text1".to_string(),
+        duration: 2.5,
+        world_x: 3.5,
+        world_y: 4.5,
+    });
+}
+
+#[test]
+fn logic_explosion_call() {
+    round_trip(Packet::LogicExplosionCall {
+        team: 1,
+        x: 2.5,
+        y: 3.5,
+        radius: 4.5,
+        damage: 5.5,
+        air: true,
+        ground: true,
+        pierce: true,
+    });
+}
+
+#[test]
+fn menu_call() {
+    round_trip(Packet::MenuCall {
+        menu_id: 101,
+        title: "text2".to_string(),
+        message: "text3".to_string(),
+        options: vec![
+            vec!["Yes".to_string(), "No".to_string()],
+            vec!["Close".to_string()],
+        ],
+    });
+}
+
+#[test]
+fn menu_choose_call() {
+    round_trip(Packet::MenuChooseCall {
+        player: 101,
+        menu_id: 102,
+        option: 103,
+    });
+}
+
+#[test]
+fn objective_completed_call() {
+    round_trip(Packet::ObjectiveCompletedCall {
+        flags_removed: vec!["a".to_string(), "b".to_string()],
+        flags_added: vec!["a".to_string(), "b".to_string()],
+    });
+}
+
+#[test]
+fn open_uri_call() {
+    round_trip(Packet::OpenUriCall {
+        uri: "text1".to_string(),
+    });
+}
+
+#[test]
+fn payload_dropped_call() {
+    round_trip(Packet::PayloadDroppedCall {
+        unit: Unit {
+            unit_type: 2,
+            id: 201,
+        },
+        x: 2.5,
+        y: 3.5,
+    });
+}
+
+#[test]
+fn picked_build_payload_call() {
+    round_trip(Packet::PickedBuildPayloadCall {
+        unit: Unit {
+            unit_type: 2,
+            id: 201,
+        },
+        build: Tile { x: 2, y: 3 },
+        on_ground: true,
+    });
+}
+
+#[test]
+fn picked_unit_payload_call() {
+    round_trip(Packet::PickedUnitPayloadCall {
+        unit: Unit {
+            unit_type: 2,
+            id: 201,
+        },
+        target: Unit {
+            unit_type: 2,
+            id: 202,
+        },
+    });
+}
+
+#[test]
+fn ping_call() {
+    round_trip(Packet::PingCall {
+        time: 1700000000001,
+    });
+}
+
+#[test]
+fn ping_response_call() {
+    round_trip(Packet::PingResponseCall {
+        time: 1700000000001,
+    });
+}
+
+#[test]
+fn player_disconnect_call() {
+    round_trip(Packet::PlayerDisconnectCall { player: 101 });
+}
+
+#[test]
+fn spawn_call() {
+    round_trip(Packet::SpawnCall {
+        tile_x: 10,
+        tile_y: 11,
+        entity: 3,
+    });
+}
+
+#[test]
+fn remove_queue_block_call() {
+    round_trip(Packet::RemoveQueueBlockCall {
+        x: 101,
+        y: 102,
+        breaking: true,
+    });
+}
+
+#[test]
+fn remove_tile_call() {
+    round_trip(Packet::RemoveTileCall {
+        tile: Tile { x: 1, y: 2 },
+    });
+}
+
+#[test]
+fn remove_world_label_call() {
+    round_trip(Packet::RemoveWorldLabelCall { id: 101 });
+}
+
+#[test]
+fn request_build_payload_call() {
+    round_trip(Packet::RequestBuildPayloadCall {
+        build: Tile { x: 1, y: 2 },
+    });
+}
+
+#[test]
+fn request_debug_status_call() {
+    round_trip(Packet::RequestDebugStatusCall);
+}
+
+#[test]
+fn request_drop_payload_call() {
+    round_trip(Packet::RequestDropPayloadCall { x: 1.5, y: 2.5 });
+}
+
+#[test]
+fn request_item_call() {
+    round_trip(Packet::RequestItemCall {
+        player: 101,
+        build: Tile { x: 2, y: 3 },
+        item: 13,
+        amount: 104,
+    });
+}
+
+#[test]
+fn request_unit_payload_call() {
+    round_trip(Packet::RequestUnitPayloadCall {
+        target: Unit {
+            unit_type: 2,
+            id: 201,
+        },
+    });
+}
+
+#[test]
+fn researched_call() {
+    round_trip(Packet::ResearchedCall {
+        content: Content {
+            content_type: 1,
+            id: 42,
+        },
+    });
+}
+
+#[test]
+fn rotate_block_call() {
+    round_trip(Packet::RotateBlockCall {
+        entity: 3,
+        tile: Tile { x: 5, y: 6 },
+        rotation: 1,
+    });
+}
+
+#[test]
+fn sector_capture_call() {
+    round_trip(Packet::SectorCaptureCall);
+}
+
+#[test]
+fn send_chat_message_call() {
+    round_trip(Packet::SendChatMessageCall {
+        message: "hi".to_string(),
+    });
+}
+
+#[test]
+fn send_message_call() {
+    round_trip(Packet::SendMessageCall {
+        message: "text1".to_string(),
+    });
+}
+
+#[test]
+fn send_message_call2() {
+    round_trip(Packet::SendMessageCall2 {
+        message: "[coral][[Player]: hi".to_string(),
+        unformatted: Some("hi".to_string()),
+        sender: 3,
+    });
+}
+
+#[test]
+fn server_packet_reliable_call() {
+    round_trip(Packet::ServerPacketReliableCall {
+        packet_type: "text1".to_string(),
+        contents: "text2".to_string(),
+    });
+}
+
+#[test]
+fn server_packet_unreliable_call() {
+    round_trip(Packet::ServerPacketUnreliableCall {
+        packet_type: "text1".to_string(),
+        contents: "text2".to_string(),
+    });
+}
+
+#[test]
+fn set_camera_position_call() {
+    round_trip(Packet::SetCameraPositionCall { x: 1.5, y: 2.5 });
+}
+
+#[test]
+fn set_flag_call() {
+    round_trip(Packet::SetFlagCall {
+        flag: "text1".to_string(),
+        add: true,
+    });
+}
+
+#[test]
+fn set_floor_call() {
+    round_trip(Packet::SetFloorCall {
+        tile: Tile { x: 1, y: 2 },
+        floor: 12,
+        overlay: 13,
+    });
+}
+
+#[test]
+fn set_hud_text_call() {
+    round_trip(Packet::SetHudTextCall {
+        message: "text1".to_string(),
+    });
+}
+
+#[test]
+fn set_hud_text_reliable_call() {
+    round_trip(Packet::SetHudTextReliableCall {
+        message: "text1".to_string(),
+    });
+}
+
+#[test]
+fn set_item_call() {
+    round_trip(Packet::SetItemCall {
+        build: Tile { x: 1, y: 2 },
+        item: 12,
+        amount: 103,
+    });
+}
+
+#[test]
+fn set_map_area_call() {
+    round_trip(Packet::SetMapAreaCall {
+        x: 101,
+        y: 102,
+        width: 103,
+        height: 104,
+    });
+}
+
+#[test]
+fn set_objectives_call() {
+    round_trip(Packet::SetObjectivesCall {
+        objectives: r#"{"pvp":true}"#.to_string(),
+    });
+}
+
+#[test]
+fn set_overlay_call() {
+    round_trip(Packet::SetOverlayCall {
+        tile: Tile { x: 1, y: 2 },
+        overlay: 12,
+    });
+}
+
+#[test]
+fn set_player_team_editor_call() {
+    round_trip(Packet::SetPlayerTeamEditorCall { team: 1 });
+}
+
+#[test]
+fn set_position_call() {
+    round_trip(Packet::SetPositionCall { x: 1.5, y: 2.5 });
+}
+
+#[test]
+fn set_rules_call() {
+    round_trip(Packet::SetRulesCall {
+        rules: r#"{"pvp":true}"#.to_string(),
+    });
+}
+
+#[test]
+fn set_team_call() {
+    round_trip(Packet::SetTeamCall {
+        build: Tile { x: 1, y: 2 },
+        team: 2,
+    });
+}
+
+#[test]
+fn set_tile_call() {
+    round_trip(Packet::SetTileCall {
+        tile: Tile { x: 1, y: 2 },
+        block: 12,
+        team: 3,
+        rotation: 104,
+    });
+}
+
+#[test]
+fn set_unit_command_call() {
+    round_trip(Packet::SetUnitCommandCall {
+        player: 101,
+        unit_ids: vec![2, 3],
+        command: Some(3),
+    });
+}
+
+#[test]
+fn sound_call() {
+    round_trip(Packet::SoundCall {
+        sound: 11,
+        volume: 2.5,
+        pitch: 3.5,
+        pan: 4.5,
+    });
+}
+
+#[test]
+fn sound_at_call() {
+    round_trip(Packet::SoundAtCall {
+        sound: 11,
+        x: 2.5,
+        y: 3.5,
+        volume: 4.5,
+        pitch: 5.5,
+    });
+}
+
+#[test]
+fn spawn_effect_call() {
+    round_trip(Packet::SpawnEffectCall {
+        x: 1.5,
+        y: 2.5,
+        rotation: 3.5,
+        unit_type: 14,
+    });
+}
+
+#[test]
+fn state_snapshot() {
+    round_trip(Packet::StateSnapshot {
+        wave_time: 120.0,
+        wave: 4,
+        enemies: 12,
+        paused: false,
+        game_over: false,
+        time_data: 3600,
+        tps: 60,
+        rand0: 1,
+        rand1: 2,
+        cores: HashMap::from([
+            (1, HashMap::from([(0, 1000), (3, 25)])),
+            (2, HashMap::new()),
+        ]),
+    });
+}
+
+#[test]
+fn sync_variable_call() {
+    round_trip(Packet::SyncVariableCall {
+        build: Tile { x: 1, y: 2 },
+        variable: 102,
+        value: Object::Int(3),
+    });
+}
+
+#[test]
+fn take_items_call() {
+    round_trip(Packet::TakeItemsCall {
+        build: Tile { x: 1, y: 2 },
+        item: 12,
+        amount: 103,
+        to: Unit {
+            unit_type: 2,
+            id: 204,
+        },
+    });
+}
+
+#[test]
+fn text_input_call() {
+    round_trip(Packet::TextInputCall {
+        text_input_id: 101,
+        title: "text2".to_string(),
+        message: "text3".to_string(),
+        text_length: 104,
+        default: "text5".to_string(),
+        numeric: true,
+    });
+}
+
+#[test]
+fn text_input_result_call() {
+    round_trip(Packet::TextInputResultCall {
+        text_input_id: 101,
+        text: Some("text2".to_string()),
+    });
+}
+
+#[test]
+fn tile_config_call() {
+    round_trip(Packet::TileConfigCall {
+        player: 3,
+        tile: Tile { x: 5, y: 6 },
+        value: Object::Int(7),
+    });
+}
+
+#[test]
+fn tile_tap_call() {
+    round_trip(Packet::TileTapCall {
+        player: 101,
+        tile: Tile { x: 2, y: 3 },
+    });
+}
+
+#[test]
+fn trace_info_call() {
+    round_trip(Packet::TraceInfoCall {
+        player: 101,
+        info: TraceInfo {
+            ip: Some("127.0.0.1".to_string()),
+            uuid: Some("AAAAAAAAAAA=".to_string()),
+            locale: Some("en".to_string()),
+            modded: false,
+            mobile: true,
+            times_joined: 3,
+            times_kicked: 1,
+            ips: vec!["127.0.0.1".to_string()],
+            names: vec!["Player".to_string()],
+        },
+    });
+}
+
+#[test]
+fn transfer_inventory_call() {
+    round_trip(Packet::TransferInventoryCall {
+        player: 101,
+        build: Tile { x: 2, y: 3 },
+    });
+}
+
+#[test]
+fn transfer_item_to_call() {
+    round_trip(Packet::TransferItemToCall {
+        unit: Unit {
+            unit_type: 2,
+            id: 201,
+        },
+        item: 12,
+        amount: 103,
+        x: 4.5,
+        y: 5.5,
+        build: Tile { x: 6, y: 7 },
+    });
+}
+
+#[test]
+fn transfer_item_to_unit_call() {
+    round_trip(Packet::TransferItemToUnitCall {
+        item: 11,
+        x: 2.5,
+        y: 3.5,
+        to: 104,
+    });
+}
+
+#[test]
+fn unit_block_spawn_call() {
+    round_trip(Packet::UnitBlockSpawnCall {
+        tile: Tile { x: 1, y: 2 },
+    });
+}
+
+#[test]
+fn unit_building_control_select_call() {
+    round_trip(Packet::UnitBuildingControlSelectCall {
+        unit: Unit {
+            unit_type: 2,
+            id: 201,
+        },
+        build: Tile { x: 2, y: 3 },
+    });
+}
+
+#[test]
+fn unit_cap_death_call() {
+    round_trip(Packet::UnitCapDeathCall {
+        unit: Unit {
+            unit_type: 2,
+            id: 201,
+        },
+    });
+}
+
+#[test]
+fn unit_clear_call() {
+    round_trip(Packet::UnitClearCall { player: 101 });
+}
+
+#[test]
+fn unit_control_call() {
+    round_trip(Packet::UnitControlCall {
+        player: 101,
+        unit: Unit {
+            unit_type: 2,
+            id: 202,
+        },
+    });
+}
+
+#[test]
+fn unit_death_call() {
+    round_trip(Packet::UnitDeathCall { id: 101 });
+}
+
+#[test]
+fn unit_destroy_call() {
+    round_trip(Packet::UnitDestroyCall { id: 101 });
+}
+
+#[test]
+fn unit_entered_payload_call() {
+    round_trip(Packet::UnitEnteredPayloadCall {
+        unit: Unit {
+            unit_type: 2,
+            id: 201,
+        },
+        build: Tile { x: 2, y: 3 },
+    });
+}
+
+#[test]
+fn unit_env_death_call() {
+    round_trip(Packet::UnitEnvDeathCall {
+        unit: Unit {
+            unit_type: 2,
+            id: 201,
+        },
+    });
+}
+
+#[test]
+fn unit_safe_death_call() {
+    round_trip(Packet::UnitSafeDeathCall {
+        unit: Unit {
+            unit_type: 2,
+            id: 201,
+        },
+    });
+}
+
+#[test]
+fn unit_tether_block_spawned_call() {
+    round_trip(Packet::UnitTetherBlockSpawnedCall {
+        tile: Tile { x: 1, y: 2 },
+        id: 102,
+    });
+}
+
+#[test]
+fn update_game_over_call() {
+    round_trip(Packet::UpdateGameOverCall { winner: 1 });
+}
+
+#[test]
+fn warning_toast_call() {
+    round_trip(Packet::WarningToastCall {
+        unicode: 101,
+        text: "text2".to_string(),
+    });
+}
+
+#[test]
+fn world_data_begin_call() {
+    round_trip(Packet::WorldDataBeginCall);
+}
+
+#[test]
+fn world_stream() {
+    let mut map = Map::new(4, 3);
+    map.set_floor(0, 0, 2);
+    map.set_floor(1, 0, 2);
+    map.set_ore(1, 0, 5);
+    map.set_block_id(3, 0, 1);
+    map.set_block(
+        3,
+        0,
+        block(
+            "conveyor",
+            "Conveyor",
+            Some(SpecificBlockData::Conveyor {
+                items: vec![ConveyorItem {
+                    item_id: 0,
+                    x: 0.0,
+                    y: 128.0 / 255.0,
+                }],
+            }),
+        ),
+    );
+    map.set_block_id(1, 2, 3);
+    map.set_block(
+        1,
+        2,
+        block(
+            "core-shard",
+            "CoreBlock",
+            Some(SpecificBlockData::CoreBlock {
+                command_position: Vec2 { x: 8.0, y: 16.0 },
+            }),
+        ),
+    );
+
+    let rules = Rules {
+        pvp: true,
+        unit_cap: 24,
+        banned_blocks: ["router".to_string()].into(),
+        ..Default::default()
+    };
+    let tags = HashMap::from([
+        ("name".to_string(), "Ground Zero".to_string()),
+        ("author".to_string(), "Anuke".to_string()),
+        ("steamid".to_string(), "0".to_string()),
+    ]);
+    let map_info = MapInfo::new(tags, &map);
+    let markers = HashMap::from([
+        (
+            0,
+            Marker {
+                kind: MarkerKind::ShapeText,
+                pos: Some(Vec2 { x: 4.0, y: 5.5 }),
+                text: Some("Defend the core".to_string()),
+                other: serde_json::from_str(r#"{"radius":6.5,"sides":4,"color":"ffd37fff"}"#)
+                    .unwrap(),
+                ..Default::default()
+            },
+        ),
+        (
+            3,
+            Marker {
+                kind: MarkerKind::Line,
+                pos: Some(Vec2 { x: 0.0, y: 0.0 }),
+                end_pos: Some(Vec2 { x: 10.0, y: 2.0 }),
+                ..Default::default()
+            },
+        ),
+        (
+            4,
+            Marker {
+                kind: MarkerKind::Other("CustomMarker".to_string()),
+                ..Default::default()
+            },
+        ),
+    ]);
+    round_trip(Packet::WorldStream(Box::new(World {
+        rules,
+        map_info,
+        wave: 4,
+        wave_time: 120.0,
+        tick: 5000.0,
+        seed0: 11,
+        seed1: 12,
+        id: 3,
+        player: PlayerInfo {
+            revision: 0,
+            admin: true,
+            boosting: false,
+            color: 0xffa108ff,
+            mouse_x: 12.5,
+            mouse_y: -4.0,
+            name: Some("bot".to_string()),
+            shooting: true,
+            team: 2,
+            typing: false,
+            unit: Unit {
+                unit_type: 0,
+                id: 0,
+            },
+            x: 80.0,
+            y: 96.0,
+        },
+        content_map: content_map(),
+        map,
+        team_plans: HashMap::from([(
+            1,
+            vec![TeamPlan {
+                x: 4,
+                y: 5,
+                rotation: 1,
+                block_id: 2,
+                config: Object::Int(3),
+            }],
+        )]),
+        markers,
+        custom_chunks: HashMap::from([("plugin".to_string(), vec![1, 2, 3])]),
+    })));
+}
+
+#[test]
+fn entity_snapshot_units() {
+    let mut units = HashMap::new();
+    units.insert(
+        1,
+        generic_unit(
+            None,
+            Controller::Command {
+                controller_type: 8,
+                attack: Some(AttackTarget::Building(Tile { x: 4, y: 5 })),
+                position: Some(Vec2 { x: 1.0, y: 2.0 }),
+                command: Some(2),
+                queue: vec![
+                    CommandTarget::Unit(7),
+                    CommandTarget::Position(Vec2 { x: 3.0, y: 4.0 }),
+                ],
+                stance: Some(1),
+            },
+            None,
+        ),
+    );
+    units.insert(
+        2,
+        generic_unit(
+            None,
+            Controller::Player { id: 3 },
+            Some(vec![
+                Payload::Unit(Box::new(generic_unit(Some(7), Controller::Other(2), None))),
+                Payload::Block {
+                    block_id: 2,
+                    block: Box::new(block(
+                        "sorter",
+                        "Sorter",
+                        Some(SpecificBlockData::Sorter {
+                            sort_item: 0,
+                            buffer: Some(
+                                DirectionalItemBuffer::read(
+                                    &mut Reader::new(vec![
+                                        0, 1, 0, 0, 0, 0, 0, 0, 0, 9, 0, 0, 0, 0, 0, 0,
+                                    ]),
+                                    20,
+                                )
+                                .unwrap(),
+                            ),
+                        }),
+                    )),
+                },
+            ]),
+        ),
+    );
+
+    round_trip(Packet::EntitySnapshot { units });
+}
+
+#[test]
+fn tile_config_call_objects() {
+    let objects = vec![
+        Object::Null,
+        Object::Int(5),
+        Object::Long(6),
+        Object::Float(1.5),
+        Object::String(Some("text".to_string())),
+        Object::String(None),
+        Object::Content(Content {
+            content_type: 0,
+            id: 1,
+        }),
+        Object::IntSequence(vec![1, 2]),
+        Object::Point2(Point2 { x: 1, y: -2 }),
+        Object::Point2Array(vec![Point2 { x: 3, y: 4 }]),
+        Object::TechNode(Content {
+            content_type: 1,
+            id: 2,
+        }),
+        Object::Boolean(true),
+        Object::Double(2.5),
+        Object::Building(Tile { x: 5, y: 6 }),
+        Object::LAccess(3),
+        Object::ByteArray(vec![1, 2, 3]),
+        Object::BooleanArray(vec![true, false]),
+        Object::Unit(8),
+        Object::Vec2Array(vec![Vec2 { x: 1.0, y: 2.0 }]),
+        Object::Vec2(Vec2 { x: 3.0, y: 4.0 }),
+        Object::Team(2),
+        Object::IntArray(vec![9, 10]),
+        Object::ObjectArray(vec![Object::Int(1), Object::Null]),
+        Object::UnitCommand(4),
+    ];
+
+    for value in objects {
+        round_trip(Packet::TileConfigCall {
+            player: 1,
+            tile: Tile { x: 2, y: 3 },
+            value,
+        });
+    }
+}
+
+#[test]
+fn compressed_packet() {
+    round_trip(Packet::SendMessageCall {
+        message: "a".repeat(1000),
+    });
+}
+
+#[test]
+fn markers_from_arc_ubjson() {
+    // {"7":{"class":"Point","pos":{"x":3,"y":4},"radius":5.0}}, as Arc's UBJsonWriter writes it
+    let mut data = vec![b'{', b'i', 1, b'7', b'{'];
+    data.extend([b'i', 5]);
+    data.extend(b"class");
+    data.extend([b'S', b'i', 5]);
+    data.extend(b"Point");
+    data.extend([b'i', 3]);
+    data.extend(b"pos");
+    data.extend([
+        b'{', b'i', 1, b'x', b'l', 0, 0, 0, 3, b'i', 1, b'y', b'i', 4, b'}',
+    ]);
+    data.extend([b'i', 6]);
+    data.extend(b"radius");
+    data.push(b'd');
+    data.extend(5.0f32.to_be_bytes());
+    data.extend([b'}', b'}']);
+
+    let markers = read_markers(&mut Reader::new(data)).unwrap();
+    let marker = &markers[&7];
+    assert_eq!(marker.kind, MarkerKind::Point);
+    assert_eq!(marker.pos, Some(Vec2 { x: 3.0, y: 4.0 }));
+    assert_eq!(marker.other["radius"], 5.0);
+}
@@ -0,0 +1,101 @@
+use mindustry_net::rules::{Rules, TeamRule};
+
+#[test]
+fn missing_fields_use_defaults() {
+    let rules = Rules::from_json("{}").unwrap();
+    assert_eq!(rules, Rules::default());
+    assert_eq!(rules.wave_spacing, 7200.0);
+    assert_eq!(rules.deconstruct_refund_multiplier, 0.5);
+}
+
+#[test]
+fn parses_rules() {
+    let rules = Rules::from_json(
+        r#"{
+            "pvp": true,
+            "waveSpacing": 3600,
+            "unitCap": 12,
+            "unitCapVariable": false,
+            "buildCostMultiplier": 0.5,
+            "buildSpeedMultiplier": 2,
+            "bannedBlocks": ["router", "bridge-conveyor"],
+            "bannedUnits": ["dagger"],
+            "teams": {"1": {"cheat": true, "buildSpeedMultiplier": 3}},
+            "fog": true,
+            "lighting": true,
+            "env": 3
+        }"#,
+    )
+    .unwrap();
+
+    assert!(rules.pvp);
+    assert_eq!(rules.wave_spacing, 3600.0);
+    assert_eq!(rules.unit_cap, 12);
+    assert!(!rules.unit_cap_variable);
+    assert_eq!(rules.build_cost_multiplier, 0.5);
+    assert!(rules.is_banned_block("router"));
+    assert!(!rules.is_banned_block("conveyor"));
+    assert!(rules.is_banned_unit("dagger"));
+    assert!(rules.fog && rules.lighting);
+
+    assert!(rules.team(1).cheat);
+    assert_eq!(rules.team(2), TeamRule::default());
+    assert_eq!(rules.build_speed_multiplier(1), 6.0);
+    assert_eq!(rules.build_speed_multiplier(2), 2.0);
+
+    // Unknown fields survive a round trip
+    let written = Rules::from_json(&rules.to_json()).unwrap();
+    assert_eq!(written, rules);
+    assert_eq!(written.other.get("env"), Some(&serde_json::json!(3)));
+}
+
+#[test]
+fn parses_arc_minimal_json() {
+    // Laid out like the game's JsonIO writes rules, with arc's default "minimal" output
+    let rules = Rules::from_json(
+        "{waveTeam:2,attackMode:true,waves:true,waveSpacing:5400.0,unitCap:12,\
+         bannedBlocks:[router,bridge-conveyor],bannedUnits:[dagger],\
+         teams:{1:{cheat:true,buildSpeedMultiplier:3.0}},modeName:Attack Mode,\
+         fog:true,ambientLight:{r:0.01,g:0.01,b:0.04,a:0.99},planet:serpulo,\
+         description:\"Hold, then attack\",weather:[],tags:{}}",
+    )
+    .unwrap();
+
+    assert!(rules.attack_mode && rules.waves && rules.fog);
+    assert_eq!(rules.wave_spacing, 5400.0);
+    assert_eq!(rules.unit_cap, 12);
+    assert!(rules.is_banned_block("bridge-conveyor"));
+    assert!(rules.is_banned_unit("dagger"));
+    assert!(rules.team(1).cheat);
+    assert_eq!(rules.build_speed_multiplier(1), 3.0);
+    assert_eq!(rules.other["modeName"], "Attack Mode");
+    assert_eq!(rules.other["planet"], "serpulo");
+    assert_eq!(rules.other["description"], "Hold, then attack");
+    assert_eq!(rules.other["ambientLight"]["a"], 0.99);
+}
+
+#[test]
+fn invalid_rules() {
+    assert!(Rules::from_json("{waves:true").is_err());
+    assert!(Rules::from_json("{pvp:3}").is_err());
+    let nested = format!("{{tags:{}{}}}", "[".repeat(200), "]".repeat(200));
+    assert!(Rules::from_json(&nested).is_err());
+
+    assert_eq!(Rules::from_json_or_default(""), Rules::default());
+    assert_eq!(Rules::from_json_or_default("{waves:"), Rules::default());
+}
+
+#[test]
+fn set_rule() {
+    let mut rules = Rules::default();
+    rules.set_rule("unitCap", "30").unwrap();
+    rules.set_rule("bannedBlocks", r#"["router"]"#).unwrap();
+    rules.set_rule("ambientLight", r#"{"r":0.1}"#).unwrap();
+    assert_eq!(rules.unit_cap, 30);
+    assert!(rules.is_banned_block("router"));
+    assert!(rules.other.contains_key("ambientLight"));
+    rules.set_rule("bannedUnits", "[flare,horizon]").unwrap();
+    assert!(rules.is_banned_unit("horizon"));
+
+    assert!(rules.set_rule("pvp", "3").is_err());
+}
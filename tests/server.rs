@@ -0,0 +1,520 @@
+mod common;
+
+use common::{content_map, player, stone_map};
+use mindustry_net::client::{
+    Client, ClientEvent, DisconnectCause, EventKind, Kick, Reconnect, Region, Subscription,
+};
+use mindustry_net::content::{ContentStore, MemoryContentStore};
+use mindustry_net::packet::Packet;
+use mindustry_net::save_io::Map;
+use mindustry_net::server::{Server, ServerError};
+use mindustry_net::type_io::{KickReason, Tile, Unit};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time;
+
+async fn next_event(receiver: &mut Subscription) -> ClientEvent {
+    time::timeout(Duration::from_secs(5), receiver.recv())
+        .await
+        .expect("timed out waiting for an event")
+        .unwrap()
+}
+
+#[tokio::test]
+async fn client_loads_world() {
+    let mut server = Server::bind("127.0.0.1:0", stone_map(50, 50), content_map())
+        .await
+        .unwrap();
+    let address = server.local_addr().unwrap();
+
+    let mut client = Client::new(address.to_string(), "bot".to_string())
+        .await
+        .unwrap();
+    let protocol = client.protocol.clone();
+    let mut receiver = client.subscribe();
+    tokio::spawn(async move { client.handle_packets().await });
+
+    let mut connection = server.accept().await.unwrap();
+    assert_eq!(connection.name, "bot");
+    assert_eq!(connection.version, 146);
+
+    assert!(matches!(
+        next_event(&mut receiver).await,
+        ClientEvent::Connected
+    ));
+    assert!(matches!(
+        next_event(&mut receiver).await,
+        ClientEvent::WorldLoaded
+    ));
+    {
+        let protocol = protocol.lock().await;
+        assert_eq!(protocol.state.player_id, connection.id);
+        assert_eq!(protocol.state.map, stone_map(50, 50));
+    }
+
+    let mut units = HashMap::new();
+    units.insert(connection.id, player(80.0, 96.0));
+    connection
+        .send_unreliable(Packet::EntitySnapshot { units })
+        .await
+        .unwrap();
+    assert!(matches!(
+        next_event(&mut receiver).await,
+        ClientEvent::PositionChanged { x: 80.0, y: 96.0 }
+    ));
+    assert!(matches!(
+        next_event(&mut receiver).await,
+        ClientEvent::UnitSnapshot
+    ));
+    assert_eq!(
+        protocol.lock().await.state.units.get(&connection.id),
+        Some(&player(80.0, 96.0))
+    );
+
+    connection
+        .send(Packet::StateSnapshot {
+            wave_time: 120.0,
+            wave: 2,
+            enemies: 0,
+            paused: false,
+            game_over: false,
+            time_data: 0,
+            tps: 60,
+            rand0: 1,
+            rand1: 2,
+            cores: HashMap::new(),
+        })
+        .await
+        .unwrap();
+    assert!(matches!(
+        next_event(&mut receiver).await,
+        ClientEvent::WaveChanged { wave: 2 }
+    ));
+    assert!(matches!(
+        next_event(&mut receiver).await,
+        ClientEvent::StateUpdated
+    ));
+    connection
+        .send(Packet::SendMessageCall2 {
+            message: "[coral][[Server]:[white] hello".to_string(),
+            unformatted: Some("hello".to_string()),
+            sender: 0,
+        })
+        .await
+        .unwrap();
+    match next_event(&mut receiver).await {
+        ClientEvent::ChatMessage { unformatted, .. } => {
+            assert_eq!(unformatted.as_deref(), Some("hello"))
+        }
+        event => panic!("unexpected event {event:?}"),
+    }
+
+    // The client keeps the connection alive with snapshots
+    assert!(matches!(
+        connection.recv().await.unwrap(),
+        Packet::ConnectCallConfirm | Packet::ClientSnapshot { .. }
+    ));
+
+    // Too large for a single frame, so it arrives as a stream
+    let message = "a".repeat(u16::MAX as usize);
+    protocol
+        .lock()
+        .await
+        .send(
+            Packet::SendChatMessageCall {
+                message: message.clone(),
+            },
+            true,
+        )
+        .unwrap();
+    loop {
+        let packet = time::timeout(Duration::from_secs(5), connection.recv())
+            .await
+            .expect("timed out waiting for the message")
+            .unwrap();
+        if let Packet::SendChatMessageCall { message: received } = packet {
+            assert_eq!(received, message);
+            break;
+        }
+    }
+
+    drop(connection);
+    assert!(matches!(
+        next_event(&mut receiver).await,
+        ClientEvent::Disconnected {
+            cause: DisconnectCause::Closed
+        }
+    ));
+}
+
+#[test]
+fn region_at_map_edge() {
+    let region = Region {
+        x: i16::MAX - 1,
+        y: 0,
+        width: 4,
+        height: i16::MAX,
+    };
+    assert!(region.contains(&Tile {
+        x: i16::MAX,
+        y: i16::MAX - 1
+    }));
+    assert!(!region.contains(&Tile {
+        x: i16::MAX - 2,
+        y: 0
+    }));
+}
+
+#[tokio::test]
+async fn subscriptions() {
+    let mut server = Server::bind("127.0.0.1:0", stone_map(50, 50), content_map())
+        .await
+        .unwrap();
+    let address = server.local_addr().unwrap();
+
+    let mut client = Client::new(address.to_string(), "bot".to_string())
+        .await
+        .unwrap();
+    let mut all = client.subscribe();
+    let mut chat = client.subscribe().kinds([EventKind::ChatMessage]);
+    let mut region = client.subscribe().region(Region {
+        x: 0,
+        y: 0,
+        width: 2,
+        height: 2,
+    });
+    // Dropped subscriptions do not affect the others
+    drop(client.subscribe());
+    tokio::spawn(async move { client.handle_packets().await });
+
+    let mut connection = server.accept().await.unwrap();
+    for tile in [Tile { x: 5, y: 5 }, Tile { x: 1, y: 1 }] {
+        connection
+            .send(Packet::DeconstructFinish {
+                tile,
+                block: 1,
+                builder: Unit {
+                    unit_type: 0,
+                    id: 0,
+                },
+            })
+            .await
+            .unwrap();
+    }
+    connection
+        .send(Packet::SendMessageCall2 {
+            message: "hello".to_string(),
+            unformatted: None,
+            sender: 0,
+        })
+        .await
+        .unwrap();
+
+    assert!(matches!(
+        next_event(&mut chat).await,
+        ClientEvent::ChatMessage { .. }
+    ));
+    match next_event(&mut region).await {
+        ClientEvent::BlockChanged { tile, .. } => assert_eq!(tile, Tile { x: 1, y: 1 }),
+        event => panic!("unexpected event {event:?}"),
+    }
+
+    let mut kinds = vec![];
+    for _ in 0..5 {
+        kinds.push(next_event(&mut all).await.kind());
+    }
+    assert_eq!(
+        kinds,
+        vec![
+            EventKind::Connected,
+            EventKind::WorldLoaded,
+            EventKind::BlockChanged,
+            EventKind::BlockChanged,
+            EventKind::ChatMessage
+        ]
+    );
+}
+
+#[tokio::test]
+async fn reconnects_until_banned() {
+    let mut server = Server::bind("127.0.0.1:0", stone_map(50, 50), content_map())
+        .await
+        .unwrap();
+    let address = server.local_addr().unwrap();
+
+    let mut client = Client::builder(address.to_string(), "bot".to_string())
+        .reconnect(Reconnect {
+            initial_delay: Duration::from_millis(10),
+            max_delay: Duration::from_millis(100),
+            max_attempts: Some(3),
+        })
+        .connect()
+        .await
+        .unwrap();
+    let mut receiver = client.subscribe().kinds([
+        EventKind::Connected,
+        EventKind::Kicked,
+        EventKind::Disconnected,
+        EventKind::Reconnecting,
+    ]);
+    let task = tokio::spawn(async move { client.handle_packets().await });
+
+    let connection = server.accept().await.unwrap();
+    assert!(matches!(
+        next_event(&mut receiver).await,
+        ClientEvent::Connected
+    ));
+    drop(connection);
+    assert!(matches!(
+        next_event(&mut receiver).await,
+        ClientEvent::Disconnected {
+            cause: DisconnectCause::Closed
+        }
+    ));
+    match next_event(&mut receiver).await {
+        ClientEvent::Reconnecting { attempt, delay } => {
+            assert_eq!(attempt, 1);
+            assert_eq!(delay, Duration::from_millis(10));
+        }
+        event => panic!("unexpected event {event:?}"),
+    }
+
+    let mut connection = server.accept().await.unwrap();
+    assert!(matches!(
+        next_event(&mut receiver).await,
+        ClientEvent::Connected
+    ));
+    connection
+        .send(Packet::KickCall2 {
+            reason: KickReason::Banned,
+        })
+        .await
+        .unwrap();
+    drop(connection);
+    assert!(matches!(
+        next_event(&mut receiver).await,
+        ClientEvent::Kicked { .. }
+    ));
+    assert!(matches!(
+        next_event(&mut receiver).await,
+        ClientEvent::Disconnected {
+            cause: DisconnectCause::Kicked(Kick::Reason(KickReason::Banned))
+        }
+    ));
+
+    // Bans are not retried
+    time::timeout(Duration::from_secs(5), task)
+        .await
+        .expect("client kept reconnecting")
+        .unwrap();
+}
+
+#[tokio::test]
+async fn drops_client_without_udp_registration() {
+    let mut server = Server::bind("127.0.0.1:0", stone_map(50, 50), content_map())
+        .await
+        .unwrap();
+    server.set_handshake_timeout(Duration::from_millis(100));
+    let address = server.local_addr().unwrap();
+
+    // Connects over TCP but never registers its UDP channel
+    let _stalled = tokio::net::TcpStream::connect(address).await.unwrap();
+    match server.accept().await {
+        Err(ServerError::Handshake(reason)) => assert!(
+            reason.ends_with("did not register its UDP channel within 100ms"),
+            "{reason}"
+        ),
+        result => panic!(
+            "unexpected result {:?}",
+            result.map(|connection| connection.id)
+        ),
+    }
+}
+
+#[tokio::test]
+async fn serves_clients_past_stalled_handshakes() {
+    let server = Server::bind("127.0.0.1:0", stone_map(50, 50), content_map())
+        .await
+        .unwrap();
+    let address = server.local_addr().unwrap();
+    tokio::spawn(server.serve(vec![]));
+
+    let _stalled = tokio::net::TcpStream::connect(address).await.unwrap();
+    let mut client = Client::new(address.to_string(), "bot".to_string())
+        .await
+        .unwrap();
+    let mut receiver = client.subscribe().kinds([EventKind::WorldLoaded]);
+    tokio::spawn(async move { client.handle_packets().await });
+
+    // Well before the stalled handshake times out
+    let event = time::timeout(Duration::from_secs(2), receiver.recv())
+        .await
+        .expect("client waited for the stalled handshake");
+    assert!(matches!(event, Some(ClientEvent::WorldLoaded)));
+}
+
+#[tokio::test]
+async fn times_out_on_silent_server() {
+    // Accepts the connection but never sends a frame
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let accept = tokio::spawn(async move { listener.accept().await.unwrap() });
+
+    let mut client = Client::builder(address.to_string(), "bot".to_string())
+        .timeout(Duration::from_millis(200))
+        .connect()
+        .await
+        .unwrap();
+    let mut receiver = client.subscribe();
+    let task = tokio::spawn(async move { client.handle_packets().await });
+
+    assert!(matches!(
+        next_event(&mut receiver).await,
+        ClientEvent::Disconnected {
+            cause: DisconnectCause::TimedOut
+        }
+    ));
+    time::timeout(Duration::from_secs(5), task)
+        .await
+        .expect("client did not stop")
+        .unwrap();
+    drop(accept.await.unwrap());
+}
+
+#[tokio::test]
+async fn disconnect() {
+    let mut server = Server::bind("127.0.0.1:0", stone_map(50, 50), content_map())
+        .await
+        .unwrap();
+    let address = server.local_addr().unwrap();
+
+    let mut client = Client::builder(address.to_string(), "bot".to_string())
+        .reconnect(Reconnect::default())
+        .connect()
+        .await
+        .unwrap();
+    let handle = client.disconnect_handle();
+    let mut receiver = client
+        .subscribe()
+        .kinds([EventKind::WorldLoaded, EventKind::Disconnected]);
+    let task = tokio::spawn(async move { client.handle_packets().await });
+
+    let mut connection = server.accept().await.unwrap();
+    assert!(matches!(
+        next_event(&mut receiver).await,
+        ClientEvent::WorldLoaded
+    ));
+
+    handle.disconnect();
+    assert!(matches!(
+        next_event(&mut receiver).await,
+        ClientEvent::Disconnected {
+            cause: DisconnectCause::Requested
+        }
+    ));
+    time::timeout(Duration::from_secs(5), task)
+        .await
+        .expect("client did not stop")
+        .unwrap();
+
+    // The sockets are closed, so the server sees the connection end
+    time::timeout(Duration::from_secs(5), async {
+        while connection.recv().await.is_ok() {}
+    })
+    .await
+    .expect("connection stayed open");
+}
+
+#[tokio::test]
+async fn follows_redirect() {
+    let mut server = Server::bind("127.0.0.1:0", stone_map(50, 50), content_map())
+        .await
+        .unwrap();
+    let mut target = Server::bind("127.0.0.1:0", Map::new(10, 10), content_map())
+        .await
+        .unwrap();
+    let address = server.local_addr().unwrap();
+    let target_address = target.local_addr().unwrap();
+
+    let mut client = Client::new(address.to_string(), "bot".to_string())
+        .await
+        .unwrap();
+    let protocol = client.protocol.clone();
+    let mut receiver = client.subscribe().kinds([
+        EventKind::Connected,
+        EventKind::Disconnected,
+        EventKind::WorldLoaded,
+    ]);
+    tokio::spawn(async move { client.handle_packets().await });
+
+    let mut connection = server.accept().await.unwrap();
+    assert!(matches!(
+        next_event(&mut receiver).await,
+        ClientEvent::Connected
+    ));
+    assert!(matches!(
+        next_event(&mut receiver).await,
+        ClientEvent::WorldLoaded
+    ));
+    connection
+        .send(Packet::ConnectCall {
+            ip: target_address.ip().to_string(),
+            port: target_address.port() as u32,
+        })
+        .await
+        .unwrap();
+
+    match next_event(&mut receiver).await {
+        ClientEvent::Disconnected {
+            cause: DisconnectCause::Redirected { port, .. },
+        } => assert_eq!(port, target_address.port() as u32),
+        event => panic!("unexpected event {event:?}"),
+    }
+    let connection = target.accept().await.unwrap();
+    assert_eq!(connection.name, "bot");
+    assert!(matches!(
+        next_event(&mut receiver).await,
+        ClientEvent::Connected
+    ));
+    assert!(matches!(
+        next_event(&mut receiver).await,
+        ClientEvent::WorldLoaded
+    ));
+    assert_eq!(protocol.lock().await.state.map.width, 10);
+}
+
+#[tokio::test]
+async fn caches_content() {
+    let mut server = Server::bind("127.0.0.1:0", stone_map(50, 50), content_map())
+        .await
+        .unwrap();
+    let address = server.local_addr().unwrap();
+    let store = Arc::new(MemoryContentStore::new());
+
+    let mut client = Client::builder(address.to_string(), "bot".to_string())
+        .content_store(store.clone())
+        .connect()
+        .await
+        .unwrap();
+    assert_eq!(client.protocol.lock().await.content_map(), &None);
+    let mut receiver = client.subscribe().kinds([EventKind::WorldLoaded]);
+    tokio::spawn(async move { client.handle_packets().await });
+    let _connection = server.accept().await.unwrap();
+    next_event(&mut receiver).await;
+    assert_eq!(
+        store.load(&address.to_string()).unwrap(),
+        Some(content_map())
+    );
+
+    // The next client knows the content before the world arrives
+    let client = Client::builder(address.to_string(), "bot".to_string())
+        .content_store(store)
+        .connect()
+        .await
+        .unwrap();
+    assert_eq!(
+        client.protocol.lock().await.content_map(),
+        &Some(content_map())
+    );
+}
@@ -0,0 +1,246 @@
+use flate2::Compression;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use futures_util::{SinkExt, StreamExt};
+use mindustry_net::client::QueuedPacket;
+use mindustry_net::codec::PacketCodec;
+use mindustry_net::content::ContentRegistry;
+use mindustry_net::packet::{
+    AnyPacket, MAX_FRAME_DATA, Packet, PacketError, STREAM_CHUNK_SIZE, World, parse_packet,
+    write_packet_data, write_packet_frames,
+};
+use mindustry_net::protocol::ClientProtocol;
+use mindustry_net::rules::Rules;
+use mindustry_net::save_io::{Map, MapInfo};
+use mindustry_net::stream_builder::StreamBuilder;
+use mindustry_net::type_io::Unit;
+use mindustry_net::unit_io::PlayerInfo;
+use mindustry_net::version::ProtocolVersion;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use tokio::io::duplex;
+use tokio_util::codec::Framed;
+
+/// A chat message whose encoded data is exactly `size` bytes
+fn chat(size: usize) -> Packet {
+    let overhead = write_packet_data(Packet::SendChatMessageCall {
+        message: "a".to_string(),
+    })
+    .unwrap()
+    .1
+    .len()
+        - 1;
+    Packet::SendChatMessageCall {
+        message: "a".repeat(size - overhead),
+    }
+}
+
+fn decode(frame: &[u8]) -> Packet {
+    match parse_packet(frame[2..].to_vec(), &None, ProtocolVersion::default()).unwrap() {
+        AnyPacket::Regular(packet) => packet,
+        other => panic!("unexpected packet {other:?}"),
+    }
+}
+
+/// Reassembles the packets of a single stream
+fn reassemble(packets: impl IntoIterator<Item = Packet>) -> Packet {
+    let mut packets = packets.into_iter();
+    let mut stream = match packets.next() {
+        Some(Packet::StreamBegin {
+            id,
+            total,
+            stream_type,
+        }) => StreamBuilder::new(id, stream_type, total),
+        other => panic!("expected StreamBegin, got {other:?}"),
+    };
+    for packet in packets {
+        match packet {
+            Packet::StreamChunk { id, data } => {
+                assert_eq!(id, stream.id);
+                assert!(data.len() <= STREAM_CHUNK_SIZE);
+                stream.add(data);
+            }
+            other => panic!("expected StreamChunk, got {other:?}"),
+        }
+    }
+    assert!(stream.is_done());
+    stream.build(&None, ProtocolVersion::default()).unwrap()
+}
+
+#[test]
+fn largest_single_frame() {
+    let packet = chat(MAX_FRAME_DATA);
+    let mut stream_id = 0;
+    let frames = write_packet_frames(packet.clone(), &mut stream_id).unwrap();
+    assert_eq!(frames.len(), 1);
+    assert_eq!(stream_id, 0);
+    assert_eq!(decode(&frames[0]), packet);
+}
+
+#[test]
+fn smallest_stream() {
+    let packet = chat(MAX_FRAME_DATA + 1);
+    let mut stream_id = 5;
+    let frames = write_packet_frames(packet.clone(), &mut stream_id).unwrap();
+    assert_eq!(stream_id, 6);
+    assert_eq!(
+        frames.len(),
+        1 + (MAX_FRAME_DATA + 1).div_ceil(STREAM_CHUNK_SIZE)
+    );
+
+    let packets: Vec<Packet> = frames.iter().map(|frame| decode(frame)).collect();
+    assert!(matches!(packets[0], Packet::StreamBegin { id: 5, .. }));
+    assert_eq!(reassemble(packets), packet);
+}
+
+#[test]
+fn largest_string() {
+    let packet = Packet::SendChatMessageCall {
+        message: "b".repeat(u16::MAX as usize),
+    };
+    let mut stream_id = 0;
+    let frames = write_packet_frames(packet.clone(), &mut stream_id).unwrap();
+    assert_eq!(reassemble(frames.iter().map(|frame| decode(frame))), packet);
+}
+
+#[test]
+fn string_too_long() {
+    // The length prefix counts bytes, so 21846 three byte characters do not fit either
+    for message in ["b".repeat(u16::MAX as usize + 1), "€".repeat(21846)] {
+        let length = message.len();
+        let mut stream_id = 0;
+        match write_packet_frames(Packet::SendChatMessageCall { message }, &mut stream_id) {
+            Err(PacketError::Encode {
+                packet_id,
+                field,
+                reason,
+            }) => {
+                assert_eq!(packet_id, None);
+                assert_eq!(field, "message");
+                assert_eq!(
+                    reason,
+                    format!("string of {length} bytes is longer than 65535")
+                );
+            }
+            result => panic!("unexpected result {result:?}"),
+        }
+        assert_eq!(stream_id, 0);
+    }
+}
+
+#[test]
+fn protocol_streams_reliably() {
+    let mut protocol = ClientProtocol::new("bot".to_string(), None);
+    let packet = chat(MAX_FRAME_DATA + 4);
+    protocol.send(packet.clone(), false).unwrap();
+
+    let mut transmits: Vec<QueuedPacket> = vec![];
+    while let Some(transmit) = protocol.poll_transmit() {
+        transmits.push(transmit);
+    }
+    assert!(transmits.len() > 1);
+    assert!(transmits.iter().all(|transmit| transmit.reliable));
+    assert_eq!(
+        reassemble(transmits.iter().map(|transmit| decode(&transmit.packet))),
+        packet
+    );
+
+    // Small unreliable packets stay single datagrams without a length prefix
+    protocol.send(chat(100), false).unwrap();
+    let transmit = protocol.poll_transmit().unwrap();
+    assert!(!transmit.reliable);
+    assert!(matches!(
+        parse_packet(transmit.packet, &None, ProtocolVersion::default()).unwrap(),
+        AnyPacket::Regular(Packet::SendChatMessageCall { .. })
+    ));
+}
+
+#[tokio::test]
+async fn codec_streams_large_packets() {
+    let (client, server) = duplex(4096);
+    let mut client = Framed::new(client, PacketCodec::default());
+    let mut server = Framed::new(server, PacketCodec::default());
+
+    let packet = chat(MAX_FRAME_DATA + 1);
+    let sent = packet.clone();
+    tokio::spawn(async move {
+        client.send(sent).await.unwrap();
+        client.send(chat(10)).await.unwrap();
+    });
+
+    let mut packets = vec![];
+    while let Some(received) = server.next().await {
+        match received.unwrap() {
+            AnyPacket::Regular(packet) => packets.push(packet),
+            other => panic!("unexpected packet {other:?}"),
+        }
+    }
+    assert_eq!(packets.pop(), Some(chat(10)));
+    assert_eq!(reassemble(packets), packet);
+}
+
+fn world() -> Packet {
+    Packet::WorldStream(Box::new(World {
+        rules: Rules::default(),
+        map_info: MapInfo::new(HashMap::new(), &Map::new(2, 2)),
+        wave: 1,
+        wave_time: 0.0,
+        tick: 0.0,
+        seed0: 0,
+        seed1: 0,
+        id: 1,
+        player: PlayerInfo {
+            revision: 0,
+            admin: false,
+            boosting: false,
+            color: 0xffa108ff,
+            mouse_x: 0.0,
+            mouse_y: 0.0,
+            name: Some("bot".to_string()),
+            shooting: false,
+            team: 1,
+            typing: false,
+            unit: Unit {
+                unit_type: 0,
+                id: 0,
+            },
+            x: 0.0,
+            y: 0.0,
+        },
+        content_map: ContentRegistry::new(HashMap::from([(
+            "block".to_string(),
+            vec!["air".to_string()],
+        )])),
+        map: Map::new(2, 2),
+        team_plans: HashMap::new(),
+        markers: HashMap::new(),
+        custom_chunks: HashMap::new(),
+    }))
+}
+
+fn build_checked(stream_type: u8, data: Vec<u8>) -> (Packet, usize) {
+    let mut stream = StreamBuilder::new(0, stream_type, data.len() as u32);
+    stream.add(data);
+    stream
+        .build_checked(&None, ProtocolVersion::default())
+        .unwrap()
+}
+
+#[test]
+fn stream_trailing_bytes() {
+    let (stream_type, data) = write_packet_data(world()).unwrap();
+    assert_eq!(build_checked(stream_type, data.clone()), (world(), 0));
+
+    // Bytes left over in the compressed world data count as trailing bytes
+    let mut world_data = vec![];
+    ZlibDecoder::new(data.as_slice())
+        .read_to_end(&mut world_data)
+        .unwrap();
+    world_data.extend_from_slice(&[1, 2, 3]);
+    let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+    encoder.write_all(&world_data).unwrap();
+    assert_eq!(
+        build_checked(stream_type, encoder.finish().unwrap()),
+        (world(), 3)
+    );
+}
@@ -0,0 +1,189 @@
+use mindustry_net::block_io::{BaseBlockData, Block, SpecificBlockData};
+use mindustry_net::client::ClientInfo;
+use mindustry_net::content::ContentRegistry;
+use mindustry_net::packet::read_block_snapshot;
+use mindustry_net::protocol::ClientProtocol;
+use mindustry_net::save_io::{Map, read_map};
+use mindustry_net::type_io::Reader;
+use mindustry_net::version::ProtocolVersion;
+use std::collections::HashMap;
+
+/// A 2x1 map of stone floor, the first tile holding block 1 with the given tile bytes
+fn map_fixture(tile: &[u8]) -> Vec<u8> {
+    let mut data = vec![
+        0x00, 0x02, 0x00, 0x01, // size
+        0x00, 0x01, 0x00, 0x00, 0x01, // floor 1, no ore, repeated once
+        0x00, 0x01, // block 1
+    ];
+    data.extend_from_slice(tile);
+    data.extend_from_slice(&[0x00, 0x00, 0x00, 0x00]); // air, no data, not repeated
+    data
+}
+
+/// Build 146 tiles carry a single data byte (flag 2)
+fn v146_map() -> Vec<u8> {
+    map_fixture(&[0x02, 0x07])
+}
+
+/// v8 tiles carry data, floor data, overlay data and an int (flag 4)
+fn v149_map() -> Vec<u8> {
+    map_fixture(&[0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x09])
+}
+
+fn read(data: Vec<u8>, version: ProtocolVersion) -> (Map, usize) {
+    let mut reader = Reader::with_version(data, version);
+    let map = read_map(&mut reader, &ContentRegistry::default()).unwrap();
+    (map, reader.remaining())
+}
+
+fn expected_map() -> Map {
+    let mut map = Map::new(2, 1);
+    map.set_floor(0, 0, 1);
+    map.set_floor(1, 0, 1);
+    map.set_block_id(0, 0, 1);
+    map
+}
+
+#[test]
+fn builds() {
+    assert_eq!(ProtocolVersion::from_build(145), None);
+    assert_eq!(
+        ProtocolVersion::from_build(146),
+        Some(ProtocolVersion::V146)
+    );
+    assert_eq!(
+        ProtocolVersion::from_build(148),
+        Some(ProtocolVersion::V146)
+    );
+    assert_eq!(
+        ProtocolVersion::from_build(149),
+        Some(ProtocolVersion::V149)
+    );
+    assert_eq!(
+        ProtocolVersion::from_build(150),
+        Some(ProtocolVersion::V149)
+    );
+    assert_eq!(ProtocolVersion::default().build(), 146);
+}
+
+/// v8 uses the build 146 ids until its own are mapped
+#[test]
+fn entity_classes() {
+    for version in [ProtocolVersion::V146, ProtocolVersion::V149] {
+        assert_eq!(version.entity_class(12), Some("Player"));
+        assert_eq!(version.entity_class(46), Some("CrawlUnit"));
+        assert_eq!(version.entity_class(1), None);
+    }
+}
+
+#[test]
+fn v146_tile_data() {
+    assert_eq!(read(v146_map(), ProtocolVersion::V146), (expected_map(), 0));
+}
+
+#[test]
+fn v149_tile_data() {
+    assert_eq!(read(v149_map(), ProtocolVersion::V149), (expected_map(), 0));
+    // The old single data byte is still understood
+    assert_eq!(read(v146_map(), ProtocolVersion::V149), (expected_map(), 0));
+}
+
+#[test]
+fn v149_tile_data_with_v146_layout() {
+    // Without the extended data block the fixture desyncs and leaves bytes behind
+    let (_, remaining) = read(v149_map(), ProtocolVersion::V146);
+    assert_ne!(remaining, 0);
+}
+
+#[test]
+fn protocol_uses_connect_build() {
+    let mut info = ClientInfo::new("bot".to_string());
+    info.version = 149;
+    let protocol = ClientProtocol::with_info(info.clone(), None);
+    assert_eq!(protocol.version(), ProtocolVersion::V149);
+
+    info.version = 140;
+    let protocol = ClientProtocol::with_info(info, None);
+    assert_eq!(protocol.version(), ProtocolVersion::OLDEST);
+}
+
+#[test]
+fn building_revisions() {
+    for version in [ProtocolVersion::V146, ProtocolVersion::V149] {
+        assert_eq!(version.building_revision("Conveyor"), 1);
+        assert_eq!(version.building_revision("UnitFactory"), 3);
+        assert_eq!(version.building_revision("Build3"), 1);
+        assert_eq!(version.building_revision("Wall"), 0);
+    }
+    assert_eq!(ProtocolVersion::V146.building_revision("LogicBlock"), 2);
+    assert_eq!(ProtocolVersion::V149.building_revision("LogicBlock"), 3);
+}
+
+/// A block snapshot of the processor at (0, 0), with the tag and icon a v8 server writes
+fn processor_snapshot() -> Vec<u8> {
+    #[rustfmt::skip]
+    let data = vec![
+        0x00, 0x00, 0x00, 0x00, // tile
+        0x00, 0x01, // block id
+        0x42, 0x8c, 0x00, 0x00, 0x80, 0x01, 0x03, 0x01, 0x00, // health, rotation, team, version, on, modules
+        0xff, 0x00, // efficiency
+        0x00, 0x00, 0x00, 0x02, 0x78, 0x9c, // compressed program
+        0x00, 0x00, 0x00, 0x00, // variables
+        0x00, 0x00, 0x00, 0x00, // memory
+        0x01, 0x00, 0x04, b'g', b'a', b't', b'e', // tag
+        0xf0, 0x01, // icon
+    ];
+    data
+}
+
+fn processor_tag(version: ProtocolVersion) -> (Option<String>, Option<u16>) {
+    let content_map = ContentRegistry::new(HashMap::from([(
+        "block".to_string(),
+        vec!["air".to_string(), "logic-processor".to_string()],
+    )]));
+    let mut map = Map::new(1, 1);
+    map.set_block_id(0, 0, 1);
+    map.set_block(
+        0,
+        0,
+        Block {
+            name: "logic-processor".to_string(),
+            block_type: "LogicBlock".to_string(),
+            revision: 2,
+            base: BaseBlockData {
+                health: 70.0,
+                rotation: 0,
+                version: 3,
+                legacy: false,
+                on: Some(1),
+                team: 1,
+                module_bitmask: 0,
+                items: None,
+                liquids: None,
+                power: None,
+                efficiency: Some(255),
+                optional_efficiency: Some(0),
+                visible_flags: None,
+            },
+            specific: None,
+        },
+    );
+
+    let (buildings, error) =
+        read_block_snapshot(1, processor_snapshot(), version, &content_map, &map);
+    assert!(error.is_none());
+    match &buildings[0].1.specific {
+        Some(SpecificBlockData::LogicBlock { tag, icon_tag, .. }) => (tag.clone(), *icon_tag),
+        specific => panic!("unexpected data {specific:?}"),
+    }
+}
+
+#[test]
+fn processor_snapshot_per_version() {
+    // Build 146 processors end after their memory
+    assert_eq!(processor_tag(ProtocolVersion::V146), (None, None));
+    assert_eq!(
+        processor_tag(ProtocolVersion::V149),
+        (Some("gate".to_string()), Some(0xf001))
+    );
+}